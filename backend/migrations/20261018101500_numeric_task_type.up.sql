@@ -0,0 +1 @@
+ALTER TYPE TASK_TYPE ADD VALUE IF NOT EXISTS 'numeric';
@@ -0,0 +1,2 @@
+-- Postgres can't drop a single value from an enum, so 'cloze' stays in TASK_TYPE.
+-- Tasks of this type have to be removed by hand before downgrading the backend.
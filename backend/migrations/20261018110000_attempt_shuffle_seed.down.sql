@@ -0,0 +1,5 @@
+ALTER TABLE attempts
+    DROP COLUMN IF EXISTS shuffle_seed;
+
+ALTER TABLE exams
+    DROP COLUMN IF EXISTS shuffle_entities;
@@ -0,0 +1,8 @@
+ALTER TABLE exams
+    ADD COLUMN IF NOT EXISTS shuffle_entities BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- existing attempts get a random seed too, new ones are seeded by the backend
+ALTER TABLE attempts
+    ADD COLUMN IF NOT EXISTS shuffle_seed BIGINT NOT NULL DEFAULT (random() * 2147483647)::BIGINT;
+ALTER TABLE attempts
+    ALTER COLUMN shuffle_seed DROP DEFAULT;
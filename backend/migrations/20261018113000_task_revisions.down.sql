@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS attempt_tasks;
+DROP TABLE IF EXISTS task_revisions;
@@ -0,0 +1,34 @@
+-- immutable snapshots of tasks, one per create/update
+CREATE TABLE IF NOT EXISTS task_revisions
+(
+    id            BIGSERIAL PRIMARY KEY,
+    -- no FK on purpose: attempts keep being graded against a revision after the task is deleted
+    task_id       INTEGER     NOT NULL,
+    revision      INTEGER     NOT NULL,
+    title         TEXT        NOT NULL,
+    description   TEXT,
+    task_type     TASK_TYPE   NOT NULL,
+    points        INTEGER     NOT NULL,
+    configuration JSONB       NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (task_id, revision)
+);
+
+INSERT INTO task_revisions (task_id, revision, title, description, task_type, points, configuration)
+SELECT id, 1, title, description, task_type, points, configuration
+FROM tasks;
+
+-- revisions of tasks an attempt was started with
+CREATE TABLE IF NOT EXISTS attempt_tasks
+(
+    attempt_id       UUID   NOT NULL REFERENCES attempts (id) ON DELETE CASCADE,
+    task_revision_id BIGINT NOT NULL REFERENCES task_revisions (id),
+    PRIMARY KEY (attempt_id, task_revision_id)
+);
+
+-- existing attempts are pinned to what their exams contain right now
+INSERT INTO attempt_tasks (attempt_id, task_revision_id)
+SELECT a.id, tr.id
+FROM attempts a
+    JOIN exam_entities ee ON ee.exam_id = a.exam_id AND ee.entity_type = 'task'
+    JOIN task_revisions tr ON tr.task_id = ee.task_id;
@@ -0,0 +1,5 @@
+ALTER TABLE task_revisions
+    DROP COLUMN IF EXISTS template;
+
+ALTER TABLE tasks
+    DROP COLUMN IF EXISTS template;
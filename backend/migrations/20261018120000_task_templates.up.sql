@@ -0,0 +1,6 @@
+-- variables of parameterized tasks, '{}' for plain ones
+ALTER TABLE tasks
+    ADD COLUMN IF NOT EXISTS template JSONB NOT NULL DEFAULT '{}';
+
+ALTER TABLE task_revisions
+    ADD COLUMN IF NOT EXISTS template JSONB NOT NULL DEFAULT '{}';
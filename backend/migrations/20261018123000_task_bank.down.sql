@@ -0,0 +1,15 @@
+ALTER TABLE task_revisions
+    DROP COLUMN IF EXISTS author_id,
+    DROP COLUMN IF EXISTS difficulty,
+    DROP COLUMN IF EXISTS tags;
+
+DROP INDEX IF EXISTS tasks_search_idx;
+DROP INDEX IF EXISTS tasks_tags_idx;
+
+ALTER TABLE tasks
+    DROP COLUMN IF EXISTS search,
+    DROP COLUMN IF EXISTS author_id,
+    DROP COLUMN IF EXISTS difficulty,
+    DROP COLUMN IF EXISTS tags;
+
+DROP TYPE IF EXISTS TASK_DIFFICULTY;
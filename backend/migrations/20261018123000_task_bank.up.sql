@@ -0,0 +1,25 @@
+DO
+$$
+    BEGIN
+        CREATE TYPE TASK_DIFFICULTY AS ENUM ('easy', 'medium', 'hard');
+    EXCEPTION
+        WHEN duplicate_object THEN NULL;
+    END;
+$$;
+
+ALTER TABLE tasks
+    ADD COLUMN IF NOT EXISTS tags       TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS difficulty TASK_DIFFICULTY,
+    ADD COLUMN IF NOT EXISTS author_id  UUID REFERENCES users (id) ON DELETE SET NULL,
+    -- 'simple': tasks are written in more than one language, so no stemming
+    ADD COLUMN IF NOT EXISTS search     TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', title || ' ' || COALESCE(description, ''))
+        ) STORED;
+
+CREATE INDEX IF NOT EXISTS tasks_tags_idx ON tasks USING GIN (tags);
+CREATE INDEX IF NOT EXISTS tasks_search_idx ON tasks USING GIN (search);
+
+ALTER TABLE task_revisions
+    ADD COLUMN IF NOT EXISTS tags       TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS difficulty TASK_DIFFICULTY,
+    ADD COLUMN IF NOT EXISTS author_id  UUID;
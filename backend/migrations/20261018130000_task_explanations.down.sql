@@ -0,0 +1,5 @@
+ALTER TABLE task_revisions
+    DROP COLUMN IF EXISTS explanation;
+
+ALTER TABLE tasks
+    DROP COLUMN IF EXISTS explanation;
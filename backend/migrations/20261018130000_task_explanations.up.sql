@@ -0,0 +1,5 @@
+ALTER TABLE tasks
+    ADD COLUMN IF NOT EXISTS explanation TEXT;
+
+ALTER TABLE task_revisions
+    ADD COLUMN IF NOT EXISTS explanation TEXT;
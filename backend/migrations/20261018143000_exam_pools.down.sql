@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS attempt_pool_draws;
+
+DELETE FROM exam_entities
+WHERE entity_type = 'pool';
+
+ALTER TABLE IF EXISTS exam_entities
+    DROP COLUMN IF EXISTS pool_id;
+
+DROP TABLE IF EXISTS exam_pools;
+
+-- Postgres can't drop a single value from an enum, so 'pool' stays in EXAM_ENTITY_TYPE.
@@ -0,0 +1,24 @@
+ALTER TYPE EXAM_ENTITY_TYPE ADD VALUE IF NOT EXISTS 'pool';
+
+-- "draw `count` tasks out of these" entities of exams
+CREATE TABLE IF NOT EXISTS exam_pools
+(
+    id                 UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    count              INTEGER NOT NULL CHECK (count > 0),
+    task_ids           INTEGER[] NOT NULL DEFAULT '{}',
+    tag                TEXT,
+    balance_difficulty BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+ALTER TABLE IF EXISTS exam_entities
+    ADD COLUMN IF NOT EXISTS pool_id UUID REFERENCES exam_pools (id) ON DELETE CASCADE;
+
+-- tasks drawn from pools when an attempt started; they are pinned in attempt_tasks too
+CREATE TABLE IF NOT EXISTS attempt_pool_draws
+(
+    attempt_id UUID    NOT NULL REFERENCES attempts (id) ON DELETE CASCADE,
+    -- no reference: the draw outlives the pool
+    pool_id    UUID    NOT NULL,
+    task_id    INTEGER NOT NULL,
+    PRIMARY KEY (attempt_id, task_id)
+);
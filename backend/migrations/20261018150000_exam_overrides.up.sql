@@ -0,0 +1,18 @@
+-- accommodations of an exam for a single user or for users with an attribute
+CREATE TABLE IF NOT EXISTS exam_overrides
+(
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    exam_id         UUID    NOT NULL REFERENCES exams (id) ON DELETE CASCADE,
+    user_id         UUID REFERENCES users (id) ON DELETE CASCADE,
+    attribute_key   TEXT,
+    attribute_value TEXT,
+    time_multiplier DOUBLE PRECISION,
+    extra_minutes   INTEGER NOT NULL DEFAULT 0,
+    extra_attempts  INTEGER NOT NULL DEFAULT 0,
+    starts_at       TIMESTAMPTZ,
+    ends_at         TIMESTAMPTZ,
+    CHECK ((user_id IS NULL) <> (attribute_key IS NULL AND attribute_value IS NULL)),
+    CHECK ((attribute_key IS NULL) = (attribute_value IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS exam_overrides_exam_id_idx ON exam_overrides (exam_id);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS attempts_unscored_idx;
+
+ALTER TABLE IF EXISTS attempts
+    DROP COLUMN IF EXISTS scoring_retry_at,
+    DROP COLUMN IF EXISTS scoring_failures,
+    DROP COLUMN IF EXISTS scoring_error,
+    DROP COLUMN IF EXISTS scored_at;
@@ -0,0 +1,15 @@
+-- attempts are scored by a background worker once they expire
+ALTER TABLE IF EXISTS attempts
+    ADD COLUMN IF NOT EXISTS scored_at        TIMESTAMPTZ,
+    -- the last failure to score the attempt
+    ADD COLUMN IF NOT EXISTS scoring_error    TEXT,
+    ADD COLUMN IF NOT EXISTS scoring_failures INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS scoring_retry_at TIMESTAMPTZ;
+
+-- attempts scored so far, and expired ones without answers that were never going to be
+UPDATE attempts
+SET scored_at = ends_at
+WHERE ends_at <= NOW()
+  AND (scoring_data -> 'results' <> '{}'::jsonb OR answer_data -> 'answers' = '{}'::jsonb);
+
+CREATE INDEX IF NOT EXISTS attempts_unscored_idx ON attempts (ends_at) WHERE scored_at IS NULL;
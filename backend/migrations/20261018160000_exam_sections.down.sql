@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS attempt_sections;
+
+DELETE FROM exam_entities
+WHERE entity_type = 'section';
+
+ALTER TABLE IF EXISTS exam_entities
+    DROP COLUMN IF EXISTS section_id;
+
+DROP TABLE IF EXISTS exam_sections;
+
+-- Postgres can't drop a single value from an enum, so 'section' stays in EXAM_ENTITY_TYPE.
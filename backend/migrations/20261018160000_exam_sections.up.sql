@@ -0,0 +1,25 @@
+ALTER TYPE EXAM_ENTITY_TYPE ADD VALUE IF NOT EXISTS 'section';
+
+-- the tasks after a section entity, up to the next one, make up the section
+CREATE TABLE IF NOT EXISTS exam_sections
+(
+    id                   UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title                TEXT    NOT NULL,
+    -- seconds, like exams.duration; counted from when the section is first opened
+    duration             INTEGER CHECK (duration > 0),
+    locked_after_leaving BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+ALTER TABLE IF EXISTS exam_entities
+    ADD COLUMN IF NOT EXISTS section_id UUID REFERENCES exam_sections (id) ON DELETE CASCADE;
+
+-- sections an attempt has been to; the one without left_at is the current one
+CREATE TABLE IF NOT EXISTS attempt_sections
+(
+    attempt_id UUID        NOT NULL REFERENCES attempts (id) ON DELETE CASCADE,
+    -- no reference: the progress outlives the section
+    section_id UUID        NOT NULL,
+    opened_at  TIMESTAMPTZ NOT NULL,
+    left_at    TIMESTAMPTZ,
+    PRIMARY KEY (attempt_id, section_id)
+);
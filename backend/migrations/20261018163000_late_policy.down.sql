@@ -0,0 +1,5 @@
+ALTER TABLE IF EXISTS attempts
+    DROP COLUMN IF EXISTS due_at;
+
+ALTER TABLE IF EXISTS exams
+    DROP COLUMN IF EXISTS late_policy;
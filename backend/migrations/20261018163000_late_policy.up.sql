@@ -0,0 +1,14 @@
+-- optional penalties for answers that come after the deadline, see LatePolicy
+ALTER TABLE IF EXISTS exams
+    ADD COLUMN IF NOT EXISTS late_policy JSONB;
+
+-- answers after due_at are late; with a late policy the attempt ends later than that
+ALTER TABLE IF EXISTS attempts
+    ADD COLUMN IF NOT EXISTS due_at TIMESTAMPTZ;
+
+UPDATE attempts
+SET due_at = ends_at
+WHERE due_at IS NULL;
+
+ALTER TABLE IF EXISTS attempts
+    ALTER COLUMN due_at SET NOT NULL;
@@ -0,0 +1,13 @@
+-- `Code` answers waiting for their tests, run by the background worker
+CREATE TABLE IF NOT EXISTS pending_code_runs
+(
+    attempt_id    UUID        NOT NULL REFERENCES attempts (id) ON DELETE CASCADE,
+    task_id       INTEGER     NOT NULL,
+    -- tells the run apart from one queued again for the same answer meanwhile
+    queued_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- a worker running it holds it until then
+    claimed_until TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (attempt_id, task_id)
+);
+
+CREATE INDEX IF NOT EXISTS pending_code_runs_claimed_until_idx ON pending_code_runs (claimed_until);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS exams_similarity_stale_idx;
+
+ALTER TABLE IF EXISTS exams
+    DROP COLUMN IF EXISTS similarity_stale;
+
+ALTER TABLE IF EXISTS attempts
+    DROP COLUMN IF EXISTS similar_answers;
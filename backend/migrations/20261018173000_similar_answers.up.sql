@@ -0,0 +1,13 @@
+-- flagged by the similarity report at the default threshold
+ALTER TABLE IF EXISTS attempts
+    ADD COLUMN IF NOT EXISTS similar_answers BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- an attempt of the exam has been scored since its flags were last updated
+ALTER TABLE IF EXISTS exams
+    ADD COLUMN IF NOT EXISTS similarity_stale BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS exams_similarity_stale_idx ON exams (id) WHERE similarity_stale;
+
+UPDATE exams
+SET similarity_stale = TRUE
+WHERE id IN (SELECT exam_id FROM attempts WHERE scored_at IS NOT NULL);
@@ -0,0 +1,2 @@
+DROP FUNCTION IF EXISTS filter_tasks;
+DROP VIEW IF EXISTS task_usages;
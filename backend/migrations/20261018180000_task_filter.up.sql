@@ -0,0 +1,42 @@
+-- every course a task is used in: placed in an exam, in an exam's pool (by id
+-- or by tag) or in a practice
+CREATE OR REPLACE VIEW task_usages AS
+SELECT ee.task_id, tp.course_id
+FROM exam_entities ee
+    JOIN exams e ON e.id = ee.exam_id
+    JOIN topics tp ON tp.id = e.topic_id
+WHERE ee.entity_type = 'task'
+UNION ALL
+SELECT t.id AS task_id, tp.course_id
+FROM exam_entities ee
+    JOIN exam_pools p ON p.id = ee.pool_id
+    JOIN tasks t ON t.id = ANY (p.task_ids) OR (p.tag IS NOT NULL AND t.tags @> ARRAY [p.tag])
+    JOIN exams e ON e.id = ee.exam_id
+    JOIN topics tp ON tp.id = e.topic_id
+WHERE ee.entity_type = 'pool'
+UNION ALL
+SELECT pt.task_id, tp.course_id
+FROM practice_tasks pt
+    JOIN practices pr ON pr.id = pt.practice_id
+    JOIN topics tp ON tp.id = pr.topic_id;
+
+-- tasks of the bank that pass the filters of a listing, ranked by the search
+-- query when there is one; NULL leaves a filter out
+CREATE OR REPLACE FUNCTION filter_tasks(task_type TASK_TYPE, tag TEXT, difficulty TASK_DIFFICULTY,
+                                        author_id UUID, unused BOOLEAN, course_id INTEGER, q TEXT)
+    RETURNS TABLE (id INTEGER, rank REAL)
+    LANGUAGE SQL
+    STABLE
+AS
+$$
+SELECT t.id,
+       CASE WHEN $7 IS NULL THEN 0 ELSE ts_rank(t.search, websearch_to_tsquery('simple', $7)) END
+FROM tasks t
+WHERE ($1 IS NULL OR t.task_type = $1)
+  AND ($2 IS NULL OR t.tags @> ARRAY [$2])
+  AND ($3 IS NULL OR t.difficulty = $3)
+  AND ($4 IS NULL OR t.author_id = $4)
+  AND (NOT $5 OR NOT EXISTS (SELECT 1 FROM task_usages u WHERE u.task_id = t.id))
+  AND ($6 IS NULL OR EXISTS (SELECT 1 FROM task_usages u WHERE u.task_id = t.id AND u.course_id = $6))
+  AND ($7 IS NULL OR t.search @@ websearch_to_tsquery('simple', $7))
+$$;
@@ -0,0 +1,2 @@
+ALTER TABLE IF EXISTS pending_code_runs
+    DROP COLUMN IF EXISTS answer;
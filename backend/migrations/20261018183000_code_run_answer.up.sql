@@ -0,0 +1,4 @@
+-- the answer the run was queued for, so that scoring the attempt again only
+-- queues it anew once the answer changes
+ALTER TABLE IF EXISTS pending_code_runs
+    ADD COLUMN IF NOT EXISTS answer JSONB;
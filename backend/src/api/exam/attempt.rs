@@ -1,18 +1,18 @@
 use crate::api::exam::ExamState;
 use crate::domain::account::model::UserRole;
-use crate::domain::exam::model::{Exam, ExamExtendedEntity};
+use crate::domain::exam::model::{Exam, ExamExtendedEntity, SectionProgress};
 use crate::dto::exam::{
     AttemptListingQuery, AttemptVisibilityPatchRequest, ExamAttempt, ExamAttemptAdminSchema,
-    ExamAttemptSchema, ExamAttemptsListDTO, TaskAnswerDTO, TaskVerdictPatchRequest,
+    ExamAttemptSchema, ExamAttemptsListDTO, FileUploadRequestDTO, FileUploadSlotDTO,
+    LatePenaltyWaiverRequest, RubricGradeRequest, SimilarityQuery, TaskAnswerDTO,
+    TaskSimilarityDTO, TaskVerdictPatchRequest,
 };
-use crate::dto::task::TaskVerdict;
 use crate::errors::LMSError;
 use crate::infrastructure::jwt::AccessTokenClaim;
-use crate::utils::ValidatedQuery;
+use crate::utils::{ValidatedJson, ValidatedQuery};
 use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
-use std::cmp::max;
 use uuid::Uuid;
 
 /// Start new attempt
@@ -84,7 +84,8 @@ pub async fn stop_attempt(
         (status = 200, description = "Successfully patched"),
         (status = 400, description = "Wrong data format"),
         (status = 401, description = "No auth data found"),
-        (status = 404, description = "Exam or attempt not found")
+        (status = 404, description = "Exam or attempt not found"),
+        (status = 409, description = "Task's section isn't the one the attempt is in")
     ),
     security(
         ("BearerAuth" = [])
@@ -103,6 +104,99 @@ pub async fn patch_attempt(
     Ok(StatusCode::OK)
 }
 
+/// Get a form to upload a file answer with
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/{exam_id}/attempt/upload",
+    request_body = FileUploadRequestDTO,
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = FileUploadSlotDTO, description = "Upload form for the file; answer the task with its file_id once uploaded"),
+        (status = 400, description = "Wrong data format, not a file upload task or a content type the task doesn't take"),
+        (status = 401, description = "No auth data found"),
+        (status = 404, description = "Exam, task or active attempt not found"),
+        (status = 409, description = "Task's section isn't the one the attempt is in")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn create_upload_slot(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+    ValidatedJson(request): ValidatedJson<FileUploadRequestDTO>,
+) -> Result<Json<FileUploadSlotDTO>, LMSError> {
+    let slot = state
+        .exam_service
+        .create_upload_slot(exam_id, claims.sub, request.task_id, &request.content_type)
+        .await?;
+    Ok(Json(slot))
+}
+
+/// Get progress of the last attempt through exam's sections
+#[utoipa::path(
+    get,
+    tag = "Exam",
+    path = "/{exam_id}/attempt/sections",
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = Vec<SectionProgress>, description = "Sections in exam order; empty for an exam without them"),
+        (status = 401, description = "No auth data found"),
+        (status = 404, description = "Exam or attempt not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_section_progress(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+) -> Result<Json<Vec<SectionProgress>>, LMSError> {
+    let progress = state
+        .exam_service
+        .get_section_progress(exam_id, claims.sub)
+        .await?;
+    Ok(Json(progress))
+}
+
+/// Move active attempt to a section, leaving the current one
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/{exam_id}/attempt/sections/{section_id}",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("section_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = Vec<SectionProgress>, description = "Successfully moved to the section"),
+        (status = 401, description = "No auth data found"),
+        (status = 404, description = "Exam, section or active attempt not found"),
+        (status = 409, description = "Section is closed or previous sections haven't been opened yet")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn move_to_section(
+    claims: AccessTokenClaim,
+    Path((exam_id, section_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+) -> Result<Json<Vec<SectionProgress>>, LMSError> {
+    let progress = state
+        .exam_service
+        .move_to_section(exam_id, claims.sub, section_id)
+        .await?;
+    Ok(Json(progress))
+}
+
 /// Get last attempt
 #[utoipa::path(
     get,
@@ -126,30 +220,17 @@ pub async fn get_last_attempt(
     Path(exam_id): Path<Uuid>,
     State(state): State<ExamState>,
 ) -> Result<Json<ExamAttemptSchema>, LMSError> {
-    let mut attempt: ExamAttemptSchema = state
+    let attempt = state
         .exam_service
         .get_user_last_attempt_in_exam(exam_id, claims.sub)
-        .await?
-        .into();
-    let entities = state.exam_service.get_entities(exam_id).await?;
-    let tasks = entities
-        .iter()
-        .filter_map(|e| match e {
-            ExamExtendedEntity::Task { task } => Some(task),
-            ExamExtendedEntity::Text { .. } => None,
-        })
-        .collect::<Vec<_>>();
+        .await?;
+    let tasks = state.exam_service.get_attempt_tasks(&attempt).await?;
+    let mut attempt: ExamAttemptSchema = attempt.into();
 
     attempt.max_score = tasks.iter().map(|t| t.points).sum();
     if let Some(scoring_data) = attempt.scoring_data.as_mut() {
         if scoring_data.show_results {
-            attempt.score = Some(
-                scoring_data
-                    .results
-                    .values()
-                    .map(TaskVerdict::score)
-                    .sum::<f64>(),
-            );
+            attempt.score = Some(scoring_data.total_score());
         } else {
             attempt.scoring_data = None;
         }
@@ -176,8 +257,6 @@ pub async fn get_last_attempt(
         ("BearerAuth" = [])
     )
 )]
-#[allow(clippy::cast_sign_loss)]
-#[allow(clippy::cast_possible_wrap)]
 pub async fn get_self_exam_attempts(
     claims: AccessTokenClaim,
     Path(exam_id): Path<Uuid>,
@@ -195,27 +274,17 @@ pub async fn get_self_exam_attempts(
         .iter()
         .map(|x| ExamAttemptSchema::from(x.clone()))
         .collect();
-    let entities = state.exam_service.get_entities(exam_id).await?;
-    let tasks = entities
-        .iter()
-        .filter_map(|e| match e {
-            ExamExtendedEntity::Task { task } => Some(task),
-            ExamExtendedEntity::Text { .. } => None,
-        })
-        .collect::<Vec<_>>();
+    let attempt_ids: Vec<Uuid> = attempts.iter().map(|a| a.id).collect();
+    let attempt_tasks = state.exam_service.get_attempts_tasks(&attempt_ids).await?;
+    let limits = state.exam_service.get_limits(&exam, claims.sub).await?;
 
-    let max_score: i64 = tasks.iter().map(|t| t.points).sum();
     for attempt in &mut attempts {
-        attempt.max_score = max_score;
+        attempt.max_score = attempt_tasks
+            .get(&attempt.id)
+            .map_or(0, |revisions| revisions.iter().map(|r| r.task.points).sum());
         if let Some(scoring_data) = attempt.scoring_data.as_mut() {
             if scoring_data.show_results {
-                attempt.score = Some(
-                    scoring_data
-                        .results
-                        .values()
-                        .map(TaskVerdict::score)
-                        .sum::<f64>(),
-                );
+                attempt.score = Some(scoring_data.total_score());
             } else {
                 attempt.scoring_data = None;
             }
@@ -223,8 +292,8 @@ pub async fn get_self_exam_attempts(
     }
 
     Ok(Json(ExamAttemptsListDTO {
-        attempts_left: max(i64::from(exam.tries_count) - attempts.len() as i64, 0),
-        ran_out_of_attempts: exam.tries_count != 0 && attempts.len() >= exam.tries_count as usize,
+        attempts_left: limits.attempts_left(attempts.len()),
+        ran_out_of_attempts: limits.ran_out_of_attempts(attempts.len()),
         attempts,
     }))
 }
@@ -272,17 +341,112 @@ pub async fn get_attempts_by_exam(
         .await?;
     let user_ids: Vec<Uuid> = exam_attempts.iter().map(|a| a.user_id).collect();
     let usernames = state.exam_service.get_usernames(&user_ids).await?;
-    let attempts: Vec<ExamAttemptAdminSchema> = exam_attempts
-        .into_iter()
-        .map(|a| {
-            let username = usernames.get(&a.user_id).cloned().unwrap_or_default();
-            ExamAttemptAdminSchema::from_attempt(a, username)
-        })
-        .collect();
+    let similar = state
+        .exam_service
+        .attempts_with_similar_answers(exam_id)
+        .await?;
+    let mut scoring_errors = state.exam_service.scoring_errors(exam_id).await?;
+    let mut attempts: Vec<ExamAttemptAdminSchema> = Vec::with_capacity(exam_attempts.len());
+    for attempt in exam_attempts {
+        let username = usernames.get(&attempt.user_id).cloned().unwrap_or_default();
+        let files = state.exam_service.attempt_file_links(&attempt).await?;
+        let similar_answers = similar.contains(&attempt.id);
+        let scoring_error = scoring_errors.remove(&attempt.id);
+        attempts.push(ExamAttemptAdminSchema::from_attempt(
+            attempt,
+            username,
+            files,
+            similar_answers,
+            scoring_error,
+        ));
+    }
 
     Ok(Json(attempts))
 }
 
+/// Similar answers of different learners to the exam's free-text tasks
+#[utoipa::path(
+    get,
+    tag = "Exam",
+    path = "/{exam_id}/admin/similarity",
+    description = "Compares every pair of `LongText` answers (and `ShortText` ones of tasks \
+                   without auto grading) of different learners by word 3-gram Jaccard \
+                   similarity and reports the pairs from `threshold` on, per task.",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("threshold" = Option<f64>, Query, description = "From 0 to 1, default 0.5")
+    ),
+    responses(
+        (status = 200, body = Vec<TaskSimilarityDTO>, description = "Similar answer pairs by task"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You have no permissions (teacher / admin) to access this endpoint"),
+        (status = 404, description = "Exam not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_similarity_report(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+    ValidatedQuery(query): ValidatedQuery<SimilarityQuery>,
+) -> Result<Json<Vec<TaskSimilarityDTO>>, LMSError> {
+    if !matches!(claims.role, UserRole::Teacher | UserRole::Admin) {
+        return Err(LMSError::Forbidden(
+            "Student can't access admin endpoints".to_string(),
+        ));
+    }
+    let _ = state
+        .exam_service
+        .get_exam(exam_id, claims.sub, claims.role)
+        .await?;
+    let report = state
+        .exam_service
+        .similarity_report(exam_id, query.threshold)
+        .await?;
+
+    Ok(Json(report))
+}
+
+/// Get exam entities in the order the attempt's owner saw them
+#[utoipa::path(
+    get,
+    tag = "Exam",
+    path = "/{exam_id}/admin/attempt/{attempt_id}/entities",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("attempt_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = Vec<ExamExtendedEntity>, description = "Entities with shuffled options and order as shown in the attempt"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You have no permissions (teacher / admin) to access this endpoint"),
+        (status = 404, description = "Exam / attempt not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_attempt_entities(
+    claims: AccessTokenClaim,
+    Path((exam_id, attempt_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+) -> Result<Json<Vec<ExamExtendedEntity>>, LMSError> {
+    if !matches!(claims.role, UserRole::Teacher | UserRole::Admin) {
+        return Err(LMSError::Forbidden(
+            "Student can't access admin endpoints".to_string(),
+        ));
+    }
+    let entities = state
+        .exam_service
+        .get_attempt_entities_by_id(exam_id, attempt_id)
+        .await?;
+
+    Ok(Json(entities))
+}
+
 /// Patch verdict for user's attempt
 #[utoipa::path(
     patch,
@@ -328,6 +492,92 @@ pub async fn patch_attempt_task_verdict(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Grade a task of user's attempt by the task's rubric
+#[utoipa::path(
+    patch,
+    tag = "Exam",
+    path = "/{exam_id}/admin/attempt/rubric/{attempt_id}",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("attempt_id" = Uuid, Path)
+    ),
+    request_body = RubricGradeRequest,
+    responses(
+        (status = 204, description = "Successfully graded; the verdict and the filled-in rubric are saved"),
+        (status = 400, description = "Wrong data format / the task has no rubric / invalid levels"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You have no permissions (teacher / admin) to access this endpoint"),
+        (status = 404, description = "Exam / attempt / task not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn grade_attempt_task_by_rubric(
+    claims: AccessTokenClaim,
+    Path((exam_id, attempt_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+    ValidatedJson(request): ValidatedJson<RubricGradeRequest>,
+) -> Result<StatusCode, LMSError> {
+    if !matches!(claims.role, UserRole::Teacher | UserRole::Admin) {
+        return Err(LMSError::Forbidden(
+            "Student can't access admin endpoints".to_string(),
+        ));
+    }
+    state
+        .exam_service
+        .grade_attempt_by_rubric(
+            attempt_id,
+            exam_id,
+            request.task_id,
+            &request.levels,
+            request.comment.as_deref(),
+        )
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Waive the late penalty of user's attempt, or take it back
+#[utoipa::path(
+    patch,
+    tag = "Exam",
+    path = "/{exam_id}/admin/attempt/late_penalty/{attempt_id}",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("attempt_id" = Uuid, Path)
+    ),
+    request_body = LatePenaltyWaiverRequest,
+    responses(
+        (status = 204, description = "Successfully changed the waiver; the penalty stays listed in scoring data"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You have no permissions (teacher / admin) to access this endpoint"),
+        (status = 404, description = "Exam / attempt not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn waive_late_penalty(
+    claims: AccessTokenClaim,
+    Path((exam_id, attempt_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+    Json(request): Json<LatePenaltyWaiverRequest>,
+) -> Result<StatusCode, LMSError> {
+    if !matches!(claims.role, UserRole::Teacher | UserRole::Admin) {
+        return Err(LMSError::Forbidden(
+            "Student can't access admin endpoints".to_string(),
+        ));
+    }
+    state
+        .exam_service
+        .waive_late_penalty(attempt_id, exam_id, request.waived)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Change `show_results` for an attempt by id
 #[utoipa::path(
     patch,
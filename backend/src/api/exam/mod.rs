@@ -1,4 +1,5 @@
 mod attempt;
+mod overrides;
 mod routes;
 
 use crate::api::exam::attempt::{
@@ -10,6 +11,7 @@ use crate::domain::exam::service::ExamService;
 use crate::infrastructure::jwt::JWT;
 use attempt::*;
 use axum_macros::FromRef;
+use overrides::*;
 use routes::*;
 use std::sync::Arc;
 use utoipa_axum::router::OpenApiRouter;
@@ -37,12 +39,26 @@ pub fn configure(
         .routes(routes!(create, get_by_id, delete_exam, update_exam))
         .routes(routes!(update_exam_entities, start_new_attempt))
         .routes(routes!(stop_attempt, patch_attempt, get_last_attempt))
+        .routes(routes!(create_upload_slot))
+        .routes(routes!(get_section_progress))
+        .routes(routes!(move_to_section))
         .routes(routes!(get_entities))
         .routes(routes!(get_self_exam_attempts))
         .routes(routes!(create_text, update_text, delete_text))
+        .routes(routes!(create_pool, update_pool, delete_pool))
+        .routes(routes!(create_section, update_section, delete_section))
+        .routes(routes!(get_overrides, create_override))
+        .routes(routes!(update_override, delete_override))
         .routes(routes!(get_attempts_by_exam, patch_attempt_task_verdict))
+        .routes(routes!(grade_attempt_task_by_rubric))
+        .routes(routes!(waive_late_penalty))
+        .routes(routes!(get_attempt_entities))
+        .routes(routes!(get_similarity_report))
         .routes(routes!(change_visibility_for_attempt_by_id))
         .routes(routes!(change_visibility_for_attempts_by_exam))
         .routes(routes!(score_unscored))
+        .routes(routes!(regrade))
+        .routes(routes!(export_qti))
+        .routes(routes!(import_qti))
         .with_state(state)
 }
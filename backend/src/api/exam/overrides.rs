@@ -0,0 +1,151 @@
+use crate::api::exam::ExamState;
+use crate::domain::account::model::UserRole;
+use crate::domain::exam::model::ExamOverride;
+use crate::dto::exam::ExamOverrideUpsertDTO;
+use crate::errors::LMSError;
+use crate::infrastructure::jwt::AccessTokenClaim;
+use crate::utils::ValidatedJson;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+/// List exam's overrides for users and attribute groups
+#[utoipa::path(
+    get,
+    tag = "Exam",
+    path = "/{exam_id}/admin/overrides",
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = Vec<ExamOverride>, description = "Personal overrides first, then group ones"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't view overrides"),
+        (status = 404, description = "Exam not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_overrides(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+) -> Result<Json<Vec<ExamOverride>>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't view overrides".to_string()));
+    }
+    let overrides = state.exam_service.get_overrides(exam_id).await?;
+    Ok(Json(overrides))
+}
+
+/// Create an override of exam's limits. Attempts already started keep their end.
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/{exam_id}/admin/overrides",
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    request_body = ExamOverrideUpsertDTO,
+    responses(
+        (status = 201, body = ExamOverride, description = "Successfully created override"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't create overrides"),
+        (status = 404, description = "Exam or user not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn create_override(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+    ValidatedJson(data): ValidatedJson<ExamOverrideUpsertDTO>,
+) -> Result<(StatusCode, Json<ExamOverride>), LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden(
+            "You can't create overrides".to_string(),
+        ));
+    }
+    let exam_override = state.exam_service.create_override(exam_id, data).await?;
+    Ok((StatusCode::CREATED, Json(exam_override)))
+}
+
+/// Update an override of exam's limits
+#[utoipa::path(
+    put,
+    tag = "Exam",
+    path = "/{exam_id}/admin/overrides/{override_id}",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("override_id" = Uuid, Path)
+    ),
+    request_body = ExamOverrideUpsertDTO,
+    responses(
+        (status = 200, body = ExamOverride, description = "Successfully updated override"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't update overrides"),
+        (status = 404, description = "Override or user not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn update_override(
+    claims: AccessTokenClaim,
+    Path((exam_id, override_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+    ValidatedJson(data): ValidatedJson<ExamOverrideUpsertDTO>,
+) -> Result<Json<ExamOverride>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden(
+            "You can't update overrides".to_string(),
+        ));
+    }
+    let exam_override = state
+        .exam_service
+        .update_override(exam_id, override_id, data)
+        .await?;
+    Ok(Json(exam_override))
+}
+
+/// Delete an override of exam's limits
+#[utoipa::path(
+    delete,
+    tag = "Exam",
+    path = "/{exam_id}/admin/overrides/{override_id}",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("override_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 204, description = "Successfully deleted override"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't delete overrides"),
+        (status = 404, description = "Override not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn delete_override(
+    claims: AccessTokenClaim,
+    Path((exam_id, override_id)): Path<(Uuid, Uuid)>,
+    State(state): State<ExamState>,
+) -> Result<StatusCode, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden(
+            "You can't delete overrides".to_string(),
+        ));
+    }
+    let () = state
+        .exam_service
+        .delete_override(exam_id, override_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,20 +1,21 @@
 use crate::api::exam::ExamState;
 use crate::domain::account::model::UserRole;
-use crate::domain::exam::model::{Exam, ExamEntity, ExamExtendedEntity, TextEntity};
-use crate::domain::task::model::TaskConfig;
+use crate::domain::exam::model::{Exam, ExamEntity, PoolEntity, SectionEntity, TextEntity};
 use crate::dto::exam::{
-    CreateExamResponseDTO, ExamScoringAmount, TextUpsertDTO, UpsertExamRequestDTO,
+    AttemptRegradeDTO, CreateExamResponseDTO, ExamImportQuery, ExamImportReportDTO,
+    ExamScoringAmount, PoolUpsertDTO, RegradeQuery, SectionUpsertDTO, TextUpsertDTO,
+    UpsertExamRequestDTO,
 };
 use crate::dto::task::PubExamExtendedEntity;
 use crate::errors::LMSError;
 use crate::infrastructure::jwt::AccessTokenClaim;
-use crate::utils::ValidatedJson;
+use crate::utils::{ValidatedJson, ValidatedQuery};
 use axum::Json;
+use axum::body::{Body, Bytes};
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
+use axum::response::Response;
 use chrono::Utc;
-use rand::prelude::SliceRandom;
-use rand::rng;
 use uuid::Uuid;
 
 /// Create new exam
@@ -178,6 +179,133 @@ pub async fn score_unscored(
     Ok(Json(ExamScoringAmount { amount }))
 }
 
+/// Regrade finished attempts against the latest task revisions
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/{exam_id}/regrade",
+    params(
+        ("exam_id" = Uuid, Path),
+        ("commit" = Option<bool>, Query, description = "Apply the changes instead of only previewing them")
+    ),
+    responses(
+        (status = 200, body = Vec<AttemptRegradeDTO>, description = "Attempts whose task revisions change, with old and new scores"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "User has no permission to regrade exam"),
+        (status = 404, description = "Exam not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn regrade(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+    ValidatedQuery(query): ValidatedQuery<RegradeQuery>,
+) -> Result<Json<Vec<AttemptRegradeDTO>>, LMSError> {
+    // TODO: ACL for tasks (owners)
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't regrade exams".to_string()));
+    }
+    let _ = state
+        .exam_service
+        .get_exam(exam_id, claims.sub, claims.role)
+        .await?;
+    let regraded = state
+        .exam_service
+        .regrade_against_latest(exam_id, query.commit)
+        .await?;
+    Ok(Json(regraded))
+}
+
+/// Export exam as a QTI 2.1 package
+#[utoipa::path(
+    get,
+    tag = "Exam",
+    path = "/{exam_id}/qti",
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, description = "QTI 2.1 content package (application/zip)"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "User has no permission to export exam"),
+        (status = 404, description = "Exam not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn export_qti(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ExamState>,
+) -> Result<Response, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't export exams".to_string()));
+    }
+    let _ = state
+        .exam_service
+        .get_exam(exam_id, claims.sub, claims.role)
+        .await?;
+    let file = state.exam_service.export_qti(exam_id).await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, file.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file.filename),
+        )
+        .body(Body::from(file.bytes))
+        .map_err(|e| LMSError::ServerError(e.to_string()))
+}
+
+/// Import exam from a QTI 2.1 package
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/qti",
+    description = "Converts the assessment test of a QTI 2.1 package into an exam: items become \
+                   tasks and rubric blocks become texts, in test order. Reports what was lost \
+                   for each item. Dry run unless `commit` is set.",
+    params(
+        ("topic_id" = i32, Query, description = "Topic of the new exam"),
+        ("commit" = Option<bool>, Query, description = "Create the exam (default: dry run)")
+    ),
+    request_body(
+        content = Vec<u8>,
+        content_type = "application/zip",
+        description = "The package"
+    ),
+    responses(
+        (status = 200, body = ExamImportReportDTO, description = "Import report"),
+        (status = 400, description = "The package can't be read"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "User has no permission to create exams"),
+        (status = 404, description = "Topic not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn import_qti(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    ValidatedQuery(query): ValidatedQuery<ExamImportQuery>,
+    body: Bytes,
+) -> Result<Json<ExamImportReportDTO>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't import exams".to_string()));
+    }
+    let report = state
+        .exam_service
+        .import_qti(&body, query.topic_id, query.commit, claims.sub, claims.role)
+        .await?;
+    Ok(Json(report))
+}
+
 /// Update exam's entities
 #[utoipa::path(
     put,
@@ -247,33 +375,22 @@ pub async fn get_entities(
         || attempts.iter().any(|att| att.scoring_data.show_results)
         || matches!(claims.role, UserRole::Admin | UserRole::Teacher)
     {
-        let entities = state.exam_service.get_entities(exam_id).await?;
-        let mut public_entities: Vec<PubExamExtendedEntity> = Vec::new();
-        for entity in entities {
-            match entity.clone() {
-                ExamExtendedEntity::Task { mut task } => {
-                    match &mut task.configuration {
-                        TaskConfig::SingleChoice {
-                            options, shuffle, ..
-                        }
-                        | TaskConfig::MultipleChoice {
-                            options, shuffle, ..
-                        } if *shuffle => {
-                            options.shuffle(&mut rng());
-                        }
-                        TaskConfig::Ordering { items, .. } => {
-                            items.shuffle(&mut rng());
-                        }
-                        _ => {}
-                    }
-                    public_entities.push(PubExamExtendedEntity::Task { task: task.into() });
-                }
-                ExamExtendedEntity::Text { .. } => {
-                    public_entities.push(entity.into());
-                }
+        // the active attempt, or the latest one when reviewing results
+        let attempt = attempts
+            .iter()
+            .find(|att| att.ends_at > Utc::now())
+            .or_else(|| attempts.last());
+        let entities = match attempt {
+            Some(attempt) if matches!(claims.role, UserRole::Student) => {
+                state
+                    .exam_service
+                    .get_opened_attempt_entities(attempt)
+                    .await?
             }
-        }
-        return Ok(Json(public_entities));
+            Some(attempt) => state.exam_service.get_attempt_entities(attempt).await?,
+            None => state.exam_service.get_entities(exam_id).await?,
+        };
+        return Ok(Json(entities.into_iter().map(Into::into).collect()));
     }
     Err(LMSError::Forbidden(
         "You have no permission to view entities".to_string(),
@@ -368,3 +485,184 @@ pub async fn delete_text(
     let () = state.exam_service.delete_text(text_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Create new task pool
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/pool/new",
+    request_body = PoolUpsertDTO,
+    responses(
+        (status = 201, description = "Successfully created pool", body = PoolEntity),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't create pools")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn create_pool(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    ValidatedJson(pool): ValidatedJson<PoolUpsertDTO>,
+) -> Result<(StatusCode, Json<PoolEntity>), LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't create pools".to_string()));
+    }
+    let pool_entity = state.exam_service.create_pool(pool).await?;
+    Ok((StatusCode::CREATED, Json(pool_entity)))
+}
+
+/// Update task pool by id. Attempts already started keep their draw.
+#[utoipa::path(
+    put,
+    tag = "Exam",
+    path = "/pool/{pool_id}",
+    params(
+        ("pool_id" = Uuid, Path)
+    ),
+    request_body = PoolUpsertDTO,
+    responses(
+        (status = 200, description = "Successfully updated pool", body = PoolEntity),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't update pools"),
+        (status = 404, description = "Pool not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn update_pool(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    Path(pool_id): Path<Uuid>,
+    ValidatedJson(pool): ValidatedJson<PoolUpsertDTO>,
+) -> Result<Json<PoolEntity>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't update pools".to_string()));
+    }
+    let pool_entity = state.exam_service.update_pool(pool_id, pool).await?;
+    Ok(Json(pool_entity))
+}
+
+/// Delete task pool by id
+#[utoipa::path(
+    delete,
+    tag = "Exam",
+    path = "/pool/{pool_id}",
+    responses(
+        (status = 204, description = "Successfully deleted pool"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't delete pools"),
+        (status = 404, description = "Pool not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn delete_pool(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    Path(pool_id): Path<Uuid>,
+) -> Result<StatusCode, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't delete pools".to_string()));
+    }
+    let () = state.exam_service.delete_pool(pool_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Create new exam section
+#[utoipa::path(
+    post,
+    tag = "Exam",
+    path = "/section/new",
+    request_body = SectionUpsertDTO,
+    responses(
+        (status = 201, description = "Successfully created section", body = SectionEntity),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't create sections")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn create_section(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    ValidatedJson(section): ValidatedJson<SectionUpsertDTO>,
+) -> Result<(StatusCode, Json<SectionEntity>), LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't create sections".to_string()));
+    }
+    let section_entity = state.exam_service.create_section(section).await?;
+    Ok((StatusCode::CREATED, Json(section_entity)))
+}
+
+/// Update exam section by id. Sections already opened in attempts run on the new time limit.
+#[utoipa::path(
+    put,
+    tag = "Exam",
+    path = "/section/{section_id}",
+    params(
+        ("section_id" = Uuid, Path)
+    ),
+    request_body = SectionUpsertDTO,
+    responses(
+        (status = 200, description = "Successfully updated section", body = SectionEntity),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't update sections"),
+        (status = 404, description = "Section not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn update_section(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    Path(section_id): Path<Uuid>,
+    ValidatedJson(section): ValidatedJson<SectionUpsertDTO>,
+) -> Result<Json<SectionEntity>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't update sections".to_string()));
+    }
+    let section_entity = state
+        .exam_service
+        .update_section(section_id, section)
+        .await?;
+    Ok(Json(section_entity))
+}
+
+/// Delete exam section by id
+#[utoipa::path(
+    delete,
+    tag = "Exam",
+    path = "/section/{section_id}",
+    responses(
+        (status = 204, description = "Successfully deleted section"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "You can't delete sections"),
+        (status = 404, description = "Section not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn delete_section(
+    claims: AccessTokenClaim,
+    State(state): State<ExamState>,
+    Path(section_id): Path<Uuid>,
+) -> Result<StatusCode, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't delete sections".to_string()));
+    }
+    let () = state.exam_service.delete_section(section_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -22,6 +22,7 @@ pub fn configure(report_service: ReportService, jwt: Arc<JWT>) -> OpenApiRouter
 
     OpenApiRouter::new()
         .routes(routes!(get_exam_gradebook))
+        .routes(routes!(get_exam_item_analysis))
         .routes(routes!(export_exam_results))
         .with_state(state)
 }
@@ -9,8 +9,12 @@ use uuid::Uuid;
 
 use crate::infrastructure::jwt::AccessTokenClaim;
 use crate::{
-    api::report::ReportState, domain::account::model::UserRole, domain::report::model::Gradebook,
-    dto::report::ExportQuery, errors::LMSError, utils::ValidatedQuery,
+    api::report::ReportState,
+    domain::account::model::UserRole,
+    domain::report::model::{Gradebook, ItemAnalysis},
+    dto::report::ExportQuery,
+    errors::LMSError,
+    utils::ValidatedQuery,
 };
 
 /// Exam gradebook: per-attempt scores plus summary statistics.
@@ -50,6 +54,44 @@ pub async fn get_exam_gradebook(
     Ok(Json(gradebook))
 }
 
+/// Item analysis of an exam: difficulty, discrimination, option picks and
+/// common wrong answers of every task.
+#[utoipa::path(
+    get,
+    tag = "Report",
+    path = "/exam/{exam_id}/items",
+    params(
+        ("exam_id" = Uuid, Path)
+    ),
+    responses(
+        (status = 200, body = ItemAnalysis, description = "Item statistics of the exam's tasks"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "Only teachers/admins can view reports"),
+        (status = 404, description = "Exam not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_exam_item_analysis(
+    claims: AccessTokenClaim,
+    Path(exam_id): Path<Uuid>,
+    State(state): State<ReportState>,
+) -> Result<Json<ItemAnalysis>, LMSError> {
+    if !matches!(claims.role, UserRole::Teacher | UserRole::Admin) {
+        return Err(LMSError::Forbidden(
+            "Only teachers and admins can view reports".to_string(),
+        ));
+    }
+
+    let analysis = state
+        .report_service
+        .exam_item_analysis(exam_id, claims.sub, claims.role)
+        .await?;
+
+    Ok(Json(analysis))
+}
+
 /// Export exam results as a downloadable file (CSV or XLSX).
 #[utoipa::path(
     get,
@@ -60,7 +102,7 @@ pub async fn get_exam_gradebook(
         ("format" = Option<String>, Query, description = "csv (default) or xlsx")
     ),
     responses(
-        (status = 200, description = "Exam results file (text/csv or xlsx)"),
+        (status = 200, description = "Exam results file (text/csv or xlsx) with the item analysis after the gradebook"),
         (status = 401, description = "No auth data found"),
         (status = 403, description = "Only teachers/admins can export results"),
         (status = 404, description = "Exam not found")
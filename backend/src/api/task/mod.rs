@@ -30,5 +30,8 @@ pub fn configure(
         .routes(routes!(create, get_by_id, delete_task, update_task))
         .routes(routes!(list_tasks))
         .routes(routes!(get_by_id_admin))
+        .routes(routes!(get_revisions))
+        .routes(routes!(import_tasks))
+        .routes(routes!(export_tasks))
         .with_state(state)
 }
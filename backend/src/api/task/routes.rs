@@ -1,17 +1,19 @@
 use crate::api::task::TaskState;
 use crate::domain::account::model::UserRole;
-use crate::domain::task::model::{Task, TaskConfig};
+use crate::domain::task::model::{Task, TaskDifficulty, TaskRevision, TaskType};
 use crate::dto::task::{
-    CreateTaskResponseDTO, LimitOffsetDTO, PublicTaskDTO, UpsertTaskRequestDTO,
+    CreateTaskResponseDTO, PublicTaskDTO, TaskExportRequestDTO, TaskFilterDTO, TaskImportQuery,
+    TaskImportReportDTO, TaskListResponseDTO, UpsertTaskRequestDTO,
 };
 use crate::errors::LMSError;
 use crate::infrastructure::jwt::AccessTokenClaim;
 use crate::utils::{ValidatedJson, ValidatedQuery};
 use axum::Json;
+use axum::body::{Body, Bytes};
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use rand::prelude::SliceRandom;
-use rand::rng;
+use axum::http::{StatusCode, header};
+use axum::response::Response;
+use rand::{random, rng};
 
 /// Create new task
 #[utoipa::path(
@@ -38,7 +40,7 @@ pub async fn create(
         return Err(LMSError::Forbidden("You can't create tasks".to_string()));
     }
 
-    let task = state.task_service.create_task(payload).await?;
+    let task = state.task_service.create_task(payload, claims.sub).await?;
 
     Ok((StatusCode::CREATED, Json(task.into())))
 }
@@ -71,18 +73,12 @@ pub async fn get_by_id(
             "Students can't access task from catalogue".to_string(),
         ));
     }
-    let mut task = state.task_service.get_task(task_id).await?;
-    match &mut task.configuration {
-        TaskConfig::SingleChoice {
-            options, shuffle, ..
-        }
-        | TaskConfig::MultipleChoice {
-            options, shuffle, ..
-        } if *shuffle => {
-            options.shuffle(&mut rng());
-        }
-        _ => {}
-    }
+    let mut task = state
+        .task_service
+        .get_task(task_id)
+        .await?
+        .instantiate(random())?;
+    task.configuration.shuffle(&mut rng());
     Ok(Json(task.into()))
 }
 
@@ -118,6 +114,38 @@ pub async fn get_by_id_admin(
     Ok(Json(task))
 }
 
+/// List task revisions (admin view)
+#[utoipa::path(
+    get,
+    tag = "Task",
+    path = "/{task_id}/revisions",
+    params(
+        ("task_id" = i32, Path)
+    ),
+    responses(
+        (status = 200, body = Vec<TaskRevision>, description = "Task revisions, oldest first"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "Students can't access task revisions"),
+        (status = 404, description = "Task not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn get_revisions(
+    claims: AccessTokenClaim,
+    State(state): State<TaskState>,
+    Path(task_id): Path<i32>,
+) -> Result<Json<Vec<TaskRevision>>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden(
+            "Students can't access task revisions".to_string(),
+        ));
+    }
+    let revisions = state.task_service.get_task_revisions(task_id).await?;
+    Ok(Json(revisions))
+}
+
 /// Delete task by id
 #[utoipa::path(
     delete,
@@ -191,13 +219,21 @@ pub async fn update_task(
     get,
     tag = "Task",
     path = "/list",
-    description = "List all tasks. Limit <= 20.",
+    description = "Search the task bank. Limit <= 20; every filter that is set must match.",
     params(
         ("limit" = i32, Query),
-        ("offset" = i32, Query)
+        ("offset" = i32, Query),
+        ("task_type" = Option<TaskType>, Query),
+        ("tag" = Option<String>, Query, description = "Tasks having this tag"),
+        ("difficulty" = Option<TaskDifficulty>, Query),
+        ("author_id" = Option<uuid::Uuid>, Query),
+        ("unused" = Option<bool>, Query, description = "Only tasks in no exam and no practice"),
+        ("course_id" = Option<i32>, Query, description = "Only tasks used in this course's exams or practices"),
+        ("q" = Option<String>, Query, description = "Full-text search over title and description")
     ),
     responses(
-        (status = 200, description = "Successfully got tasks list"),
+        (status = 200, body = TaskListResponseDTO, description = "Successfully got tasks list"),
+        (status = 400, description = "Wrong query"),
         (status = 401, description = "No auth data found"),
         (status = 403, description = "User has no permission to view tasks list")
     ),
@@ -208,15 +244,94 @@ pub async fn update_task(
 pub async fn list_tasks(
     claims: AccessTokenClaim,
     State(state): State<TaskState>,
-    ValidatedQuery(query): ValidatedQuery<LimitOffsetDTO>,
-) -> Result<Json<Vec<Task>>, LMSError> {
+    ValidatedQuery(query): ValidatedQuery<TaskFilterDTO>,
+) -> Result<Json<TaskListResponseDTO>, LMSError> {
     // TODO: ACL for tasks (owners)
     if matches!(claims.role, UserRole::Student) {
         return Err(LMSError::Forbidden("You can't list tasks".to_string()));
     }
-    let task = state
+    let task = state.task_service.get_tasks(&query).await?;
+    Ok(task.into())
+}
+
+/// Import tasks from Moodle XML, GIFT or QTI
+#[utoipa::path(
+    post,
+    tag = "Task",
+    path = "/import",
+    description = "Converts the questions of a Moodle XML, GIFT or QTI 2.1 package file into tasks \
+                   and reports what was lost for each one. Dry run unless `commit` is set.",
+    params(
+        ("format" = crate::dto::task::InterchangeFormatDTO, Query),
+        ("commit" = Option<bool>, Query, description = "Create the tasks (default: dry run)")
+    ),
+    request_body(
+        content = Vec<u8>,
+        content_type = "application/octet-stream",
+        description = "The file contents"
+    ),
+    responses(
+        (status = 200, body = TaskImportReportDTO, description = "Per-question import report"),
+        (status = 400, description = "The file can't be parsed"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "User has no permission to create tasks")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn import_tasks(
+    claims: AccessTokenClaim,
+    State(state): State<TaskState>,
+    ValidatedQuery(query): ValidatedQuery<TaskImportQuery>,
+    body: Bytes,
+) -> Result<Json<TaskImportReportDTO>, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't import tasks".to_string()));
+    }
+    let report = state
         .task_service
-        .get_tasks(query.limit, query.offset)
+        .import_tasks(query.format.into(), &body, query.commit, claims.sub)
         .await?;
-    Ok(task.into())
+    Ok(Json(report))
+}
+
+/// Export tasks to Moodle XML, GIFT or QTI
+#[utoipa::path(
+    post,
+    tag = "Task",
+    path = "/export",
+    request_body = TaskExportRequestDTO,
+    responses(
+        (status = 200, description = "Tasks file (application/xml, text/plain or application/zip)"),
+        (status = 400, description = "Wrong data format"),
+        (status = 401, description = "No auth data found"),
+        (status = 403, description = "User has no permission to export tasks"),
+        (status = 404, description = "Some tasks are not found")
+    ),
+    security(
+        ("BearerAuth" = [])
+    )
+)]
+pub async fn export_tasks(
+    claims: AccessTokenClaim,
+    State(state): State<TaskState>,
+    ValidatedJson(payload): ValidatedJson<TaskExportRequestDTO>,
+) -> Result<Response, LMSError> {
+    if matches!(claims.role, UserRole::Student) {
+        return Err(LMSError::Forbidden("You can't export tasks".to_string()));
+    }
+    let file = state
+        .task_service
+        .export_tasks(payload.format.into(), &payload.task_ids)
+        .await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, file.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file.filename),
+        )
+        .body(Body::from(file.bytes))
+        .map_err(|e| LMSError::ServerError(e.to_string()))
 }
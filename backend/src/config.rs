@@ -33,8 +33,21 @@ pub struct Config {
     pub ctfd_token: String,
     // used for auth in CTFd -> LMS
     pub ctfd_auth_token: String,
+
+    // runs learners' programs instead of serving the API, see domain::exam::scorer
+    pub code_runner: bool,
+    // how many learners' programs run at once, see infrastructure::sandbox
+    pub sandbox_workers: usize,
+
+    // how many expired attempts are scored at once, see domain::exam::scorer
+    pub scoring_workers: usize,
 }
 
+/// `SANDBOX_WORKERS` when it's not set.
+const DEFAULT_SANDBOX_WORKERS: usize = 2;
+/// `SCORING_WORKERS` when it's not set.
+const DEFAULT_SCORING_WORKERS: usize = 4;
+
 pub fn env(key: &str) -> String {
     dotenvy::var(key).unwrap_or_else(|_| panic!("`{key}` environment variable not found"))
 }
@@ -67,6 +80,12 @@ impl Config {
             frontend_redirect_url: env("FRONTEND_REDIRECT_URL"),
             ctfd_token: env("CTFD_TOKEN"),
             ctfd_auth_token: env("CTFD_AUTH_TOKEN"),
+
+            code_runner: dotenvy::var("CODE_RUNNER").map_or(Ok(false), |value| value.parse())?,
+            sandbox_workers: dotenvy::var("SANDBOX_WORKERS")
+                .map_or(Ok(DEFAULT_SANDBOX_WORKERS), |workers| workers.parse())?,
+            scoring_workers: dotenvy::var("SCORING_WORKERS")
+                .map_or(Ok(DEFAULT_SCORING_WORKERS), |workers| workers.parse())?,
         };
 
         if let Err(validation_errors) = config.validate() {
@@ -1,3 +1,5 @@
 pub mod model;
 pub mod repository;
+pub mod scorer;
 pub mod service;
+pub mod similarity;
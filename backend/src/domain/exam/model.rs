@@ -1,7 +1,13 @@
-use crate::domain::task::model::Task;
-use chrono::{DateTime, Utc};
+use crate::domain::account::model::Attributes;
+use crate::domain::task::model::{Task, TaskDifficulty};
+use crate::dto::task::UpsertTaskRequestDTO;
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use sqlx::types::Json;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -18,6 +24,10 @@ pub struct Exam {
     pub starts_at: Option<DateTime<Utc>>,
     pub ends_at: Option<DateTime<Utc>>,
     pub scoring_policy: ExamScoringPolicy,
+    pub shuffle_entities: bool,
+    /// Without one nothing is taken after the deadline.
+    #[schema(value_type = Option<LatePolicy>)]
+    pub late_policy: Option<Json<LatePolicy>>,
 }
 
 #[derive(Serialize, Deserialize, sqlx::Type, ToSchema)]
@@ -43,6 +53,166 @@ pub enum ExamScoringPolicy {
     Average,
 }
 
+/// How an exam takes answers that come after the deadline: in full during a
+/// grace period, then losing a share of their score for every hour late, and
+/// not at all after the cutoff.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct LatePolicy {
+    #[serde(default)]
+    pub grace_minutes: i32,
+    pub penalty: LatePenaltyKind,
+    /// Percent of a late answer's score taken per hour past the grace period.
+    pub percent_per_hour: f64,
+    /// Hours after the deadline answers are taken for.
+    pub cutoff_hours: i32,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LatePenaltyKind {
+    /// Grows by the minute.
+    Linear,
+    /// Every started hour counts in full.
+    Step,
+}
+
+impl LatePolicy {
+    pub fn window(&self) -> TimeDelta {
+        TimeDelta::hours(i64::from(self.cutoff_hours))
+    }
+
+    /// Share of its score an answer `late_by` after the deadline loses.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn penalty_rate(&self, late_by: TimeDelta) -> f64 {
+        let overdue = late_by - TimeDelta::minutes(i64::from(self.grace_minutes));
+        if overdue <= TimeDelta::zero() {
+            return 0f64;
+        }
+        let hours = overdue.num_seconds() as f64 / 3600f64;
+        let hours = match self.penalty {
+            LatePenaltyKind::Linear => hours,
+            LatePenaltyKind::Step => hours.ceil(),
+        };
+        (hours * self.percent_per_hour / 100f64).min(1f64)
+    }
+}
+
+/// Accommodations of an exam for one user or, by an attribute, for a group of
+/// users, e.g. extra time or a personal window.
+#[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, Debug)]
+pub struct ExamOverride {
+    pub id: Uuid,
+    pub exam_id: Uuid,
+    /// Set for a personal override, which takes precedence over group ones.
+    pub user_id: Option<Uuid>,
+    pub attribute_key: Option<String>,
+    pub attribute_value: Option<String>,
+    /// Duration of an attempt is multiplied by this.
+    pub time_multiplier: Option<f64>,
+    /// Minutes added to an attempt after the multiplier.
+    pub extra_minutes: i32,
+    /// Attempts on top of the exam's own ones; unlimited exams stay unlimited.
+    pub extra_attempts: i32,
+    /// Replace the exam's window when set.
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl ExamOverride {
+    fn applies_to(&self, user_id: Uuid, attributes: &Attributes) -> bool {
+        match (self.user_id, &self.attribute_key, &self.attribute_value) {
+            (Some(id), _, _) => id == user_id,
+            (None, Some(key), Some(value)) => attributes.get(key) == Some(value),
+            _ => false,
+        }
+    }
+}
+
+/// Limits of an exam as they are for a particular user.
+#[derive(Clone, Debug)]
+pub struct ExamLimits {
+    /// 0 stands for unlimited, as for [`Exam::tries_count`].
+    pub tries_count: i32,
+    /// Seconds.
+    pub duration: i64,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub late_policy: Option<LatePolicy>,
+}
+
+impl ExamLimits {
+    /// Applies the overrides of `exam` meant for the user. A personal one
+    /// replaces those of groups, of which every field takes the most lenient
+    /// value among all matching ones.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn new(
+        exam: &Exam,
+        overrides: &[ExamOverride],
+        user_id: Uuid,
+        attributes: &Attributes,
+    ) -> Self {
+        let matching: Vec<&ExamOverride> = overrides
+            .iter()
+            .filter(|o| o.applies_to(user_id, attributes))
+            .collect();
+        let personal: Vec<&ExamOverride> = matching
+            .iter()
+            .copied()
+            .filter(|o| o.user_id.is_some())
+            .collect();
+        let applied = if personal.is_empty() {
+            matching
+        } else {
+            personal
+        };
+
+        let multiplier = applied
+            .iter()
+            .filter_map(|o| o.time_multiplier)
+            .fold(1f64, f64::max);
+        let extra_minutes = applied.iter().map(|o| o.extra_minutes).max().unwrap_or(0);
+        let extra_attempts = applied.iter().map(|o| o.extra_attempts).max().unwrap_or(0);
+        let starts_at = applied.iter().filter_map(|o| o.starts_at).min();
+        let ends_at = applied.iter().filter_map(|o| o.ends_at).max();
+
+        Self {
+            tries_count: if exam.tries_count == 0 {
+                0
+            } else {
+                exam.tries_count.saturating_add(extra_attempts)
+            },
+            duration: (f64::from(exam.duration) * multiplier).round() as i64
+                + i64::from(extra_minutes) * 60,
+            starts_at: starts_at.or(exam.starts_at),
+            ends_at: ends_at.or(exam.ends_at),
+            late_policy: exam.late_policy.as_deref().cloned(),
+        }
+    }
+
+    /// When answers of an attempt started at `started_at` stop being on time.
+    pub fn due_at(&self, started_at: DateTime<Utc>) -> DateTime<Utc> {
+        let due_at = started_at + TimeDelta::seconds(self.duration);
+        self.ends_at.map_or(due_at, |ends_at| due_at.min(ends_at))
+    }
+
+    /// When answers due at `due_at` stop being taken.
+    pub fn closes_at(&self, due_at: DateTime<Utc>) -> DateTime<Utc> {
+        self.late_policy
+            .as_ref()
+            .and_then(|policy| due_at.checked_add_signed(policy.window()))
+            .unwrap_or(due_at)
+    }
+
+    pub fn ran_out_of_attempts(&self, used: usize) -> bool {
+        self.tries_count != 0 && used >= usize::try_from(self.tries_count).unwrap_or_default()
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn attempts_left(&self, used: usize) -> i64 {
+        (i64::from(self.tries_count) - used as i64).max(0)
+    }
+}
+
 #[derive(
     Serialize, Deserialize, FromRow, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash,
 )]
@@ -51,12 +221,172 @@ pub struct TextEntity {
     pub text: String,
 }
 
+/// Splits an exam into parts taken one after another. The tasks after a
+/// section, up to the next one, belong to it.
+#[derive(
+    Serialize, Deserialize, FromRow, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug,
+)]
+pub struct SectionEntity {
+    pub id: Uuid,
+    pub title: String,
+    /// Seconds the section stays open for since it was first opened; it is
+    /// limited by the attempt's own time either way.
+    pub duration: Option<i32>,
+    /// Once left, the section can't be opened again.
+    pub locked_after_leaving: bool,
+}
+
+/// A section an attempt has been to.
+#[derive(FromRow, Clone, Debug)]
+pub struct SectionVisit {
+    pub section_id: Uuid,
+    pub opened_at: DateTime<Utc>,
+    /// `None` for the section the attempt is in.
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+/// A `Code` answer of an attempt waiting for its tests to run.
+#[derive(FromRow, Clone, Debug)]
+pub struct PendingCodeRun {
+    pub attempt_id: Uuid,
+    pub task_id: i32,
+    pub queued_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionState {
+    NotOpened,
+    /// The section the attempt is in; only its tasks take answers.
+    Open,
+    /// Left, but can be opened again.
+    Left,
+    /// Out of time, or left while locked after leaving.
+    Closed,
+}
+
+/// Where an attempt is with a section.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct SectionProgress {
+    pub section: SectionEntity,
+    pub state: SectionState,
+    pub opened_at: Option<DateTime<Utc>>,
+    /// When the section closes by itself.
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+impl SectionProgress {
+    /// Progress through `sections`, in exam order, of an attempt ending at
+    /// `ends_at` that has made `visits`.
+    pub fn list(
+        sections: &[SectionEntity],
+        visits: &[SectionVisit],
+        ends_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Vec<Self> {
+        sections
+            .iter()
+            .map(|section| {
+                let Some(visit) = visits.iter().find(|v| v.section_id == section.id) else {
+                    return Self {
+                        section: section.clone(),
+                        state: SectionState::NotOpened,
+                        opened_at: None,
+                        deadline: None,
+                    };
+                };
+                let deadline = section.duration.map_or(ends_at, |duration| {
+                    ends_at.min(visit.opened_at + TimeDelta::seconds(i64::from(duration)))
+                });
+                let state = if deadline <= now
+                    || (visit.left_at.is_some() && section.locked_after_leaving)
+                {
+                    SectionState::Closed
+                } else if visit.left_at.is_some() {
+                    SectionState::Left
+                } else {
+                    SectionState::Open
+                };
+                Self {
+                    section: section.clone(),
+                    state,
+                    opened_at: Some(visit.opened_at),
+                    deadline: Some(deadline),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Stands for `count` tasks drawn anew for every attempt out of `task_ids`
+/// and, with a `tag`, the tasks having it.
+#[derive(
+    Serialize, Deserialize, FromRow, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash,
+)]
+pub struct PoolEntity {
+    pub id: Uuid,
+    pub count: i32,
+    pub task_ids: Vec<i32>,
+    pub tag: Option<String>,
+    /// Draw evenly across difficulties instead of uniformly across tasks.
+    pub balance_difficulty: bool,
+}
+
+impl PoolEntity {
+    /// Ids of up to `count` tasks out of `candidates`, in the order they were
+    /// drawn. When balanced, every difficulty (unset being one too) takes its
+    /// turn to give a random task until it runs out.
+    pub fn draw<R: Rng + ?Sized>(&self, candidates: &[&Task], rng: &mut R) -> Vec<i64> {
+        let count = usize::try_from(self.count).unwrap_or_default();
+        if !self.balance_difficulty {
+            return candidates
+                .choose_multiple(rng, count)
+                .map(|task| task.id)
+                .collect();
+        }
+        let mut groups: Vec<Vec<i64>> = [
+            Some(TaskDifficulty::Easy),
+            Some(TaskDifficulty::Medium),
+            Some(TaskDifficulty::Hard),
+            None,
+        ]
+        .into_iter()
+        .map(|difficulty| {
+            let mut group: Vec<i64> = candidates
+                .iter()
+                .filter(|task| task.difficulty == difficulty)
+                .map(|task| task.id)
+                .collect();
+            group.shuffle(rng);
+            group
+        })
+        .filter(|group| !group.is_empty())
+        .collect();
+        // the difficulty to start from is random too, so that `count` smaller
+        // than the number of groups doesn't always favour easy tasks
+        groups.shuffle(rng);
+        let mut drawn = Vec::new();
+        while drawn.len() < count && groups.iter().any(|group| !group.is_empty()) {
+            for group in &mut groups {
+                if drawn.len() < count
+                    && let Some(id) = group.pop()
+                {
+                    drawn.push(id);
+                }
+            }
+        }
+        drawn
+    }
+}
+
 #[derive(Serialize, Deserialize, sqlx::Type, ToSchema, Debug)]
 #[sqlx(type_name = "EXAM_ENTITY_TYPE")]
 #[sqlx(rename_all = "lowercase")]
 pub enum ExamEntityType {
     Task,
     Text,
+    Pool,
+    Section,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash)]
@@ -64,11 +394,300 @@ pub enum ExamEntityType {
 pub enum ExamEntity {
     Task { id: i32 },
     Text { id: Uuid },
+    Pool { id: Uuid },
+    Section { id: Uuid },
 }
 
-#[derive(Serialize, Deserialize, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash)]
+/// An entity of an exam being imported, created along with the exam.
+#[allow(clippy::large_enum_variant)]
+pub enum ImportedExamEntity {
+    Task(UpsertTaskRequestDTO),
+    Text(String),
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
 #[serde(tag = "name", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExamExtendedEntity {
     Task { task: Task },
     Text { text: TextEntity },
+    Pool { pool: PoolEntity },
+    Section { section: SectionEntity },
+}
+
+/// Lays `entities` out the way the owner of an attempt with `seed` sees them.
+///
+/// Every task gets its own generator derived from the seed and its id, so its
+/// options don't depend on where the task ends up. With `shuffle_order` tasks
+/// also swap places with the other tasks of their section, while texts and
+/// sections keep their positions.
+/// `ChaCha8Rng` is used because its output is stable across `rand` releases.
+#[allow(clippy::cast_sign_loss)]
+pub fn shuffle_for_attempt(entities: &mut [ExamExtendedEntity], seed: i64, shuffle_order: bool) {
+    for entity in entities.iter_mut() {
+        if let ExamExtendedEntity::Task { task } = entity {
+            let mut rng = ChaCha8Rng::seed_from_u64((seed ^ task.id) as u64);
+            task.configuration.shuffle(&mut rng);
+        }
+    }
+
+    if shuffle_order {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+        for slots in task_slots_by_section(entities) {
+            let mut order = slots.clone();
+            order.shuffle(&mut rng);
+            let tasks: Vec<ExamExtendedEntity> =
+                order.iter().map(|&i| entities[i].clone()).collect();
+            for (slot, task) in slots.into_iter().zip(tasks) {
+                entities[slot] = task;
+            }
+        }
+    }
+}
+
+/// Drops what belongs to the sections `progress` has as not opened yet,
+/// keeping the sections themselves to move to.
+pub fn hide_unopened_sections(
+    entities: Vec<ExamExtendedEntity>,
+    progress: &[SectionProgress],
+) -> Vec<ExamExtendedEntity> {
+    let mut hidden = false;
+    entities
+        .into_iter()
+        .filter(|entity| {
+            let ExamExtendedEntity::Section { section } = entity else {
+                return !hidden;
+            };
+            hidden = progress
+                .iter()
+                .any(|p| p.section.id == section.id && p.state == SectionState::NotOpened);
+            true
+        })
+        .collect()
+}
+
+/// Positions of tasks among `entities`, split at every section.
+fn task_slots_by_section(entities: &[ExamExtendedEntity]) -> Vec<Vec<usize>> {
+    let mut groups = vec![Vec::new()];
+    for (i, entity) in entities.iter().enumerate() {
+        match entity {
+            ExamExtendedEntity::Section { .. } => groups.push(Vec::new()),
+            ExamExtendedEntity::Task { .. } => groups.last_mut().expect("never empty").push(i),
+            _ => {}
+        }
+    }
+    groups
+}
+
+/// Object storage key of a file uploaded for `task_id` in an attempt. Keys are
+/// scoped to the attempt, so an answer can only point at its owner's uploads.
+pub fn attempt_file_path(attempt_id: Uuid, task_id: usize, file_id: Uuid) -> String {
+    format!("attempts/{attempt_id}/{task_id}/{file_id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(penalty: LatePenaltyKind) -> LatePolicy {
+        LatePolicy {
+            grace_minutes: 10,
+            penalty,
+            percent_per_hour: 20f64,
+            cutoff_hours: 24,
+        }
+    }
+
+    fn exam(tries_count: i32) -> Exam {
+        Exam {
+            id: Uuid::nil(),
+            topic_id: 1,
+            tries_count,
+            duration: 3600,
+            name: "Exam".to_string(),
+            description: None,
+            r#type: ExamType::Instant,
+            starts_at: None,
+            ends_at: None,
+            scoring_policy: ExamScoringPolicy::default(),
+            shuffle_entities: false,
+            late_policy: None,
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        DateTime::from_timestamp(i64::from(hour) * 3600, 0).expect("Valid timestamp")
+    }
+
+    fn group(key: &str, value: &str) -> ExamOverride {
+        ExamOverride {
+            id: Uuid::new_v4(),
+            exam_id: Uuid::nil(),
+            user_id: None,
+            attribute_key: Some(key.to_string()),
+            attribute_value: Some(value.to_string()),
+            time_multiplier: None,
+            extra_minutes: 0,
+            extra_attempts: 0,
+            starts_at: None,
+            ends_at: None,
+        }
+    }
+
+    fn assert_rate(policy: &LatePolicy, minutes: i64, rate: f64) {
+        let actual = policy.penalty_rate(TimeDelta::minutes(minutes));
+        assert!(
+            (actual - rate).abs() < 1e-9,
+            "{minutes} minutes late cost {actual} instead of {rate}"
+        );
+    }
+
+    #[test]
+    fn grace_period_is_free() {
+        for kind in [LatePenaltyKind::Linear, LatePenaltyKind::Step] {
+            assert_rate(&policy(kind), -5, 0f64);
+            assert_rate(&policy(kind), 0, 0f64);
+            assert_rate(&policy(kind), 10, 0f64);
+        }
+    }
+
+    #[test]
+    fn linear_penalty_grows_by_the_minute() {
+        let policy = policy(LatePenaltyKind::Linear);
+        assert_rate(&policy, 40, 0.1);
+        assert_rate(&policy, 70, 0.2);
+        assert_rate(&policy, 10 + 5 * 60, 1f64);
+        assert_rate(&policy, 24 * 60, 1f64);
+    }
+
+    #[test]
+    fn step_penalty_counts_started_hours() {
+        let policy = policy(LatePenaltyKind::Step);
+        assert_rate(&policy, 11, 0.2);
+        assert_rate(&policy, 70, 0.2);
+        assert_rate(&policy, 71, 0.4);
+        assert_rate(&policy, 24 * 60, 1f64);
+    }
+
+    #[test]
+    fn limits_without_overrides_are_the_exam_ones() {
+        let limits = ExamLimits::new(&exam(3), &[], Uuid::new_v4(), &Attributes::new());
+        assert_eq!(limits.tries_count, 3);
+        assert_eq!(limits.duration, 3600);
+        assert_eq!(limits.starts_at, None);
+        assert_eq!(limits.ends_at, None);
+        assert_eq!(limits.due_at(at(1)), at(2));
+    }
+
+    #[test]
+    fn group_overrides_take_the_most_lenient_values() {
+        let user_id = Uuid::new_v4();
+        let attributes = Attributes::from([
+            ("group".to_string(), "a".to_string()),
+            ("needs".to_string(), "time".to_string()),
+        ]);
+        let overrides = [
+            ExamOverride {
+                time_multiplier: Some(1.5),
+                extra_attempts: 1,
+                ends_at: Some(at(10)),
+                ..group("group", "a")
+            },
+            ExamOverride {
+                time_multiplier: Some(1.25),
+                extra_minutes: 15,
+                starts_at: Some(at(2)),
+                ends_at: Some(at(12)),
+                ..group("needs", "time")
+            },
+            // not the user's group
+            ExamOverride {
+                time_multiplier: Some(3f64),
+                extra_attempts: 10,
+                ..group("group", "b")
+            },
+        ];
+        let limits = ExamLimits::new(&exam(2), &overrides, user_id, &attributes);
+        assert_eq!(limits.tries_count, 3);
+        assert_eq!(limits.duration, 5400 + 15 * 60);
+        assert_eq!(limits.starts_at, Some(at(2)));
+        assert_eq!(limits.ends_at, Some(at(12)));
+        // the window ends before the time does
+        assert_eq!(limits.due_at(at(11)), at(12));
+
+        // unlimited exams stay unlimited
+        let limits = ExamLimits::new(&exam(0), &overrides, user_id, &attributes);
+        assert_eq!(limits.tries_count, 0);
+    }
+
+    #[test]
+    fn personal_override_replaces_group_ones() {
+        let user_id = Uuid::new_v4();
+        let attributes = Attributes::from([("group".to_string(), "a".to_string())]);
+        let overrides = [
+            ExamOverride {
+                time_multiplier: Some(2f64),
+                extra_attempts: 5,
+                ..group("group", "a")
+            },
+            ExamOverride {
+                user_id: Some(user_id),
+                attribute_key: None,
+                attribute_value: None,
+                extra_minutes: 10,
+                ..group("group", "a")
+            },
+        ];
+        let limits = ExamLimits::new(&exam(2), &overrides, user_id, &attributes);
+        assert_eq!(limits.tries_count, 2);
+        assert_eq!(limits.duration, 3600 + 10 * 60);
+
+        // someone else's personal override doesn't apply
+        let limits = ExamLimits::new(&exam(2), &overrides, Uuid::new_v4(), &attributes);
+        assert_eq!(limits.tries_count, 7);
+        assert_eq!(limits.duration, 7200);
+    }
+
+    #[test]
+    fn unopened_sections_are_hidden() {
+        let section = |title: &str| SectionEntity {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            duration: None,
+            locked_after_leaving: false,
+        };
+        let text = |text: &str| ExamExtendedEntity::Text {
+            text: TextEntity {
+                id: Uuid::nil(),
+                text: text.to_string(),
+            },
+        };
+        let (theory, practice) = (section("Theory"), section("Practice"));
+        let entities = vec![
+            text("intro"),
+            ExamExtendedEntity::Section {
+                section: theory.clone(),
+            },
+            text("theory"),
+            ExamExtendedEntity::Section {
+                section: practice.clone(),
+            },
+            text("practice"),
+        ];
+        let visits = [SectionVisit {
+            section_id: theory.id,
+            opened_at: at(1),
+            left_at: None,
+        }];
+        let progress = SectionProgress::list(&[theory, practice], &visits, at(3), at(2));
+        let texts: Vec<String> = hide_unopened_sections(entities, &progress)
+            .into_iter()
+            .map(|entity| match entity {
+                ExamExtendedEntity::Text { text } => text.text,
+                ExamExtendedEntity::Section { section } => section.title,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(texts, ["intro", "Theory", "theory", "Practice"]);
+    }
 }
@@ -1,11 +1,19 @@
 use crate::domain::account::model::UserModel;
-use crate::domain::exam::model::{Exam, ExamEntity, ExamExtendedEntity, TextEntity};
-use crate::domain::task::model::TaskAnswer;
-use crate::dto::exam::{ExamAttempt, ScoringData, UpsertExamRequestDTO};
+use crate::domain::exam::model::{
+    Exam, ExamEntity, ExamExtendedEntity, ExamLimits, ExamOverride, ImportedExamEntity,
+    PendingCodeRun, PoolEntity, SectionEntity, SectionVisit, TextEntity,
+};
+use crate::domain::task::model::{Task, TaskAnswer, TaskRevision};
+use crate::domain::task::rubric::RubricGrade;
+use crate::dto::exam::{
+    ExamAnswer, ExamAttempt, ExamOverrideUpsertDTO, PoolUpsertDTO, ScoringData, SectionUpsertDTO,
+    UpsertExamRequestDTO,
+};
 use crate::dto::task::TaskVerdict;
 use crate::errors::Result;
 use crate::gen_openapi::DummyRepository;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use impl_unimplemented::impl_unimplemented;
 use uuid::Uuid;
 
@@ -18,6 +26,14 @@ pub trait ExamRepository {
     async fn delete(&self, id: Uuid) -> Result<()>;
     async fn get_entities(&self, id: Uuid) -> Result<Vec<ExamExtendedEntity>>;
     async fn update_entities(&self, id: Uuid, tasks: Vec<ExamEntity>) -> Result<()>;
+    /// Creates an exam together with its tasks and texts, in order, or nothing
+    /// at all. Returns the exam and the created tasks.
+    async fn import_exam(
+        &self,
+        exam_data: UpsertExamRequestDTO,
+        entities: Vec<ImportedExamEntity>,
+        author_id: Uuid,
+    ) -> Result<(Exam, Vec<Task>)>;
     async fn get_exam_attempts(
         &self,
         exam_id: Uuid,
@@ -25,11 +41,67 @@ pub trait ExamRepository {
         offset: i32,
     ) -> Result<Vec<ExamAttempt>>;
     async fn get_all_exam_attempts(&self, exam_id: Uuid) -> Result<Vec<ExamAttempt>>;
+    /// Attempts of an exam that are over but haven't been scored.
     async fn get_exam_unscored_attempts(&self, exam_id: Uuid) -> Result<Vec<ExamAttempt>>;
+    /// Takes up to `limit` attempts of any exam that are over but haven't
+    /// been scored, unless scoring them has been postponed or somebody else
+    /// took them, and postpones scoring them by others until `until`.
+    async fn claim_expired_attempts(
+        &self,
+        limit: i64,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<ExamAttempt>>;
+    /// Keeps the error on the attempt and returns how many times scoring it
+    /// has failed so far.
+    async fn record_scoring_error(&self, attempt_id: Uuid, error: String) -> Result<i32>;
+    /// The attempt isn't picked up for scoring again until then.
+    async fn postpone_scoring(&self, attempt_id: Uuid, until: DateTime<Utc>) -> Result<()>;
+    /// Errors of the exam's attempts that are still unscored because of them.
+    async fn get_scoring_errors(&self, exam_id: Uuid) -> Result<Vec<(Uuid, String)>>;
+    /// Takes up to `limit` exams with attempts scored since their similarity
+    /// flags were last updated.
+    async fn claim_stale_similarity(&self, limit: i64) -> Result<Vec<Uuid>>;
+    /// Flags exactly `attempt_ids` among the exam's attempts as having answers
+    /// similar to another learner's.
+    async fn set_similar_answers(&self, exam_id: Uuid, attempt_ids: &[Uuid]) -> Result<()>;
+    /// Has the exam's similarity flags updated again, e.g. after a failure.
+    async fn mark_similarity_stale(&self, exam_id: Uuid) -> Result<()>;
+    /// Attempts of the exam flagged by the last similarity check.
+    async fn get_similar_attempts(&self, exam_id: Uuid) -> Result<Vec<Uuid>>;
     async fn get_user_attempts_in_exam(&self, id: Uuid, user_id: Uuid) -> Result<Vec<ExamAttempt>>;
     async fn get_user_last_attempt_in_exam(&self, id: Uuid, user_id: Uuid) -> Result<ExamAttempt>;
-    async fn stop_attempt(&self, attempt_id: Uuid) -> Result<()>;
-    async fn start_exam(&self, id: Uuid, user_id: Uuid) -> Result<ExamAttempt>;
+    async fn get_attempt(&self, attempt_id: Uuid) -> Result<ExamAttempt>;
+    /// Ends the attempt unless it's past its `due_at` already, late window
+    /// included, holding it off the background scoring until `scoring_until`,
+    /// and returns whether it ended.
+    async fn stop_attempt(&self, attempt_id: Uuid, scoring_until: DateTime<Utc>) -> Result<bool>;
+    /// Starts an attempt within the user's `limits` and pins it to the latest
+    /// revisions of the exam's tasks together with the tasks drawn from its
+    /// pools, as `(pool_id, task_id)`.
+    async fn start_exam(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        limits: &ExamLimits,
+        draws: &[(Uuid, i32)],
+    ) -> Result<ExamAttempt>;
+    /// Tasks drawn from pools for an attempt, as `(pool_id, task_id)`.
+    async fn get_attempt_draws(&self, attempt_id: Uuid) -> Result<Vec<(Uuid, i32)>>;
+    /// Task revisions the given attempts are pinned to.
+    async fn get_attempts_tasks(&self, attempt_ids: &[Uuid]) -> Result<Vec<(Uuid, TaskRevision)>>;
+    async fn get_latest_task_revisions(&self, task_ids: &[i32]) -> Result<Vec<TaskRevision>>;
+    /// Re-pins an attempt to other task revisions together with its new score,
+    /// queueing the answers to `code_task_ids` to be run.
+    async fn repin_attempt(
+        &self,
+        attempt_id: Uuid,
+        revision_ids: &[i64],
+        attempt_score: &ScoringData,
+        code_task_ids: &[i32],
+    ) -> Result<()>;
+    /// Sets the answer in the user's active attempt. An attempt that is past
+    /// due may have been scored already, so it's left to be scored again,
+    /// without the result of the task.
     async fn modify_attempt(
         &self,
         exam_id: Uuid,
@@ -37,10 +109,30 @@ pub trait ExamRepository {
         task_id: usize,
         answer: TaskAnswer,
     ) -> Result<ExamAttempt>;
+    /// Scores an attempt, queueing the answers to `code_task_ids` to be run
+    /// unless a run for the same answer is queued already, and leaves the exam's similarity flags to be updated. Does nothing if
+    /// the answers are no longer `answer_data`, which the score is for, and
+    /// returns whether it did.
     async fn update_attempt_score(
         &self,
         attempt_id: Uuid,
+        answer_data: &ExamAnswer,
         attempt_score: &ScoringData,
+        code_task_ids: &[i32],
+    ) -> Result<bool>;
+    /// Takes up to `limit` queued code runs nobody is running, holding them
+    /// until `until`.
+    async fn claim_code_runs(
+        &self,
+        limit: i64,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<PendingCodeRun>>;
+    /// Takes a run off the queue, recording its verdict unless the answer has
+    /// been queued again meanwhile.
+    async fn finish_code_run(
+        &self,
+        run: &PendingCodeRun,
+        verdict: Option<TaskVerdict>,
     ) -> Result<()>;
     async fn get_user_by_id(&self, user_id: Uuid) -> Result<UserModel>;
     /// Resolves usernames for a set of user ids (for attempt listings).
@@ -49,12 +141,56 @@ pub trait ExamRepository {
     async fn update_text(&self, id: Uuid, text: String) -> Result<TextEntity>;
     async fn delete_text(&self, id: Uuid) -> Result<()>;
     async fn get_text(&self, id: Uuid) -> Result<TextEntity>;
+    async fn create_pool(&self, pool: PoolUpsertDTO) -> Result<PoolEntity>;
+    async fn update_pool(&self, id: Uuid, pool: PoolUpsertDTO) -> Result<PoolEntity>;
+    async fn delete_pool(&self, id: Uuid) -> Result<()>;
+    async fn create_section(&self, section: SectionUpsertDTO) -> Result<SectionEntity>;
+    async fn update_section(&self, id: Uuid, section: SectionUpsertDTO) -> Result<SectionEntity>;
+    async fn delete_section(&self, id: Uuid) -> Result<()>;
+    async fn get_section_visits(&self, attempt_id: Uuid) -> Result<Vec<SectionVisit>>;
+    /// Section a task of the attempt comes after, on its own or drawn from a
+    /// pool. Tasks removed from the exam since the attempt started have none.
+    async fn get_task_section(
+        &self,
+        attempt_id: Uuid,
+        task_id: i64,
+    ) -> Result<Option<SectionEntity>>;
+    /// Opens a section for an attempt, leaving the one it was in. Reopening a
+    /// section keeps its first opening time, so its timer doesn't restart.
+    async fn enter_section(&self, attempt_id: Uuid, section_id: Uuid) -> Result<()>;
+    async fn get_overrides(&self, exam_id: Uuid) -> Result<Vec<ExamOverride>>;
+    async fn create_override(
+        &self,
+        exam_id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride>;
+    async fn update_override(
+        &self,
+        exam_id: Uuid,
+        id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride>;
+    async fn delete_override(&self, exam_id: Uuid, id: Uuid) -> Result<()>;
+    /// Tasks a pool draws from: its own ones and those having its tag.
+    async fn get_pool_tasks(&self, pool: &PoolEntity) -> Result<Vec<Task>>;
     async fn update_attempt_verdict(
         &self,
         attempt_id: Uuid,
         task_id: i32,
         verdict: TaskVerdict,
     ) -> Result<()>;
+    async fn update_attempt_rubric(
+        &self,
+        attempt_id: Uuid,
+        task_id: i32,
+        verdict: TaskVerdict,
+        grades: &[RubricGrade],
+    ) -> Result<()>;
+    async fn update_attempt_late_penalty_waiver(
+        &self,
+        attempt_id: Uuid,
+        waived: bool,
+    ) -> Result<()>;
     async fn update_attempt_visibility_by_id(
         &self,
         attempt_id: Uuid,
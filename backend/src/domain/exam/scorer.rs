@@ -0,0 +1,69 @@
+//! Background scoring of attempts that are over.
+//!
+//! Attempts are scored when they are stopped, while the ones that simply run
+//! out of time are picked up here, so that reads never have to score them.
+//! An attempt is due before the late policy closes it, so it's scored at the
+//! deadline and picked up again after every late answer.
+//! Scoring leaves the similarity flags of the exam to be updated here, and
+//! queues `Code` answers, whose tests are run by an instance of its own (with
+//! `CODE_RUNNER` set), so that learners' programs never share a container
+//! with the API.
+
+use crate::domain::exam::service::ExamService;
+use crate::errors::{LMSError, Result};
+use std::time::Duration;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{info, warn};
+
+/// Pause between two rounds of scoring.
+const SCORING_INTERVAL: Duration = Duration::from_secs(30);
+/// Pause between two looks at the queue of code runs.
+const CODE_RUN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Starts scoring expired attempts in the background, `workers` at once.
+pub fn spawn(exam_service: ExamService, workers: usize) {
+    tokio::spawn(async move {
+        let mut ticks = interval(SCORING_INTERVAL);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            let service = exam_service.clone();
+            match round(async move { service.score_expired(workers).await }).await {
+                Ok(0) => {}
+                Ok(scored) => info!("Scored {scored} expired attempts"),
+                Err(e) => warn!("Failed to look up expired attempts: {e}"),
+            }
+            let service = exam_service.clone();
+            match round(async move { service.update_similarity_flags().await }).await {
+                Ok(0) => {}
+                Ok(updated) => info!("Updated similarity flags of {updated} exams"),
+                Err(e) => warn!("Failed to update similarity flags: {e}"),
+            }
+        }
+    });
+}
+
+/// Runs queued code, `sandbox_workers` at once, for good.
+pub async fn run_code(exam_service: ExamService, sandbox_workers: usize) {
+    let mut ticks = interval(CODE_RUN_INTERVAL);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let service = exam_service.clone();
+        match round(async move { service.run_pending_code(sandbox_workers).await }).await {
+            Ok(0) => {}
+            Ok(ran) => info!("Ran code of {ran} answers"),
+            Err(e) => warn!("Failed to look up queued code runs: {e}"),
+        }
+    }
+}
+
+/// Runs one round of background work as a task of its own, so that a panic in
+/// it is reported as an error instead of ending the loop.
+async fn round<T: Send + 'static>(
+    work: impl Future<Output = Result<T>> + Send + 'static,
+) -> Result<T> {
+    tokio::spawn(work)
+        .await
+        .unwrap_or_else(|e| Err(LMSError::ServerError(format!("The round panicked: {e}"))))
+}
@@ -1,29 +1,71 @@
 use crate::domain::account::model::UserRole;
-use crate::domain::exam::model::{Exam, ExamEntity, ExamExtendedEntity, ExamType, TextEntity};
+use crate::domain::exam::model::{
+    Exam, ExamEntity, ExamExtendedEntity, ExamLimits, ExamOverride, ExamScoringPolicy, ExamType,
+    ImportedExamEntity, PendingCodeRun, PoolEntity, SectionEntity, SectionProgress, SectionState,
+    TextEntity, attempt_file_path, hide_unopened_sections, shuffle_for_attempt,
+};
 use crate::domain::exam::repository::ExamRepository;
+use crate::domain::exam::similarity::{DEFAULT_THRESHOLD, Fingerprint};
+use crate::domain::report::model::ExportFile;
+use crate::domain::task::interchange::qti::{self, QtiEntity};
 use crate::domain::task::model::{
-    CtfdMetadataResponse, CtfdUsersReponse, TaskAnswer, TaskConfig, TaskType,
+    CtfdMetadataResponse, CtfdUsersReponse, Task, TaskAnswer, TaskConfig, TaskRevision, TaskType,
+    content_type_allowed,
 };
-use crate::domain::task::service::CTFD_API_URL;
+use crate::domain::task::rubric;
+use crate::domain::task::service::{CTFD_API_URL, TaskService};
 use crate::domain::topics::service::TopicService;
-use crate::dto::exam::{ExamAttempt, ScoringData, UpsertExamRequestDTO};
+use crate::dto::exam::{
+    AttemptRegradeDTO, ExamAttempt, ExamImportReportDTO, ExamOverrideUpsertDTO, FileUploadSlotDTO,
+    PoolUpsertDTO, ScoringData, SectionUpsertDTO, SimilarAnswersDTO, TaskRegradeDTO,
+    TaskSimilarityDTO, UpsertExamRequestDTO,
+};
 use crate::dto::task::TaskVerdict;
 use crate::errors::{LMSError, Result};
+use crate::infrastructure::s3::S3;
+use crate::infrastructure::sandbox::Sandbox;
 use crate::repo;
 use crate::utils::send_and_parse;
 use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
-use chrono::Utc;
-use sqlx::types::Json;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{StreamExt, stream};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{info, warn};
 use uuid::Uuid;
 
+/// Duration (in seconds) of an imported exam whose test has no time limit.
+const DEFAULT_IMPORTED_DURATION: i32 = 3600;
+/// How long (in seconds) an issued upload form stays valid.
+const UPLOAD_SLOT_TTL: u32 = 15 * 60;
+/// How long (in seconds) reviewers' download links stay valid.
+const DOWNLOAD_LINK_TTL: u32 = 60 * 60;
+/// Attempts picked up by a single round of [`ExamService::score_expired`].
+const SCORING_BATCH: i64 = 200;
+/// Delay before retrying to score an attempt, doubled with every failure.
+const SCORING_BACKOFF: TimeDelta = TimeDelta::seconds(30);
+/// Failures after which an attempt is left for a reviewer (about an hour in).
+const MAX_SCORING_FAILURES: i32 = 8;
+/// How long an attempt being scored is kept from other scorers, after which
+/// the background scoring takes it over.
+const SCORING_LEASE: TimeDelta = TimeDelta::minutes(10);
+/// How long a worker holds a code run it took, after which another one may
+/// take it over; well above the longest tests (50 of 10 seconds each).
+const CODE_RUN_LEASE: TimeDelta = TimeDelta::minutes(30);
+/// Exams whose similarity flags are updated by a single round of
+/// [`ExamService::update_similarity_flags`].
+const SIMILARITY_BATCH: i64 = 20;
+
 #[derive(Clone)]
 pub struct ExamService {
     repo: repo!(ExamRepository),
     http_client: reqwest::Client,
     ctfd_token: String,
     topic_service: TopicService,
+    s3: repo!(S3),
+    /// Only on an instance running learners' code, see `Config::code_runner`.
+    sandbox: Option<Arc<Sandbox>>,
 }
 
 impl ExamService {
@@ -32,12 +74,16 @@ impl ExamService {
         http_client: reqwest::Client,
         ctfd_token: String,
         topic_service: TopicService,
+        s3: repo!(S3),
+        sandbox: Option<Arc<Sandbox>>,
     ) -> Self {
         Self {
             repo,
             http_client,
             ctfd_token,
             topic_service,
+            s3,
+            sandbox,
         }
     }
 
@@ -70,6 +116,113 @@ impl ExamService {
         self.repo.get_entities(exam_id).await
     }
 
+    /// Exam entities laid out exactly as the attempt's owner sees them.
+    pub async fn get_attempt_entities(
+        &self,
+        attempt: &ExamAttempt,
+    ) -> Result<Vec<ExamExtendedEntity>> {
+        let exam = self.repo.get(attempt.exam_id).await?;
+        let mut pinned = self.get_attempt_tasks(attempt).await?;
+        let draws = self.repo.get_attempt_draws(attempt.id).await?;
+        let mut entities: Vec<ExamExtendedEntity> = Vec::new();
+        for entity in self.repo.get_entities(attempt.exam_id).await? {
+            match entity {
+                ExamExtendedEntity::Task { task } => {
+                    // tasks added after the attempt started aren't part of it
+                    if let Some(position) = pinned.iter().position(|t| t.id == task.id) {
+                        entities.push(ExamExtendedEntity::Task {
+                            task: pinned.remove(position),
+                        });
+                    }
+                }
+                ExamExtendedEntity::Text { .. } | ExamExtendedEntity::Section { .. } => {
+                    entities.push(entity);
+                }
+                // a pool is where the tasks drawn from it go
+                ExamExtendedEntity::Pool { pool } => {
+                    for (_, task_id) in draws.iter().filter(|(pool_id, _)| *pool_id == pool.id) {
+                        if let Some(position) =
+                            pinned.iter().position(|t| t.id == i64::from(*task_id))
+                        {
+                            entities.push(ExamExtendedEntity::Task {
+                                task: pinned.remove(position),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        // while tasks removed from the exam since still belong to it, though
+        // to none of its sections, so they go before the first one
+        let first_section = entities
+            .iter()
+            .position(|entity| matches!(entity, ExamExtendedEntity::Section { .. }))
+            .unwrap_or(entities.len());
+        entities.splice(
+            first_section..first_section,
+            pinned
+                .into_iter()
+                .map(|task| ExamExtendedEntity::Task { task }),
+        );
+        shuffle_for_attempt(&mut entities, attempt.shuffle_seed, exam.shuffle_entities);
+        Ok(entities)
+    }
+
+    /// Task revisions each of the attempts is pinned to, keyed by attempt id.
+    pub async fn get_attempts_tasks(
+        &self,
+        attempt_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<TaskRevision>>> {
+        let mut tasks: HashMap<Uuid, Vec<TaskRevision>> = HashMap::new();
+        for (attempt_id, revision) in self.repo.get_attempts_tasks(attempt_ids).await? {
+            tasks.entry(attempt_id).or_default().push(revision);
+        }
+        Ok(tasks)
+    }
+
+    /// Tasks of an attempt as of the revisions it was started with, rendered
+    /// for the attempt's seed; that's what it is validated and graded against.
+    pub async fn get_attempt_tasks(&self, attempt: &ExamAttempt) -> Result<Vec<Task>> {
+        self.repo
+            .get_attempts_tasks(&[attempt.id])
+            .await?
+            .into_iter()
+            .map(|(_, revision)| revision.task.instantiate(attempt.shuffle_seed))
+            .collect()
+    }
+
+    /// Same as [`Self::get_attempt_entities`], for the attempt's owner: what
+    /// belongs to a section they haven't opened yet stays hidden until they do.
+    pub async fn get_opened_attempt_entities(
+        &self,
+        attempt: &ExamAttempt,
+    ) -> Result<Vec<ExamExtendedEntity>> {
+        let entities = self.get_attempt_entities(attempt).await?;
+        let sections = self.get_sections(attempt.exam_id).await?;
+        if sections.is_empty() {
+            return Ok(entities);
+        }
+        let visits = self.repo.get_section_visits(attempt.id).await?;
+        let progress = SectionProgress::list(&sections, &visits, attempt.ends_at, Utc::now());
+        Ok(hide_unopened_sections(entities, &progress))
+    }
+
+    /// Same as [`Self::get_attempt_entities`], for reviewers looking at an
+    /// attempt by id.
+    pub async fn get_attempt_entities_by_id(
+        &self,
+        exam_id: Uuid,
+        attempt_id: Uuid,
+    ) -> Result<Vec<ExamExtendedEntity>> {
+        let attempt = self.repo.get_attempt(attempt_id).await?;
+        if attempt.exam_id != exam_id {
+            return Err(LMSError::NotFound(
+                "This exam has no such attempt".to_string(),
+            ));
+        }
+        self.get_attempt_entities(&attempt).await
+    }
+
     pub async fn update_entities(&self, exam_id: Uuid, entities: Vec<ExamEntity>) -> Result<()> {
         if entities.iter().collect::<HashSet<_>>().len() != entities.len() {
             return Err(LMSError::Conflict(
@@ -87,15 +240,6 @@ impl ExamService {
         ungraded_first: bool,
     ) -> Result<Vec<ExamAttempt>> {
         let mut attempts = self.repo.get_exam_attempts(exam_id, limit, offset).await?;
-        for attempt in &mut attempts {
-            if attempt.ends_at <= Utc::now()
-                && attempt.scoring_data.results.is_empty()
-                && !attempt.answer_data.answers.is_empty()
-            {
-                let scoring = self.score_attempt(attempt.clone()).await?;
-                attempt.scoring_data = Json(scoring);
-            }
-        }
         if ungraded_first {
             attempts.sort_by(|a, b| {
                 b.scoring_data
@@ -117,20 +261,9 @@ impl ExamService {
         Ok(attempts)
     }
 
-    /// Every attempt for an exam (unpaginated), scoring any whose window has
-    /// closed but were never scored. Intended for reporting/export.
-    pub async fn get_all_attempts_scored(&self, exam_id: Uuid) -> Result<Vec<ExamAttempt>> {
-        let mut attempts = self.repo.get_all_exam_attempts(exam_id).await?;
-        for attempt in &mut attempts {
-            if attempt.ends_at <= Utc::now()
-                && attempt.scoring_data.results.is_empty()
-                && !attempt.answer_data.answers.is_empty()
-            {
-                let scoring = self.score_attempt(attempt.clone()).await?;
-                attempt.scoring_data = Json(scoring);
-            }
-        }
-        Ok(attempts)
+    /// Every attempt for an exam (unpaginated). Intended for reporting/export.
+    pub async fn get_all_attempts(&self, exam_id: Uuid) -> Result<Vec<ExamAttempt>> {
+        self.repo.get_all_exam_attempts(exam_id).await
     }
 
     pub async fn get_usernames(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, String>> {
@@ -142,19 +275,10 @@ impl ExamService {
         exam_id: Uuid,
         user_id: Uuid,
     ) -> Result<Vec<ExamAttempt>> {
-        let mut attempts = self
+        let attempts = self
             .repo
             .get_user_attempts_in_exam(exam_id, user_id)
             .await?;
-        for attempt in &mut attempts {
-            if attempt.ends_at <= Utc::now()
-                && attempt.scoring_data.results.is_empty()
-                && !attempt.answer_data.answers.is_empty()
-            {
-                let scoring = self.score_attempt(attempt.clone()).await?;
-                attempt.scoring_data = Json(scoring);
-            }
-        }
         Ok(attempts)
     }
 
@@ -168,15 +292,14 @@ impl ExamService {
         task_id: i32,
         verdict: TaskVerdict,
     ) -> Result<()> {
-        let entities = self.repo.get_entities(exam_id).await?;
-        let tasks = entities
-            .iter()
-            .filter_map(|e| match e {
-                ExamExtendedEntity::Task { task } => Some(task),
-                ExamExtendedEntity::Text { .. } => None,
-            })
-            .collect::<Vec<_>>();
-        let updating_task = tasks.iter().find(|&&t| t.id == i64::from(task_id));
+        let attempt = self.repo.get_attempt(attempt_id).await?;
+        if attempt.exam_id != exam_id {
+            return Err(LMSError::NotFound(
+                "This exam has no such attempt".to_string(),
+            ));
+        }
+        let tasks = self.get_attempt_tasks(&attempt).await?;
+        let updating_task = tasks.iter().find(|t| t.id == i64::from(task_id));
         match updating_task {
             None => {
                 return Err(LMSError::NotFound(
@@ -234,6 +357,61 @@ impl ExamService {
             .await
     }
 
+    /// Grades a task of an attempt by the task's rubric, picking `levels[i]`
+    /// for the `i`-th criterion, and keeps the filled-in rubric next to the
+    /// verdict.
+    pub async fn grade_attempt_by_rubric(
+        &self,
+        attempt_id: Uuid,
+        exam_id: Uuid,
+        task_id: i32,
+        levels: &[usize],
+        comment: Option<&str>,
+    ) -> Result<()> {
+        let attempt = self.repo.get_attempt(attempt_id).await?;
+        if attempt.exam_id != exam_id {
+            return Err(LMSError::NotFound(
+                "This exam has no such attempt".to_string(),
+            ));
+        }
+        let tasks = self.get_attempt_tasks(&attempt).await?;
+        let Some(task) = tasks.iter().find(|t| t.id == i64::from(task_id)) else {
+            return Err(LMSError::NotFound(
+                "No such task exists in this exam".to_string(),
+            ));
+        };
+        let criteria = task.configuration.rubric();
+        if criteria.is_empty() {
+            return Err(LMSError::ShitHappened(
+                "This task has no rubric".to_string(),
+            ));
+        }
+        let (verdict, grades) = rubric::grade(criteria, levels, task.points, comment)
+            .map_err(LMSError::ShitHappened)?;
+
+        self.repo
+            .update_attempt_rubric(attempt_id, task_id, verdict, &grades)
+            .await
+    }
+
+    /// Takes the late penalty off the attempt's score, or puts it back.
+    pub async fn waive_late_penalty(
+        &self,
+        attempt_id: Uuid,
+        exam_id: Uuid,
+        waived: bool,
+    ) -> Result<()> {
+        let attempt = self.repo.get_attempt(attempt_id).await?;
+        if attempt.exam_id != exam_id {
+            return Err(LMSError::NotFound(
+                "This exam has no such attempt".to_string(),
+            ));
+        }
+        self.repo
+            .update_attempt_late_penalty_waiver(attempt_id, waived)
+            .await
+    }
+
     pub async fn update_attempt_visibility_by_id(
         &self,
         attempt_id: Uuid,
@@ -259,47 +437,236 @@ impl ExamService {
         exam_id: Uuid,
         user_id: Uuid,
     ) -> Result<ExamAttempt> {
-        let mut attempt = self
-            .repo
+        self.repo
             .get_user_last_attempt_in_exam(exam_id, user_id)
-            .await?;
-        if attempt.ends_at <= Utc::now()
-            && attempt.scoring_data.results.is_empty()
-            && !attempt.answer_data.answers.is_empty()
-        {
-            let scoring = self.score_attempt(attempt.clone()).await?;
-            attempt.scoring_data = Json(scoring);
-        }
-        Ok(attempt)
+            .await
     }
 
     pub async fn start_exam(&self, exam_id: Uuid, user_id: Uuid) -> Result<ExamAttempt> {
         let user = self.repo.get_user_by_id(user_id).await?;
         let exam = self.get_exam(exam_id, user_id, user.role).await?;
-        if let Some(starts_at) = exam.starts_at
+        let overrides = self.repo.get_overrides(exam_id).await?;
+        let limits = ExamLimits::new(&exam, &overrides, user_id, &user.attributes);
+        if let Some(starts_at) = limits.starts_at
             && starts_at > Utc::now()
         {
             return Err(LMSError::NotInTime("Exam hasn't started yet".to_string()));
         }
-        if let Some(ends_at) = exam.ends_at
-            && ends_at <= Utc::now()
+        // the late policy keeps the exam open past its end
+        if let Some(ends_at) = limits.ends_at
+            && limits.closes_at(ends_at) <= Utc::now()
         {
             return Err(LMSError::NotInTime("Exam has ended".to_string()));
         }
-        self.repo.start_exam(exam_id, user_id).await
+        let draws = self.draw_pools(exam_id).await?;
+        let attempt = self
+            .repo
+            .start_exam(exam_id, user_id, &limits, &draws)
+            .await?;
+        // an exam split into sections is taken from the first one
+        if let Some(section) = self.get_sections(exam_id).await?.first() {
+            self.repo.enter_section(attempt.id, section.id).await?;
+        }
+        Ok(attempt)
     }
 
-    pub async fn stop_exam(&self, exam_id: Uuid, user_id: Uuid) -> Result<()> {
+    async fn get_sections(&self, exam_id: Uuid) -> Result<Vec<SectionEntity>> {
+        Ok(self
+            .repo
+            .get_entities(exam_id)
+            .await?
+            .into_iter()
+            .filter_map(|entity| match entity {
+                ExamExtendedEntity::Section { section } => Some(section),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Progress of the user's last attempt through the exam's sections.
+    pub async fn get_section_progress(
+        &self,
+        exam_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<SectionProgress>> {
+        let attempt = self.get_user_last_attempt_in_exam(exam_id, user_id).await?;
+        let sections = self.get_sections(exam_id).await?;
+        let visits = self.repo.get_section_visits(attempt.id).await?;
+        Ok(SectionProgress::list(
+            &sections,
+            &visits,
+            attempt.ends_at,
+            Utc::now(),
+        ))
+    }
+
+    /// Moves the user's active attempt to a section. Sections are first
+    /// opened in order; a left one can be gone back to unless it is locked
+    /// after leaving or out of time.
+    pub async fn move_to_section(
+        &self,
+        exam_id: Uuid,
+        user_id: Uuid,
+        section_id: Uuid,
+    ) -> Result<Vec<SectionProgress>> {
         let attempt = self.get_user_last_attempt_in_exam(exam_id, user_id).await?;
         if attempt.ends_at <= Utc::now() {
-            if attempt.scoring_data.results.is_empty() {
-                let _ = self.score_attempt(attempt).await?;
+            return Err(LMSError::NotFound(
+                "You have no active attempts".to_string(),
+            ));
+        }
+        let sections = self.get_sections(exam_id).await?;
+        let Some(index) = sections.iter().position(|s| s.id == section_id) else {
+            return Err(LMSError::NotFound(
+                "This exam has no such section".to_string(),
+            ));
+        };
+        let visits = self.repo.get_section_visits(attempt.id).await?;
+        let progress = SectionProgress::list(&sections, &visits, attempt.ends_at, Utc::now());
+        match progress[index].state {
+            SectionState::Open => return Ok(progress),
+            SectionState::Closed => {
+                return Err(LMSError::NotInTime("This section is closed".to_string()));
+            }
+            SectionState::NotOpened
+                if progress[..index]
+                    .iter()
+                    .any(|p| p.state == SectionState::NotOpened) =>
+            {
+                return Err(LMSError::Conflict(
+                    "Sections are opened in order".to_string(),
+                ));
+            }
+            SectionState::NotOpened | SectionState::Left => {}
+        }
+        self.repo.enter_section(attempt.id, section_id).await?;
+        let visits = self.repo.get_section_visits(attempt.id).await?;
+        Ok(SectionProgress::list(
+            &sections,
+            &visits,
+            attempt.ends_at,
+            Utc::now(),
+        ))
+    }
+
+    /// Fails unless the section `task_id` is in, if any, is the one the
+    /// attempt is in.
+    async fn ensure_task_section_open(&self, attempt: &ExamAttempt, task_id: i64) -> Result<()> {
+        let Some(section) = self.repo.get_task_section(attempt.id, task_id).await? else {
+            return Ok(());
+        };
+        let visits = self.repo.get_section_visits(attempt.id).await?;
+        let state = SectionProgress::list(&[section], &visits, attempt.ends_at, Utc::now())
+            .pop()
+            .map_or(SectionState::NotOpened, |progress| progress.state);
+        match state {
+            SectionState::Open => Ok(()),
+            SectionState::NotOpened => Err(LMSError::NotInTime(
+                "This task's section hasn't been opened yet".to_string(),
+            )),
+            SectionState::Left => Err(LMSError::NotInTime(
+                "Go back to this task's section to answer it".to_string(),
+            )),
+            SectionState::Closed => Err(LMSError::NotInTime(
+                "This task's section is closed".to_string(),
+            )),
+        }
+    }
+
+    /// Limits of `exam` for the user with their accommodations applied.
+    pub async fn get_limits(&self, exam: &Exam, user_id: Uuid) -> Result<ExamLimits> {
+        let user = self.repo.get_user_by_id(user_id).await?;
+        let overrides = self.repo.get_overrides(exam.id).await?;
+        Ok(ExamLimits::new(exam, &overrides, user_id, &user.attributes))
+    }
+
+    pub async fn get_overrides(&self, exam_id: Uuid) -> Result<Vec<ExamOverride>> {
+        self.repo.get(exam_id).await?; // 404 for unknown exams rather than an empty list
+        self.repo.get_overrides(exam_id).await
+    }
+
+    pub async fn create_override(
+        &self,
+        exam_id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride> {
+        self.repo.create_override(exam_id, data).await
+    }
+
+    pub async fn update_override(
+        &self,
+        exam_id: Uuid,
+        override_id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride> {
+        self.repo.update_override(exam_id, override_id, data).await
+    }
+
+    pub async fn delete_override(&self, exam_id: Uuid, override_id: Uuid) -> Result<()> {
+        self.repo.delete_override(exam_id, override_id).await
+    }
+
+    /// Draws the tasks of every pool of the exam for a new attempt, as
+    /// `(pool_id, task_id)`. A task is never drawn twice or when the exam
+    /// already has it.
+    #[allow(clippy::cast_possible_truncation)]
+    async fn draw_pools(&self, exam_id: Uuid) -> Result<Vec<(Uuid, i32)>> {
+        let entities = self.repo.get_entities(exam_id).await?;
+        let mut taken: HashSet<i64> = entities
+            .iter()
+            .filter_map(|entity| match entity {
+                ExamExtendedEntity::Task { task } => Some(task.id),
+                _ => None,
+            })
+            .collect();
+        let mut draws = Vec::new();
+        for entity in &entities {
+            let ExamExtendedEntity::Pool { pool } = entity else {
+                continue;
+            };
+            let tasks = self.repo.get_pool_tasks(pool).await?;
+            let candidates: Vec<&Task> = tasks
+                .iter()
+                .filter(|task| !taken.contains(&task.id))
+                .collect();
+            for task_id in pool.draw(&candidates, &mut rand::rng()) {
+                taken.insert(task_id);
+                draws.push((pool.id, task_id as i32));
             }
+        }
+        Ok(draws)
+    }
+
+    /// Points a pool is expected to be worth: as many average tasks out of
+    /// the ones it draws from as it draws.
+    #[allow(clippy::cast_precision_loss)]
+    pub async fn pool_max_score(&self, pool: &PoolEntity) -> Result<f64> {
+        let tasks = self.repo.get_pool_tasks(pool).await?;
+        if tasks.is_empty() {
+            return Ok(0f64);
+        }
+        let average = tasks.iter().map(|task| task.points as f64).sum::<f64>() / tasks.len() as f64;
+        Ok(average * f64::from(pool.count).min(tasks.len() as f64))
+    }
+
+    pub async fn stop_exam(&self, exam_id: Uuid, user_id: Uuid) -> Result<()> {
+        let attempt = self.get_user_last_attempt_in_exam(exam_id, user_id).await?;
+        // past `due_at` it's been scored already, only late answers are taken
+        if attempt.due_at <= Utc::now() {
+            return Err(LMSError::NotFound(
+                "You have no active attempts".to_string(),
+            ));
+        }
+        // the background scoring leaves the attempt to us unless we fail
+        if !self
+            .repo
+            .stop_attempt(attempt.id, Utc::now() + SCORING_LEASE)
+            .await?
+        {
             return Err(LMSError::NotFound(
                 "You have no active attempts".to_string(),
             ));
         }
-        let () = self.repo.stop_attempt(attempt.id).await?;
         let _ = self.score_attempt(attempt).await?;
         Ok(())
     }
@@ -318,15 +685,9 @@ impl ExamService {
                 "You have no active attempts".to_string(),
             ));
         }
-        let entities = self.get_entities(exam_id).await?;
-        let tasks = entities
-            .iter()
-            .filter_map(|e| match e {
-                ExamExtendedEntity::Task { task } => Some(task),
-                ExamExtendedEntity::Text { .. } => None,
-            })
-            .collect::<Vec<_>>();
+        let tasks = self.get_attempt_tasks(&attempt).await?;
         if let Some(task) = tasks.iter().find(|t| t.id == task_id as i64) {
+            self.ensure_task_section_open(&attempt, task.id).await?;
             task.validate_answer(&user_answer)?;
             // CTFd tasks additionally require verifying the solve against CTFd
             if let (
@@ -346,6 +707,9 @@ impl ExamService {
                     ));
                 }
             }
+            if let TaskAnswer::FileUpload { file_id } = &user_answer {
+                self.verify_uploaded_file(&attempt, task, *file_id).await?;
+            }
             self.repo
                 .modify_attempt(exam_id, user_id, task_id, user_answer)
                 .await
@@ -354,6 +718,251 @@ impl ExamService {
         }
     }
 
+    /// Issues a form to upload a file answer for `task_id` in the user's
+    /// active attempt, limited to the task's size and allowed types.
+    #[allow(clippy::cast_possible_wrap)]
+    pub async fn create_upload_slot(
+        &self,
+        exam_id: Uuid,
+        user_id: Uuid,
+        task_id: usize,
+        content_type: &str,
+    ) -> Result<FileUploadSlotDTO> {
+        let attempt = self.get_user_last_attempt_in_exam(exam_id, user_id).await?;
+        if attempt.ends_at <= Utc::now() {
+            return Err(LMSError::NotFound(
+                "You have no active attempts".to_string(),
+            ));
+        }
+        let tasks = self.get_attempt_tasks(&attempt).await?;
+        let Some(task) = tasks.iter().find(|t| t.id == task_id as i64) else {
+            return Err(LMSError::NotFound("This exam has no such task".to_string()));
+        };
+        self.ensure_task_section_open(&attempt, task.id).await?;
+        let TaskConfig::FileUpload {
+            max_size,
+            allowed_types,
+            ..
+        } = &task.configuration
+        else {
+            return Err(LMSError::ShitHappened(
+                "This task doesn't take files".to_string(),
+            ));
+        };
+        if !content_type_allowed(allowed_types, content_type) {
+            return Err(LMSError::ShitHappened(format!(
+                "This task only takes {}",
+                allowed_types.join(", ")
+            )));
+        }
+
+        let file_id = Uuid::new_v4();
+        let presigned = self
+            .s3
+            .presign_upload(
+                &attempt_file_path(attempt.id, task_id, file_id),
+                content_type,
+                u32::try_from(*max_size).unwrap_or(u32::MAX),
+                UPLOAD_SLOT_TTL,
+            )
+            .await?;
+        Ok(FileUploadSlotDTO {
+            file_id,
+            url: presigned.url,
+            fields: presigned.fields,
+            expires_at: Utc::now() + TimeDelta::seconds(i64::from(UPLOAD_SLOT_TTL)),
+        })
+    }
+
+    /// Checks that `file_id` was uploaded through a slot of this attempt and
+    /// task and still fits the task's limits.
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    async fn verify_uploaded_file(
+        &self,
+        attempt: &ExamAttempt,
+        task: &Task,
+        file_id: Uuid,
+    ) -> Result<()> {
+        let TaskConfig::FileUpload {
+            max_size,
+            allowed_types,
+            ..
+        } = &task.configuration
+        else {
+            return Ok(());
+        };
+        let path = attempt_file_path(attempt.id, task.id as usize, file_id);
+        let Some(meta) = self.s3.object_meta(&path).await? else {
+            return Err(LMSError::ShitHappened(
+                "No such file was uploaded for this task".to_string(),
+            ));
+        };
+        if meta.size == 0 || meta.size > *max_size as u64 {
+            return Err(LMSError::ShitHappened(format!(
+                "File size must be between 1 and {max_size} bytes"
+            )));
+        }
+        if !content_type_allowed(
+            allowed_types,
+            meta.content_type.as_deref().unwrap_or_default(),
+        ) {
+            return Err(LMSError::ShitHappened(format!(
+                "This task only takes {}",
+                allowed_types.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Time-limited download links of the files answered in an attempt, by
+    /// task id.
+    pub async fn attempt_file_links(
+        &self,
+        attempt: &ExamAttempt,
+    ) -> Result<HashMap<usize, String>> {
+        let mut links = HashMap::new();
+        for (&task_id, answer) in &attempt.answer_data.answers {
+            if let TaskAnswer::FileUpload { file_id } = answer {
+                let path = attempt_file_path(attempt.id, task_id, *file_id);
+                let link = self.s3.presign_get(&path, DOWNLOAD_LINK_TTL).await?;
+                links.insert(task_id, link);
+            }
+        }
+        Ok(links)
+    }
+
+    /// Compares the answers of different learners to every `LongText` and
+    /// manually graded `ShortText` task of an exam, pair by pair, and reports
+    /// the pairs at least `threshold` similar (see `domain::exam::similarity`).
+    /// Tasks without such pairs are left out.
+    pub async fn similarity_report(
+        &self,
+        exam_id: Uuid,
+        threshold: f64,
+    ) -> Result<Vec<TaskSimilarityDTO>> {
+        struct Answer {
+            attempt_id: Uuid,
+            user_id: Uuid,
+            fingerprint: Fingerprint,
+        }
+
+        let attempts = self.repo.get_all_exam_attempts(exam_id).await?;
+        let attempt_ids: Vec<Uuid> = attempts.iter().map(|attempt| attempt.id).collect();
+        let pinned = self.get_attempts_tasks(&attempt_ids).await?;
+        // (task id, title, answers) in the order the tasks first come up
+        let mut tasks: Vec<(i64, String, Vec<Answer>)> = Vec::new();
+        for attempt in &attempts {
+            for revision in pinned.get(&attempt.id).into_iter().flatten() {
+                let task = &revision.task;
+                #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+                let answer = attempt.answer_data.answers.get(&(task.id as usize));
+                let ((TaskConfig::LongText { .. }, Some(TaskAnswer::LongText { answer }))
+                | (
+                    TaskConfig::ShortText {
+                        auto_grade: false, ..
+                    },
+                    Some(TaskAnswer::ShortText { answer }),
+                )) = (&task.configuration, answer)
+                else {
+                    continue;
+                };
+                let Some(fingerprint) = Fingerprint::new(answer) else {
+                    continue;
+                };
+                let index = tasks
+                    .iter()
+                    .position(|(id, ..)| *id == task.id)
+                    .unwrap_or_else(|| {
+                        tasks.push((task.id, task.title.clone(), Vec::new()));
+                        tasks.len() - 1
+                    });
+                tasks[index].2.push(Answer {
+                    attempt_id: attempt.id,
+                    user_id: attempt.user_id,
+                    fingerprint,
+                });
+            }
+        }
+
+        let user_ids: Vec<Uuid> = attempts.iter().map(|attempt| attempt.user_id).collect();
+        let usernames = self.get_usernames(&user_ids).await?;
+        let username = |id: &Uuid| usernames.get(id).cloned().unwrap_or_default();
+        let mut report = Vec::new();
+        for (task_id, title, answers) in tasks {
+            let mut pairs = Vec::new();
+            for (i, first) in answers.iter().enumerate() {
+                // a learner's own attempts are allowed to look alike
+                for second in answers[i + 1..]
+                    .iter()
+                    .filter(|second| second.user_id != first.user_id)
+                {
+                    let similarity = first.fingerprint.similarity(&second.fingerprint);
+                    if similarity < threshold {
+                        continue;
+                    }
+                    pairs.push(SimilarAnswersDTO {
+                        first_attempt_id: first.attempt_id,
+                        first_user_id: first.user_id,
+                        first_username: username(&first.user_id),
+                        second_attempt_id: second.attempt_id,
+                        second_user_id: second.user_id,
+                        second_username: username(&second.user_id),
+                        similarity,
+                        fragments: first.fingerprint.shared_fragments(&second.fingerprint),
+                    });
+                }
+            }
+            if pairs.is_empty() {
+                continue;
+            }
+            pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+            report.push(TaskSimilarityDTO {
+                task_id,
+                title,
+                answers_compared: answers.len(),
+                pairs,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Attempts of an exam with an answer flagged by the similarity report
+    /// at the default threshold, as of the last update of the flags.
+    pub async fn attempts_with_similar_answers(&self, exam_id: Uuid) -> Result<HashSet<Uuid>> {
+        Ok(self
+            .repo
+            .get_similar_attempts(exam_id)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Makes the similarity report at the default threshold for up to
+    /// [`SIMILARITY_BATCH`] exams with attempts scored since the last time,
+    /// stores which attempts it flags and returns for how many exams.
+    pub async fn update_similarity_flags(&self) -> Result<usize> {
+        let exam_ids = self.repo.claim_stale_similarity(SIMILARITY_BATCH).await?;
+        let mut updated = 0;
+        for exam_id in exam_ids {
+            let flagged = async {
+                let report = self.similarity_report(exam_id, DEFAULT_THRESHOLD).await?;
+                let attempt_ids: Vec<Uuid> = report
+                    .iter()
+                    .flat_map(|task| &task.pairs)
+                    .flat_map(|pair| [pair.first_attempt_id, pair.second_attempt_id])
+                    .collect();
+                self.repo.set_similar_answers(exam_id, &attempt_ids).await
+            };
+            if let Err(e) = flagged.await {
+                warn!("Failed to update similarity flags of exam {exam_id}: {e}");
+                self.repo.mark_similarity_stale(exam_id).await?;
+                continue;
+            }
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
     #[allow(clippy::too_many_lines)]
     #[allow(clippy::cast_possible_wrap)]
     #[allow(clippy::cast_precision_loss)]
@@ -363,19 +972,14 @@ impl ExamService {
         let user = self.repo.get_user_by_id(attempt.user_id).await?;
         let mut scoring_data = ScoringData {
             show_results: false,
-            results: HashMap::default(),
+            late_penalty_waived: attempt.scoring_data.late_penalty_waived,
+            ..Default::default()
         };
-        let exam = self
-            .get_exam(attempt.exam_id, attempt.user_id, user.role)
-            .await?;
-        let entities = self.get_entities(attempt.exam_id).await?;
-        let tasks = entities
-            .iter()
-            .filter_map(|e| match e {
-                ExamExtendedEntity::Task { task } => Some(task),
-                ExamExtendedEntity::Text { .. } => None,
-            })
-            .collect::<Vec<_>>();
+        // the learner may have lost access to the exam since, which doesn't
+        // change what the attempt is worth
+        let exam = self.repo.get(attempt.exam_id).await?;
+        let tasks = self.get_attempt_tasks(&attempt).await?;
+        let answer_data = attempt.answer_data.0.clone();
         for ctfd_task in tasks
             .iter()
             .filter(|x| matches!(x.task_type, TaskType::CTFd))
@@ -396,25 +1000,336 @@ impl ExamService {
             }
         }
         for (task_id, user_answer) in attempt.answer_data.answers.clone() {
-            let task = tasks
-                .iter()
-                .find(|t| t.id == task_id as i64)
-                .expect("There are answers for tasks that are not in exam");
-            scoring_data
-                .results
-                .insert(task_id, task.grade(&user_answer));
+            // old attempts may hold answers to tasks the exam no longer has
+            let Some(task) = tasks.iter().find(|t| t.id == task_id as i64) else {
+                warn!(
+                    "Attempt {} has an answer to task {task_id} that isn't in it",
+                    attempt.id
+                );
+                continue;
+            };
+            let mut verdict = task.grade(&user_answer)?;
+            // an attempt is scored again when a late answer comes, which
+            // drops the result of its own task only, so what reviewers have
+            // done for the others stands
+            if matches!(verdict, TaskVerdict::OnReview)
+                && let Some(reviewed) = attempt.scoring_data.results.get(&task_id)
+            {
+                verdict = reviewed.clone();
+                if let Some(rubric) = attempt.scoring_data.rubrics.get(&task_id) {
+                    scoring_data.rubrics.insert(task_id, rubric.clone());
+                }
+            }
+            scoring_data.results.insert(task_id, verdict);
+        }
+
+        if let Some(policy) = exam.late_policy.as_deref() {
+            for (task_id, answered_at) in &attempt.answer_data.late {
+                let rate = policy.penalty_rate(*answered_at - attempt.due_at);
+                if rate > 0f64 {
+                    scoring_data.late_penalties.insert(*task_id, rate);
+                }
+            }
         }
 
         if matches!(exam.r#type, ExamType::Instant) {
             scoring_data.show_results = true;
         }
-        self.repo
-            .update_attempt_score(attempt.id, &scoring_data)
-            .await?;
+        // the result of a run stays until the answer changes, which drops it
+        let code_runs: Vec<i32> = code_task_ids(&tasks, &attempt.answer_data.answers)
+            .into_iter()
+            .filter(|task_id| {
+                matches!(
+                    scoring_data.results.get(&(*task_id as usize)),
+                    Some(TaskVerdict::OnReview)
+                )
+            })
+            .collect();
+        if !self
+            .repo
+            .update_attempt_score(attempt.id, &answer_data, &scoring_data, &code_runs)
+            .await?
+        {
+            info!(
+                "Answers of attempt {} changed while it was scored",
+                attempt.id
+            );
+        }
 
         Ok(scoring_data)
     }
 
+    /// Runs up to `workers` queued `Code` answers at once, until none are
+    /// left, and returns how many ran. Each verdict replaces the `OnReview`
+    /// that grading left for the task; failures of the sandbox itself keep it
+    /// for a reviewer. Runs whose worker dies are taken over once their lease
+    /// is over.
+    #[allow(clippy::cast_possible_wrap)]
+    pub async fn run_pending_code(&self, workers: usize) -> Result<usize> {
+        let Some(sandbox) = &self.sandbox else {
+            return Err(LMSError::ServerError(
+                "This instance doesn't run code".to_string(),
+            ));
+        };
+        let mut ran = 0;
+        loop {
+            let runs = self
+                .repo
+                .claim_code_runs(workers.max(1) as i64, Utc::now() + CODE_RUN_LEASE)
+                .await?;
+            if runs.is_empty() {
+                return Ok(ran);
+            }
+            ran += runs.len();
+            stream::iter(runs)
+                .for_each_concurrent(workers.max(1), |run| async move {
+                    if let Err(e) = self.run_code(sandbox, &run).await {
+                        warn!(
+                            "Failed to run code of attempt {} for task {}: {e}",
+                            run.attempt_id, run.task_id
+                        );
+                    }
+                })
+                .await;
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    async fn run_code(&self, sandbox: &Sandbox, run: &PendingCodeRun) -> Result<()> {
+        let attempt = self.repo.get_attempt(run.attempt_id).await?;
+        let tasks = self.get_attempt_tasks(&attempt).await?;
+        let task = tasks.iter().find(|task| task.id == i64::from(run.task_id));
+        let answer = attempt.answer_data.answers.get(&(run.task_id as usize));
+        // regraded away from the task meanwhile
+        let (Some(task), Some(answer)) = (task, answer) else {
+            return self.repo.finish_code_run(run, None).await;
+        };
+        let verdict = match task.run_code(sandbox, answer).await {
+            Ok(verdict) => Some(verdict),
+            Err(e) => {
+                warn!(
+                    "Failed to run code of attempt {} for task {}: {e}",
+                    run.attempt_id, run.task_id
+                );
+                None
+            }
+        };
+        self.repo.finish_code_run(run, verdict).await
+    }
+
+    /// Regrades finished attempts of an exam against the latest revisions of
+    /// the tasks they are pinned to (tasks removed from the exam included) and
+    /// returns the attempts whose revisions change. Nothing is written unless
+    /// `commit` is set. A reviewer's verdict survives when the task still
+    /// needs manual review and kept its points.
+    #[allow(clippy::too_many_lines)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    pub async fn regrade_against_latest(
+        &self,
+        exam_id: Uuid,
+        commit: bool,
+    ) -> Result<Vec<AttemptRegradeDTO>> {
+        let now = Utc::now();
+        let attempts: Vec<ExamAttempt> = self
+            .repo
+            .get_all_exam_attempts(exam_id)
+            .await?
+            .into_iter()
+            .filter(|attempt| attempt.due_at <= now)
+            .collect();
+        let attempt_ids: Vec<Uuid> = attempts.iter().map(|attempt| attempt.id).collect();
+        let pinned = self.get_attempts_tasks(&attempt_ids).await?;
+        let task_ids: Vec<i32> = pinned
+            .values()
+            .flatten()
+            .map(|revision| revision.task.id as i32)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let latest: HashMap<i64, TaskRevision> = self
+            .repo
+            .get_latest_task_revisions(&task_ids)
+            .await?
+            .into_iter()
+            .map(|revision| (revision.task.id, revision))
+            .collect();
+
+        let mut regraded = Vec::new();
+        for attempt in attempts {
+            let Some(old_revisions) = pinned.get(&attempt.id) else {
+                continue;
+            };
+            let new_revisions: Vec<&TaskRevision> = old_revisions
+                .iter()
+                .map(|old| latest.get(&old.task.id).unwrap_or(old))
+                .collect();
+            if old_revisions
+                .iter()
+                .zip(&new_revisions)
+                .all(|(old, new)| old.id == new.id)
+            {
+                continue;
+            }
+
+            let mut scoring_data = ScoringData {
+                show_results: attempt.scoring_data.show_results,
+                late_penalties: attempt.scoring_data.late_penalties.clone(),
+                late_penalty_waived: attempt.scoring_data.late_penalty_waived,
+                ..Default::default()
+            };
+            let mut tasks = Vec::new();
+            // code is rerun against the new tests once the regrade is committed
+            let mut rerun = Vec::new();
+            for (old, new) in old_revisions.iter().zip(&new_revisions) {
+                let key = new.task.id as usize;
+                let task = new.task.clone().instantiate(attempt.shuffle_seed)?;
+                let old_verdict = attempt.scoring_data.results.get(&key);
+                // CTFd solves are only ever recorded in the verdicts
+                let answer = attempt.answer_data.answers.get(&key).cloned().or_else(|| {
+                    old_verdict
+                        .filter(|_| matches!(task.configuration, TaskConfig::CTFd { .. }))
+                        .map(|_| TaskAnswer::CTFd)
+                });
+                let mut review_kept = false;
+                let graded = |answer: &TaskAnswer| {
+                    task.validate_answer(answer)?;
+                    task.grade(answer).map_err(LMSError::from)
+                };
+                let verdict = answer.map(|answer| {
+                    graded(&answer).map_or_else(
+                        |_| TaskVerdict::Incorrect {
+                            comment: Some(
+                                "The answer doesn't fit the task's new revision".to_string(),
+                            ),
+                            score: 0f64,
+                            max_score: new.task.points as f64,
+                        },
+                        |verdict| match (verdict, old_verdict) {
+                            (TaskVerdict::OnReview, Some(old_verdict))
+                                if old.task.points == new.task.points =>
+                            {
+                                review_kept = true;
+                                old_verdict.clone()
+                            }
+                            (verdict, _) => verdict,
+                        },
+                    )
+                });
+
+                let old_score = old_verdict.map_or(0f64, |verdict| *verdict.score());
+                let new_score = verdict.as_ref().map_or(0f64, |verdict| *verdict.score());
+                if old.id != new.id || (old_score - new_score).abs() > f64::EPSILON {
+                    tasks.push(TaskRegradeDTO {
+                        task_id: new.task.id,
+                        old_revision: old.revision,
+                        new_revision: new.revision,
+                        old_score,
+                        new_score,
+                    });
+                }
+                if let Some(verdict) = verdict {
+                    scoring_data.results.insert(key, verdict);
+                }
+                if review_kept && let Some(grades) = attempt.scoring_data.rubrics.get(&key) {
+                    scoring_data.rubrics.insert(key, grades.clone());
+                }
+                if old.id != new.id && matches!(task.configuration, TaskConfig::Code { .. }) {
+                    rerun.push(task);
+                }
+            }
+
+            if commit {
+                let revision_ids: Vec<i64> =
+                    new_revisions.iter().map(|revision| revision.id).collect();
+                let code_runs = code_task_ids(&rerun, &attempt.answer_data.answers);
+                self.repo
+                    .repin_attempt(attempt.id, &revision_ids, &scoring_data, &code_runs)
+                    .await?;
+            }
+            regraded.push(AttemptRegradeDTO {
+                attempt_id: attempt.id,
+                user_id: attempt.user_id,
+                old_score: attempt.scoring_data.total_score(),
+                new_score: scoring_data.total_score(),
+                old_max_score: old_revisions
+                    .iter()
+                    .map(|revision| revision.task.points)
+                    .sum(),
+                new_max_score: new_revisions
+                    .iter()
+                    .map(|revision| revision.task.points)
+                    .sum(),
+                tasks,
+            });
+        }
+
+        Ok(regraded)
+    }
+
+    /// Scores up to [`SCORING_BATCH`] attempts of any exam that are over but
+    /// unscored, at most `workers` at once, and returns how many were scored.
+    /// Failures are kept on the attempts, which are retried with a growing
+    /// delay unless the failure isn't going to go away by itself.
+    pub async fn score_expired(&self, workers: usize) -> Result<usize> {
+        let attempts = self
+            .repo
+            .claim_expired_attempts(SCORING_BATCH, Utc::now() + SCORING_LEASE)
+            .await?;
+        let scored = AtomicUsize::new(0);
+        stream::iter(attempts)
+            .for_each_concurrent(workers.max(1), |attempt| async {
+                let attempt_id = attempt.id;
+                // a panic only fails the attempt it happened on
+                let service = self.clone();
+                let result = tokio::spawn(async move { service.score_attempt(attempt).await })
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(LMSError::ServerError(format!("Scoring panicked: {e}")))
+                    });
+                match result {
+                    Ok(_) => {
+                        scored.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        if let Err(e) = self.postpone_scoring(attempt_id, &e).await {
+                            warn!("Failed to record scoring error of attempt {attempt_id}: {e}");
+                        }
+                    }
+                }
+            })
+            .await;
+        Ok(scored.into_inner())
+    }
+
+    async fn postpone_scoring(&self, attempt_id: Uuid, error: &LMSError) -> Result<()> {
+        warn!("Failed to score attempt {attempt_id}: {error}");
+        let failures = self
+            .repo
+            .record_scoring_error(attempt_id, error.to_string())
+            .await?;
+        // CTFd being unreachable and the like; anything else fails the same
+        // way next time, so it waits for a reviewer to score the exam
+        let transient = matches!(error, LMSError::Unknown(_) | LMSError::DatabaseError(_));
+        let until = if transient && failures < MAX_SCORING_FAILURES {
+            Utc::now() + SCORING_BACKOFF * 2i32.pow(failures.unsigned_abs() - 1)
+        } else {
+            DateTime::<Utc>::MAX_UTC
+        };
+        self.repo.postpone_scoring(attempt_id, until).await
+    }
+
+    /// Last errors of the exam's attempts that couldn't be scored, by attempt.
+    pub async fn scoring_errors(&self, exam_id: Uuid) -> Result<HashMap<Uuid, String>> {
+        Ok(self
+            .repo
+            .get_scoring_errors(exam_id)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
     pub async fn score_unscored(&self, exam_id: Uuid) -> Result<usize> {
         let attempts = self.repo.get_exam_unscored_attempts(exam_id).await?;
         let mut counter: usize = 0;
@@ -476,4 +1391,157 @@ impl ExamService {
     pub async fn delete_text(&self, text_id: Uuid) -> Result<()> {
         self.repo.delete_text(text_id).await
     }
+
+    pub async fn create_pool(&self, mut pool: PoolUpsertDTO) -> Result<PoolEntity> {
+        Self::normalize_pool(&mut pool);
+        self.repo.create_pool(pool).await
+    }
+
+    pub async fn update_pool(&self, pool_id: Uuid, mut pool: PoolUpsertDTO) -> Result<PoolEntity> {
+        Self::normalize_pool(&mut pool);
+        self.repo.update_pool(pool_id, pool).await
+    }
+
+    pub async fn delete_pool(&self, pool_id: Uuid) -> Result<()> {
+        self.repo.delete_pool(pool_id).await
+    }
+
+    pub async fn create_section(&self, section: SectionUpsertDTO) -> Result<SectionEntity> {
+        self.repo.create_section(section).await
+    }
+
+    pub async fn update_section(
+        &self,
+        section_id: Uuid,
+        section: SectionUpsertDTO,
+    ) -> Result<SectionEntity> {
+        self.repo.update_section(section_id, section).await
+    }
+
+    pub async fn delete_section(&self, section_id: Uuid) -> Result<()> {
+        self.repo.delete_section(section_id).await
+    }
+
+    /// Tags of tasks are stored lowercase, and so is a pool's one.
+    fn normalize_pool(pool: &mut PoolUpsertDTO) {
+        pool.tag = pool.tag.as_deref().map(|tag| tag.trim().to_lowercase());
+        pool.task_ids.sort_unstable();
+        pool.task_ids.dedup();
+    }
+
+    /// Packs the exam as a QTI 2.1 assessment test with its tasks as items.
+    pub async fn export_qti(&self, exam_id: Uuid) -> Result<ExportFile> {
+        let exam = self.repo.get(exam_id).await?;
+        let entities = self.repo.get_entities(exam_id).await?;
+        Ok(ExportFile {
+            bytes: qti::export_test(&exam, &entities).map_err(LMSError::ServerError)?,
+            content_type: "application/zip",
+            filename: "exam.zip".to_string(),
+        })
+    }
+
+    /// Converts a QTI 2.1 assessment test into an exam in `topic_id`: items
+    /// become tasks, rubric blocks become texts, in test order. Nothing is
+    /// created unless `commit` is set.
+    pub async fn import_qti(
+        &self,
+        package: &[u8],
+        topic_id: i32,
+        commit: bool,
+        author_id: Uuid,
+        role: UserRole,
+    ) -> Result<ExamImportReportDTO> {
+        let _ = self
+            .topic_service
+            .get_topic_by_id(author_id, role, topic_id)
+            .await?; // need it to check for access
+        let assessment = qti::import_test(package).map_err(LMSError::ShitHappened)?;
+        let name = if assessment.title.is_empty() {
+            "Imported exam".to_string()
+        } else {
+            assessment.title
+        };
+
+        let mut layout = Vec::with_capacity(assessment.entities.len());
+        let mut texts = Vec::new();
+        let mut questions = Vec::new();
+        for entity in assessment.entities {
+            match entity {
+                QtiEntity::Text(text) => {
+                    layout.push(false);
+                    texts.push(text);
+                }
+                QtiEntity::Question(question) => {
+                    layout.push(true);
+                    questions.push(*question);
+                }
+            }
+        }
+        let text_count = texts.len();
+        let (questions, tasks) = TaskService::prepare_import(questions);
+        if !commit {
+            return Ok(ExamImportReportDTO {
+                committed: false,
+                exam_id: None,
+                name,
+                texts: text_count,
+                tasks: TaskService::import_report(questions, None),
+            });
+        }
+
+        let mut texts = texts.into_iter();
+        let mut valid = questions
+            .iter()
+            .map(|question| question.task_type.is_some());
+        let mut tasks = tasks.into_iter();
+        let mut entities = Vec::with_capacity(layout.len());
+        for is_task in layout {
+            if is_task {
+                // skipped questions have no task and are left out
+                if valid.next() == Some(true)
+                    && let Some(task) = tasks.next()
+                {
+                    entities.push(ImportedExamEntity::Task(task));
+                }
+            } else if let Some(text) = texts.next() {
+                entities.push(ImportedExamEntity::Text(text));
+            }
+        }
+        let exam_data = UpsertExamRequestDTO {
+            topic_id,
+            name: name.clone(),
+            description: assessment.description,
+            tries_count: 1,
+            duration: assessment.duration.unwrap_or(DEFAULT_IMPORTED_DURATION),
+            r#type: ExamType::Instant,
+            starts_at: None,
+            ends_at: None,
+            scoring_policy: ExamScoringPolicy::default(),
+            shuffle_entities: assessment.shuffle,
+            late_policy: None,
+        };
+        let (exam, created) = self
+            .repo
+            .import_exam(exam_data, entities, author_id)
+            .await?;
+
+        Ok(ExamImportReportDTO {
+            committed: true,
+            exam_id: Some(exam.id),
+            name,
+            texts: text_count,
+            tasks: TaskService::import_report(questions, Some(created)),
+        })
+    }
+}
+
+/// Tasks among `tasks` whose answers in `answers` have code to run.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn code_task_ids(tasks: &[Task], answers: &HashMap<usize, TaskAnswer>) -> Vec<i32> {
+    tasks
+        .iter()
+        .filter(|task| matches!(task.configuration, TaskConfig::Code { .. }))
+        .filter(|task| answers.contains_key(&(task.id as usize)))
+        .map(|task| task.id as i32)
+        .collect()
 }
@@ -0,0 +1,140 @@
+//! Near-duplicate detection for free-text answers of different learners.
+//!
+//! An answer is split into lowercase words, and every run of
+//! [`SHINGLE_WORDS`] consecutive words is a shingle. Two answers are as
+//! similar as the Jaccard index of their shingle sets, and the runs of shared
+//! shingles are the fragments they have in common.
+
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Similarity from which a pair of answers is flagged unless asked otherwise.
+pub const DEFAULT_THRESHOLD: f64 = 0.5;
+/// Words per shingle.
+const SHINGLE_WORDS: usize = 3;
+/// Answers shorter than this many words are left out: "I don't know" is the
+/// same for everyone.
+const MIN_WORDS: usize = 5;
+/// Upper bound for the shared fragments listed per pair.
+const MAX_FRAGMENTS: usize = 5;
+
+/// What an answer is compared by.
+pub struct Fingerprint {
+    words: Vec<String>,
+    /// Hash of the shingle starting at every word, in text order.
+    shingles: Vec<u64>,
+    set: HashSet<u64>,
+}
+
+impl Fingerprint {
+    /// `None` for answers too short to compare.
+    pub fn new(text: &str) -> Option<Self> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.len() < MIN_WORDS {
+            return None;
+        }
+        let shingles: Vec<u64> = words
+            .windows(SHINGLE_WORDS)
+            .map(|shingle| {
+                let mut hasher = DefaultHasher::new();
+                shingle.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        let set = shingles.iter().copied().collect();
+        Some(Self {
+            words,
+            shingles,
+            set,
+        })
+    }
+
+    /// Jaccard index of the shingle sets, from 0 (nothing shared) to 1.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn similarity(&self, other: &Self) -> f64 {
+        let shared = self.set.intersection(&other.set).count();
+        let union = self.set.len() + other.set.len() - shared;
+        shared as f64 / union as f64
+    }
+
+    /// The longest runs of this answer's words that `other` has too.
+    pub fn shared_fragments(&self, other: &Self) -> Vec<String> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut start = None;
+        for (i, shingle) in self.shingles.iter().enumerate() {
+            match (other.set.contains(shingle), start) {
+                (true, None) => start = Some(i),
+                (false, Some(first)) => {
+                    runs.push((first, i - 1));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            runs.push((first, self.shingles.len() - 1));
+        }
+        runs.sort_by_key(|(first, last)| std::cmp::Reverse(last - first));
+        runs.into_iter()
+            .take(MAX_FRAGMENTS)
+            .map(|(first, last)| self.words[first..last + SHINGLE_WORDS].join(" "))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(text: &str) -> Fingerprint {
+        Fingerprint::new(text).expect("Answer is long enough")
+    }
+
+    #[test]
+    fn short_answers_are_left_out() {
+        assert!(Fingerprint::new("I don't know").is_none());
+        assert!(Fingerprint::new("one, two... three -- four!").is_none());
+        assert!(Fingerprint::new("one two three four five").is_some());
+    }
+
+    #[test]
+    fn similarity_is_the_jaccard_index() {
+        let answer = fingerprint("The quick brown fox jumps over the lazy dog");
+        assert!((answer.similarity(&answer) - 1f64).abs() < 1e-9);
+        // case and punctuation don't matter
+        let shouting = fingerprint("THE QUICK, BROWN FOX JUMPS OVER THE LAZY DOG!");
+        assert!((answer.similarity(&shouting) - 1f64).abs() < 1e-9);
+        let unrelated = fingerprint("Completely different words are written in this one");
+        assert!(answer.similarity(&unrelated).abs() < 1e-9);
+        // 7 shingles each, 4 of them shared
+        let half = fingerprint("The quick brown fox jumps over a sleepy cat");
+        assert!((answer.similarity(&half) - 4f64 / 10f64).abs() < 1e-9);
+        assert!(
+            (answer.similarity(&half) - half.similarity(&answer)).abs() < 1e-9,
+            "Similarity is symmetric"
+        );
+    }
+
+    #[test]
+    fn shared_fragments_are_longest_first() {
+        let answer =
+            fingerprint("alpha beta gamma delta one two three epsilon zeta eta theta iota kappa");
+        let other = fingerprint(
+            "one two three and then epsilon zeta eta theta iota kappa and alpha beta gamma",
+        );
+        assert_eq!(
+            answer.shared_fragments(&other),
+            [
+                "epsilon zeta eta theta iota kappa",
+                "alpha beta gamma",
+                "one two three"
+            ]
+        );
+        let unrelated = fingerprint("nothing in common with any of those");
+        assert!(answer.shared_fragments(&unrelated).is_empty());
+    }
+}
@@ -1,6 +1,9 @@
-use crate::domain::task::model::{Task, TaskConfig, TaskType};
+use crate::domain::task::model::{Task, TaskConfig, TaskDifficulty, TaskType};
+use crate::domain::task::template::TaskTemplate;
+use crate::errors::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+use uuid::Uuid;
 
 /// A practice container as stored in the `practices` table.
 #[derive(FromRow, Debug)]
@@ -34,6 +37,11 @@ pub struct PracticeTaskRow {
     pub task_type: TaskType,
     pub points: i64,
     pub configuration: TaskConfig,
+    pub template: TaskTemplate,
+    pub tags: Vec<String>,
+    pub difficulty: Option<TaskDifficulty>,
+    pub author_id: Option<Uuid>,
+    pub explanation: Option<String>,
     pub order_index: i32,
     pub solved: bool,
     pub attempts: i32,
@@ -50,12 +58,39 @@ impl PracticeTaskRow {
                 task_type: self.task_type,
                 points: self.points,
                 configuration: self.configuration,
+                template: self.template,
+                tags: self.tags,
+                difficulty: self.difficulty,
+                author_id: self.author_id,
+                explanation: self.explanation,
             },
             self.order_index,
             self.solved,
             self.attempts,
         )
     }
+
+    /// The same row with the task rendered for a learner's template seed.
+    pub fn instantiate(self, seed: i64) -> Result<Self> {
+        let (task, order_index, solved, attempts) = self.into_task();
+        let task = task.instantiate(seed)?;
+        Ok(Self {
+            id: task.id,
+            title: task.title,
+            description: task.description,
+            task_type: task.task_type,
+            points: task.points,
+            configuration: task.configuration,
+            template: task.template,
+            tags: task.tags,
+            difficulty: task.difficulty,
+            author_id: task.author_id,
+            explanation: task.explanation,
+            order_index,
+            solved,
+            attempts,
+        })
+    }
 }
 
 /// Per-user progress for a single practice task.
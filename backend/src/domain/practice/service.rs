@@ -3,7 +3,7 @@ use crate::domain::practice::model::{
     PracticeModel, PracticeProgressModel, PracticeSummary, PracticeTaskRow,
 };
 use crate::domain::practice::repository::PracticeRepository;
-use crate::domain::task::model::{Task, TaskAnswer, TaskConfig, TaskSolution};
+use crate::domain::task::model::{Task, TaskAnswer, TaskSolution};
 use crate::domain::task::service::TaskService;
 use crate::domain::topics::service::TopicService;
 use crate::dto::practice::{CreatePracticeRequestDTO, UpdatePracticeRequestDTO};
@@ -34,18 +34,12 @@ impl PracticeService {
         }
     }
 
-    /// Practice only makes sense for task types that can be graded automatically,
-    /// since no one reviews practice submissions.
-    const fn is_auto_gradable(config: &TaskConfig) -> bool {
-        match config {
-            TaskConfig::SingleChoice { .. }
-            | TaskConfig::MultipleChoice { .. }
-            | TaskConfig::Ordering { .. } => true,
-            TaskConfig::ShortText { auto_grade, .. } => *auto_grade,
-            TaskConfig::LongText { .. }
-            | TaskConfig::FileUpload { .. }
-            | TaskConfig::CTFd { .. } => false,
-        }
+    /// Template seed of a learner's practice variants. Stable per user, so the
+    /// task they are shown is the one their answers are graded against.
+    #[allow(clippy::cast_possible_wrap)]
+    const fn practice_seed(user: Uuid) -> i64 {
+        let (high, low) = user.as_u64_pair();
+        (high ^ low) as i64
     }
 
     async fn ensure_topic_access(&self, user: Uuid, role: UserRole, topic_id: i32) -> Result<()> {
@@ -97,7 +91,13 @@ impl PracticeService {
         id: i32,
     ) -> Result<(PracticeModel, Vec<PracticeTaskRow>)> {
         let practice = self.ensure_practice_access(user, role, id).await?;
-        let tasks = self.repo.list_tasks(id, user).await?;
+        let tasks = self
+            .repo
+            .list_tasks(id, user)
+            .await?
+            .into_iter()
+            .map(|row| row.instantiate(Self::practice_seed(user)))
+            .collect::<Result<_>>()?;
         Ok((practice, tasks))
     }
 
@@ -136,12 +136,13 @@ impl PracticeService {
         task: UpsertTaskRequestDTO,
     ) -> Result<Task> {
         self.ensure_practice_access(user, role, practice_id).await?;
-        if !Self::is_auto_gradable(&task.configuration) {
+        // practice only makes sense for auto-gradable tasks, since no one reviews it
+        if !task.configuration.is_auto_gradable() {
             return Err(LMSError::ShitHappened(
                 "Only auto-gradable tasks can be used for practice".to_string(),
             ));
         }
-        let created = self.task_service.create_task(task).await?;
+        let created = self.task_service.create_task(task, user).await?;
         let order = self.repo.next_task_order(practice_id).await?;
         #[allow(clippy::cast_possible_truncation)]
         self.repo
@@ -164,7 +165,8 @@ impl PracticeService {
                 "Task is not part of this practice".to_string(),
             ));
         }
-        if !Self::is_auto_gradable(&task.configuration) {
+        // practice only makes sense for auto-gradable tasks, since no one reviews it
+        if !task.configuration.is_auto_gradable() {
             return Err(LMSError::ShitHappened(
                 "Only auto-gradable tasks can be used for practice".to_string(),
             ));
@@ -212,9 +214,13 @@ impl PracticeService {
             ));
         }
 
-        let task = self.task_service.get_task(task_id).await?;
+        let task = self
+            .task_service
+            .get_task(task_id)
+            .await?
+            .instantiate(Self::practice_seed(user))?;
         task.validate_answer(&answer)?;
-        let verdict = task.grade(&answer);
+        let verdict = task.grade(&answer)?;
         let solved = matches!(verdict, TaskVerdict::FullScore { .. });
         let solution = if solved { task.solution() } else { None };
 
@@ -16,6 +16,17 @@ pub struct CourseExamTask {
     pub points: i64,
 }
 
+/// One task pool of an exam within a course, worth as many average tasks it
+/// draws from as it draws. Produced by [`RatingRepository::course_exam_pools`].
+#[derive(FromRow, Debug, Clone)]
+pub struct CourseExamPool {
+    pub exam_id: Uuid,
+    pub exam_name: String,
+    pub scoring_policy: ExamScoringPolicy,
+    pub pool_id: Uuid,
+    pub points: f64,
+}
+
 /// One practice task reachable within a course. Produced by
 /// [`RatingRepository::course_practice_tasks`].
 #[derive(FromRow, Debug, Clone)]
@@ -27,13 +38,15 @@ pub struct CoursePracticeTask {
 }
 
 /// A scored exam attempt, scoped to a course's exams. Only the fields needed to
-/// compute a rating are fetched.
+/// compute a rating are fetched; `max_score` sums the task revisions the
+/// attempt was started with.
 #[derive(FromRow, Clone)]
 pub struct RatingAttempt {
     pub exam_id: Uuid,
     pub user_id: Uuid,
     pub started_at: DateTime<Utc>,
     pub scoring_data: Json<ScoringData>,
+    pub max_score: i64,
 }
 
 /// A single solved practice task by a user.
@@ -1,5 +1,5 @@
 use crate::domain::rating::model::{
-    CourseExamTask, CoursePracticeTask, CourseRef, PracticeSolve, RatingAttempt,
+    CourseExamPool, CourseExamTask, CoursePracticeTask, CourseRef, PracticeSolve, RatingAttempt,
 };
 use crate::domain::report::model::ReportUser;
 use crate::errors::Result;
@@ -15,6 +15,9 @@ pub trait RatingRepository {
     /// scoring policy.
     async fn course_exam_tasks(&self, course_id: i32) -> Result<Vec<CourseExamTask>>;
 
+    /// Every task pool of the course's exams with its expected worth.
+    async fn course_exam_pools(&self, course_id: i32) -> Result<Vec<CourseExamPool>>;
+
     /// Every practice task reachable within a course.
     async fn course_practice_tasks(&self, course_id: i32) -> Result<Vec<CoursePracticeTask>>;
 
@@ -1,7 +1,9 @@
 use crate::domain::account::model::UserRole;
 use crate::domain::courses::service::CourseService;
 use crate::domain::exam::model::ExamScoringPolicy;
-use crate::domain::rating::model::{CourseExamTask, CoursePracticeTask, RatingAttempt};
+use crate::domain::rating::model::{
+    CourseExamPool, CourseExamTask, CoursePracticeTask, RatingAttempt,
+};
 use crate::domain::rating::repository::RatingRepository;
 use crate::domain::report::model::ExportFile;
 use crate::domain::report::service::{ExportFormat, csv_escape};
@@ -17,8 +19,8 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use uuid::Uuid;
 
-/// A course's exams reduced to what the rating needs: the current task set (for
-/// bounding earned score) plus the total and the scoring policy.
+/// A course's exams reduced to what the rating needs: the current task set
+/// (for the total) plus the total and the scoring policy.
 struct ExamAgg {
     id: Uuid,
     name: String,
@@ -84,6 +86,7 @@ impl RatingService {
     /// Fetches all raw data for a course and folds it into a reusable aggregate.
     async fn build_aggregate(&self, course_id: i32) -> Result<CourseAggregate> {
         let exam_tasks: Vec<CourseExamTask> = self.repo.course_exam_tasks(course_id).await?;
+        let exam_pools: Vec<CourseExamPool> = self.repo.course_exam_pools(course_id).await?;
         let practice_tasks: Vec<CoursePracticeTask> =
             self.repo.course_practice_tasks(course_id).await?;
         let attempts = self.repo.course_exam_attempts(course_id).await?;
@@ -103,6 +106,18 @@ impl RatingService {
                 entry.max += row.points as f64;
             }
         }
+        // attempts draw different tasks from a pool, so it counts at its
+        // expected worth
+        for row in exam_pools {
+            let entry = exam_map.entry(row.exam_id).or_insert_with(|| ExamAgg {
+                id: row.exam_id,
+                name: row.exam_name.clone(),
+                policy: row.scoring_policy,
+                task_ids: HashSet::new(),
+                max: 0.0,
+            });
+            entry.max += row.points;
+        }
 
         let mut practice_map: HashMap<i32, PracticeAgg> = HashMap::new();
         for row in practice_tasks {
@@ -141,17 +156,17 @@ impl RatingService {
         })
     }
 
-    /// Total score of one attempt, counting only tasks still present in the exam
-    /// (so earned can never exceed the current max). On-review verdicts score 0.
-    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
-    fn attempt_score(attempt: &RatingAttempt, task_ids: &HashSet<i32>) -> f64 {
-        attempt
-            .scoring_data
-            .results
-            .iter()
-            .filter(|(task_id, _)| task_ids.contains(&(**task_id as i32)))
-            .map(|(_, verdict)| *verdict.score())
-            .sum()
+    /// Score of one attempt on the exam's current scale. Every task the attempt
+    /// was started with counts (removed ones too), and the attempt's share of
+    /// its own max is mapped onto `exam_max`, so earned never exceeds the
+    /// current max. On-review verdicts score 0.
+    #[allow(clippy::cast_precision_loss)]
+    fn attempt_score(attempt: &RatingAttempt, exam_max: f64) -> f64 {
+        if attempt.max_score <= 0 {
+            return 0.0;
+        }
+        let earned = attempt.scoring_data.total_score();
+        (earned / attempt.max_score as f64).min(1.0) * exam_max
     }
 
     /// A user's score for one exam, collapsing their attempts by the exam's
@@ -162,7 +177,7 @@ impl RatingService {
             .attempts
             .iter()
             .filter(|a| a.user_id == user && a.exam_id == exam.id)
-            .map(|a| (a.started_at, Self::attempt_score(a, &exam.task_ids)))
+            .map(|a| (a.started_at, Self::attempt_score(a, exam.max)))
             .collect();
 
         if scores.is_empty() {
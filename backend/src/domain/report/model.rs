@@ -1,3 +1,4 @@
+use crate::domain::task::model::TaskType;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
@@ -24,7 +25,8 @@ pub enum AttemptStatus {
     Graded,
 }
 
-/// A task in an exam, used for the per-task export columns.
+/// A task in an exam, used for the per-task export columns. Includes tasks
+/// removed from the exam that some attempt was still started with.
 #[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
 pub struct GradebookTask {
     pub id: i64,
@@ -42,9 +44,14 @@ pub struct GradebookRow {
     pub started_at: DateTime<Utc>,
     pub ends_at: DateTime<Utc>,
     pub score: f64,
+    /// Total points of the task revisions this attempt was started with.
+    pub max_score: i64,
     pub status: AttemptStatus,
     /// Per-task score for this attempt, keyed by task id (as string).
     pub task_scores: HashMap<String, f64>,
+    /// Points the late answers lose; not taken from `score` once waived.
+    pub late_penalty: f64,
+    pub late_penalty_waived: bool,
 }
 
 /// Aggregate statistics across all attempts of an exam.
@@ -71,6 +78,55 @@ pub struct Gradebook {
     pub summary: GradebookSummary,
 }
 
+/// How often an option of a choice task was picked.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct OptionStats {
+    pub option: String,
+    pub picked: usize,
+    /// The option is (one of) the right ones.
+    pub correct: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct AnswerCount {
+    pub answer: String,
+    pub count: usize,
+}
+
+/// Classic item statistics of a task over the finished attempts that had it.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct ItemStats {
+    pub task_id: i64,
+    pub title: String,
+    pub task_type: TaskType,
+    pub max_score: i64,
+    /// Finished attempts that had the task.
+    pub attempts: usize,
+    /// Of them, the ones whose answer is graded; the indices go by these.
+    pub graded: usize,
+    /// Mean score over max score, from 0 (nobody solved it) to 1 (everyone
+    /// did). `None` without graded answers.
+    pub difficulty: Option<f64>,
+    /// Point-biserial correlation of the task's score with the rest of the
+    /// attempt's score, from -1 to 1; low or negative values flag a broken
+    /// task. `None` when either doesn't vary.
+    pub discrimination: Option<f64>,
+    /// Share of the attempts whose answer awaits review.
+    pub on_review_share: f64,
+    /// How often each option was picked, for choice tasks only.
+    pub options: Vec<OptionStats>,
+    /// The most common wrong answers, for `ShortText` tasks only.
+    pub common_wrong_answers: Vec<AnswerCount>,
+}
+
+/// Item analysis of an exam: statistics of every task of its gradebook.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct ItemAnalysis {
+    pub exam_id: Uuid,
+    pub exam_name: String,
+    pub items: Vec<ItemStats>,
+}
+
 /// A rendered export ready to be streamed as an HTTP file response.
 pub struct ExportFile {
     pub bytes: Vec<u8>,
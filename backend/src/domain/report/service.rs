@@ -2,10 +2,12 @@ use crate::domain::account::model::UserRole;
 use crate::domain::exam::model::ExamExtendedEntity;
 use crate::domain::exam::service::ExamService;
 use crate::domain::report::model::{
-    AttemptStatus, ExportFile, Gradebook, GradebookRow, GradebookSummary, GradebookTask,
+    AnswerCount, AttemptStatus, ExportFile, Gradebook, GradebookRow, GradebookSummary,
+    GradebookTask, ItemAnalysis, ItemStats, OptionStats,
 };
 use crate::domain::report::repository::ReportRepository;
-use crate::dto::exam::ScoringData;
+use crate::domain::task::model::{TaskAnswer, TaskConfig, TaskRevision, TaskType};
+use crate::dto::exam::{ExamAttempt, ScoringData};
 use crate::dto::task::TaskVerdict;
 use crate::errors::{LMSError, Result};
 use crate::repo;
@@ -24,8 +26,38 @@ const CSV_HEADER: &[&str] = &[
     "Status",
     "Started At",
     "Submitted/Deadline",
+    "Late Penalty",
 ];
 
+const ITEM_CSV_HEADER: &[&str] = &[
+    "Task",
+    "Type",
+    "Max Score",
+    "Attempts",
+    "Graded",
+    "Difficulty",
+    "Discrimination",
+    "On Review %",
+    "Option Picks",
+    "Common Wrong Answers",
+];
+
+/// How many of the most common wrong answers an item analysis lists.
+const COMMON_ANSWERS_LIMIT: usize = 10;
+
+/// Everything the reports of an exam are built from.
+struct ExamData {
+    exam_id: Uuid,
+    exam_name: String,
+    /// Points of the tasks currently in the exam.
+    max_score: i64,
+    tasks: Vec<GradebookTask>,
+    task_types: HashMap<i64, TaskType>,
+    attempts: Vec<ExamAttempt>,
+    /// Task revisions every attempt was started with, by attempt id.
+    attempt_tasks: HashMap<Uuid, Vec<TaskRevision>>,
+}
+
 #[derive(Clone)]
 pub struct ReportService {
     exam_service: ExamService,
@@ -37,10 +69,6 @@ impl ReportService {
         Self { exam_service, repo }
     }
 
-    fn attempt_score(scoring: &ScoringData) -> f64 {
-        scoring.results.values().map(TaskVerdict::score).sum()
-    }
-
     fn status_of(scoring: &ScoringData, window_open: bool) -> AttemptStatus {
         if window_open {
             AttemptStatus::InProgress
@@ -63,23 +91,92 @@ impl ReportService {
         user: Uuid,
         role: UserRole,
     ) -> Result<Gradebook> {
+        let data = self.exam_data(exam_id, user, role).await?;
+        self.build_gradebook(data).await
+    }
+
+    /// Item statistics of every task of an exam, see [`ItemStats`]. Requires
+    /// the caller to have access to the exam.
+    pub async fn exam_item_analysis(
+        &self,
+        exam_id: Uuid,
+        user: Uuid,
+        role: UserRole,
+    ) -> Result<ItemAnalysis> {
+        let data = self.exam_data(exam_id, user, role).await?;
+        Ok(Self::build_item_analysis(&data))
+    }
+
+    async fn exam_data(&self, exam_id: Uuid, user: Uuid, role: UserRole) -> Result<ExamData> {
         let exam = self.exam_service.get_exam(exam_id, user, role).await?;
 
         let entities = self.exam_service.get_entities(exam_id).await?;
-        let tasks: Vec<GradebookTask> = entities
+        let mut task_types = HashMap::new();
+        let mut tasks: Vec<GradebookTask> = entities
             .iter()
             .filter_map(|e| match e {
-                ExamExtendedEntity::Task { task } => Some(GradebookTask {
-                    id: task.id,
-                    title: task.title.clone(),
-                    max_score: task.points,
-                }),
-                ExamExtendedEntity::Text { .. } => None,
+                ExamExtendedEntity::Task { task } => {
+                    task_types.insert(task.id, task.task_type.clone());
+                    Some(GradebookTask {
+                        id: task.id,
+                        title: task.title.clone(),
+                        max_score: task.points,
+                    })
+                }
+                ExamExtendedEntity::Text { .. }
+                | ExamExtendedEntity::Pool { .. }
+                | ExamExtendedEntity::Section { .. } => None,
             })
             .collect();
-        let max_score: i64 = tasks.iter().map(|t| t.max_score).sum();
+        // tasks drawn from pools differ between attempts, so pools are taken
+        // at their expected worth
+        let mut pool_score = 0f64;
+        for entity in &entities {
+            if let ExamExtendedEntity::Pool { pool } = entity {
+                pool_score += self.exam_service.pool_max_score(pool).await?;
+            }
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let max_score: i64 =
+            tasks.iter().map(|t| t.max_score).sum::<i64>() + pool_score.round() as i64;
+
+        let attempts = self.exam_service.get_all_attempts(exam_id).await?;
+        let attempt_ids: Vec<Uuid> = attempts.iter().map(|a| a.id).collect();
+        let attempt_tasks = self.exam_service.get_attempts_tasks(&attempt_ids).await?;
+        // tasks drawn from pools or removed from the exam still count for
+        // attempts that had them
+        for revision in attempt_tasks.values().flatten() {
+            if !tasks.iter().any(|t| t.id == revision.task.id) {
+                tasks.push(GradebookTask {
+                    id: revision.task.id,
+                    title: revision.task.title.clone(),
+                    max_score: revision.task.points,
+                });
+                task_types.insert(revision.task.id, revision.task.task_type.clone());
+            }
+        }
 
-        let attempts = self.exam_service.get_all_attempts_scored(exam_id).await?;
+        Ok(ExamData {
+            exam_id,
+            exam_name: exam.name,
+            max_score,
+            tasks,
+            task_types,
+            attempts,
+            attempt_tasks,
+        })
+    }
+
+    async fn build_gradebook(&self, data: ExamData) -> Result<Gradebook> {
+        let ExamData {
+            exam_id,
+            exam_name,
+            max_score,
+            tasks,
+            attempts,
+            attempt_tasks,
+            ..
+        } = data;
 
         let user_ids: Vec<Uuid> = attempts
             .iter()
@@ -101,7 +198,8 @@ impl ReportService {
                     .get(&a.user_id)
                     .cloned()
                     .unwrap_or_else(|| ("<unknown>".to_string(), String::new()));
-                let window_open = a.ends_at > now;
+                // the late window after `due_at` doesn't keep it in progress
+                let window_open = a.due_at > now;
                 let task_scores = tasks
                     .iter()
                     .map(|task| {
@@ -122,9 +220,14 @@ impl ReportService {
                     attempt_id: a.id,
                     started_at: a.started_at,
                     ends_at: a.ends_at,
-                    score: Self::attempt_score(&a.scoring_data),
+                    score: a.scoring_data.total_score(),
+                    max_score: attempt_tasks
+                        .get(&a.id)
+                        .map_or(0, |revisions| revisions.iter().map(|r| r.task.points).sum()),
                     status: Self::status_of(&a.scoring_data, window_open),
                     task_scores,
+                    late_penalty: a.scoring_data.late_penalty(),
+                    late_penalty_waived: a.scoring_data.late_penalty_waived,
                 }
             })
             .collect();
@@ -133,7 +236,7 @@ impl ReportService {
 
         Ok(Gradebook {
             exam_id,
-            exam_name: exam.name,
+            exam_name,
             max_score,
             tasks,
             rows,
@@ -192,6 +295,149 @@ impl ReportService {
         }
     }
 
+    fn build_item_analysis(data: &ExamData) -> ItemAnalysis {
+        let now = Utc::now();
+        let finished: Vec<&ExamAttempt> =
+            data.attempts.iter().filter(|a| a.due_at <= now).collect();
+        let items = data
+            .tasks
+            .iter()
+            .map(|task| {
+                let mut stats = ItemStats {
+                    task_id: task.id,
+                    title: task.title.clone(),
+                    task_type: data
+                        .task_types
+                        .get(&task.id)
+                        .cloned()
+                        .expect("Types are recorded along with the tasks"),
+                    max_score: task.max_score,
+                    attempts: 0,
+                    graded: 0,
+                    difficulty: None,
+                    discrimination: None,
+                    on_review_share: 0.0,
+                    options: Vec::new(),
+                    common_wrong_answers: Vec::new(),
+                };
+                Self::collect_item_stats(&mut stats, &finished, &data.attempt_tasks);
+                stats
+            })
+            .collect();
+
+        ItemAnalysis {
+            exam_id: data.exam_id,
+            exam_name: data.exam_name.clone(),
+            items,
+        }
+    }
+
+    /// Fills in the statistics of `stats`' task from the attempts that had it,
+    /// each against the revision of the task it was started with.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation
+    )]
+    fn collect_item_stats(
+        stats: &mut ItemStats,
+        attempts: &[&ExamAttempt],
+        attempt_tasks: &HashMap<Uuid, Vec<TaskRevision>>,
+    ) {
+        let key = stats.task_id as usize;
+        // (share of the task's points, score of the rest of the attempt)
+        let mut scores: Vec<(f64, f64)> = Vec::new();
+        let mut on_review = 0usize;
+        let mut wrong_answers: HashMap<String, usize> = HashMap::new();
+        for attempt in attempts {
+            let Some(task) = attempt_tasks
+                .get(&attempt.id)
+                .and_then(|revisions| revisions.iter().find(|r| r.task.id == stats.task_id))
+                .map(|revision| &revision.task)
+            else {
+                continue;
+            };
+            stats.attempts += 1;
+            let config = &task.configuration;
+            let answer = attempt.answer_data.answers.get(&key);
+            let verdict = attempt.scoring_data.results.get(&key);
+
+            match (config, answer) {
+                (
+                    TaskConfig::SingleChoice {
+                        options, correct, ..
+                    },
+                    answer,
+                ) => {
+                    for (i, option) in options.iter().enumerate() {
+                        option_stats(&mut stats.options, option).correct |= i == *correct;
+                    }
+                    if let Some(TaskAnswer::SingleChoice { answer }) = answer {
+                        option_stats(&mut stats.options, answer).picked += 1;
+                    }
+                }
+                (
+                    TaskConfig::MultipleChoice {
+                        options, correct, ..
+                    },
+                    answer,
+                ) => {
+                    for (i, option) in options.iter().enumerate() {
+                        option_stats(&mut stats.options, option).correct |= correct.contains(&i);
+                    }
+                    if let Some(TaskAnswer::MultipleChoice { answers }) = answer {
+                        for answer in answers {
+                            option_stats(&mut stats.options, answer).picked += 1;
+                        }
+                    }
+                }
+                (
+                    TaskConfig::ShortText { case_sensitive, .. },
+                    Some(TaskAnswer::ShortText { answer }),
+                ) if matches!(verdict, Some(TaskVerdict::Incorrect { .. })) => {
+                    let answer = answer.trim();
+                    let answer = if *case_sensitive {
+                        answer.to_string()
+                    } else {
+                        answer.to_lowercase()
+                    };
+                    *wrong_answers.entry(answer).or_default() += 1;
+                }
+                _ => {}
+            }
+
+            if matches!(verdict, Some(TaskVerdict::OnReview)) {
+                on_review += 1;
+                continue;
+            }
+            // an unanswered task has no verdict and scores nothing
+            let score = verdict.map_or(0.0, |v| *v.score());
+            let share = if task.points > 0 {
+                score / task.points as f64
+            } else {
+                0.0
+            };
+            scores.push((share, attempt.scoring_data.total_score() - score));
+        }
+
+        stats.graded = scores.len();
+        if !scores.is_empty() {
+            stats.difficulty =
+                Some(scores.iter().map(|(share, _)| share).sum::<f64>() / scores.len() as f64);
+        }
+        stats.discrimination = correlation(&scores);
+        if stats.attempts > 0 {
+            stats.on_review_share = on_review as f64 / stats.attempts as f64;
+        }
+        let mut wrong_answers: Vec<AnswerCount> = wrong_answers
+            .into_iter()
+            .map(|(answer, count)| AnswerCount { answer, count })
+            .collect();
+        wrong_answers.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.answer.cmp(&b.answer)));
+        wrong_answers.truncate(COMMON_ANSWERS_LIMIT);
+        stats.common_wrong_answers = wrong_answers;
+    }
+
     pub async fn exam_export(
         &self,
         exam_id: Uuid,
@@ -199,10 +445,12 @@ impl ReportService {
         role: UserRole,
         format: ExportFormat,
     ) -> Result<ExportFile> {
-        let gradebook = self.exam_gradebook(exam_id, user, role).await?;
+        let data = self.exam_data(exam_id, user, role).await?;
+        let items = Self::build_item_analysis(&data);
+        let gradebook = self.build_gradebook(data).await?;
         match format {
-            ExportFormat::Csv => Ok(Self::build_csv(&gradebook)),
-            ExportFormat::Xlsx => Self::build_xlsx(&gradebook),
+            ExportFormat::Csv => Ok(Self::build_csv(&gradebook, &items)),
+            ExportFormat::Xlsx => Self::build_xlsx(&gradebook, &items),
         }
     }
 
@@ -214,6 +462,14 @@ impl ReportService {
         }
     }
 
+    fn late_penalty_label(row: &GradebookRow) -> String {
+        if row.late_penalty_waived {
+            format!("waived ({:.2})", row.late_penalty)
+        } else {
+            format!("{:.2}", row.late_penalty)
+        }
+    }
+
     #[allow(clippy::cast_precision_loss)]
     fn percent(score: f64, max_score: i64) -> f64 {
         if max_score > 0 {
@@ -223,7 +479,9 @@ impl ReportService {
         }
     }
 
-    fn build_csv(gradebook: &Gradebook) -> ExportFile {
+    /// The gradebook, then, after an empty line, the item analysis with a
+    /// header of its own.
+    fn build_csv(gradebook: &Gradebook, items: &ItemAnalysis) -> ExportFile {
         let mut out = String::new();
 
         let mut headers: Vec<String> = CSV_HEADER.iter().map(ToString::to_string).collect();
@@ -238,11 +496,12 @@ impl ReportService {
                 csv_escape(&row.username),
                 csv_escape(&row.email),
                 format!("{:.2}", row.score),
-                gradebook.max_score.to_string(),
-                format!("{:.1}", Self::percent(row.score, gradebook.max_score)),
+                row.max_score.to_string(),
+                format!("{:.1}", Self::percent(row.score, row.max_score)),
                 Self::status_label(row.status).to_string(),
                 row.started_at.to_rfc3339(),
                 row.ends_at.to_rfc3339(),
+                Self::late_penalty_label(row),
             ];
             for task in &gradebook.tasks {
                 let score = row
@@ -256,6 +515,26 @@ impl ReportService {
             out.push('\n');
         }
 
+        out.push('\n');
+        out.push_str(&ITEM_CSV_HEADER.join(","));
+        out.push('\n');
+        for item in &items.items {
+            let fields = [
+                csv_escape(&item.title),
+                format!("{:?}", item.task_type),
+                item.max_score.to_string(),
+                item.attempts.to_string(),
+                item.graded.to_string(),
+                index_label(item.difficulty),
+                index_label(item.discrimination),
+                format!("{:.1}", item.on_review_share * 100.0),
+                csv_escape(&option_picks_label(item)),
+                csv_escape(&wrong_answers_label(item)),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+
         ExportFile {
             bytes: out.into_bytes(),
             content_type: "text/csv; charset=utf-8",
@@ -263,9 +542,10 @@ impl ReportService {
         }
     }
 
+    /// The gradebook, with the item analysis on a second sheet.
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_precision_loss)]
-    fn build_xlsx(gradebook: &Gradebook) -> Result<ExportFile> {
+    fn build_xlsx(gradebook: &Gradebook, items: &ItemAnalysis) -> Result<ExportFile> {
         let mut workbook = Workbook::new();
         let worksheet = workbook.add_worksheet();
         let bold = Format::new().set_bold();
@@ -298,10 +578,10 @@ impl ReportService {
             worksheet.write_string(r, 1, &row.email).map_err(xlsx_err)?;
             worksheet.write_number(r, 2, row.score).map_err(xlsx_err)?;
             worksheet
-                .write_number(r, 3, gradebook.max_score as f64)
+                .write_number(r, 3, row.max_score as f64)
                 .map_err(xlsx_err)?;
             worksheet
-                .write_number(r, 4, Self::percent(row.score, gradebook.max_score))
+                .write_number(r, 4, Self::percent(row.score, row.max_score))
                 .map_err(xlsx_err)?;
             worksheet
                 .write_string(r, 5, Self::status_label(row.status))
@@ -312,6 +592,15 @@ impl ReportService {
             worksheet
                 .write_string(r, 7, row.ends_at.to_rfc3339())
                 .map_err(xlsx_err)?;
+            if row.late_penalty_waived {
+                worksheet
+                    .write_string(r, 8, Self::late_penalty_label(row))
+                    .map_err(xlsx_err)?;
+            } else {
+                worksheet
+                    .write_number(r, 8, row.late_penalty)
+                    .map_err(xlsx_err)?;
+            }
             for (i, task) in gradebook.tasks.iter().enumerate() {
                 let score = row
                     .task_scores
@@ -324,6 +613,8 @@ impl ReportService {
             }
         }
 
+        Self::write_item_sheet(&mut workbook, items, &bold)?;
+
         let bytes = workbook.save_to_buffer().map_err(xlsx_err)?;
 
         Ok(ExportFile {
@@ -332,6 +623,63 @@ impl ReportService {
             filename: format!("exam-{}-results.xlsx", gradebook.exam_id),
         })
     }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    fn write_item_sheet(
+        workbook: &mut Workbook,
+        items: &ItemAnalysis,
+        bold: &Format,
+    ) -> Result<()> {
+        let xlsx_err =
+            |e: rust_xlsxwriter::XlsxError| LMSError::ServerError(format!("XLSX error: {e}"));
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Item analysis").map_err(xlsx_err)?;
+        for (col, header) in ITEM_CSV_HEADER.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, col as u16, *header, bold)
+                .map_err(xlsx_err)?;
+        }
+        for (idx, item) in items.items.iter().enumerate() {
+            let r = (idx + 1) as u32;
+            worksheet
+                .write_string(r, 0, &item.title)
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_string(r, 1, format!("{:?}", item.task_type))
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_number(r, 2, item.max_score as f64)
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_number(r, 3, item.attempts as f64)
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_number(r, 4, item.graded as f64)
+                .map_err(xlsx_err)?;
+            // undefined indices stay empty cells
+            if let Some(difficulty) = item.difficulty {
+                worksheet.write_number(r, 5, difficulty).map_err(xlsx_err)?;
+            }
+            if let Some(discrimination) = item.discrimination {
+                worksheet
+                    .write_number(r, 6, discrimination)
+                    .map_err(xlsx_err)?;
+            }
+            worksheet
+                .write_number(r, 7, item.on_review_share * 100.0)
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_string(r, 8, option_picks_label(item))
+                .map_err(xlsx_err)?;
+            worksheet
+                .write_string(r, 9, wrong_answers_label(item))
+                .map_err(xlsx_err)?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Which serialization to produce for an export.
@@ -341,6 +689,70 @@ pub enum ExportFormat {
     Xlsx,
 }
 
+/// Stats of `option` among `options`, added with nothing picked if missing.
+/// Options an answer has but the task doesn't (e.g. of an older revision)
+/// end up there too.
+fn option_stats<'a>(options: &'a mut Vec<OptionStats>, option: &str) -> &'a mut OptionStats {
+    let index = options
+        .iter()
+        .position(|o| o.option == option)
+        .unwrap_or_else(|| {
+            options.push(OptionStats {
+                option: option.to_string(),
+                picked: 0,
+                correct: false,
+            });
+            options.len() - 1
+        });
+    &mut options[index]
+}
+
+/// Pearson correlation of the pairs, `None` when either side doesn't vary.
+#[allow(clippy::cast_precision_loss)]
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        xy += dx * dy;
+        xx += dx * dx;
+        yy += dy * dy;
+    }
+    if xx < f64::EPSILON || yy < f64::EPSILON {
+        return None;
+    }
+    Some(xy / (xx * yy).sqrt())
+}
+
+/// `option: picked` for every option, the right ones marked.
+fn option_picks_label(item: &ItemStats) -> String {
+    item.options
+        .iter()
+        .map(|o| {
+            let mark = if o.correct { " (correct)" } else { "" };
+            format!("{}{mark}: {}", o.option, o.picked)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn wrong_answers_label(item: &ItemStats) -> String {
+    item.common_wrong_answers
+        .iter()
+        .map(|a| format!("{}: {}", a.answer, a.count))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn index_label(index: Option<f64>) -> String {
+    index.map_or_else(String::new, |index| format!("{index:.2}"))
+}
+
 /// Quotes a CSV field if it contains a comma, quote or newline (RFC 4180).
 pub(crate) fn csv_escape(field: &str) -> String {
     if field.contains([',', '"', '\n', '\r']) {
@@ -0,0 +1,617 @@
+//! Math expressions of `Expression` tasks: the reference answer and a
+//! learner's one are parsed into trees and compared.
+//!
+//! The language has decimal numbers, the task's variables, the constants `pi`
+//! and `e`, `+ - * /`, `^` (or `**`, right associative and tighter than unary
+//! minus: `-2^2` is `-4`), `mod` (or `%`, never negative for a positive
+//! divisor), parentheses, implicit multiplication (`2k`, `(p-1)(q-1)`) and the
+//! functions listed in [`FUNCTIONS`].
+//!
+//! Two expressions are equivalent when they are the same up to the order of
+//! terms and factors, or, failing that, when they agree within the tolerance
+//! at a fixed set of pseudo-random points of the variables' ranges. Points
+//! where the reference isn't a finite number are skipped. Everything is
+//! computed in `f64`, so integer arithmetic is exact only up to 2^53.
+
+use crate::domain::task::model::ExpressionVariable;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+/// Upper bound for the source of an expression.
+pub const EXPR_MAX_LEN: usize = 500;
+/// Upper bound for how deep an expression may nest.
+const EXPR_MAX_DEPTH: usize = 32;
+/// Upper bound for the number of variables of a task.
+const MAX_VARIABLES: usize = 10;
+/// Upper bound for the magnitude of a variable's range.
+const VALUE_LIMIT: f64 = 1e15;
+/// How many points expressions are compared at.
+const SAMPLE_POINTS: usize = 32;
+/// How many of them the reference has to be defined at.
+pub const MIN_DEFINED_POINTS: usize = 8;
+/// Seed of the points, fixed so that a verdict never changes on regrading.
+const SAMPLE_SEED: u64 = 0x5eed;
+
+const CONSTANTS: [(&str, f64); 2] = [("pi", std::f64::consts::PI), ("e", std::f64::consts::E)];
+
+/// Functions with the least and the most arguments they take:
+/// - `sqrt`, `abs`, `exp`, `ln`, `log2`, `log10`, `sin`, `cos`, `tan`,
+///   `floor`, `ceil`;
+/// - `log(x)` is `ln(x)`, `log(x, b)` takes the logarithm to base `b`;
+/// - `min(a, b)`, `max(a, b)` and `gcd(a, b)`, the latter for integers only.
+const FUNCTIONS: [(&str, usize, usize); 15] = [
+    ("sqrt", 1, 1),
+    ("abs", 1, 1),
+    ("exp", 1, 1),
+    ("ln", 1, 1),
+    ("log", 1, 2),
+    ("log2", 1, 1),
+    ("log10", 1, 1),
+    ("sin", 1, 1),
+    ("cos", 1, 1),
+    ("tan", 1, 1),
+    ("floor", 1, 1),
+    ("ceil", 1, 1),
+    ("min", 2, 2),
+    ("max", 2, 2),
+    ("gcd", 2, 2),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 9] = ["**", "+", "-", "*", "/", "^", "(", ")", ","];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let literal = &rest[..end];
+            tokens.push(Token::Number(
+                literal
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{literal}'"))?,
+            ));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(match &rest[..end] {
+                "mod" => Token::Op("mod"),
+                name => Token::Ident(name.to_string()),
+            });
+            rest = &rest[end..];
+        } else if c == '%' {
+            tokens.push(Token::Op("mod"));
+            rest = &rest[1..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(if *op == "**" { "^" } else { op }));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!("Unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Var(String),
+    Neg(Box<Self>),
+    Binary(&'static str, Box<Self>, Box<Self>),
+    Call(&'static str, Vec<Self>),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    variables: &'a [ExpressionVariable],
+}
+
+/// An expression over the variables it was parsed with.
+#[derive(Debug)]
+pub struct MathExpr(Expr);
+
+/// Parses `source`, which may only use the given variables.
+pub fn parse(source: &str, variables: &[ExpressionVariable]) -> Result<MathExpr, String> {
+    if source.len() > EXPR_MAX_LEN {
+        return Err(format!(
+            "Expression is longer than {EXPR_MAX_LEN} characters"
+        ));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        depth: 0,
+        variables,
+    };
+    let expr = parser.sum()?;
+    parser
+        .tokens
+        .get(parser.position)
+        .map_or(Ok(MathExpr(expr)), |token| {
+            Err(format!("Unexpected {}", describe(token)))
+        })
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {value}"),
+        Token::Ident(name) => format!("'{name}'"),
+        Token::Op(op) => format!("'{op}'"),
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect_op(&mut self, expected: &str) -> Result<(), String> {
+        if self.peek_op() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{expected}'"))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        while let Some(op @ ("+" | "-")) = self.peek_op() {
+            self.position += 1;
+            let right = self.product()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            left = match self.peek() {
+                Some(Token::Op(op @ ("*" | "/" | "mod"))) => {
+                    let op = *op;
+                    self.position += 1;
+                    Expr::Binary(op, Box::new(left), Box::new(self.unary()?))
+                }
+                // whatever starts an operand right after another one multiplies it
+                Some(Token::Number(_) | Token::Ident(_) | Token::Op("(")) => {
+                    Expr::Binary("*", Box::new(left), Box::new(self.power()?))
+                }
+                _ => return Ok(left),
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > EXPR_MAX_DEPTH {
+            return Err("Expression is nested too deep".to_string());
+        }
+        let expr = match self.peek_op() {
+            Some("-") => {
+                self.position += 1;
+                Expr::Neg(Box::new(self.unary()?))
+            }
+            Some("+") => {
+                self.position += 1;
+                self.unary()?
+            }
+            _ => self.power()?,
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.peek_op() != Some("^") {
+            return Ok(base);
+        }
+        self.position += 1;
+        Ok(Expr::Binary("^", Box::new(base), Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| "Unexpected end of expression".to_string())?;
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Ident(name) => {
+                if self.variables.iter().any(|variable| variable.name == name) {
+                    return Ok(Expr::Var(name));
+                }
+                if let Some((_, value)) = CONSTANTS.iter().find(|(constant, _)| *constant == name) {
+                    return Ok(Expr::Number(*value));
+                }
+                let Some(&(function, min, max)) = FUNCTIONS.iter().find(|(f, ..)| *f == name)
+                else {
+                    return Err(format!("Unknown variable '{name}'"));
+                };
+                self.expect_op("(")?;
+                let mut args = vec![self.sum()?];
+                while self.peek_op() == Some(",") {
+                    self.position += 1;
+                    args.push(self.sum()?);
+                }
+                self.expect_op(")")?;
+                if !(min..=max).contains(&args.len()) {
+                    return Err(format!("Wrong number of arguments for '{function}'"));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Token::Op("(") => {
+                let expr = self.sum()?;
+                self.expect_op(")")?;
+                Ok(expr)
+            }
+            Token::Op(op) => Err(format!("Unexpected '{op}'")),
+        }
+    }
+}
+
+/// Checks the variables of a task: valid and unique names that don't clash
+/// with the language's own, and non-empty finite ranges.
+pub fn check_variables(variables: &[ExpressionVariable]) -> Result<(), String> {
+    if variables.len() > MAX_VARIABLES {
+        return Err(format!(
+            "A task can't have more than {MAX_VARIABLES} variables"
+        ));
+    }
+    for (i, variable) in variables.iter().enumerate() {
+        let name = variable.name.as_str();
+        if tokenize(name).ok().as_deref() != Some(&[Token::Ident(name.to_string())]) {
+            return Err(format!("Invalid variable name '{name}'"));
+        }
+        if CONSTANTS.iter().any(|(constant, _)| *constant == name)
+            || FUNCTIONS.iter().any(|(function, ..)| *function == name)
+        {
+            return Err(format!("'{name}' is a constant or a function name"));
+        }
+        if variables[..i].iter().any(|other| other.name == name) {
+            return Err(format!("Variable '{name}' is declared twice"));
+        }
+        if !(variable.min.abs() <= VALUE_LIMIT && variable.max.abs() <= VALUE_LIMIT)
+            || variable.min > variable.max
+        {
+            return Err(format!(
+                "Variable '{name}' needs min <= max, both within ±{VALUE_LIMIT:e}"
+            ));
+        }
+        if variable.integer && variable.min.ceil() > variable.max.floor() {
+            return Err(format!("Variable '{name}' has no integers in its range"));
+        }
+    }
+    Ok(())
+}
+
+/// The points expressions are compared at. Variables have to pass
+/// [`check_variables`].
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn sample_points(variables: &[ExpressionVariable]) -> Vec<HashMap<&str, f64>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(SAMPLE_SEED);
+    (0..SAMPLE_POINTS)
+        .map(|_| {
+            variables
+                .iter()
+                .map(|variable| {
+                    let value = if variable.integer {
+                        rng.random_range(variable.min.ceil() as i64..=variable.max.floor() as i64)
+                            as f64
+                    } else {
+                        rng.random_range(variable.min..=variable.max)
+                    };
+                    (variable.name.as_str(), value)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl MathExpr {
+    /// At how many of the sample points the expression is a finite number.
+    pub fn defined_points(&self, variables: &[ExpressionVariable]) -> usize {
+        sample_points(variables)
+            .iter()
+            .filter(|point| self.0.eval(point).is_finite())
+            .count()
+    }
+
+    /// Whether `answer` is the same as this reference, up to `tolerance`
+    /// relative to the reference's value (but at least `tolerance` absolute).
+    pub fn is_equivalent(
+        &self,
+        answer: &Self,
+        variables: &[ExpressionVariable],
+        tolerance: f64,
+    ) -> bool {
+        if self.0.canonical() == answer.0.canonical() {
+            return true;
+        }
+        let mut compared = 0;
+        for point in sample_points(variables) {
+            let expected = self.0.eval(&point);
+            if !expected.is_finite() {
+                continue;
+            }
+            // a tiny epsilon keeps zero-tolerance tasks from failing on float noise
+            let scale = expected.abs().max(1f64);
+            let allowed = f64::EPSILON.mul_add(scale, tolerance * scale);
+            let difference = (answer.0.eval(&point) - expected).abs();
+            if difference.is_nan() || difference > allowed {
+                return false;
+            }
+            compared += 1;
+        }
+        compared > 0
+    }
+}
+
+impl Expr {
+    fn eval(&self, values: &HashMap<&str, f64>) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Var(name) => values.get(name.as_str()).copied().unwrap_or(f64::NAN),
+            Self::Neg(expr) => -expr.eval(values),
+            Self::Binary(op, left, right) => {
+                let (left, right) = (left.eval(values), right.eval(values));
+                match *op {
+                    "+" => left + right,
+                    "-" => left - right,
+                    "*" => left * right,
+                    "/" => left / right,
+                    "^" => left.powf(right),
+                    _ => left.rem_euclid(right),
+                }
+            }
+            Self::Call(name, args) => {
+                let args: Vec<f64> = args.iter().map(|arg| arg.eval(values)).collect();
+                call(name, &args)
+            }
+        }
+    }
+
+    /// Text of the expression with the operands of sums and products sorted,
+    /// so that reordered terms and factors give the same text.
+    fn canonical(&self) -> String {
+        match self {
+            Self::Number(value) => value.to_string(),
+            Self::Var(name) => name.clone(),
+            Self::Neg(_) | Self::Binary("+" | "-", ..) => {
+                let mut terms = Vec::new();
+                self.collect_terms(false, &mut terms);
+                terms.sort();
+                format!("({})", terms.join(" + "))
+            }
+            Self::Binary("*", ..) => {
+                let mut factors = Vec::new();
+                self.collect_factors(&mut factors);
+                factors.sort();
+                format!("({})", factors.join(" * "))
+            }
+            Self::Binary(op, left, right) => {
+                format!("{op}({}, {})", left.canonical(), right.canonical())
+            }
+            Self::Call(name, args) => format!(
+                "{name}({})",
+                args.iter()
+                    .map(Self::canonical)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn collect_terms(&self, negated: bool, terms: &mut Vec<String>) {
+        match self {
+            Self::Binary("+", left, right) => {
+                left.collect_terms(negated, terms);
+                right.collect_terms(negated, terms);
+            }
+            Self::Binary("-", left, right) => {
+                left.collect_terms(negated, terms);
+                right.collect_terms(!negated, terms);
+            }
+            Self::Neg(expr) => expr.collect_terms(!negated, terms),
+            term if negated => terms.push(format!("-{}", term.canonical())),
+            term => terms.push(term.canonical()),
+        }
+    }
+
+    fn collect_factors(&self, factors: &mut Vec<String>) {
+        match self {
+            Self::Binary("*", left, right) => {
+                left.collect_factors(factors);
+                right.collect_factors(factors);
+            }
+            factor => factors.push(factor.canonical()),
+        }
+    }
+}
+
+/// Evaluates a function of [`FUNCTIONS`]; the parser has checked the arity.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn call(name: &str, args: &[f64]) -> f64 {
+    let x = args[0];
+    match (name, args.get(1).copied()) {
+        ("sqrt", _) => x.sqrt(),
+        ("abs", _) => x.abs(),
+        ("exp", _) => x.exp(),
+        ("ln" | "log", None) => x.ln(),
+        ("log", Some(base)) => x.log(base),
+        ("log2", _) => x.log2(),
+        ("log10", _) => x.log10(),
+        ("sin", _) => x.sin(),
+        ("cos", _) => x.cos(),
+        ("tan", _) => x.tan(),
+        ("floor", _) => x.floor(),
+        ("ceil", _) => x.ceil(),
+        ("min", Some(y)) => x.min(y),
+        ("max", Some(y)) => x.max(y),
+        ("gcd", Some(y)) => {
+            let exact = |value: f64| value.fract() == 0f64 && value.abs() <= 2f64.powi(53);
+            if !exact(x) || !exact(y) {
+                return f64::NAN;
+            }
+            let (mut a, mut b) = (x.abs() as u64, y.abs() as u64);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a as f64
+        }
+        _ => f64::NAN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, min: f64, max: f64, integer: bool) -> ExpressionVariable {
+        ExpressionVariable {
+            name: name.to_string(),
+            min,
+            max,
+            integer,
+        }
+    }
+
+    fn value(source: &str) -> f64 {
+        parse(source, &[])
+            .expect("Invalid expression")
+            .0
+            .eval(&HashMap::new())
+    }
+
+    fn equivalent(reference: &str, answer: &str, variables: &[ExpressionVariable]) -> bool {
+        let reference = parse(reference, variables).expect("Invalid reference");
+        let answer = parse(answer, variables).expect("Invalid answer");
+        reference.is_equivalent(&answer, variables, 1e-9)
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("-2^2", -4.0),
+            ("2^3^2", 512.0),
+            ("2**3", 8.0),
+            ("-7 mod 3", 2.0),
+            ("7 % 3", 1.0),
+            ("2(3 + 4)", 14.0),
+            ("(1 + 1)(2 + 2)", 8.0),
+            ("log(8, 2)", 3.0),
+            ("gcd(12, 18)", 6.0),
+            ("max(1, min(5, 3))", 3.0),
+        ];
+        for (source, expected) in cases {
+            assert!(
+                (value(source) - expected).abs() < 1e-12,
+                "{source} is {}",
+                value(source)
+            );
+        }
+        assert!(value("gcd(1.5, 3)").is_nan());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let variables = [variable("x", 0.0, 1.0, false)];
+        // names run on, so this is no product of x and x
+        assert!(parse("xx", &variables).is_err());
+        for source in [
+            "y + 1",
+            "x +",
+            "(x",
+            "sqrt(x, 2)",
+            "min(x)",
+            "x $ 1",
+            "1.2.3",
+            "",
+        ] {
+            assert!(parse(source, &variables).is_err(), "{source} parsed");
+        }
+        assert!(parse(&"(".repeat(40), &variables).is_err());
+        assert!(parse(&"1+".repeat(300), &variables).is_err());
+    }
+
+    #[test]
+    fn variables_are_checked() {
+        assert!(check_variables(&[variable("x", -1.0, 1.0, false)]).is_ok());
+        for variables in [
+            vec![variable("2x", 0.0, 1.0, false)],
+            vec![variable("pi", 0.0, 1.0, false)],
+            vec![
+                variable("x", 0.0, 1.0, false),
+                variable("x", 0.0, 1.0, false),
+            ],
+            vec![variable("x", 1.0, 0.0, false)],
+            vec![variable("x", 0.0, 1e16, false)],
+            vec![variable("x", 0.2, 0.8, true)],
+        ] {
+            assert!(check_variables(&variables).is_err());
+        }
+    }
+
+    #[test]
+    fn reordered_expressions_are_equivalent() {
+        let variables = [
+            variable("a", 1.0, 10.0, false),
+            variable("b", 1.0, 10.0, false),
+        ];
+        assert!(equivalent("a*b + 1", "1 + b a", &variables));
+        assert!(equivalent("(a + b)^2", "a^2 + 2a b + b^2", &variables));
+        assert!(equivalent("a - b", "-(b - a)", &variables));
+        assert!(!equivalent("a - b", "b - a", &variables));
+        assert!(!equivalent("a / b", "b / a", &variables));
+    }
+
+    #[test]
+    fn integer_variables_get_integer_values() {
+        let variables = [variable("n", 1.0, 20.0, true)];
+        assert!(equivalent("n mod 1", "0", &variables));
+        assert!(equivalent("floor(n)", "n", &variables));
+    }
+
+    #[test]
+    fn undefined_points_are_counted() {
+        let variables = [variable("x", -10.0, 10.0, false)];
+        let defined = |source: &str| {
+            parse(source, &variables)
+                .expect("Invalid expression")
+                .defined_points(&variables)
+        };
+        assert_eq!(defined("x^2"), SAMPLE_POINTS);
+        assert!(defined("sqrt(x)") < SAMPLE_POINTS);
+        assert_eq!(defined("1 / 0"), 0);
+    }
+}
@@ -0,0 +1,472 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    MultipleChoiceScoring, PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+    validate_explanations,
+};
+use crate::dto::task::TaskVerdict;
+use std::collections::HashSet;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct SingleChoiceGrader;
+
+impl TaskGrader for SingleChoiceGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::SingleChoice {
+            options,
+            correct,
+            explanations,
+            ..
+        } = config
+        else {
+            return;
+        };
+        validate_explanations(options, explanations, errors);
+        if options.is_empty() {
+            let mut error = ValidationError::new("empty_options");
+            error.message = Some("Options must not be empty".into());
+            errors.add("options", error);
+        }
+
+        if options.iter().any(String::is_empty) {
+            let mut error = ValidationError::new("empty_option");
+            error.message = Some("Option value must not be empty".into());
+            errors.add("options", error);
+        }
+
+        if *correct >= options.len() {
+            let mut error = ValidationError::new("invalid_correct_index");
+            error.message = Some("Invalid index specified for correct answer".into());
+            errors.add("correct", error);
+        }
+    }
+
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        match answer {
+            TaskAnswer::SingleChoice { .. } => Ok(()),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::SingleChoice {
+            options, correct, ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::SingleChoice));
+        };
+        let TaskAnswer::SingleChoice { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        Ok(if options.get(*correct) == Some(answer) {
+            TaskVerdict::FullScore {
+                comment: None,
+                score: points,
+                max_score: points,
+            }
+        } else {
+            TaskVerdict::Incorrect {
+                comment: None,
+                score: 0f64,
+                max_score: points,
+            }
+        })
+    }
+
+    /// Explanation of the picked option.
+    fn feedback<'a>(
+        &self,
+        config: &'a TaskConfig,
+        answer: &TaskAnswer,
+        _full_score: bool,
+    ) -> Vec<&'a str> {
+        let (
+            TaskConfig::SingleChoice {
+                options,
+                explanations,
+                ..
+            },
+            TaskAnswer::SingleChoice { answer },
+        ) = (config, answer)
+        else {
+            return Vec::new();
+        };
+        options
+            .iter()
+            .position(|option| option == answer)
+            .and_then(|i| explanations.get(i))
+            .map(String::as_str)
+            .into_iter()
+            .collect()
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::SingleChoice { options, .. } => {
+                Ok(PublicTaskConfig::SingleChoice { options })
+            }
+            _ => Err(GradeError::ConfigMismatch(TaskType::SingleChoice)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::SingleChoice {
+            options, correct, ..
+        } = config
+        else {
+            return None;
+        };
+        options
+            .get(*correct)
+            .map(|label| TaskSolution::SingleChoice {
+                answer: label.clone(),
+            })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+pub struct MultipleChoiceGrader;
+
+impl TaskGrader for MultipleChoiceGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::MultipleChoice {
+            options,
+            correct,
+            scoring,
+            explanations,
+            ..
+        } = config
+        else {
+            return;
+        };
+        validate_explanations(options, explanations, errors);
+        if let MultipleChoiceScoring::NegativeMarking { penalty } = scoring
+            && !(*penalty >= 0f64 && *penalty <= MultipleChoiceScoring::MAX_PENALTY)
+        {
+            let mut error = ValidationError::new("invalid_penalty");
+            error.message = Some("Penalty must be in [0, 1]".into());
+            errors.add("scoring", error);
+        }
+        if options.is_empty() {
+            let mut error = ValidationError::new("empty_options");
+            error.message = Some("Options must not be empty".into());
+            errors.add("options", error);
+        }
+
+        if options.iter().any(String::is_empty) {
+            let mut error = ValidationError::new("empty_option");
+            error.message = Some("Option value must not be empty".into());
+            errors.add("options", error);
+        }
+
+        if (*correct).iter().any(|&x| x >= options.len()) {
+            let mut error = ValidationError::new("invalid_correct_index");
+            error.message = Some("Invalid index specified for correct answer".into());
+            errors.add("correct", error);
+        }
+    }
+
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        match answer {
+            TaskAnswer::MultipleChoice { .. } => Ok(()),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::MultipleChoice {
+            options,
+            correct,
+            scoring,
+            ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::MultipleChoice));
+        };
+        let TaskAnswer::MultipleChoice { answers } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        let correct_answers: HashSet<_> = correct.iter().filter_map(|&i| options.get(i)).collect();
+        let user_answers: HashSet<_> = answers.iter().collect();
+
+        if user_answers == correct_answers {
+            return Ok(TaskVerdict::FullScore {
+                comment: None,
+                score: points,
+                max_score: points,
+            });
+        }
+
+        let score_multiplier = scoring.share(
+            options.len(),
+            correct_answers.len(),
+            correct_answers.intersection(&user_answers).count(),
+            user_answers.difference(&correct_answers).count(),
+        );
+        if score_multiplier <= 0f64 {
+            return Ok(TaskVerdict::Incorrect {
+                comment: None,
+                score: points * score_multiplier,
+                max_score: points,
+            });
+        }
+
+        Ok(TaskVerdict::PartialScore {
+            score: points * score_multiplier.min(1f64),
+            comment: None,
+            max_score: points,
+        })
+    }
+
+    /// Explanations of the options the learner picked or should have picked.
+    fn feedback<'a>(
+        &self,
+        config: &'a TaskConfig,
+        answer: &TaskAnswer,
+        _full_score: bool,
+    ) -> Vec<&'a str> {
+        let (
+            TaskConfig::MultipleChoice {
+                options,
+                correct,
+                explanations,
+                ..
+            },
+            TaskAnswer::MultipleChoice { answers },
+        ) = (config, answer)
+        else {
+            return Vec::new();
+        };
+        options
+            .iter()
+            .zip(explanations)
+            .enumerate()
+            .filter(|(i, (option, _))| answers.contains(option) || correct.contains(i))
+            .map(|(_, (_, explanation))| explanation.as_str())
+            .collect()
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::MultipleChoice {
+                options, scoring, ..
+            } => Ok(PublicTaskConfig::MultipleChoice { options, scoring }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::MultipleChoice)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::MultipleChoice {
+            options, correct, ..
+        } = config
+        else {
+            return None;
+        };
+        Some(TaskSolution::MultipleChoice {
+            answers: correct
+                .iter()
+                .filter_map(|&i| options.get(i).cloned())
+                .collect(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::{Value, json};
+
+    fn single() -> TaskConfig {
+        config(json!({
+            "name": "single_choice",
+            "options": ["a", "b", "c"],
+            "correct": 1,
+            "shuffle": false,
+        }))
+    }
+
+    fn multiple(scoring: &Value) -> TaskConfig {
+        config(json!({
+            "name": "multiple_choice",
+            "options": ["a", "b", "c", "d"],
+            "correct": [0, 1],
+            "shuffle": false,
+            "scoring": scoring,
+        }))
+    }
+
+    fn picked(options: &[&str]) -> TaskAnswer {
+        answer(json!({"name": "multiple_choice", "answers": options}))
+    }
+
+    #[test]
+    fn single_choice_config_is_validated() {
+        assert!(invalid_fields(&SingleChoiceGrader, &single()).is_empty());
+        let broken = config(json!({
+            "name": "single_choice",
+            "options": ["a", ""],
+            "correct": 2,
+            "shuffle": false,
+            "explanations": ["only one"],
+        }));
+        assert_eq!(
+            invalid_fields(&SingleChoiceGrader, &broken),
+            ["correct", "explanations", "options"]
+        );
+    }
+
+    #[test]
+    fn single_choice_scores_the_correct_option() {
+        let grade = |option: &str| {
+            SingleChoiceGrader.grade(
+                &single(),
+                &answer(json!({"name": "single_choice", "answer": option})),
+                2.0,
+            )
+        };
+        assert_verdict(grade("b"), "full_score", 2.0);
+        assert_verdict(grade("a"), "incorrect", 0.0);
+        assert_verdict(grade("z"), "incorrect", 0.0);
+    }
+
+    #[test]
+    fn single_choice_rejects_other_answers() {
+        let other = answer(json!({"name": "short_text", "answer": "b"}));
+        assert!(matches!(
+            SingleChoiceGrader.grade(&single(), &other, 2.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+        assert!(matches!(
+            SingleChoiceGrader.validate_answer(&single(), &other),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn single_choice_public_config_hides_the_correct_option() {
+        let Ok(PublicTaskConfig::SingleChoice { options }) =
+            SingleChoiceGrader.public_config(single())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(options, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn multiple_choice_config_is_validated() {
+        let valid = multiple(&json!({"strategy": "all_or_nothing"}));
+        assert!(invalid_fields(&MultipleChoiceGrader, &valid).is_empty());
+        let broken = config(json!({
+            "name": "multiple_choice",
+            "options": [],
+            "correct": [0],
+            "shuffle": false,
+            "scoring": {"strategy": "negative_marking", "penalty": 1.5},
+        }));
+        assert_eq!(
+            invalid_fields(&MultipleChoiceGrader, &broken),
+            ["correct", "options", "scoring"]
+        );
+    }
+
+    #[test]
+    fn multiple_choice_needs_the_exact_set_for_full_score() {
+        let config = multiple(&json!({"strategy": "all_or_nothing"}));
+        let grade = |options: &[&str]| MultipleChoiceGrader.grade(&config, &picked(options), 2.0);
+        assert_verdict(grade(&["b", "a"]), "full_score", 2.0);
+        assert_verdict(grade(&["a"]), "incorrect", 0.0);
+        assert_verdict(grade(&["a", "b", "c"]), "incorrect", 0.0);
+    }
+
+    #[test]
+    fn multiple_choice_partial_strategies() {
+        let grade = |scoring: Value, options: &[&str]| {
+            MultipleChoiceGrader.grade(&multiple(&scoring), &picked(options), 2.0)
+        };
+        let correct_minus_incorrect = || json!({"strategy": "correct_minus_incorrect"});
+        assert_verdict(
+            grade(correct_minus_incorrect(), &["a"]),
+            "partial_score",
+            1.0,
+        );
+        assert_verdict(
+            grade(correct_minus_incorrect(), &["a", "c"]),
+            "incorrect",
+            0.0,
+        );
+        // a, c and d are in the right state, b is missed
+        assert_verdict(
+            grade(json!({"strategy": "per_option"}), &["a"]),
+            "partial_score",
+            1.5,
+        );
+        // the legacy flag still reads as correct minus incorrect
+        assert_verdict(grade(json!(true), &["a"]), "partial_score", 1.0);
+    }
+
+    #[test]
+    fn negative_marking_can_go_below_zero() {
+        let config = multiple(&json!({"strategy": "negative_marking", "penalty": 0.5}));
+        let grade = |options: &[&str]| MultipleChoiceGrader.grade(&config, &picked(options), 2.0);
+        assert_verdict(grade(&["a"]), "partial_score", 1.0);
+        assert_verdict(grade(&["a", "c"]), "incorrect", 0.0);
+        assert_verdict(grade(&["c"]), "incorrect", -1.0);
+    }
+
+    #[test]
+    fn multiple_choice_rejects_other_answers() {
+        let config = multiple(&json!({"strategy": "all_or_nothing"}));
+        let other = answer(json!({"name": "single_choice", "answer": "a"}));
+        assert!(matches!(
+            MultipleChoiceGrader.grade(&config, &other, 2.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn multiple_choice_public_config_keeps_the_scoring() {
+        let config = multiple(&json!({"strategy": "per_option"}));
+        let Ok(PublicTaskConfig::MultipleChoice { options, scoring }) =
+            MultipleChoiceGrader.public_config(config)
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(options, ["a", "b", "c", "d"]);
+        assert_eq!(scoring, MultipleChoiceScoring::PerOption);
+    }
+
+    #[test]
+    fn feedback_explains_picked_and_missed_options() {
+        let config = config(json!({
+            "name": "multiple_choice",
+            "options": ["a", "b", "c"],
+            "correct": [0],
+            "shuffle": false,
+            "scoring": {"strategy": "all_or_nothing"},
+            "explanations": ["right", "wrong", "also wrong"],
+        }));
+        assert_eq!(
+            MultipleChoiceGrader.feedback(&config, &picked(&["b"]), false),
+            ["right", "wrong"]
+        );
+    }
+}
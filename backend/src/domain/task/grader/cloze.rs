@@ -0,0 +1,256 @@
+use super::{GradeError, TaskGrader, verdict_for_share};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType, cloze_blank_numbers,
+};
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+/// Upper bound for the text typed into a single `Cloze` blank.
+const CLOZE_BLANK_MAX_LEN: usize = 200;
+
+pub struct ClozeGrader;
+
+impl TaskGrader for ClozeGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Cloze { text, blanks, .. } = config else {
+            return;
+        };
+        if blanks.is_empty() {
+            let mut error = ValidationError::new("empty_blanks");
+            error.message = Some("Blanks must not be empty".into());
+            errors.add("blanks", error);
+        }
+
+        let mut numbers = cloze_blank_numbers(text);
+        numbers.sort_unstable();
+        if !numbers.iter().copied().eq(1..=blanks.len()) {
+            let mut error = ValidationError::new("invalid_blank_markers");
+            error.message = Some(
+                format!(
+                    "Text must contain markers {{{{1}}}}..{{{{{}}}}} exactly once each",
+                    blanks.len()
+                )
+                .into(),
+            );
+            errors.add("text", error);
+        }
+
+        if blanks
+            .iter()
+            .any(|blank| blank.answers.is_empty() || blank.answers.iter().any(String::is_empty))
+        {
+            let mut error = ValidationError::new("empty_answers");
+            error.message = Some("Every blank needs at least one non-empty answer".into());
+            errors.add("blanks", error);
+        }
+
+        if blanks.iter().any(|blank| {
+            blank.options.iter().any(String::is_empty)
+                || (!blank.options.is_empty()
+                    && blank.answers.iter().any(|x| !blank.options.contains(x)))
+        }) {
+            let mut error = ValidationError::new("invalid_options");
+            error.message = Some("Dropdown answers must be among its non-empty options".into());
+            errors.add("blanks", error);
+        }
+
+        if blanks
+            .iter()
+            .any(|blank| !blank.weight.is_finite() || blank.weight <= 0f64)
+        {
+            let mut error = ValidationError::new("invalid_weight");
+            error.message = Some("Blank weight must be a positive number".into());
+            errors.add("blanks", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::Cloze { blanks, .. } = config else {
+            return Err(GradeError::ConfigMismatch(TaskType::Cloze));
+        };
+        let TaskAnswer::Cloze { answers } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if answers.len() != blanks.len() {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Expected answers for {} blanks, got {}",
+                blanks.len(),
+                answers.len()
+            )));
+        }
+        if answers.iter().any(|x| x.len() > CLOZE_BLANK_MAX_LEN) {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Your answer length is more than allowed ({CLOZE_BLANK_MAX_LEN})"
+            )));
+        }
+        // an empty string is a blank left unfilled
+        if answers.iter().zip(blanks).any(|(answer, blank)| {
+            !answer.is_empty() && !blank.options.is_empty() && !blank.options.contains(answer)
+        }) {
+            return Err(GradeError::InvalidAnswer(
+                "Your answer contains options that are not in the task".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::Cloze {
+            blanks,
+            case_sensitive,
+            ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Cloze));
+        };
+        let TaskAnswer::Cloze { answers } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        let matches = |expected: &str, answer: &str| {
+            if *case_sensitive {
+                expected == answer.trim()
+            } else {
+                expected.to_lowercase() == answer.trim().to_lowercase()
+            }
+        };
+        let total_weight: f64 = blanks.iter().map(|blank| blank.weight).sum();
+        let correct_weight: f64 = blanks
+            .iter()
+            .zip(answers)
+            .filter(|(blank, answer)| blank.answers.iter().any(|x| matches(x, answer)))
+            .map(|(blank, _)| blank.weight)
+            .sum();
+
+        Ok(verdict_for_share(correct_weight / total_weight, points))
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Cloze { text, blanks, .. } => Ok(PublicTaskConfig::Cloze {
+                text,
+                blanks: blanks.into_iter().map(Into::into).collect(),
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Cloze)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::Cloze { blanks, .. } = config else {
+            return None;
+        };
+        Some(TaskSolution::Cloze {
+            answers: blanks
+                .iter()
+                .map(|blank| blank.answers.first().cloned().unwrap_or_default())
+                .collect(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::json;
+
+    fn cloze() -> TaskConfig {
+        config(json!({
+            "name": "cloze",
+            "text": "{{1}} is the capital of {{2}}",
+            "blanks": [
+                {"answers": ["Paris"], "weight": 3.0},
+                {"answers": ["France"], "options": ["France", "Spain"]},
+            ],
+            "case_sensitive": false,
+        }))
+    }
+
+    fn filled(answers: &[&str]) -> TaskAnswer {
+        answer(json!({"name": "cloze", "answers": answers}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&ClozeGrader, &cloze()).is_empty());
+        let broken = config(json!({
+            "name": "cloze",
+            "text": "{{1}} and {{1}}",
+            "blanks": [
+                {"answers": ["Italy"], "options": ["France"], "weight": 0.0},
+                {"answers": []},
+            ],
+            "case_sensitive": false,
+        }));
+        assert_eq!(invalid_fields(&ClozeGrader, &broken), ["blanks", "text"]);
+    }
+
+    #[test]
+    fn blanks_are_weighted() {
+        let config = cloze();
+        assert_verdict(
+            ClozeGrader.grade(&config, &filled(&[" paris", "France"]), 4.0),
+            "full_score",
+            4.0,
+        );
+        assert_verdict(
+            ClozeGrader.grade(&config, &filled(&["Paris", "Spain"]), 4.0),
+            "partial_score",
+            3.0,
+        );
+        assert_verdict(
+            ClozeGrader.grade(&config, &filled(&["", "France"]), 4.0),
+            "partial_score",
+            1.0,
+        );
+        assert_verdict(
+            ClozeGrader.grade(&config, &filled(&["Rome", ""]), 4.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn answers_are_checked() {
+        let config = cloze();
+        assert!(
+            ClozeGrader
+                .validate_answer(&config, &filled(&["", ""]))
+                .is_ok()
+        );
+        assert!(matches!(
+            ClozeGrader.validate_answer(&config, &filled(&["Paris"])),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        assert!(matches!(
+            ClozeGrader.validate_answer(&config, &filled(&["Paris", "Italy"])),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "short_text", "answer": "Paris"}));
+        assert!(matches!(
+            ClozeGrader.grade(&config, &other, 4.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_answers() {
+        let Ok(PublicTaskConfig::Cloze { text, blanks }) = ClozeGrader.public_config(cloze())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(text, "{{1}} is the capital of {{2}}");
+        assert!(blanks[0].options.is_empty());
+        assert_eq!(blanks[1].options, ["France", "Spain"]);
+    }
+}
@@ -0,0 +1,346 @@
+use super::{GradeError, TaskGrader, verdict_for_share};
+use crate::domain::task::model::{
+    CodeLanguage, CodeTestCase, PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use crate::errors::LMSError;
+use crate::infrastructure::sandbox::{self, DirAccess, Limits, OUTPUT_LIMIT, RunStatus, Sandbox};
+use std::fmt::Write;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+use validator::{ValidationError, ValidationErrors};
+
+/// Upper bound for the size of a submitted program.
+const SOURCE_MAX_LEN: usize = 64 * 1024;
+/// Upper bound for the number of tests of a task.
+const MAX_TESTS: usize = 50;
+/// Bounds for the per-test time limit, in milliseconds.
+const TIME_LIMIT_RANGE: std::ops::RangeInclusive<u32> = 100..=10_000;
+/// Bounds for the per-test memory limit, in megabytes.
+const MEMORY_LIMIT_RANGE: std::ops::RangeInclusive<u32> = 16..=1024;
+/// Limits of compiling a C submission.
+const COMPILE_LIMITS: Limits = Limits {
+    time: Duration::from_secs(15),
+    memory: 512 * 1024 * 1024,
+};
+/// Upper bound for the compiler output quoted in the verdict.
+const COMPILER_OUTPUT_MAX_LEN: usize = 2000;
+
+pub struct CodeGrader;
+
+impl TaskGrader for CodeGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Code {
+            tests,
+            time_limit_ms,
+            memory_limit_mb,
+            ..
+        } = config
+        else {
+            return;
+        };
+        if tests.is_empty() || tests.len() > MAX_TESTS {
+            let mut error = ValidationError::new("invalid_tests");
+            error.message = Some(format!("A task needs from 1 to {MAX_TESTS} tests").into());
+            errors.add("tests", error);
+        }
+        if tests.iter().any(|test| {
+            test.stdin.len() > OUTPUT_LIMIT || test.expected_stdout.len() > OUTPUT_LIMIT
+        }) {
+            let mut error = ValidationError::new("test_too_long");
+            error.message =
+                Some(format!("Test input and output must be at most {OUTPUT_LIMIT} bytes").into());
+            errors.add("tests", error);
+        }
+        if tests
+            .iter()
+            .any(|test| !test.weight.is_finite() || test.weight <= 0f64)
+        {
+            let mut error = ValidationError::new("invalid_weight");
+            error.message = Some("Test weight must be a positive number".into());
+            errors.add("tests", error);
+        }
+        if !TIME_LIMIT_RANGE.contains(time_limit_ms) {
+            let mut error = ValidationError::new("invalid_time_limit");
+            error.message = Some(
+                format!(
+                    "Time limit must be from {} to {} ms",
+                    TIME_LIMIT_RANGE.start(),
+                    TIME_LIMIT_RANGE.end()
+                )
+                .into(),
+            );
+            errors.add("time_limit_ms", error);
+        }
+        if !MEMORY_LIMIT_RANGE.contains(memory_limit_mb) {
+            let mut error = ValidationError::new("invalid_memory_limit");
+            error.message = Some(
+                format!(
+                    "Memory limit must be from {} to {} MB",
+                    MEMORY_LIMIT_RANGE.start(),
+                    MEMORY_LIMIT_RANGE.end()
+                )
+                .into(),
+            );
+            errors.add("memory_limit_mb", error);
+        }
+    }
+
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskAnswer::Code { source } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if source.len() > SOURCE_MAX_LEN {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Your program is larger than allowed ({SOURCE_MAX_LEN} bytes)"
+            )));
+        }
+        Ok(())
+    }
+
+    /// The tests are run in the background by [`run_tests`].
+    fn grade(
+        &self,
+        _config: &TaskConfig,
+        answer: &TaskAnswer,
+        _points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        match answer {
+            TaskAnswer::Code { .. } => Ok(TaskVerdict::OnReview),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Code {
+                language,
+                time_limit_ms,
+                memory_limit_mb,
+                ..
+            } => Ok(PublicTaskConfig::Code {
+                language,
+                time_limit_ms,
+                memory_limit_mb,
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Code)),
+        }
+    }
+
+    fn solution(&self, _config: &TaskConfig) -> Option<TaskSolution> {
+        None
+    }
+
+    /// Graded without a reviewer, but in the background, which practice
+    /// doesn't wait for.
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        false
+    }
+}
+
+/// Runs `source` against the tests of a `Code` task worth `points`.
+///
+/// The verdict goes by the weights of the passed tests and comes with a
+/// breakdown for its comment, a line per test saying only whether it passed,
+/// so neither hidden test data nor anything the program did with it makes it
+/// there.
+pub async fn run_tests(
+    sandbox: &Sandbox,
+    config: &TaskConfig,
+    source: &str,
+    points: f64,
+) -> crate::errors::Result<(TaskVerdict, String)> {
+    let TaskConfig::Code {
+        language,
+        tests,
+        time_limit_ms,
+        memory_limit_mb,
+    } = config
+    else {
+        return Err(GradeError::ConfigMismatch(TaskType::Code).into());
+    };
+    let sandbox_error = |e: std::io::Error| LMSError::ServerError(format!("Sandbox failed: {e}"));
+    let dir = tempfile::tempdir().map_err(sandbox_error)?;
+    let (source_file, program, args) = match language {
+        CodeLanguage::Python => ("main.py", "python3", vec!["-I", "main.py"]),
+        CodeLanguage::C => ("main.c", "./main", Vec::new()),
+        CodeLanguage::Bash => ("main.sh", "bash", vec!["main.sh"]),
+    };
+    let source_path = dir.path().join(source_file);
+    tokio::fs::write(&source_path, source)
+        .await
+        .map_err(sandbox_error)?;
+    tokio::fs::set_permissions(&source_path, Permissions::from_mode(0o444))
+        .await
+        .map_err(sandbox_error)?;
+
+    if matches!(language, CodeLanguage::C) {
+        let compiled = sandbox
+            .run(
+                dir.path(),
+                Path::new("cc"),
+                &["-std=c11", "-O2", "-o", "main", "main.c", "-lm"],
+                b"",
+                COMPILE_LIMITS,
+                DirAccess::ReadWrite,
+            )
+            .await
+            .map_err(sandbox_error)?;
+        if compiled.status != RunStatus::Exited(0) {
+            let output: String = String::from_utf8_lossy(&compiled.stderr)
+                .chars()
+                .take(COMPILER_OUTPUT_MAX_LEN)
+                .collect();
+            return Ok((
+                verdict_for_share(0f64, points),
+                format!("Compilation failed:\n{}", output.trim_end()),
+            ));
+        }
+        // so that tests can't replace it
+        sandbox::seal(&dir.path().join("main")).map_err(sandbox_error)?;
+    }
+
+    let limits = Limits {
+        time: Duration::from_millis(u64::from(*time_limit_ms)),
+        memory: u64::from(*memory_limit_mb) * 1024 * 1024,
+    };
+    let mut comment = String::new();
+    let mut passed_weight = 0f64;
+    for (number, test) in (1..).zip(tests) {
+        let output = sandbox
+            .run(
+                dir.path(),
+                Path::new(program),
+                &args,
+                test.stdin.as_bytes(),
+                limits,
+                DirAccess::ReadOnly,
+            )
+            .await
+            .map_err(sandbox_error)?;
+        // the tests are hidden, so how a failed one went stays untold: exit
+        // codes and the like would let a submission leak the test data
+        let passed = matches!(output.status, RunStatus::Exited(0))
+            && !output.output_truncated
+            && same_output(&String::from_utf8_lossy(&output.stdout), test);
+        let outcome = if passed {
+            passed_weight += test.weight;
+            "passed"
+        } else {
+            "failed"
+        };
+        let _ = writeln!(comment, "Test {number}: {outcome}");
+    }
+
+    let total_weight: f64 = tests.iter().map(|test| test.weight).sum();
+    Ok((
+        verdict_for_share(passed_weight / total_weight, points),
+        comment.trim_end().to_string(),
+    ))
+}
+
+/// Whether `actual` is the test's expected output, ignoring trailing
+/// whitespace of every line and trailing empty lines.
+fn same_output(actual: &str, test: &CodeTestCase) -> bool {
+    fn lines(text: &str) -> Vec<&str> {
+        let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+    lines(actual) == lines(&test.expected_stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{answer, config, invalid_fields};
+    use serde_json::json;
+
+    fn code() -> TaskConfig {
+        config(json!({
+            "name": "code",
+            "language": "python",
+            "tests": [{"stdin": "2 3", "expected_stdout": "5"}],
+            "time_limit_ms": 1000,
+            "memory_limit_mb": 64,
+        }))
+    }
+
+    fn test_case(expected_stdout: &str) -> CodeTestCase {
+        CodeTestCase {
+            stdin: String::new(),
+            expected_stdout: expected_stdout.to_string(),
+            weight: 1f64,
+        }
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&CodeGrader, &code()).is_empty());
+        let broken = config(json!({
+            "name": "code",
+            "language": "c",
+            "tests": [],
+            "time_limit_ms": 50,
+            "memory_limit_mb": 4096,
+        }));
+        assert_eq!(
+            invalid_fields(&CodeGrader, &broken),
+            ["memory_limit_mb", "tests", "time_limit_ms"]
+        );
+        let bad_weight = config(json!({
+            "name": "code",
+            "language": "bash",
+            "tests": [{"expected_stdout": "", "weight": 0}],
+            "time_limit_ms": 1000,
+            "memory_limit_mb": 64,
+        }));
+        assert_eq!(invalid_fields(&CodeGrader, &bad_weight), ["tests"]);
+    }
+
+    #[test]
+    fn answers_wait_for_their_tests() {
+        let source = answer(json!({"name": "code", "source": "print(5)"}));
+        assert!(matches!(
+            CodeGrader.grade(&code(), &source, 1.0),
+            Ok(TaskVerdict::OnReview)
+        ));
+        let other = answer(json!({"name": "long_text", "answer": "print(5)"}));
+        assert!(matches!(
+            CodeGrader.grade(&code(), &other, 1.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+        let huge = answer(json!({"name": "code", "source": "#".repeat(SOURCE_MAX_LEN + 1)}));
+        assert!(matches!(
+            CodeGrader.validate_answer(&code(), &huge),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_tests() {
+        let Ok(PublicTaskConfig::Code {
+            language,
+            time_limit_ms,
+            memory_limit_mb,
+        }) = CodeGrader.public_config(code())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(language, CodeLanguage::Python);
+        assert_eq!((time_limit_ms, memory_limit_mb), (1000, 64));
+    }
+
+    #[test]
+    fn output_is_compared_by_lines() {
+        assert!(same_output("1\n2\n", &test_case("1\n2")));
+        assert!(same_output("1  \r\n2\n\n\n", &test_case("1\n2\n")));
+        assert!(!same_output("1\n 2", &test_case("1\n2")));
+        assert!(!same_output("1\n\n2", &test_case("1\n2")));
+        assert!(!same_output("", &test_case("0")));
+    }
+}
@@ -0,0 +1,90 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use validator::ValidationErrors;
+
+pub struct CtfdGrader;
+
+impl TaskGrader for CtfdGrader {
+    fn validate_config(&self, _config: &TaskConfig, _errors: &mut ValidationErrors) {} // TODO: go to CTFd to check task
+
+    /// Whether the task is actually solved is asked from `CTFd` by the caller.
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        match answer {
+            TaskAnswer::CTFd => Ok(()),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn grade(
+        &self,
+        _config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        match answer {
+            // if answer exists then task is solved
+            TaskAnswer::CTFd => Ok(TaskVerdict::FullScore {
+                comment: None,
+                score: points,
+                max_score: points,
+            }),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::CTFd { task_id } => Ok(PublicTaskConfig::CTFd { task_id }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::CTFd)),
+        }
+    }
+
+    fn solution(&self, _config: &TaskConfig) -> Option<TaskSolution> {
+        None
+    }
+
+    /// Solved on the `CTFd` side, so nothing for practice to check.
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::json;
+
+    fn ctfd() -> TaskConfig {
+        config(json!({"name": "ctfd", "task_id": 7}))
+    }
+
+    #[test]
+    fn config_is_left_to_ctfd() {
+        assert!(invalid_fields(&CtfdGrader, &ctfd()).is_empty());
+    }
+
+    #[test]
+    fn a_recorded_solve_scores_full() {
+        let solve = answer(json!({"name": "ctfd"}));
+        assert_verdict(CtfdGrader.grade(&ctfd(), &solve, 10.0), "full_score", 10.0);
+        let other = answer(json!({"name": "short_text", "answer": "flag{}"}));
+        assert!(matches!(
+            CtfdGrader.grade(&ctfd(), &other, 10.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_keeps_the_task() {
+        let Ok(PublicTaskConfig::CTFd { task_id }) = CtfdGrader.public_config(ctfd()) else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(task_id, 7);
+    }
+}
@@ -0,0 +1,224 @@
+use super::{GradeError, TaskGrader, verdict_for_share};
+use crate::domain::task::expression::{MIN_DEFINED_POINTS, check_variables, parse};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct ExpressionGrader;
+
+impl TaskGrader for ExpressionGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Expression {
+            answer,
+            variables,
+            tolerance,
+        } = config
+        else {
+            return;
+        };
+        if let Err(e) = check_variables(variables) {
+            let mut error = ValidationError::new("invalid_variables");
+            error.message = Some(e.into());
+            errors.add("variables", error);
+            // the answer can't be checked against broken variables
+            return;
+        }
+        match parse(answer, variables) {
+            Err(e) => {
+                let mut error = ValidationError::new("invalid_answer");
+                error.message = Some(format!("Answer is not a valid expression: {e}").into());
+                errors.add("answer", error);
+            }
+            Ok(parsed) if parsed.defined_points(variables) < MIN_DEFINED_POINTS => {
+                let mut error = ValidationError::new("undefined_answer");
+                error.message =
+                    Some("Answer must be a finite number for most values of the variables".into());
+                errors.add("answer", error);
+            }
+            Ok(_) => {}
+        }
+        if !tolerance.is_finite() || !(0f64..1f64).contains(tolerance) {
+            let mut error = ValidationError::new("invalid_tolerance");
+            error.message = Some("Tolerance must be from 0 to 1 (exclusive)".into());
+            errors.add("tolerance", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::Expression { variables, .. } = config else {
+            return Err(GradeError::ConfigMismatch(TaskType::Expression));
+        };
+        let TaskAnswer::Expression { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        parse(answer, variables)
+            .map(|_| ())
+            .map_err(|e| GradeError::InvalidAnswer(format!("Your answer can't be parsed: {e}")))
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::Expression {
+            answer: expected,
+            variables,
+            tolerance,
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Expression));
+        };
+        let TaskAnswer::Expression { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        // both are validated beforehand, whatever doesn't parse is simply wrong
+        let matched = parse(expected, variables)
+            .and_then(|expected| Ok((expected, parse(answer, variables)?)))
+            .is_ok_and(|(expected, answer)| expected.is_equivalent(&answer, variables, *tolerance));
+        Ok(verdict_for_share(if matched { 1f64 } else { 0f64 }, points))
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Expression { variables, .. } => Ok(PublicTaskConfig::Expression {
+                variables: variables
+                    .into_iter()
+                    .map(|variable| variable.name)
+                    .collect(),
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Expression)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::Expression { answer, .. } = config else {
+            return None;
+        };
+        Some(TaskSolution::Expression {
+            answer: answer.clone(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::json;
+
+    fn expression() -> TaskConfig {
+        config(json!({
+            "name": "expression",
+            "answer": "(p - 1)(q - 1)",
+            "variables": [
+                {"name": "p", "min": 2, "max": 100, "integer": true},
+                {"name": "q", "min": 2, "max": 100, "integer": true},
+            ],
+        }))
+    }
+
+    fn typed(text: &str) -> TaskAnswer {
+        answer(json!({"name": "expression", "answer": text}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&ExpressionGrader, &expression()).is_empty());
+        let broken = config(json!({
+            "name": "expression",
+            "answer": "x +",
+            "variables": [{"name": "x", "min": 0, "max": 1}],
+            "tolerance": 1.0,
+        }));
+        assert_eq!(
+            invalid_fields(&ExpressionGrader, &broken),
+            ["answer", "tolerance"]
+        );
+        let undefined = config(json!({
+            "name": "expression",
+            "answer": "ln(x)",
+            "variables": [{"name": "x", "min": -10, "max": -1}],
+        }));
+        assert_eq!(invalid_fields(&ExpressionGrader, &undefined), ["answer"]);
+        let bad_variables = config(json!({
+            "name": "expression",
+            "answer": "y",
+            "variables": [{"name": "sin", "min": 0, "max": 1}],
+        }));
+        assert_eq!(
+            invalid_fields(&ExpressionGrader, &bad_variables),
+            ["variables"]
+        );
+    }
+
+    #[test]
+    fn equivalent_answers_pass() {
+        let config = expression();
+        for text in ["(q - 1)(p - 1)", "p q - p - q + 1"] {
+            assert_verdict(
+                ExpressionGrader.grade(&config, &typed(text), 2.0),
+                "full_score",
+                2.0,
+            );
+        }
+        assert_verdict(
+            ExpressionGrader.grade(&config, &typed("p q"), 2.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn tolerance_is_relative() {
+        let config = config(json!({
+            "name": "expression",
+            "answer": "pi * r^2",
+            "variables": [{"name": "r", "min": 1, "max": 10}],
+            "tolerance": 0.01,
+        }));
+        assert_verdict(
+            ExpressionGrader.grade(&config, &typed("3.14 r^2"), 1.0),
+            "full_score",
+            1.0,
+        );
+        assert_verdict(
+            ExpressionGrader.grade(&config, &typed("3 r^2"), 1.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn answers_are_checked() {
+        let config = expression();
+        assert!(matches!(
+            ExpressionGrader.validate_answer(&config, &typed("p + x")),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "numeric", "answer": "1"}));
+        assert!(matches!(
+            ExpressionGrader.grade(&config, &other, 2.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_only_names_the_variables() {
+        let Ok(PublicTaskConfig::Expression { variables }) =
+            ExpressionGrader.public_config(expression())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(variables, ["p", "q"]);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::domain::task::rubric::validate_rubric;
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+/// Upper bound for a single MIME type a `FileUpload` task accepts.
+const CONTENT_TYPE_MAX_LEN: usize = 100;
+
+pub struct FileUploadGrader;
+
+impl TaskGrader for FileUploadGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::FileUpload {
+            max_size,
+            allowed_types,
+            rubric,
+        } = config
+        else {
+            return;
+        };
+        validate_rubric(rubric, errors);
+        if *max_size == 0 || *max_size > 10 * 1024 * 1024 {
+            let mut error = ValidationError::new("invalid_file_upload");
+            error.message = Some("File max size must be less than or equals to 10 MB".into());
+            errors.add("max_size", error);
+        }
+        if allowed_types.iter().any(|allowed| {
+            allowed.len() > CONTENT_TYPE_MAX_LEN
+                || allowed
+                    .split_once('/')
+                    .is_none_or(|(kind, subtype)| kind.is_empty() || subtype.is_empty())
+        }) {
+            let mut error = ValidationError::new("invalid_content_type");
+            error.message = Some("Allowed types must be MIME types like image/png".into());
+            errors.add("allowed_types", error);
+        }
+    }
+
+    /// The uploaded file itself is checked when the answer is saved, see
+    /// `ExamService::modify_attempt`.
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        match answer {
+            TaskAnswer::FileUpload { .. } => Ok(()),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn grade(
+        &self,
+        _config: &TaskConfig,
+        answer: &TaskAnswer,
+        _points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        match answer {
+            TaskAnswer::FileUpload { .. } => Ok(TaskVerdict::OnReview),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::FileUpload {
+                max_size,
+                allowed_types,
+                ..
+            } => Ok(PublicTaskConfig::FileUpload {
+                max_size,
+                allowed_types,
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::FileUpload)),
+        }
+    }
+
+    fn solution(&self, _config: &TaskConfig) -> Option<TaskSolution> {
+        None
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{answer, config, invalid_fields};
+    use serde_json::json;
+
+    fn file_upload() -> TaskConfig {
+        config(json!({
+            "name": "file_upload",
+            "max_size": 1024,
+            "allowed_types": ["image/*", "application/pdf"],
+        }))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&FileUploadGrader, &file_upload()).is_empty());
+        let broken = config(json!({
+            "name": "file_upload",
+            "max_size": 0,
+            "allowed_types": ["image", "/png"],
+        }));
+        assert_eq!(
+            invalid_fields(&FileUploadGrader, &broken),
+            ["allowed_types", "max_size"]
+        );
+    }
+
+    #[test]
+    fn answers_go_to_review() {
+        let file = answer(json!({"name": "file_upload", "file_id": uuid::Uuid::nil()}));
+        assert!(matches!(
+            FileUploadGrader.grade(&file_upload(), &file, 5.0),
+            Ok(TaskVerdict::OnReview)
+        ));
+        let other = answer(json!({"name": "long_text", "answer": "file"}));
+        assert!(matches!(
+            FileUploadGrader.grade(&file_upload(), &other, 5.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_rubric() {
+        let Ok(PublicTaskConfig::FileUpload {
+            max_size,
+            allowed_types,
+        }) = FileUploadGrader.public_config(file_upload())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(max_size, 1024);
+        assert_eq!(allowed_types, ["image/*", "application/pdf"]);
+    }
+}
@@ -0,0 +1,129 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::domain::task::rubric::validate_rubric;
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct LongTextGrader;
+
+impl TaskGrader for LongTextGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::LongText {
+            max_chars_count,
+            rubric,
+        } = config
+        else {
+            return;
+        };
+        validate_rubric(rubric, errors);
+        if *max_chars_count == 0 || *max_chars_count > 5000 {
+            let mut error = ValidationError::new("invalid_max_chars_count");
+            error.message = Some("Maximum chars count (5000) exceeded".into());
+            errors.add("max_chars_count", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::LongText {
+            max_chars_count, ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::LongText));
+        };
+        let TaskAnswer::LongText { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if answer.len() > *max_chars_count {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Your answer length is more than allowed ({max_chars_count})"
+            )));
+        }
+        Ok(())
+    }
+
+    fn grade(
+        &self,
+        _config: &TaskConfig,
+        answer: &TaskAnswer,
+        _points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        match answer {
+            TaskAnswer::LongText { .. } => Ok(TaskVerdict::OnReview),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::LongText {
+                max_chars_count, ..
+            } => Ok(PublicTaskConfig::LongText { max_chars_count }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::LongText)),
+        }
+    }
+
+    fn solution(&self, _config: &TaskConfig) -> Option<TaskSolution> {
+        None
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{answer, config, invalid_fields};
+    use serde_json::json;
+
+    fn long_text() -> TaskConfig {
+        config(json!({"name": "long_text", "max_chars_count": 10}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&LongTextGrader, &long_text()).is_empty());
+        let broken = config(json!({
+            "name": "long_text",
+            "max_chars_count": 6000,
+            "rubric": [{"title": "Clarity", "levels": []}],
+        }));
+        assert_eq!(
+            invalid_fields(&LongTextGrader, &broken),
+            ["max_chars_count", "rubric"]
+        );
+    }
+
+    #[test]
+    fn answers_go_to_review() {
+        let essay = answer(json!({"name": "long_text", "answer": "Essay"}));
+        assert!(matches!(
+            LongTextGrader.grade(&long_text(), &essay, 5.0),
+            Ok(TaskVerdict::OnReview)
+        ));
+        assert!(!LongTextGrader.is_auto_gradable(&long_text()));
+        let too_long = answer(json!({"name": "long_text", "answer": "A longer essay"}));
+        assert!(matches!(
+            LongTextGrader.validate_answer(&long_text(), &too_long),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "short_text", "answer": "Essay"}));
+        assert!(matches!(
+            LongTextGrader.grade(&long_text(), &other, 5.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_rubric() {
+        let Ok(PublicTaskConfig::LongText { max_chars_count }) =
+            LongTextGrader.public_config(long_text())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(max_chars_count, 10);
+    }
+}
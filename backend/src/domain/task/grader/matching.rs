@@ -0,0 +1,240 @@
+use super::{GradeError, TaskGrader, verdict_for_share};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use std::collections::HashSet;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct MatchingGrader;
+
+impl TaskGrader for MatchingGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Matching {
+            left,
+            right,
+            correct,
+            ..
+        } = config
+        else {
+            return;
+        };
+        if left.is_empty() || right.is_empty() {
+            let mut error = ValidationError::new("empty_options");
+            error.message = Some("Both sides must not be empty".into());
+            errors.add("left", error);
+        }
+
+        if left.iter().chain(right).any(String::is_empty) {
+            let mut error = ValidationError::new("empty_option");
+            error.message = Some("Option value must not be empty".into());
+            errors.add("left", error);
+        }
+
+        // answers reference items by value, so they have to be unique per side
+        if left.iter().collect::<HashSet<_>>().len() != left.len()
+            || right.iter().collect::<HashSet<_>>().len() != right.len()
+        {
+            let mut error = ValidationError::new("duplicate_option");
+            error.message = Some("Items must be unique on each side".into());
+            errors.add("left", error);
+        }
+
+        if correct.len() != left.len() || correct.iter().any(|&x| x >= right.len()) {
+            let mut error = ValidationError::new("invalid_correct_index");
+            error.message = Some("Every left item must be paired with a right item".into());
+            errors.add("correct", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::Matching { left, right, .. } = config else {
+            return Err(GradeError::ConfigMismatch(TaskType::Matching));
+        };
+        let TaskAnswer::Matching { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if answer
+            .iter()
+            .any(|(l, r)| !left.contains(l) || !right.contains(r))
+        {
+            return Err(GradeError::InvalidAnswer(
+                "Your answer contains items that are not in the task".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::Matching {
+            left,
+            right,
+            correct,
+            partial_score,
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Matching));
+        };
+        let TaskAnswer::Matching { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        // unanswered left items count as wrong pairs
+        let correct_count = left
+            .iter()
+            .zip(correct)
+            .filter(|&(l, &r)| right.get(r).is_some_and(|r| answer.get(l) == Some(r)))
+            .count();
+
+        let share = if correct_count == left.len() {
+            1f64
+        } else if *partial_score {
+            correct_count as f64 / left.len() as f64
+        } else {
+            0f64
+        };
+        Ok(verdict_for_share(share, points))
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Matching {
+                left,
+                right,
+                partial_score,
+                ..
+            } => Ok(PublicTaskConfig::Matching {
+                left,
+                right,
+                partial_score,
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Matching)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::Matching {
+            left,
+            right,
+            correct,
+            ..
+        } = config
+        else {
+            return None;
+        };
+        Some(TaskSolution::Matching {
+            answer: left
+                .iter()
+                .zip(correct)
+                .filter_map(|(l, &r)| right.get(r).map(|r| (l.clone(), r.clone())))
+                .collect(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::json;
+
+    fn matching(partial_score: bool) -> TaskConfig {
+        config(json!({
+            "name": "matching",
+            "left": ["cat", "dog", "cow"],
+            "right": ["meow", "woof", "moo", "quack"],
+            "correct": [0, 1, 2],
+            "partial_score": partial_score,
+        }))
+    }
+
+    fn paired(pairs: &[(&str, &str)]) -> TaskAnswer {
+        let pairs: serde_json::Map<String, serde_json::Value> = pairs
+            .iter()
+            .map(|(left, right)| ((*left).to_string(), json!(right)))
+            .collect();
+        answer(json!({"name": "matching", "answer": pairs}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&MatchingGrader, &matching(true)).is_empty());
+        let broken = config(json!({
+            "name": "matching",
+            "left": ["cat", "cat"],
+            "right": ["meow"],
+            "correct": [0, 1],
+            "partial_score": false,
+        }));
+        assert_eq!(
+            invalid_fields(&MatchingGrader, &broken),
+            ["correct", "left"]
+        );
+    }
+
+    #[test]
+    fn pairs_are_scored() {
+        let all = [("cat", "meow"), ("dog", "woof"), ("cow", "moo")];
+        let two = [("cat", "meow"), ("dog", "woof"), ("cow", "quack")];
+        assert_verdict(
+            MatchingGrader.grade(&matching(false), &paired(&all), 3.0),
+            "full_score",
+            3.0,
+        );
+        assert_verdict(
+            MatchingGrader.grade(&matching(true), &paired(&two), 3.0),
+            "partial_score",
+            2.0,
+        );
+        assert_verdict(
+            MatchingGrader.grade(&matching(false), &paired(&two), 3.0),
+            "incorrect",
+            0.0,
+        );
+        // unanswered items count as wrong
+        assert_verdict(
+            MatchingGrader.grade(&matching(true), &paired(&[("cat", "meow")]), 3.0),
+            "partial_score",
+            1.0,
+        );
+    }
+
+    #[test]
+    fn answers_are_checked() {
+        assert!(matches!(
+            MatchingGrader.validate_answer(&matching(true), &paired(&[("cat", "bark")])),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "ordering", "answer": ["cat"]}));
+        assert!(matches!(
+            MatchingGrader.grade(&matching(true), &other, 3.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_pairs() {
+        let Ok(PublicTaskConfig::Matching {
+            left,
+            right,
+            partial_score,
+        }) = MatchingGrader.public_config(matching(true))
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(left, ["cat", "dog", "cow"]);
+        assert_eq!(right, ["meow", "woof", "moo", "quack"]);
+        assert!(partial_score);
+    }
+}
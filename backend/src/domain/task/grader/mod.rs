@@ -0,0 +1,205 @@
+//! Per-type task behaviour: what makes a configuration valid, how answers are
+//! checked and graded, and what of a task its learners get to see.
+//!
+//! Every task type has a [`TaskGrader`] in its own module, and [`grader`]
+//! picks it by [`TaskType`]. A new type is a new module plus a line there.
+//! Graders are only handed configurations of their own type (the registry
+//! dispatches on it), while answers come from learners and may be of any
+//! type; a foreign one is a [`GradeError::AnswerMismatch`].
+
+mod choice;
+mod cloze;
+pub mod code;
+mod ctfd;
+mod expression;
+mod file_upload;
+mod long_text;
+mod matching;
+mod numeric;
+mod ordering;
+mod short_text;
+
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use crate::errors::LMSError;
+use validator::ValidationErrors;
+
+pub trait TaskGrader: Sync {
+    /// Adds whatever is wrong with the configuration to `errors`.
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors);
+
+    /// Checks that the answer is of this type and within the task's limits
+    /// (e.g. text length). External checks (like `CTFd` solves) are left to
+    /// the caller.
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError>;
+
+    /// Scores an answer to a task worth `points`. Types that need manual
+    /// review give [`TaskVerdict::OnReview`]. The verdict has no comment, see
+    /// [`Self::feedback`].
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError>;
+
+    /// What the learner should read with an automatic verdict, like the
+    /// explanations of the options involved.
+    fn feedback<'a>(
+        &self,
+        _config: &'a TaskConfig,
+        _answer: &TaskAnswer,
+        _full_score: bool,
+    ) -> Vec<&'a str> {
+        Vec::new()
+    }
+
+    /// The configuration without whatever gives the answer away.
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError>;
+
+    /// The canonical correct answer, if there is one worth showing to a
+    /// learner who already solved the task.
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution>;
+
+    /// Whether answers are graded without a reviewer.
+    fn is_auto_gradable(&self, config: &TaskConfig) -> bool;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GradeError {
+    #[error("You've sent an answer for another task type")]
+    AnswerMismatch,
+
+    /// The answer is of the right type but breaks the task's limits.
+    #[error("{0}")]
+    InvalidAnswer(String),
+
+    /// A grader got another type's configuration, which the registry never does.
+    #[error("The {0:?} grader got a configuration of another type")]
+    ConfigMismatch(TaskType),
+}
+
+impl From<GradeError> for LMSError {
+    fn from(error: GradeError) -> Self {
+        match error {
+            GradeError::ConfigMismatch(_) => Self::ServerError(error.to_string()),
+            GradeError::AnswerMismatch | GradeError::InvalidAnswer(_) => {
+                Self::ShitHappened(error.to_string())
+            }
+        }
+    }
+}
+
+/// The grader of a task type.
+pub const fn grader(task_type: &TaskType) -> &'static dyn TaskGrader {
+    match task_type {
+        TaskType::SingleChoice => &choice::SingleChoiceGrader,
+        TaskType::MultipleChoice => &choice::MultipleChoiceGrader,
+        TaskType::ShortText => &short_text::ShortTextGrader,
+        TaskType::LongText => &long_text::LongTextGrader,
+        TaskType::Ordering => &ordering::OrderingGrader,
+        TaskType::FileUpload => &file_upload::FileUploadGrader,
+        TaskType::CTFd => &ctfd::CtfdGrader,
+        TaskType::Numeric => &numeric::NumericGrader,
+        TaskType::Matching => &matching::MatchingGrader,
+        TaskType::Cloze => &cloze::ClozeGrader,
+        TaskType::Code => &code::CodeGrader,
+        TaskType::Expression => &expression::ExpressionGrader,
+    }
+}
+
+/// Verdict for an answer that earned `share` of `points`: full, partial or
+/// none at all.
+fn verdict_for_share(share: f64, points: f64) -> TaskVerdict {
+    if share >= 1f64 {
+        TaskVerdict::FullScore {
+            comment: None,
+            score: points,
+            max_score: points,
+        }
+    } else if share > 0f64 {
+        TaskVerdict::PartialScore {
+            comment: None,
+            score: points * share,
+            max_score: points,
+        }
+    } else {
+        TaskVerdict::Incorrect {
+            comment: None,
+            score: 0f64,
+            max_score: points,
+        }
+    }
+}
+
+/// Helpers for the graders' tests.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{GradeError, TaskGrader};
+    use crate::domain::task::model::{TaskAnswer, TaskConfig};
+    use crate::dto::task::TaskVerdict;
+    use serde_json::Value;
+    use validator::ValidationErrors;
+
+    pub fn config(value: Value) -> TaskConfig {
+        serde_json::from_value(value).expect("Invalid JSON for TaskConfig")
+    }
+
+    pub fn answer(value: Value) -> TaskAnswer {
+        serde_json::from_value(value).expect("Invalid JSON for TaskAnswer")
+    }
+
+    /// Fields of `config` that `grader` finds fault with, sorted.
+    pub fn invalid_fields(grader: &dyn TaskGrader, config: &TaskConfig) -> Vec<String> {
+        let mut errors = ValidationErrors::new();
+        grader.validate_config(config, &mut errors);
+        let mut fields: Vec<String> = errors
+            .field_errors()
+            .keys()
+            .map(ToString::to_string)
+            .collect();
+        fields.sort();
+        fields
+    }
+
+    /// Checks the kind (`full_score`, `partial_score`, ...) and score of a
+    /// verdict.
+    pub fn assert_verdict(verdict: Result<TaskVerdict, GradeError>, kind: &str, score: f64) {
+        let verdict = verdict.expect("Grading failed");
+        let value = serde_json::to_value(&verdict).expect("Invalid TaskVerdict");
+        assert_eq!(value["verdict"], kind);
+        assert!(
+            (verdict.score() - score).abs() < 1e-9,
+            "Score {} instead of {score}",
+            verdict.score()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::assert_verdict;
+
+    #[test]
+    fn verdict_follows_the_share() {
+        assert_verdict(Ok(verdict_for_share(1.5, 4.0)), "full_score", 4.0);
+        assert_verdict(Ok(verdict_for_share(0.25, 4.0)), "partial_score", 1.0);
+        assert_verdict(Ok(verdict_for_share(0.0, 4.0)), "incorrect", 0.0);
+        assert_verdict(Ok(verdict_for_share(-0.5, 4.0)), "incorrect", 0.0);
+    }
+
+    #[test]
+    fn mismatches_are_told_apart() {
+        assert!(matches!(
+            LMSError::from(GradeError::AnswerMismatch),
+            LMSError::ShitHappened(_)
+        ));
+        assert!(matches!(
+            LMSError::from(GradeError::ConfigMismatch(TaskType::Code)),
+            LMSError::ServerError(_)
+        ));
+    }
+}
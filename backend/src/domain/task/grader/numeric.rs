@@ -0,0 +1,290 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType, parse_numeric_answer,
+};
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+/// Upper bound for the raw text of a `Numeric` answer.
+const NUMERIC_ANSWER_MAX_LEN: usize = 100;
+
+pub struct NumericGrader;
+
+impl TaskGrader for NumericGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Numeric {
+            answer,
+            absolute_tolerance,
+            relative_tolerance,
+            units,
+            unit_required,
+            ..
+        } = config
+        else {
+            return;
+        };
+        if !answer.is_finite() {
+            let mut error = ValidationError::new("invalid_answer");
+            error.message = Some("Answer must be a finite number".into());
+            errors.add("answer", error);
+        }
+        if !absolute_tolerance.is_finite() || *absolute_tolerance < 0f64 {
+            let mut error = ValidationError::new("invalid_tolerance");
+            error.message = Some("Tolerance must be a non-negative number".into());
+            errors.add("absolute_tolerance", error);
+        }
+        if !relative_tolerance.is_finite() || *relative_tolerance < 0f64 {
+            let mut error = ValidationError::new("invalid_tolerance");
+            error.message = Some("Tolerance must be a non-negative number".into());
+            errors.add("relative_tolerance", error);
+        }
+        if units.iter().any(|unit| unit.trim().is_empty()) {
+            let mut error = ValidationError::new("empty_unit");
+            error.message = Some("Unit value must not be empty".into());
+            errors.add("units", error);
+        }
+        if *unit_required && units.is_empty() {
+            let mut error = ValidationError::new("empty_units");
+            error.message = Some("Units must not be empty if unit_required is on".into());
+            errors.add("units", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::Numeric {
+            units,
+            unit_required,
+            bases,
+            ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Numeric));
+        };
+        let TaskAnswer::Numeric { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if answer.len() > NUMERIC_ANSWER_MAX_LEN {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Your answer length is more than allowed ({NUMERIC_ANSWER_MAX_LEN})"
+            )));
+        }
+        parse_numeric_answer(answer, units, *unit_required, bases)
+            .map(|_| ())
+            .map_err(GradeError::InvalidAnswer)
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::Numeric {
+            answer: expected,
+            absolute_tolerance,
+            relative_tolerance,
+            units,
+            unit_required,
+            bases,
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Numeric));
+        };
+        let TaskAnswer::Numeric { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        // a tiny epsilon keeps zero-tolerance tasks from failing on float noise
+        let tolerance = f64::EPSILON.mul_add(
+            expected.abs().max(1f64),
+            absolute_tolerance.max(relative_tolerance * expected.abs()),
+        );
+        let matched = parse_numeric_answer(answer, units, *unit_required, bases)
+            .is_ok_and(|value| (value - expected).abs() <= tolerance);
+        Ok(if matched {
+            TaskVerdict::FullScore {
+                comment: None,
+                score: points,
+                max_score: points,
+            }
+        } else {
+            TaskVerdict::Incorrect {
+                comment: None,
+                score: 0f64,
+                max_score: points,
+            }
+        })
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Numeric {
+                units,
+                unit_required,
+                bases,
+                ..
+            } => Ok(PublicTaskConfig::Numeric {
+                units,
+                unit_required,
+                bases,
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Numeric)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::Numeric { answer, units, .. } = config else {
+            return None;
+        };
+        Some(TaskSolution::Numeric {
+            answer: *answer,
+            unit: units.first().cloned(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use crate::domain::task::model::NumericBase;
+    use serde_json::json;
+
+    fn numeric() -> TaskConfig {
+        config(json!({
+            "name": "numeric",
+            "answer": 42.0,
+            "absolute_tolerance": 0.5,
+            "units": ["ms", "s"],
+            "bases": ["hex"],
+        }))
+    }
+
+    fn typed(text: &str) -> TaskAnswer {
+        answer(json!({"name": "numeric", "answer": text}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&NumericGrader, &numeric()).is_empty());
+        let broken = config(json!({
+            "name": "numeric",
+            "answer": 1.0,
+            "absolute_tolerance": -1.0,
+            "relative_tolerance": -0.1,
+            "unit_required": true,
+        }));
+        assert_eq!(
+            invalid_fields(&NumericGrader, &broken),
+            ["absolute_tolerance", "relative_tolerance", "units"]
+        );
+    }
+
+    #[test]
+    fn answers_within_tolerance_pass() {
+        let config = numeric();
+        for text in ["42", "42,3", "41.6 ms", "0x2a", "+42s"] {
+            assert_verdict(
+                NumericGrader.grade(&config, &typed(text), 2.0),
+                "full_score",
+                2.0,
+            );
+        }
+        for text in ["43", "0b101010", "forty-two"] {
+            assert_verdict(
+                NumericGrader.grade(&config, &typed(text), 2.0),
+                "incorrect",
+                0.0,
+            );
+        }
+    }
+
+    #[test]
+    fn comma_is_only_a_decimal_separator() {
+        let plain = config(json!({"name": "numeric", "answer": 1.5}));
+        assert!(NumericGrader.validate_answer(&plain, &typed("1,5")).is_ok());
+        assert!(
+            NumericGrader
+                .validate_answer(&plain, &typed("0,125"))
+                .is_ok()
+        );
+        for text in ["1,000", "1,2,3", "1.000,5", "1,5.0"] {
+            assert!(
+                matches!(
+                    NumericGrader.validate_answer(&plain, &typed(text)),
+                    Err(GradeError::InvalidAnswer(_))
+                ),
+                "{text} was accepted"
+            );
+        }
+        let thousand = config(json!({"name": "numeric", "answer": 1000.0}));
+        assert_verdict(
+            NumericGrader.grade(&thousand, &typed("1,000"), 1.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn relative_tolerance_scales_with_the_answer() {
+        let config = config(json!({
+            "name": "numeric",
+            "answer": 1000.0,
+            "relative_tolerance": 0.01,
+        }));
+        assert_verdict(
+            NumericGrader.grade(&config, &typed("1009"), 1.0),
+            "full_score",
+            1.0,
+        );
+        assert_verdict(
+            NumericGrader.grade(&config, &typed("1011"), 1.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn answers_are_checked() {
+        let required = config(json!({
+            "name": "numeric",
+            "answer": 5.0,
+            "units": ["kg"],
+            "unit_required": true,
+        }));
+        assert!(
+            NumericGrader
+                .validate_answer(&required, &typed("5 kg"))
+                .is_ok()
+        );
+        assert!(matches!(
+            NumericGrader.validate_answer(&required, &typed("5")),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "short_text", "answer": "5"}));
+        assert!(matches!(
+            NumericGrader.grade(&required, &other, 1.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_answer() {
+        let Ok(PublicTaskConfig::Numeric {
+            units,
+            unit_required,
+            bases,
+        }) = NumericGrader.public_config(numeric())
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(units, ["ms", "s"]);
+        assert!(!unit_required);
+        assert!(bases == [NumericBase::Hex]);
+    }
+}
@@ -0,0 +1,243 @@
+use super::{GradeError, TaskGrader};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType,
+};
+use crate::dto::task::TaskVerdict;
+use std::collections::HashSet;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct OrderingGrader;
+
+impl TaskGrader for OrderingGrader {
+    // checking if order answers belong in items vec & all items are included in ordering
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::Ordering { items, answers, .. } = config else {
+            return;
+        };
+        if answers.iter().any(|answer| {
+            // Check if any position is out of bounds
+            answer.iter().any(|&position| position >= items.len())
+                // Or if the answer does not contain all items (duplicates or missing)
+                || answer.iter().collect::<HashSet<_>>().len() != items.len()
+        }) {
+            let mut error = ValidationError::new("invalid_ordering");
+            error.message = Some("Invalid order index specified for answer".into());
+            errors.add("answers", error);
+        }
+    }
+
+    fn validate_answer(&self, _config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        match answer {
+            TaskAnswer::Ordering { .. } => Ok(()),
+            _ => Err(GradeError::AnswerMismatch),
+        }
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::Ordering {
+            items,
+            answers,
+            partial_score,
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::Ordering));
+        };
+        let TaskAnswer::Ordering { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        let precomputed_answers: Vec<Vec<String>> = answers
+            .iter()
+            .map(|correct| {
+                correct
+                    .iter()
+                    .filter_map(|&i| items.get(i).cloned())
+                    .collect()
+            })
+            .collect();
+        if precomputed_answers
+            .iter()
+            .any(|precomputed| precomputed == answer)
+        {
+            return Ok(TaskVerdict::FullScore {
+                comment: None,
+                score: points,
+                max_score: points,
+            });
+        }
+
+        // partial credit only makes sense for a permutation of all items
+        let is_permutation = answer.len() == items.len()
+            && answer.iter().collect::<HashSet<_>>() == items.iter().collect();
+        let score_multiplier = if is_permutation {
+            precomputed_answers
+                .iter()
+                .map(|precomputed| {
+                    let ranks: Vec<usize> = answer
+                        .iter()
+                        .filter_map(|item| precomputed.iter().position(|x| x == item))
+                        .collect();
+                    partial_score.similarity(&ranks)
+                })
+                .fold(0f64, f64::max)
+        } else {
+            0f64
+        };
+        if score_multiplier <= 0f64 {
+            return Ok(TaskVerdict::Incorrect {
+                comment: None,
+                score: 0f64,
+                max_score: points,
+            });
+        }
+
+        Ok(TaskVerdict::PartialScore {
+            score: points * score_multiplier,
+            comment: None,
+            max_score: points,
+        })
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::Ordering {
+                items,
+                partial_score,
+                ..
+            } => Ok(PublicTaskConfig::Ordering {
+                items,
+                partial_score,
+            }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::Ordering)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::Ordering { items, answers, .. } = config else {
+            return None;
+        };
+        answers.first().map(|order| TaskSolution::Ordering {
+            answer: order
+                .iter()
+                .filter_map(|&i| items.get(i).cloned())
+                .collect(),
+        })
+    }
+
+    fn is_auto_gradable(&self, _config: &TaskConfig) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use crate::domain::task::model::OrderingPartialScore;
+    use serde_json::json;
+
+    fn ordering(partial_score: &str) -> TaskConfig {
+        config(json!({
+            "name": "ordering",
+            "items": ["a", "b", "c", "d"],
+            "answers": [[0, 1, 2, 3]],
+            "partial_score": partial_score,
+        }))
+    }
+
+    fn ordered(items: &[&str]) -> TaskAnswer {
+        answer(json!({"name": "ordering", "answer": items}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&OrderingGrader, &ordering("off")).is_empty());
+        for answers in [
+            json!([[0, 1, 2, 4]]),
+            json!([[0, 0, 1, 2]]),
+            json!([[0, 1]]),
+        ] {
+            let broken = config(json!({
+                "name": "ordering",
+                "items": ["a", "b", "c", "d"],
+                "answers": answers,
+            }));
+            assert_eq!(invalid_fields(&OrderingGrader, &broken), ["answers"]);
+        }
+    }
+
+    #[test]
+    fn any_accepted_order_scores_full() {
+        let config = config(json!({
+            "name": "ordering",
+            "items": ["a", "b", "c"],
+            "answers": [[0, 1, 2], [2, 1, 0]],
+        }));
+        assert_verdict(
+            OrderingGrader.grade(&config, &ordered(&["c", "b", "a"]), 3.0),
+            "full_score",
+            3.0,
+        );
+        assert_verdict(
+            OrderingGrader.grade(&config, &ordered(&["b", "a", "c"]), 3.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn partial_strategies() {
+        let answer = ordered(&["b", "a", "c", "d"]);
+        // b, c, d keep their order: 2 of 3 steps
+        assert_verdict(
+            OrderingGrader.grade(&ordering("longest_subsequence"), &answer, 3.0),
+            "partial_score",
+            2.0,
+        );
+        // one of six pairs is swapped
+        assert_verdict(
+            OrderingGrader.grade(&ordering("kendall_tau"), &answer, 6.0),
+            "partial_score",
+            5.0,
+        );
+        assert_verdict(
+            OrderingGrader.grade(&ordering("off"), &answer, 3.0),
+            "incorrect",
+            0.0,
+        );
+        // partial credit is only for permutations of all items
+        assert_verdict(
+            OrderingGrader.grade(&ordering("kendall_tau"), &ordered(&["a", "b", "c"]), 3.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn other_answers_are_rejected() {
+        let other = answer(json!({"name": "matching", "answer": {}}));
+        assert!(matches!(
+            OrderingGrader.grade(&ordering("off"), &other, 3.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn public_config_hides_the_order() {
+        let Ok(PublicTaskConfig::Ordering {
+            items,
+            partial_score,
+        }) = OrderingGrader.public_config(ordering("kendall_tau"))
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(items, ["a", "b", "c", "d"]);
+        assert!(partial_score == OrderingPartialScore::KendallTau);
+    }
+}
@@ -0,0 +1,477 @@
+use super::{GradeError, TaskGrader, verdict_for_share};
+use crate::domain::task::model::{
+    PublicTaskConfig, TaskAnswer, TaskConfig, TaskSolution, TaskType, compile_answer_regex,
+    normalize_short_text,
+};
+use crate::dto::task::TaskVerdict;
+use validator::{ValidationError, ValidationErrors};
+
+pub struct ShortTextGrader;
+
+impl TaskGrader for ShortTextGrader {
+    fn validate_config(&self, config: &TaskConfig, errors: &mut ValidationErrors) {
+        let TaskConfig::ShortText {
+            auto_grade,
+            max_chars_count,
+            case_sensitive,
+            answers,
+            patterns,
+            feedback,
+            max_distance,
+            ..
+        } = config
+        else {
+            return;
+        };
+        if *auto_grade && answers.is_empty() && patterns.is_empty() {
+            let mut error = ValidationError::new("empty_options");
+            error.message = Some("Options must not be empty if auto_grade is on".into());
+            errors.add("answers", error);
+        }
+        // a distance as long as an answer would let any short enough text through
+        let shortest = answers
+            .iter()
+            .chain(
+                patterns
+                    .iter()
+                    .filter(|pattern| !pattern.regex)
+                    .map(|pattern| &pattern.value),
+            )
+            .map(|value| value.chars().count())
+            .min();
+        if shortest.is_some_and(|shortest| *max_distance >= shortest) {
+            let mut error = ValidationError::new("invalid_max_distance");
+            error.message = Some("Maximum distance must be shorter than every answer".into());
+            errors.add("max_distance", error);
+        }
+        if patterns.iter().any(|pattern| pattern.value.is_empty()) {
+            let mut error = ValidationError::new("empty_pattern");
+            error.message = Some("Pattern value must not be empty".into());
+            errors.add("patterns", error);
+        }
+        if patterns
+            .iter()
+            .any(|pattern| !(pattern.score > 0f64 && pattern.score <= 1f64))
+        {
+            let mut error = ValidationError::new("invalid_pattern_score");
+            error.message = Some("Pattern score must be in (0, 1]".into());
+            errors.add("patterns", error);
+        }
+        for pattern in patterns.iter().filter(|pattern| pattern.regex) {
+            if let Err(e) = compile_answer_regex(&pattern.value, *case_sensitive) {
+                let mut error = ValidationError::new("invalid_regex");
+                error.message = Some(format!("Invalid pattern {}: {e}", pattern.value).into());
+                errors.add("patterns", error);
+            }
+        }
+        if feedback
+            .iter()
+            .any(|item| item.value.is_empty() || item.comment.trim().is_empty())
+        {
+            let mut error = ValidationError::new("empty_feedback");
+            error.message = Some("Feedback value and comment must not be empty".into());
+            errors.add("feedback", error);
+        }
+        for item in feedback.iter().filter(|item| item.regex) {
+            if let Err(e) = compile_answer_regex(&item.value, *case_sensitive) {
+                let mut error = ValidationError::new("invalid_regex");
+                error.message = Some(format!("Invalid pattern {}: {e}", item.value).into());
+                errors.add("feedback", error);
+            }
+        }
+        if *max_chars_count == 0 || *max_chars_count > 500 {
+            let mut error = ValidationError::new("invalid_max_chars_count");
+            error.message = Some("Maximum chars count (500) exceeded".into());
+            errors.add("max_chars_count", error);
+        }
+    }
+
+    fn validate_answer(&self, config: &TaskConfig, answer: &TaskAnswer) -> Result<(), GradeError> {
+        let TaskConfig::ShortText {
+            max_chars_count, ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::ShortText));
+        };
+        let TaskAnswer::ShortText { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if answer.len() > *max_chars_count {
+            return Err(GradeError::InvalidAnswer(format!(
+                "Your answer length is more than allowed ({max_chars_count})"
+            )));
+        }
+        Ok(())
+    }
+
+    fn grade(
+        &self,
+        config: &TaskConfig,
+        answer: &TaskAnswer,
+        points: f64,
+    ) -> Result<TaskVerdict, GradeError> {
+        let TaskConfig::ShortText {
+            answers,
+            auto_grade,
+            case_sensitive,
+            whitespace,
+            normalize_unicode,
+            max_distance,
+            patterns,
+            ..
+        } = config
+        else {
+            return Err(GradeError::ConfigMismatch(TaskType::ShortText));
+        };
+        let TaskAnswer::ShortText { answer } = answer else {
+            return Err(GradeError::AnswerMismatch);
+        };
+        if !auto_grade {
+            return Ok(TaskVerdict::OnReview);
+        }
+        let normalize = |value: &str| normalize_short_text(value, *whitespace, *normalize_unicode);
+        let fold_case = |value: String| {
+            if *case_sensitive {
+                value
+            } else {
+                value.to_lowercase()
+            }
+        };
+        let answer = normalize(answer);
+        let folded_answer = fold_case(answer.clone());
+        let plain_matches = |expected: &str| {
+            strsim::levenshtein(&fold_case(normalize(expected)), &folded_answer) <= *max_distance
+        };
+
+        let mut share = if answers.iter().any(|x| plain_matches(x)) {
+            1f64
+        } else {
+            0f64
+        };
+        for pattern in patterns {
+            if pattern.score <= share {
+                continue;
+            }
+            let matched = if pattern.regex {
+                // patterns are checked in validate_config, a broken one just never matches
+                compile_answer_regex(&pattern.value, *case_sensitive)
+                    .is_ok_and(|regex| regex.is_match(&answer))
+            } else {
+                plain_matches(&pattern.value)
+            };
+            if matched {
+                share = pattern.score;
+            }
+        }
+
+        Ok(verdict_for_share(share, points))
+    }
+
+    /// The comment for an anticipated wrong answer.
+    fn feedback<'a>(
+        &self,
+        config: &'a TaskConfig,
+        answer: &TaskAnswer,
+        full_score: bool,
+    ) -> Vec<&'a str> {
+        let (
+            TaskConfig::ShortText {
+                case_sensitive,
+                whitespace,
+                normalize_unicode,
+                feedback,
+                ..
+            },
+            TaskAnswer::ShortText { answer },
+        ) = (config, answer)
+        else {
+            return Vec::new();
+        };
+        if full_score {
+            return Vec::new();
+        }
+        let answer = normalize_short_text(answer, *whitespace, *normalize_unicode);
+        feedback
+            .iter()
+            .find(|item| {
+                if item.regex {
+                    compile_answer_regex(&item.value, *case_sensitive)
+                        .is_ok_and(|regex| regex.is_match(&answer))
+                } else {
+                    let expected =
+                        normalize_short_text(&item.value, *whitespace, *normalize_unicode);
+                    if *case_sensitive {
+                        expected == answer
+                    } else {
+                        expected.to_lowercase() == answer.to_lowercase()
+                    }
+                }
+            })
+            .map(|item| item.comment.as_str())
+            .into_iter()
+            .collect()
+    }
+
+    fn public_config(&self, config: TaskConfig) -> Result<PublicTaskConfig, GradeError> {
+        match config {
+            TaskConfig::ShortText {
+                max_chars_count, ..
+            } => Ok(PublicTaskConfig::ShortText { max_chars_count }),
+            _ => Err(GradeError::ConfigMismatch(TaskType::ShortText)),
+        }
+    }
+
+    fn solution(&self, config: &TaskConfig) -> Option<TaskSolution> {
+        let TaskConfig::ShortText {
+            auto_grade,
+            answers,
+            patterns,
+            ..
+        } = config
+        else {
+            return None;
+        };
+        // regexes and partial-credit patterns aren't something to show as "the" answer
+        let answers: Vec<String> = answers
+            .iter()
+            .cloned()
+            .chain(
+                patterns
+                    .iter()
+                    .filter(|pattern| !pattern.regex && pattern.score >= 1f64)
+                    .map(|pattern| pattern.value.clone()),
+            )
+            .collect();
+        if *auto_grade && !answers.is_empty() {
+            Some(TaskSolution::ShortText { answers })
+        } else {
+            None
+        }
+    }
+
+    /// Only with `auto_grade` on; otherwise every answer goes to a reviewer.
+    fn is_auto_gradable(&self, config: &TaskConfig) -> bool {
+        matches!(
+            config,
+            TaskConfig::ShortText {
+                auto_grade: true,
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::{
+        answer, assert_verdict, config, invalid_fields,
+    };
+    use serde_json::{Value, json};
+
+    fn short_text(extra: Value) -> TaskConfig {
+        let mut value = json!({
+            "name": "short_text",
+            "auto_grade": true,
+            "max_chars_count": 100,
+            "case_sensitive": false,
+            "answers": ["Paris"],
+        });
+        let Value::Object(extra) = extra else {
+            panic!("An object");
+        };
+        value.as_object_mut().expect("An object").extend(extra);
+        config(value)
+    }
+
+    fn typed(text: &str) -> TaskAnswer {
+        answer(json!({"name": "short_text", "answer": text}))
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(invalid_fields(&ShortTextGrader, &short_text(json!({}))).is_empty());
+        let broken = short_text(json!({
+            "answers": [],
+            "max_chars_count": 0,
+            "patterns": [{"value": "(", "regex": true, "score": 1.5}],
+            "feedback": [{"value": "x", "comment": " "}],
+        }));
+        assert_eq!(
+            invalid_fields(&ShortTextGrader, &broken),
+            ["feedback", "max_chars_count", "patterns"]
+        );
+        let nothing_to_grade_by = short_text(json!({"answers": []}));
+        assert_eq!(
+            invalid_fields(&ShortTextGrader, &nothing_to_grade_by),
+            ["answers"]
+        );
+    }
+
+    #[test]
+    fn answers_match_after_normalizing() {
+        let config = short_text(json!({}));
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("  paris "), 3.0),
+            "full_score",
+            3.0,
+        );
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("London"), 3.0),
+            "incorrect",
+            0.0,
+        );
+
+        let strict = short_text(json!({"case_sensitive": true, "whitespace": "remove"}));
+        assert_verdict(
+            ShortTextGrader.grade(&strict, &typed("Pa ris"), 3.0),
+            "full_score",
+            3.0,
+        );
+        assert_verdict(
+            ShortTextGrader.grade(&strict, &typed("paris"), 3.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn typos_within_the_distance_pass() {
+        let config = short_text(json!({"max_distance": 1}));
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("Pariss"), 3.0),
+            "full_score",
+            3.0,
+        );
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("Parisss"), 3.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn distance_is_shorter_than_the_answers() {
+        let config = short_text(json!({"max_distance": 4}));
+        assert!(invalid_fields(&ShortTextGrader, &config).is_empty());
+        let config = short_text(json!({"max_distance": 5}));
+        assert_eq!(invalid_fields(&ShortTextGrader, &config), ["max_distance"]);
+        let config = short_text(json!({
+            "max_distance": 2,
+            "patterns": [{"value": "Pa", "score": 0.5}],
+        }));
+        assert_eq!(invalid_fields(&ShortTextGrader, &config), ["max_distance"]);
+        // regexes aren't compared by distance
+        let config = short_text(json!({
+            "max_distance": 2,
+            "patterns": [{"value": "P.", "regex": true, "score": 0.5}],
+        }));
+        assert!(invalid_fields(&ShortTextGrader, &config).is_empty());
+    }
+
+    #[test]
+    fn regexes_are_cached_per_case_mode() {
+        let insensitive = compile_answer_regex("par.s", false).expect("A valid pattern");
+        assert!(insensitive.is_match("PARIS"));
+        let sensitive = compile_answer_regex("par.s", true).expect("A valid pattern");
+        assert!(!sensitive.is_match("PARIS"));
+        assert!(
+            compile_answer_regex("par.s", false)
+                .expect("A valid pattern")
+                .is_match("PARIS")
+        );
+        assert!(compile_answer_regex("(", false).is_err());
+    }
+
+    #[test]
+    fn patterns_give_their_share() {
+        let config = short_text(json!({
+            "patterns": [
+                {"value": "par.*", "regex": true, "score": 0.5},
+                {"value": "Lutetia", "score": 0.75},
+            ],
+        }));
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("Parma"), 4.0),
+            "partial_score",
+            2.0,
+        );
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("lutetia"), 4.0),
+            "partial_score",
+            3.0,
+        );
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("Paris"), 4.0),
+            "full_score",
+            4.0,
+        );
+        // the regex has to match the whole answer
+        assert_verdict(
+            ShortTextGrader.grade(&config, &typed("Spar"), 4.0),
+            "incorrect",
+            0.0,
+        );
+    }
+
+    #[test]
+    fn without_auto_grade_answers_go_to_review() {
+        let config = short_text(json!({"auto_grade": false}));
+        assert!(matches!(
+            ShortTextGrader.grade(&config, &typed("Paris"), 3.0),
+            Ok(TaskVerdict::OnReview)
+        ));
+        assert!(!ShortTextGrader.is_auto_gradable(&config));
+    }
+
+    #[test]
+    fn answers_are_checked() {
+        let config = short_text(json!({"max_chars_count": 3}));
+        assert!(matches!(
+            ShortTextGrader.validate_answer(&config, &typed("Paris")),
+            Err(GradeError::InvalidAnswer(_))
+        ));
+        let other = answer(json!({"name": "long_text", "answer": "Paris"}));
+        assert!(matches!(
+            ShortTextGrader.validate_answer(&config, &other),
+            Err(GradeError::AnswerMismatch)
+        ));
+        assert!(matches!(
+            ShortTextGrader.grade(&config, &other, 3.0),
+            Err(GradeError::AnswerMismatch)
+        ));
+    }
+
+    #[test]
+    fn feedback_is_for_wrong_answers() {
+        let config = short_text(json!({
+            "feedback": [
+                {"value": "lon.*", "regex": true, "comment": "That's England"},
+                {"value": "Rome", "comment": "That's Italy"},
+            ],
+        }));
+        assert_eq!(
+            ShortTextGrader.feedback(&config, &typed("London"), false),
+            ["That's England"]
+        );
+        assert_eq!(
+            ShortTextGrader.feedback(&config, &typed("rome"), false),
+            ["That's Italy"]
+        );
+        assert!(
+            ShortTextGrader
+                .feedback(&config, &typed("Paris"), true)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn public_config_hides_the_answers() {
+        let Ok(PublicTaskConfig::ShortText { max_chars_count }) =
+            ShortTextGrader.public_config(short_text(json!({})))
+        else {
+            panic!("Unexpected public config");
+        };
+        assert_eq!(max_chars_count, 100);
+    }
+}
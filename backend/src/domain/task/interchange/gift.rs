@@ -0,0 +1,503 @@
+//! GIFT, Moodle's plain text question format: `::Title:: Text {=right ~wrong}`,
+//! questions separated by blank lines.
+
+use super::{
+    ImportedQuestion, QuestionDraft, category_tag, push_short_answer, short_answer_text,
+    strip_html, unsupported_reason, wrong_option_grade,
+};
+use crate::domain::task::model::{MultipleChoiceScoring, Task, TaskConfig, WhitespaceMode};
+use std::fmt::Write;
+
+/// Characters that have to be escaped with `\` in GIFT text.
+const SPECIAL: [char; 7] = ['~', '=', '#', '{', '}', ':', '\\'];
+
+/// Byte index of the first `pattern` not preceded by a backslash escape.
+fn find_unescaped(text: &str, pattern: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if text[index..].starts_with(pattern) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(next) => result.push(next),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            result.push('\\');
+            result.push(c);
+        } else if c == '\n' {
+            result.push_str("\\n");
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// An answer of the `{...}` block: `=` or `~`, an optional `%weight%` and the
+/// raw (still escaped) text with feedback cut off.
+struct Item {
+    correct: bool,
+    weight: Option<f64>,
+    text: String,
+}
+
+/// Splits an answer block into items, reporting whether any had feedback.
+fn split_items(block: &str) -> (Vec<Item>, bool) {
+    let mut raw = Vec::new();
+    let mut escaped = false;
+    let mut start: Option<(bool, usize)> = None;
+    for (index, c) in block.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == '~' {
+            // `->` in matching pairs never starts with these, so any
+            // unescaped one begins the next answer
+            if let Some((correct, from)) = start {
+                raw.push((correct, &block[from..index]));
+            }
+            start = Some((c == '=', index + 1));
+        }
+    }
+    if let Some((correct, from)) = start {
+        raw.push((correct, &block[from..]));
+    }
+
+    let mut had_feedback = false;
+    let items = raw
+        .into_iter()
+        .map(|(correct, text)| {
+            let feedback = find_unescaped(text, "#");
+            had_feedback |= feedback.is_some();
+            let mut text = feedback.map_or(text, |index| &text[..index]).trim();
+            let mut weight = None;
+            if let Some(rest) = text.strip_prefix('%')
+                && let Some(end) = rest.find('%')
+            {
+                weight = rest[..end].trim().parse::<f64>().ok();
+                text = rest[end + 1..].trim();
+            }
+            Item {
+                correct,
+                weight,
+                text: text.to_string(),
+            }
+        })
+        .collect();
+    (items, had_feedback)
+}
+
+/// Reads every question of a GIFT file, in file order.
+pub fn import(source: &str) -> Vec<ImportedQuestion> {
+    let mut blocks: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in source.lines() {
+        if line.trim().is_empty() {
+            blocks.push(Vec::new());
+        } else if !line.trim_start().starts_with("//") {
+            blocks.last_mut().expect("Never empty").push(line);
+        }
+    }
+
+    let mut tags = Vec::new();
+    let mut imported = Vec::new();
+    for mut block in blocks.into_iter().filter(|block| !block.is_empty()) {
+        while let Some(category) = block
+            .first()
+            .and_then(|line| line.trim().strip_prefix("$CATEGORY:"))
+        {
+            tags = category_tag(category.trim()).into_iter().collect();
+            block.remove(0);
+        }
+        if block.is_empty() {
+            continue;
+        }
+        imported.push(import_question(&block.join("\n"), &tags));
+    }
+    imported
+}
+
+/// `answer`, `answer:tolerance` or `low..high` into an answer and its
+/// tolerance.
+fn parse_numeric(value: &str) -> Option<(f64, f64)> {
+    let number = |text: &str| text.trim().parse::<f64>().ok();
+    if let Some(colon) = find_unescaped(value, ":") {
+        return number(&value[..colon]).zip(number(&value[colon + 1..]));
+    }
+    if let Some((low, high)) = value.split_once("..") {
+        return number(low)
+            .zip(number(high))
+            .map(|(low, high)| (f64::midpoint(low, high), (high - low).abs() / 2f64));
+    }
+    number(value).map(|answer| (answer, 0f64))
+}
+
+#[allow(clippy::too_many_lines)]
+fn import_question(source: &str, tags: &[String]) -> ImportedQuestion {
+    let mut rest = source.trim();
+    let mut name = String::new();
+    if let Some(after) = rest.strip_prefix("::")
+        && let Some(end) = find_unescaped(after, "::")
+    {
+        name = unescape(after[..end].trim());
+        rest = after[end + 2..].trim_start();
+    }
+
+    let Some(open) = find_unescaped(rest, "{") else {
+        return ImportedQuestion::skipped(
+            name,
+            "Descriptions are not questions and are skipped".to_string(),
+        );
+    };
+    let Some(close) = find_unescaped(&rest[open..], "}").map(|close| open + close) else {
+        return ImportedQuestion::skipped(name, "The answer block is not closed".to_string());
+    };
+    let block = rest[open + 1..close].trim();
+    let mut warnings = Vec::new();
+
+    let mut before = rest[..open].trim();
+    let mut is_html = false;
+    if let Some(format_end) = before.strip_prefix('[').and_then(|text| text.find(']')) {
+        is_html = &before[1..=format_end] == "html";
+        before = before[format_end + 2..].trim_start();
+    }
+    let after = rest[close + 1..].trim();
+    let mut text = unescape(before);
+    if !after.is_empty() {
+        // "missing word" questions have the answers in the middle of the text
+        text = format!("{text} _____ {}", unescape(after));
+    }
+    if is_html {
+        let stripped = strip_html(&text);
+        if stripped != text {
+            warnings.push("HTML formatting of the question text is dropped".to_string());
+        }
+        text = stripped;
+    }
+    let mut draft = QuestionDraft {
+        name,
+        text,
+        points: 1,
+        tags: tags.to_vec(),
+        warnings,
+    };
+
+    let configuration = if block.is_empty() {
+        TaskConfig::LongText {
+            max_chars_count: 5000,
+            rubric: Vec::new(),
+        }
+    } else if let Some(numbers) = block.strip_prefix('#') {
+        let (items, had_feedback) = split_items(numbers);
+        let (value, extra) = if items.is_empty() {
+            let value = find_unescaped(numbers, "#").map_or(numbers, |index| &numbers[..index]);
+            (value.trim().to_string(), false)
+        } else {
+            let mut full = items
+                .iter()
+                .filter(|item| item.correct && item.weight.is_none_or(|weight| weight >= 100f64));
+            let Some(first) = full.next() else {
+                return draft.skip("The question has no fully correct answer");
+            };
+            (first.text.clone(), items.len() > 1)
+        };
+        if had_feedback || (items.is_empty() && find_unescaped(numbers, "#").is_some()) {
+            draft.warnings.push("Feedback is not imported".to_string());
+        }
+        if extra {
+            draft.warnings.push(
+                "Only the first fully correct answer is kept, other answers are dropped"
+                    .to_string(),
+            );
+        }
+        let Some((answer, tolerance)) = parse_numeric(&value) else {
+            return draft.skip(format!("'{value}' is not a numeric answer"));
+        };
+        TaskConfig::Numeric {
+            answer,
+            absolute_tolerance: tolerance.abs(),
+            relative_tolerance: 0f64,
+            units: Vec::new(),
+            unit_required: false,
+            bases: Vec::new(),
+        }
+    } else if let Some(value) = {
+        let verdict = find_unescaped(block, "#").map_or(block, |index| &block[..index]);
+        match verdict.trim().to_uppercase().as_str() {
+            "T" | "TRUE" => Some(0),
+            "F" | "FALSE" => Some(1),
+            _ => None,
+        }
+    } {
+        if find_unescaped(block, "#").is_some() {
+            draft.warnings.push("Feedback is not imported".to_string());
+        }
+        TaskConfig::SingleChoice {
+            options: vec!["True".to_string(), "False".to_string()],
+            correct: value,
+            shuffle: false,
+            explanations: Vec::new(),
+        }
+    } else {
+        let (items, had_feedback) = split_items(block);
+        if had_feedback {
+            draft.warnings.push("Feedback is not imported".to_string());
+        }
+        if items.is_empty() {
+            return draft.skip("The answer block has no answers");
+        }
+        if items
+            .iter()
+            .any(|item| find_unescaped(&item.text, "->").is_some())
+        {
+            let mut left = Vec::new();
+            let mut right: Vec<String> = Vec::new();
+            let mut correct = Vec::new();
+            for item in &items {
+                let Some(arrow) = find_unescaped(&item.text, "->") else {
+                    return draft.skip("Every matching answer needs a '->'");
+                };
+                let answer = unescape(item.text[arrow + 2..].trim());
+                let position = right
+                    .iter()
+                    .position(|known| *known == answer)
+                    .unwrap_or_else(|| {
+                        right.push(answer);
+                        right.len() - 1
+                    });
+                let question = unescape(item.text[..arrow].trim());
+                if !question.is_empty() {
+                    left.push(question);
+                    correct.push(position);
+                }
+            }
+            TaskConfig::Matching {
+                left,
+                right,
+                correct,
+                partial_score: true,
+            }
+        } else if items.iter().any(|item| !item.correct) {
+            let options: Vec<String> = items.iter().map(|item| unescape(&item.text)).collect();
+            let weighted = items.iter().any(|item| item.weight.is_some());
+            let correct: Vec<usize> = items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.weight.map_or(item.correct, |weight| weight > 0f64))
+                .map(|(index, _)| index)
+                .collect();
+            if correct.is_empty() {
+                return draft.skip("The question has no correct answer");
+            }
+            if !weighted && correct.len() == 1 {
+                TaskConfig::SingleChoice {
+                    options,
+                    correct: correct[0],
+                    shuffle: true,
+                    explanations: Vec::new(),
+                }
+            } else {
+                let weights: Vec<f64> = items
+                    .iter()
+                    .filter_map(|item| item.weight.filter(|weight| *weight > 0f64))
+                    .collect();
+                if weights
+                    .iter()
+                    .any(|weight| (weight - weights[0]).abs() > 0.01)
+                {
+                    draft.warnings.push(
+                        "Answer weights are dropped, every correct answer weighs the same"
+                            .to_string(),
+                    );
+                }
+                TaskConfig::MultipleChoice {
+                    options,
+                    correct,
+                    shuffle: true,
+                    scoring: MultipleChoiceScoring::CorrectMinusIncorrect,
+                    explanations: Vec::new(),
+                }
+            }
+        } else {
+            let mut answers = Vec::new();
+            let mut patterns = Vec::new();
+            for item in &items {
+                let score = item
+                    .weight
+                    .map_or(1f64, |weight| (weight / 100f64).min(1f64));
+                if score > 0f64 {
+                    push_short_answer(&unescape(&item.text), score, &mut answers, &mut patterns);
+                }
+            }
+            if answers.is_empty() && patterns.is_empty() {
+                return draft.skip("The question has no correct answer");
+            }
+            TaskConfig::ShortText {
+                auto_grade: true,
+                max_chars_count: 100,
+                case_sensitive: false,
+                answers,
+                whitespace: WhitespaceMode::Trim,
+                normalize_unicode: false,
+                max_distance: 0,
+                patterns,
+                feedback: Vec::new(),
+            }
+        }
+    };
+    draft.into_imported(configuration)
+}
+
+fn format_weight(weight: f64) -> String {
+    let rounded = format!("{weight:.5}");
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Renders tasks as a GIFT file. Tasks that can't be expressed are left out
+/// with a comment in their place.
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::cast_precision_loss)]
+pub fn export(tasks: &[Task]) -> String {
+    let mut gift = String::new();
+    for task in tasks {
+        if let Some(reason) = unsupported_reason(task) {
+            let _ = writeln!(gift, "// skipped: {reason}\n");
+            continue;
+        }
+        let _ = writeln!(gift, "// task {}, {} point(s)", task.id, task.points);
+        let _ = write!(
+            gift,
+            "::{}::[markdown]{} {{",
+            escape(&task.title),
+            escape(task.description.as_deref().unwrap_or_default())
+        );
+        match &task.configuration {
+            TaskConfig::SingleChoice {
+                options,
+                correct,
+                shuffle: false,
+                ..
+            } if options == &["True", "False"] => {
+                gift.push_str(if *correct == 0 { "T" } else { "F" });
+            }
+            TaskConfig::SingleChoice {
+                options, correct, ..
+            } => {
+                gift.push('\n');
+                for (index, option) in options.iter().enumerate() {
+                    let marker = if index == *correct { '=' } else { '~' };
+                    let _ = writeln!(gift, "  {marker}{}", escape(option));
+                }
+            }
+            TaskConfig::MultipleChoice {
+                options,
+                correct,
+                scoring,
+                ..
+            } => {
+                gift.push('\n');
+                let correct_count = correct.len().max(1) as f64;
+                let wrong_grade = wrong_option_grade(*scoring, options.len(), correct.len());
+                for (index, option) in options.iter().enumerate() {
+                    let weight = if correct.contains(&index) {
+                        100f64 / correct_count
+                    } else {
+                        wrong_grade
+                    };
+                    let _ = writeln!(gift, "  ~%{}%{}", format_weight(weight), escape(option));
+                }
+            }
+            TaskConfig::ShortText {
+                answers, patterns, ..
+            } => {
+                gift.push('\n');
+                for answer in answers {
+                    let _ = writeln!(gift, "  ={}", escape(&answer.replace('*', "\\*")));
+                }
+                for pattern in patterns {
+                    match short_answer_text(pattern) {
+                        Some(text) => {
+                            let _ = writeln!(
+                                gift,
+                                "  =%{}%{}",
+                                format_weight(pattern.score * 100f64),
+                                escape(&text)
+                            );
+                        }
+                        None => {
+                            let _ = writeln!(
+                                gift,
+                                "  // regex answer has no equivalent: {}",
+                                pattern.value.replace('\n', " ")
+                            );
+                        }
+                    }
+                }
+            }
+            TaskConfig::Matching {
+                left,
+                right,
+                correct,
+                ..
+            } => {
+                let distractors: Vec<&str> = right
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !correct.contains(index))
+                    .map(|(_, item)| item.as_str())
+                    .collect();
+                if !distractors.is_empty() {
+                    let _ = write!(
+                        gift,
+                        "\n// distractors GIFT can't express: {}",
+                        distractors.join(", ").replace('\n', " ")
+                    );
+                }
+                gift.push('\n');
+                for (item, answer) in left.iter().zip(correct) {
+                    let _ = writeln!(gift, "  ={} -> {}", escape(item), escape(&right[*answer]));
+                }
+            }
+            TaskConfig::Numeric {
+                answer,
+                absolute_tolerance,
+                relative_tolerance,
+                ..
+            } => {
+                let tolerance = absolute_tolerance.max(relative_tolerance * answer.abs());
+                let _ = write!(gift, "#{answer}:{tolerance}");
+            }
+            _ => {}
+        }
+        gift.push_str("}\n\n");
+    }
+    gift
+}
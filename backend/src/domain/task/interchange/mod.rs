@@ -0,0 +1,253 @@
+//! Converting task bank questions to and from other LMSes' formats.
+//!
+//! Import is lossy by nature: every question comes back with warnings for whatever
+//! couldn't be carried over, or without a task at all if nothing could.
+
+pub mod gift;
+pub mod moodle;
+pub mod qti;
+mod xml;
+
+use crate::domain::task::model::{AnswerPattern, MultipleChoiceScoring, Task, TaskConfig};
+use crate::domain::task::template::TaskTemplate;
+use crate::dto::task::UpsertTaskRequestDTO;
+
+/// Upper bound for task titles, mirrors `UpsertTaskRequestDTO`.
+const TITLE_MAX_LEN: usize = 50;
+
+#[derive(Clone, Copy)]
+pub enum InterchangeFormat {
+    MoodleXml,
+    Gift,
+    /// IMS QTI 2.1 content package (zip).
+    Qti,
+}
+
+/// A single question of an imported file.
+pub struct ImportedQuestion {
+    pub name: String,
+    /// `None` when the question can't be represented at all; `warnings` then
+    /// say why.
+    pub task: Option<UpsertTaskRequestDTO>,
+    pub warnings: Vec<String>,
+}
+
+impl ImportedQuestion {
+    fn skipped(name: String, reason: String) -> Self {
+        Self {
+            name,
+            task: None,
+            warnings: vec![reason],
+        }
+    }
+}
+
+/// Parts of a question shared by all formats, before it becomes a task.
+struct QuestionDraft {
+    name: String,
+    text: String,
+    points: i32,
+    tags: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl QuestionDraft {
+    fn into_imported(mut self, configuration: TaskConfig) -> ImportedQuestion {
+        let source = if self.name.trim().is_empty() {
+            &self.text
+        } else {
+            &self.name
+        };
+        let mut title: String = source.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.chars().count() > TITLE_MAX_LEN {
+            title = title.chars().take(TITLE_MAX_LEN).collect();
+            self.warnings
+                .push(format!("The title is cut to {TITLE_MAX_LEN} characters"));
+        }
+        ImportedQuestion {
+            name: self.name,
+            task: Some(UpsertTaskRequestDTO {
+                title,
+                description: Some(self.text).filter(|text| !text.trim().is_empty()),
+                task_type: configuration.task_type(),
+                points: self.points,
+                configuration,
+                template: TaskTemplate::default(),
+                tags: self.tags,
+                difficulty: None,
+                explanation: None,
+            }),
+            warnings: self.warnings,
+        }
+    }
+
+    fn skip(self, reason: impl Into<String>) -> ImportedQuestion {
+        let mut warnings = self.warnings;
+        warnings.push(reason.into());
+        ImportedQuestion {
+            name: self.name,
+            task: None,
+            warnings,
+        }
+    }
+}
+
+/// Turns an exported category path (`$course$/top/Networks`) into the tag of
+/// the questions under it.
+fn category_tag(path: &str) -> Option<String> {
+    path.rsplit('/')
+        .map(str::trim)
+        .find(|segment| !segment.is_empty() && !segment.starts_with('$'))
+        .filter(|segment| !segment.eq_ignore_ascii_case("top"))
+        .map(str::to_lowercase)
+}
+
+/// Plain text out of the HTML that question texts and answers usually are:
+/// tags dropped (block ones become line breaks), common entities decoded.
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .to_lowercase();
+        if [
+            "p", "br", "div", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6",
+        ]
+        .iter()
+        .any(|block| tag == *block || tag.starts_with(&format!("{block} ")))
+        {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Points of a task out of a fractional grade.
+#[allow(clippy::cast_possible_truncation)]
+fn points_from_grade(grade: f64, warnings: &mut Vec<String>) -> i32 {
+    if !grade.is_finite() || grade < 0f64 {
+        warnings.push("The grade is invalid, 1 point is used".to_string());
+        return 1;
+    }
+    if grade.fract() != 0f64 {
+        warnings.push(format!("The grade {grade} is rounded to whole points"));
+    }
+    grade.round().min(f64::from(i32::MAX)) as i32
+}
+
+/// Adds a Moodle short answer to the task's accepted answers: `*` matches any
+/// run of characters and becomes a regex pattern, `\*` is a literal star.
+fn push_short_answer(
+    text: &str,
+    score: f64,
+    answers: &mut Vec<String>,
+    patterns: &mut Vec<AnswerPattern>,
+) {
+    let mut pieces = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.as_str().starts_with('*') => {
+                chars.next();
+                pieces.last_mut().expect("Never empty").push('*');
+            }
+            '*' => pieces.push(String::new()),
+            _ => pieces.last_mut().expect("Never empty").push(c),
+        }
+    }
+    if pieces.len() > 1 {
+        let regex = pieces
+            .iter()
+            .map(|piece| regex::escape(piece))
+            .collect::<Vec<_>>()
+            .join(".*");
+        patterns.push(AnswerPattern {
+            value: regex,
+            regex: true,
+            score,
+        });
+    } else if score >= 1f64 {
+        answers.extend(pieces);
+    } else {
+        patterns.extend(pieces.into_iter().map(|value| AnswerPattern {
+            value,
+            regex: false,
+            score,
+        }));
+    }
+}
+
+/// The reverse of [`push_short_answer`]: a pattern as a Moodle short answer,
+/// if it is a literal or a regex made only of literals and `.*`.
+fn short_answer_text(pattern: &AnswerPattern) -> Option<String> {
+    if !pattern.regex {
+        return Some(pattern.value.replace('*', "\\*"));
+    }
+    pattern
+        .value
+        .split(".*")
+        .map(|piece| {
+            let mut literal = String::with_capacity(piece.len());
+            let mut chars = piece.chars();
+            while let Some(c) = chars.next() {
+                literal.push(if c == '\\' { chars.next()? } else { c });
+            }
+            (regex::escape(&literal) == piece).then(|| literal.replace('*', "\\*"))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|pieces| pieces.join("*"))
+}
+
+/// Grade percentage of a wrong `MultipleChoice` option in formats that score
+/// by summing the picked options' grades, floored at zero. Per-option scoring
+/// can't be expressed that way and falls back to the default partial scheme.
+#[allow(clippy::cast_precision_loss)]
+fn wrong_option_grade(scoring: MultipleChoiceScoring, options: usize, correct: usize) -> f64 {
+    match scoring {
+        // any wrong pick zeroes the question
+        MultipleChoiceScoring::AllOrNothing => -100f64,
+        MultipleChoiceScoring::CorrectMinusIncorrect | MultipleChoiceScoring::PerOption => {
+            -100f64 / options.saturating_sub(correct).max(1) as f64
+        }
+        MultipleChoiceScoring::NegativeMarking { penalty } => -100f64 * penalty,
+    }
+}
+
+/// Why a task is left out of an export, if it is.
+fn unsupported_reason(task: &Task) -> Option<String> {
+    match &task.configuration {
+        TaskConfig::Ordering { .. }
+        | TaskConfig::FileUpload { .. }
+        | TaskConfig::CTFd { .. }
+        | TaskConfig::Cloze { .. }
+        | TaskConfig::Code { .. }
+        | TaskConfig::Expression { .. } => Some(format!(
+            "task {} ({:?}) has no equivalent in this format",
+            task.id, task.task_type
+        )),
+        _ if !task.template.is_empty() => Some(format!(
+            "task {} is parameterized, which this format can't express",
+            task.id
+        )),
+        _ => None,
+    }
+}
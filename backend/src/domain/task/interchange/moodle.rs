@@ -0,0 +1,505 @@
+//! Moodle XML (`<quiz><question type="...">...</question></quiz>`).
+
+use super::xml::{Node, parse_document};
+use super::{
+    ImportedQuestion, QuestionDraft, category_tag, points_from_grade, push_short_answer,
+    short_answer_text, strip_html, unsupported_reason, wrong_option_grade,
+};
+use crate::domain::task::model::{MultipleChoiceScoring, Task, TaskConfig, WhitespaceMode};
+use quick_xml::escape::escape;
+use std::fmt::Write;
+
+struct Answer {
+    text: String,
+    fraction: f64,
+    node_index: usize,
+}
+
+fn answers(question: &Node) -> Vec<Answer> {
+    question
+        .children("answer")
+        .enumerate()
+        .map(|(node_index, answer)| Answer {
+            text: strip_html(answer.inner_text().unwrap_or_default()),
+            fraction: answer
+                .attribute("fraction")
+                .and_then(|fraction| fraction.trim().parse().ok())
+                .unwrap_or(0f64),
+            node_index,
+        })
+        .collect()
+}
+
+fn has_feedback(question: &Node) -> bool {
+    let filled = |node: &Node, name: &str| {
+        node.text_of(name)
+            .is_some_and(|text| !strip_html(text).is_empty())
+    };
+    [
+        "generalfeedback",
+        "correctfeedback",
+        "partiallycorrectfeedback",
+        "incorrectfeedback",
+    ]
+    .iter()
+    .any(|name| filled(question, name))
+        || question
+            .children("answer")
+            .any(|answer| filled(answer, "feedback"))
+}
+
+/// Reads every `<question>` of a Moodle XML file, in file order. A malformed
+/// file fails as a whole.
+pub fn import(source: &str) -> Result<Vec<ImportedQuestion>, String> {
+    let document = parse_document(source)?;
+    let quiz = document
+        .child("quiz")
+        .ok_or_else(|| "Not a Moodle XML file: no <quiz> element".to_string())?;
+
+    let mut tags = Vec::new();
+    let mut imported = Vec::new();
+    for question in quiz.children("question") {
+        let kind = question.attribute("type").unwrap_or_default();
+        if kind == "category" {
+            tags = question
+                .text_of("category")
+                .and_then(category_tag)
+                .into_iter()
+                .collect();
+            continue;
+        }
+        imported.push(import_question(question, kind, &tags));
+    }
+    Ok(imported)
+}
+
+#[allow(clippy::too_many_lines)]
+fn import_question(question: &Node, kind: &str, tags: &[String]) -> ImportedQuestion {
+    let name = question
+        .text_of("name")
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let mut warnings = Vec::new();
+    let raw_text = question.text_of("questiontext").unwrap_or_default();
+    let is_html = question
+        .child("questiontext")
+        .and_then(|text| text.attribute("format"))
+        .is_none_or(|format| format == "html");
+    let text = if is_html {
+        let text = strip_html(raw_text);
+        if text != raw_text.trim() {
+            warnings.push("HTML formatting of the question text is dropped".to_string());
+        }
+        text
+    } else {
+        raw_text.trim().to_string()
+    };
+    if question
+        .child("questiontext")
+        .is_some_and(|text| text.child("file").is_some())
+    {
+        warnings.push("Embedded files are not imported".to_string());
+    }
+    if has_feedback(question) {
+        warnings.push("Feedback is not imported".to_string());
+    }
+    let grade = question
+        .value_of("defaultgrade")
+        .and_then(|grade| grade.parse().ok())
+        .unwrap_or(1f64);
+    let points = points_from_grade(grade, &mut warnings);
+    let mut draft = QuestionDraft {
+        name,
+        text,
+        points,
+        tags: tags.to_vec(),
+        warnings,
+    };
+
+    let answers = answers(question);
+    let configuration = match kind {
+        "multichoice" => {
+            let options: Vec<String> = answers.iter().map(|answer| answer.text.clone()).collect();
+            let shuffle = question.flag("shuffleanswers").unwrap_or(true);
+            if question.flag("single").unwrap_or(true) {
+                let best = answers
+                    .iter()
+                    .filter(|answer| answer.fraction > 0f64)
+                    .max_by(|a, b| a.fraction.total_cmp(&b.fraction));
+                let Some(best) = best else {
+                    return draft.skip("The question has no correct answer");
+                };
+                if answers
+                    .iter()
+                    .any(|answer| answer.fraction != 0f64 && answer.fraction < 100f64)
+                {
+                    draft
+                        .warnings
+                        .push("Partial credit is dropped, only the best answer counts".to_string());
+                }
+                TaskConfig::SingleChoice {
+                    options,
+                    correct: best.node_index,
+                    shuffle,
+                    explanations: Vec::new(),
+                }
+            } else {
+                let correct: Vec<usize> = answers
+                    .iter()
+                    .filter(|answer| answer.fraction > 0f64)
+                    .map(|answer| answer.node_index)
+                    .collect();
+                let weights: Vec<f64> = answers
+                    .iter()
+                    .filter(|answer| answer.fraction > 0f64)
+                    .map(|answer| answer.fraction)
+                    .collect();
+                if weights
+                    .iter()
+                    .any(|weight| (weight - weights[0]).abs() > 0.01)
+                {
+                    draft.warnings.push(
+                        "Answer weights are dropped, every correct answer weighs the same"
+                            .to_string(),
+                    );
+                }
+                TaskConfig::MultipleChoice {
+                    options,
+                    correct,
+                    shuffle,
+                    scoring: MultipleChoiceScoring::CorrectMinusIncorrect,
+                    explanations: Vec::new(),
+                }
+            }
+        }
+        "truefalse" => {
+            let Some(correct) = answers
+                .iter()
+                .find(|answer| answer.fraction >= 100f64)
+                .map(|answer| usize::from(answer.text.to_lowercase() != "true"))
+            else {
+                return draft.skip("The question has no correct answer");
+            };
+            TaskConfig::SingleChoice {
+                options: vec!["True".to_string(), "False".to_string()],
+                correct,
+                shuffle: false,
+                explanations: Vec::new(),
+            }
+        }
+        "shortanswer" => {
+            let mut full = Vec::new();
+            let mut patterns = Vec::new();
+            for answer in answers.iter().filter(|answer| answer.fraction > 0f64) {
+                let score = (answer.fraction / 100f64).min(1f64);
+                push_short_answer(&answer.text, score, &mut full, &mut patterns);
+            }
+            if full.is_empty() && patterns.is_empty() {
+                return draft.skip("The question has no correct answer");
+            }
+            TaskConfig::ShortText {
+                auto_grade: true,
+                max_chars_count: 100,
+                case_sensitive: question.flag("usecase").unwrap_or(false),
+                answers: full,
+                whitespace: WhitespaceMode::Trim,
+                normalize_unicode: false,
+                max_distance: 0,
+                patterns,
+                feedback: Vec::new(),
+            }
+        }
+        "matching" => {
+            let mut left = Vec::new();
+            let mut right: Vec<String> = Vec::new();
+            let mut correct = Vec::new();
+            for subquestion in question.children("subquestion") {
+                let answer = strip_html(subquestion.text_of("answer").unwrap_or_default());
+                if answer.is_empty() {
+                    draft
+                        .warnings
+                        .push("A pair without an answer is dropped".to_string());
+                    continue;
+                }
+                let position = right
+                    .iter()
+                    .position(|item| *item == answer)
+                    .unwrap_or_else(|| {
+                        right.push(answer.clone());
+                        right.len() - 1
+                    });
+                let item = strip_html(subquestion.inner_text().unwrap_or_default());
+                // a subquestion without text only adds a distractor
+                if !item.is_empty() {
+                    left.push(item);
+                    correct.push(position);
+                }
+            }
+            if left.is_empty() {
+                return draft.skip("The question has nothing to match");
+            }
+            TaskConfig::Matching {
+                left,
+                right,
+                correct,
+                partial_score: true,
+            }
+        }
+        "essay" => {
+            if question
+                .value_of("attachments")
+                .and_then(|attachments| attachments.parse::<i32>().ok())
+                .is_some_and(|attachments| attachments != 0)
+            {
+                draft
+                    .warnings
+                    .push("Attachments are dropped, only the text answer is kept".to_string());
+            }
+            TaskConfig::LongText {
+                max_chars_count: 5000,
+                rubric: Vec::new(),
+            }
+        }
+        "numerical" => {
+            let mut full = question
+                .children("answer")
+                .filter(|answer| {
+                    answer
+                        .attribute("fraction")
+                        .and_then(|fraction| fraction.trim().parse::<f64>().ok())
+                        .is_some_and(|fraction| fraction >= 100f64)
+                })
+                .filter_map(|answer| {
+                    let value = answer.inner_text()?.trim().parse::<f64>().ok()?;
+                    let tolerance = answer
+                        .value_of("tolerance")
+                        .and_then(|tolerance| tolerance.parse::<f64>().ok())
+                        .unwrap_or(0f64);
+                    Some((value, tolerance.abs()))
+                });
+            let Some((answer, tolerance)) = full.next() else {
+                return draft.skip("The question has no numeric correct answer");
+            };
+            if full.next().is_some() || answers.iter().any(|answer| answer.fraction < 100f64) {
+                draft.warnings.push(
+                    "Only the first fully correct answer is kept, other answers are dropped"
+                        .to_string(),
+                );
+            }
+            let mut units = Vec::new();
+            for unit in question
+                .child("units")
+                .into_iter()
+                .flat_map(|units| units.children("unit"))
+            {
+                let multiplier = unit
+                    .value_of("multiplier")
+                    .and_then(|multiplier| multiplier.parse::<f64>().ok())
+                    .unwrap_or(1f64);
+                match unit.value_of("unit_name") {
+                    Some(name) if (multiplier - 1f64).abs() < f64::EPSILON && !name.is_empty() => {
+                        units.push(name.to_string());
+                    }
+                    Some(name) => draft
+                        .warnings
+                        .push(format!("Unit '{name}' with a multiplier is dropped")),
+                    None => {}
+                }
+            }
+            TaskConfig::Numeric {
+                answer,
+                absolute_tolerance: tolerance,
+                relative_tolerance: 0f64,
+                units,
+                unit_required: false,
+                bases: Vec::new(),
+            }
+        }
+        "description" => {
+            return ImportedQuestion::skipped(
+                draft.name,
+                "Descriptions are not questions and are skipped".to_string(),
+            );
+        }
+        other => {
+            return draft.skip(format!("Question type '{other}' is not supported"));
+        }
+    };
+    draft.into_imported(configuration)
+}
+
+fn push_text(xml: &mut String, indent: &str, tag: &str, text: &str) {
+    let _ = writeln!(xml, "{indent}<{tag}><text>{}</text></{tag}>", escape(text));
+}
+
+fn push_answer(xml: &mut String, fraction: f64, text: &str) {
+    let _ = writeln!(
+        xml,
+        "    <answer fraction=\"{}\" format=\"plain_text\"><text>{}</text></answer>",
+        format_fraction(fraction),
+        escape(text)
+    );
+}
+
+fn format_fraction(fraction: f64) -> String {
+    let rounded = format!("{fraction:.5}");
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Renders tasks as a Moodle XML file. Tasks that can't be expressed are left
+/// out with an XML comment in their place.
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::cast_precision_loss)]
+pub fn export(tasks: &[Task]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n");
+    for task in tasks {
+        if let Some(reason) = unsupported_reason(task) {
+            let _ = writeln!(xml, "  <!-- skipped: {} -->", reason.replace("--", "- -"));
+            continue;
+        }
+        let kind = match task.configuration {
+            TaskConfig::SingleChoice { .. } | TaskConfig::MultipleChoice { .. } => "multichoice",
+            TaskConfig::ShortText { .. } => "shortanswer",
+            TaskConfig::Matching { .. } => "matching",
+            TaskConfig::Numeric { .. } => "numerical",
+            _ => "essay",
+        };
+        let _ = writeln!(xml, "  <question type=\"{kind}\">");
+        push_text(&mut xml, "    ", "name", &task.title);
+        let _ = writeln!(
+            xml,
+            "    <questiontext format=\"markdown\"><text>{}</text></questiontext>",
+            escape(task.description.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(xml, "    <defaultgrade>{}</defaultgrade>", task.points);
+        match &task.configuration {
+            TaskConfig::SingleChoice {
+                options,
+                correct,
+                shuffle,
+                ..
+            } => {
+                let _ = writeln!(xml, "    <single>true</single>");
+                let _ = writeln!(
+                    xml,
+                    "    <shuffleanswers>{}</shuffleanswers>",
+                    u8::from(*shuffle)
+                );
+                for (index, option) in options.iter().enumerate() {
+                    push_answer(
+                        &mut xml,
+                        if index == *correct { 100f64 } else { 0f64 },
+                        option,
+                    );
+                }
+            }
+            TaskConfig::MultipleChoice {
+                options,
+                correct,
+                shuffle,
+                scoring,
+                ..
+            } => {
+                let _ = writeln!(xml, "    <single>false</single>");
+                let _ = writeln!(
+                    xml,
+                    "    <shuffleanswers>{}</shuffleanswers>",
+                    u8::from(*shuffle)
+                );
+                let correct_count = correct.len().max(1) as f64;
+                let wrong_grade = wrong_option_grade(*scoring, options.len(), correct.len());
+                for (index, option) in options.iter().enumerate() {
+                    let fraction = if correct.contains(&index) {
+                        100f64 / correct_count
+                    } else {
+                        wrong_grade
+                    };
+                    push_answer(&mut xml, fraction, option);
+                }
+            }
+            TaskConfig::ShortText {
+                case_sensitive,
+                answers,
+                patterns,
+                ..
+            } => {
+                let _ = writeln!(xml, "    <usecase>{}</usecase>", u8::from(*case_sensitive));
+                for answer in answers {
+                    push_answer(&mut xml, 100f64, &answer.replace('*', "\\*"));
+                }
+                for pattern in patterns {
+                    match short_answer_text(pattern) {
+                        Some(text) => push_answer(&mut xml, pattern.score * 100f64, &text),
+                        None => {
+                            let _ = writeln!(
+                                xml,
+                                "    <!-- regex answer has no equivalent: {} -->",
+                                escape(&pattern.value).replace("--", "- -")
+                            );
+                        }
+                    }
+                }
+            }
+            TaskConfig::Matching {
+                left,
+                right,
+                correct,
+                ..
+            } => {
+                let _ = writeln!(xml, "    <shuffleanswers>true</shuffleanswers>");
+                for (item, answer) in left.iter().zip(correct) {
+                    let _ = writeln!(
+                        xml,
+                        "    <subquestion format=\"plain_text\"><text>{}</text><answer><text>{}</text></answer></subquestion>",
+                        escape(item),
+                        escape(&right[*answer])
+                    );
+                }
+                for (index, distractor) in right.iter().enumerate() {
+                    if !correct.contains(&index) {
+                        let _ = writeln!(
+                            xml,
+                            "    <subquestion format=\"plain_text\"><text></text><answer><text>{}</text></answer></subquestion>",
+                            escape(distractor)
+                        );
+                    }
+                }
+            }
+            TaskConfig::Numeric {
+                answer,
+                absolute_tolerance,
+                relative_tolerance,
+                units,
+                ..
+            } => {
+                let tolerance = absolute_tolerance.max(relative_tolerance * answer.abs());
+                let _ = writeln!(
+                    xml,
+                    "    <answer fraction=\"100\"><text>{answer}</text><tolerance>{tolerance}</tolerance></answer>"
+                );
+                if !units.is_empty() {
+                    let _ = writeln!(xml, "    <units>");
+                    for unit in units {
+                        let _ = writeln!(
+                            xml,
+                            "      <unit><multiplier>1</multiplier><unit_name>{}</unit_name></unit>",
+                            escape(unit)
+                        );
+                    }
+                    let _ = writeln!(xml, "    </units>");
+                }
+            }
+            TaskConfig::LongText { .. } => {
+                let _ = writeln!(xml, "    <responseformat>plain</responseformat>");
+            }
+            _ => {}
+        }
+        let _ = writeln!(xml, "  </question>");
+    }
+    xml.push_str("</quiz>\n");
+    xml
+}
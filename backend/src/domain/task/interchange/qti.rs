@@ -0,0 +1,1661 @@
+//! IMS QTI 2.1 content packages: a zip with `imsmanifest.xml`, one
+//! `assessmentItem` file per task and, for an exam, an `assessmentTest`.
+//!
+//! Every task becomes an item with a single `RESPONSE` (one per blank for
+//! `Cloze`), its points go to the `MAXSCORE` outcome. An exam becomes a test
+//! with one section: tasks are item references in exam order, texts are
+//! `fixed` subsections holding a rubric block, so shuffling (the section's
+//! `ordering`) moves tasks around them just like `shuffle_entities` does.
+
+use super::xml::{Node, TEXT_NODE, parse_document};
+use super::{ImportedQuestion, QuestionDraft, points_from_grade};
+use crate::domain::exam::model::{Exam, ExamExtendedEntity};
+use crate::domain::task::model::{
+    AnswerPattern, CLOZE_BLANK, ClozeBlank, MultipleChoiceScoring, OrderingPartialScore, Task,
+    TaskConfig, WhitespaceMode,
+};
+use quick_xml::escape::escape;
+use std::fmt::Write as _;
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const QTI_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imsqti_v2p1";
+const CP_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imscp_v1p1";
+const MATCH_CORRECT: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/match_correct";
+const MAP_RESPONSE: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/map_response";
+const ITEM_RESOURCE: &str = "imsqti_item_xmlv2p1";
+const TEST_RESOURCE: &str = "imsqti_test_xmlv2p1";
+const RESPONSE: &str = "RESPONSE";
+
+/// Unpacked size limit for an uploaded package.
+const MAX_PACKAGE_SIZE: u64 = 50 * 1024 * 1024;
+/// `FileUpload` size limit for imported upload interactions, QTI has none.
+const UPLOAD_MAX_SIZE: usize = 10 * 1024 * 1024;
+const SHORT_TEXT_MAX_LEN: usize = 500;
+const LONG_TEXT_MAX_LEN: usize = 5000;
+
+/// Interactions that map onto a task type.
+const MAPPED_INTERACTIONS: [&str; 7] = [
+    "choiceInteraction",
+    "orderInteraction",
+    "matchInteraction",
+    "textEntryInteraction",
+    "inlineChoiceInteraction",
+    "extendedTextInteraction",
+    "uploadInteraction",
+];
+
+const UNMAPPED_INTERACTIONS: [&str; 15] = [
+    "associateInteraction",
+    "gapMatchInteraction",
+    "hottextInteraction",
+    "hotspotInteraction",
+    "selectPointInteraction",
+    "graphicOrderInteraction",
+    "graphicAssociateInteraction",
+    "graphicGapMatchInteraction",
+    "positionObjectInteraction",
+    "sliderInteraction",
+    "drawingInteraction",
+    "mediaInteraction",
+    "customInteraction",
+    "endAttemptInteraction",
+    "portableCustomInteraction",
+];
+
+/// What an imported test consists of, in test order.
+pub enum QtiEntity {
+    Text(String),
+    Question(Box<ImportedQuestion>),
+}
+
+/// An `assessmentTest` read back as the parts of an exam.
+pub struct QtiTest {
+    pub title: String,
+    pub description: Option<String>,
+    /// Seconds, from the test's `timeLimits`.
+    pub duration: Option<i32>,
+    /// Whether any section shuffles its parts.
+    pub shuffle: bool,
+    pub entities: Vec<QtiEntity>,
+}
+
+/* ---------------------------------- export --------------------------------- */
+
+/// Packs tasks as standalone items. Tasks QTI can't express are left out
+/// with a comment in the manifest.
+pub fn export(tasks: &[Task]) -> Result<Vec<u8>, String> {
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for task in tasks {
+        match item_xml(task) {
+            Ok(xml) => files.push((item_path(task), xml)),
+            Err(reason) => skipped.push(reason),
+        }
+    }
+    let manifest = manifest_xml(None, &files, &skipped);
+    write_package(manifest, files)
+}
+
+/// Packs an exam as a test with its tasks as items.
+pub fn export_test(exam: &Exam, entities: &[ExamExtendedEntity]) -> Result<Vec<u8>, String> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut parts = String::new();
+    for (position, entity) in entities.iter().enumerate() {
+        match entity {
+            ExamExtendedEntity::Task { task } => {
+                let path = item_path(task);
+                if !files.iter().any(|(file, _)| *file == path) {
+                    match item_xml(task) {
+                        Ok(xml) => files.push((path.clone(), xml)),
+                        Err(reason) => {
+                            let _ = writeln!(parts, "      <!-- skipped: {} -->", comment(&reason));
+                            skipped.push(reason);
+                            continue;
+                        }
+                    }
+                }
+                let _ = writeln!(
+                    parts,
+                    "      <assessmentItemRef identifier=\"ref-{position}\" href=\"{path}\">\
+                     <weight identifier=\"POINTS\" value=\"{}\"/></assessmentItemRef>",
+                    task.points
+                );
+            }
+            ExamExtendedEntity::Text { text } => {
+                let _ = writeln!(
+                    parts,
+                    "      <assessmentSection identifier=\"text-{}\" title=\"\" visible=\"true\" fixed=\"true\">\
+                     <rubricBlock view=\"candidate\">{}</rubricBlock></assessmentSection>",
+                    text.id,
+                    paragraphs(&text.text)
+                );
+            }
+            ExamExtendedEntity::Pool { pool } => {
+                let reason = format!("pool {} is drawn from per attempt", pool.id);
+                let _ = writeln!(parts, "      <!-- skipped: {} -->", comment(&reason));
+                skipped.push(reason);
+            }
+            // QTI sections can't carry their timers, and the order of tasks
+            // is kept anyway
+            ExamExtendedEntity::Section { .. } => {}
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<assessmentTest xmlns=\"{QTI_NAMESPACE}\" identifier=\"exam-{}\" title=\"{}\">",
+        exam.id,
+        escape(&exam.name)
+    );
+    xml.push_str(
+        "  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\"/>\n",
+    );
+    let _ = writeln!(xml, "  <timeLimits maxTime=\"{}\"/>", exam.duration);
+    xml.push_str(
+        "  <testPart identifier=\"part-1\" navigationMode=\"nonlinear\" submissionMode=\"simultaneous\">\n",
+    );
+    let _ = writeln!(
+        xml,
+        "    <assessmentSection identifier=\"section-1\" title=\"{}\" visible=\"true\">",
+        escape(&exam.name)
+    );
+    let _ = writeln!(
+        xml,
+        "      <ordering shuffle=\"{}\"/>",
+        exam.shuffle_entities
+    );
+    if let Some(description) = exam
+        .description
+        .as_deref()
+        .filter(|text| !text.trim().is_empty())
+    {
+        let _ = writeln!(
+            xml,
+            "      <rubricBlock view=\"candidate\" class=\"description\">{}</rubricBlock>",
+            paragraphs(description)
+        );
+    }
+    xml.push_str(&parts);
+    xml.push_str("    </assessmentSection>\n  </testPart>\n");
+    xml.push_str(
+        "  <outcomeProcessing><setOutcomeValue identifier=\"SCORE\"><sum>\
+         <testVariables variableIdentifier=\"SCORE\" weightIdentifier=\"POINTS\"/>\
+         </sum></setOutcomeValue></outcomeProcessing>\n",
+    );
+    xml.push_str("</assessmentTest>\n");
+
+    let test = (format!("exam-{}.xml", exam.id), xml);
+    let manifest = manifest_xml(Some(&test.0), &files, &skipped);
+    files.insert(0, test);
+    write_package(manifest, files)
+}
+
+fn item_path(task: &Task) -> String {
+    format!("items/task-{}.xml", task.id)
+}
+
+fn comment(text: &str) -> String {
+    text.replace("--", "- -")
+}
+
+fn manifest_xml(test: Option<&str>, items: &[(String, String)], skipped: &[String]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<manifest xmlns=\"{CP_NAMESPACE}\" identifier=\"manifest\">"
+    );
+    xml.push_str(
+        "  <metadata><schema>QTIv2.1 Package</schema><schemaversion>1.0.0</schemaversion></metadata>\n",
+    );
+    xml.push_str("  <organizations/>\n  <resources>\n");
+    for reason in skipped {
+        let _ = writeln!(xml, "    <!-- skipped: {} -->", comment(reason));
+    }
+    let identifier = |path: &str| {
+        path.trim_start_matches("items/")
+            .trim_end_matches(".xml")
+            .to_string()
+    };
+    if let Some(test) = test {
+        let _ = write!(
+            xml,
+            "    <resource identifier=\"{}\" type=\"{TEST_RESOURCE}\" href=\"{test}\"><file href=\"{test}\"/>",
+            identifier(test)
+        );
+        for (item, _) in items {
+            let _ = write!(xml, "<dependency identifierref=\"{}\"/>", identifier(item));
+        }
+        xml.push_str("</resource>\n");
+    }
+    for (item, _) in items {
+        let _ = writeln!(
+            xml,
+            "    <resource identifier=\"{}\" type=\"{ITEM_RESOURCE}\" href=\"{item}\"><file href=\"{item}\"/></resource>",
+            identifier(item)
+        );
+    }
+    xml.push_str("  </resources>\n</manifest>\n");
+    xml
+}
+
+fn write_package(manifest: String, files: Vec<(String, String)>) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (path, content) in std::iter::once(("imsmanifest.xml".to_string(), manifest)).chain(files) {
+        zip.start_file(path, options).map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(zip.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+/// Markdown text as XHTML paragraphs; paragraphs with indented lines or code
+/// fences go to `<pre>` to keep their layout.
+fn paragraphs(text: &str) -> String {
+    let mut html = String::new();
+    for paragraph in text
+        .replace("\r\n", "\n")
+        .split("\n\n")
+        .map(|paragraph| paragraph.trim_matches('\n'))
+        .filter(|paragraph| !paragraph.trim().is_empty())
+    {
+        if paragraph
+            .lines()
+            .any(|line| line.starts_with([' ', '\t']) || line.starts_with("```"))
+        {
+            let _ = write!(html, "<pre>{}</pre>", escape(paragraph));
+        } else {
+            let lines: Vec<String> = paragraph
+                .lines()
+                .map(|line| escape(line.trim()).into_owned())
+                .collect();
+            let _ = write!(html, "<p>{}</p>", lines.join("<br/>"));
+        }
+    }
+    html
+}
+
+fn format_number(value: f64) -> String {
+    format!("{value}")
+}
+
+struct ItemWriter {
+    declarations: String,
+    body: String,
+    processing: String,
+}
+
+impl ItemWriter {
+    fn declare(
+        &mut self,
+        identifier: &str,
+        cardinality: &str,
+        base_type: &str,
+        correct: &[String],
+    ) {
+        let _ = write!(
+            self.declarations,
+            "  <responseDeclaration identifier=\"{identifier}\" cardinality=\"{cardinality}\" baseType=\"{base_type}\">"
+        );
+        if !correct.is_empty() {
+            self.declarations.push_str("<correctResponse>");
+            for value in correct {
+                let _ = write!(self.declarations, "<value>{}</value>", escape(value));
+            }
+            self.declarations.push_str("</correctResponse>");
+        }
+    }
+
+    /// Ends a declaration opened with [`Self::declare`], with a mapping of
+    /// `(key, value, case sensitive)` entries if there are any.
+    fn end_declaration(&mut self, mapping: &[(String, f64, bool)], bounds: Option<(f64, f64)>) {
+        if !mapping.is_empty() {
+            self.declarations.push_str("<mapping defaultValue=\"0\"");
+            if let Some((lower, upper)) = bounds {
+                let _ = write!(
+                    self.declarations,
+                    " lowerBound=\"{}\" upperBound=\"{}\"",
+                    format_number(lower),
+                    format_number(upper)
+                );
+            }
+            self.declarations.push('>');
+            for (key, value, case_sensitive) in mapping {
+                let _ = write!(
+                    self.declarations,
+                    "<mapEntry mapKey=\"{}\" mappedValue=\"{}\" caseSensitive=\"{case_sensitive}\"/>",
+                    escape(key),
+                    format_number(*value)
+                );
+            }
+            self.declarations.push_str("</mapping>");
+        }
+        self.declarations.push_str("</responseDeclaration>\n");
+    }
+
+    fn template(&mut self, template: &str) {
+        self.processing = format!("  <responseProcessing template=\"{template}\"/>\n");
+    }
+
+    /// `SCORE` = 1 when `condition` holds, 0 otherwise.
+    fn score_if(&mut self, condition: &str) {
+        self.processing = format!(
+            "  <responseProcessing><responseCondition><responseIf>{condition}\
+             <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">1</baseValue></setOutcomeValue>\
+             </responseIf><responseElse>\
+             <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">0</baseValue></setOutcomeValue>\
+             </responseElse></responseCondition></responseProcessing>\n"
+        );
+    }
+}
+
+/// Renders a task as an `assessmentItem`, or says why it can't be.
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::cast_precision_loss)]
+fn item_xml(task: &Task) -> Result<String, String> {
+    if !task.template.is_empty() {
+        return Err(format!(
+            "task {} is parameterized, which QTI items here can't express",
+            task.id
+        ));
+    }
+    let mut item = ItemWriter {
+        declarations: String::new(),
+        body: String::new(),
+        processing: String::new(),
+    };
+    if let Some(description) = task
+        .description
+        .as_deref()
+        .filter(|text| !text.trim().is_empty())
+    {
+        let _ = write!(
+            item.body,
+            "<div class=\"description\">{}</div>",
+            paragraphs(description)
+        );
+    }
+
+    match &task.configuration {
+        TaskConfig::SingleChoice {
+            options,
+            correct,
+            shuffle,
+            ..
+        } => {
+            item.declare(
+                RESPONSE,
+                "single",
+                "identifier",
+                &[format!("choice-{correct}")],
+            );
+            item.end_declaration(&[], None);
+            push_choices(&mut item.body, options, *shuffle, 1);
+            item.template(MATCH_CORRECT);
+        }
+        TaskConfig::MultipleChoice {
+            options,
+            correct,
+            shuffle,
+            scoring,
+            ..
+        } => {
+            let correct_ids: Vec<String> = correct.iter().map(|i| format!("choice-{i}")).collect();
+            item.declare(RESPONSE, "multiple", "identifier", &correct_ids);
+            let share = 1f64 / correct.len().max(1) as f64;
+            // +1/n for a right choice, minus the penalty for a wrong one
+            let (penalty, lower) = match scoring {
+                MultipleChoiceScoring::AllOrNothing => (None, 0f64),
+                // per option scoring has no mapping equivalent, the closest is
+                // the default partial one
+                MultipleChoiceScoring::CorrectMinusIncorrect | MultipleChoiceScoring::PerOption => {
+                    (Some(share), 0f64)
+                }
+                MultipleChoiceScoring::NegativeMarking { penalty } => (
+                    Some(*penalty),
+                    0f64 - penalty * options.len().saturating_sub(correct.len()) as f64,
+                ),
+            };
+            if let Some(penalty) = penalty {
+                let mapping: Vec<(String, f64, bool)> = (0..options.len())
+                    .map(|i| {
+                        let value = if correct.contains(&i) {
+                            share
+                        } else {
+                            -penalty
+                        };
+                        (format!("choice-{i}"), value, true)
+                    })
+                    .collect();
+                item.end_declaration(&mapping, Some((lower, 1f64)));
+                item.template(MAP_RESPONSE);
+            } else {
+                item.end_declaration(&[], None);
+                item.template(MATCH_CORRECT);
+            }
+            push_choices(&mut item.body, options, *shuffle, 0);
+        }
+        TaskConfig::ShortText {
+            max_chars_count,
+            case_sensitive,
+            answers,
+            patterns,
+            ..
+        } => {
+            item.declare(
+                RESPONSE,
+                "single",
+                "string",
+                &answers[..answers.len().min(1)],
+            );
+            let mut mapping: Vec<(String, f64, bool)> = answers
+                .iter()
+                .map(|answer| (answer.clone(), 1f64, *case_sensitive))
+                .collect();
+            for pattern in patterns {
+                if pattern.regex {
+                    let _ = write!(
+                        item.body,
+                        "<!-- regex answer has no equivalent: {} -->",
+                        comment(&pattern.value)
+                    );
+                } else {
+                    mapping.push((pattern.value.clone(), pattern.score, *case_sensitive));
+                }
+            }
+            item.end_declaration(&mapping, Some((0f64, 1f64)));
+            let _ = write!(
+                item.body,
+                "<p><textEntryInteraction responseIdentifier=\"{RESPONSE}\" expectedLength=\"{max_chars_count}\"/></p>"
+            );
+            item.template(MAP_RESPONSE);
+        }
+        TaskConfig::LongText {
+            max_chars_count, ..
+        } => {
+            item.declare(RESPONSE, "single", "string", &[]);
+            item.end_declaration(&[], None);
+            let _ = write!(
+                item.body,
+                "<extendedTextInteraction responseIdentifier=\"{RESPONSE}\" expectedLength=\"{max_chars_count}\"/>"
+            );
+        }
+        TaskConfig::Ordering {
+            items,
+            answers,
+            partial_score,
+        } => {
+            let ids = |order: &Vec<usize>| -> Vec<String> {
+                order.iter().map(|i| format!("item-{i}")).collect()
+            };
+            item.declare(
+                RESPONSE,
+                "ordered",
+                "identifier",
+                &answers.first().map(ids).unwrap_or_default(),
+            );
+            item.end_declaration(&[], None);
+            if answers.len() > 1 {
+                let mut condition = String::from("<or>");
+                for order in answers {
+                    condition.push_str("<match><variable identifier=\"RESPONSE\"/><ordered>");
+                    for id in ids(order) {
+                        let _ = write!(
+                            condition,
+                            "<baseValue baseType=\"identifier\">{id}</baseValue>"
+                        );
+                    }
+                    condition.push_str("</ordered></match>");
+                }
+                condition.push_str("</or>");
+                item.score_if(&condition);
+            } else {
+                item.template(MATCH_CORRECT);
+            }
+            if *partial_score != OrderingPartialScore::Off {
+                item.body
+                    .push_str("<!-- partial scoring of the order has no equivalent -->");
+            }
+            let _ = write!(
+                item.body,
+                "<orderInteraction responseIdentifier=\"{RESPONSE}\" shuffle=\"true\">"
+            );
+            for (i, text) in items.iter().enumerate() {
+                let _ = write!(
+                    item.body,
+                    "<simpleChoice identifier=\"item-{i}\">{}</simpleChoice>",
+                    escape(text)
+                );
+            }
+            item.body.push_str("</orderInteraction>");
+        }
+        TaskConfig::FileUpload { allowed_types, .. } => {
+            item.declare(RESPONSE, "single", "file", &[]);
+            item.end_declaration(&[], None);
+            // QTI takes a single expected type; wildcards and lists are left out
+            let expected = match allowed_types.as_slice() {
+                [allowed] if !allowed.ends_with("/*") => {
+                    format!(" type=\"{}\"", escape(allowed.as_str()))
+                }
+                _ => String::new(),
+            };
+            let _ = write!(
+                item.body,
+                "<uploadInteraction responseIdentifier=\"{RESPONSE}\"{expected}/>"
+            );
+        }
+        TaskConfig::CTFd { .. } => {
+            return Err(format!(
+                "task {} is a CTFd challenge, which has no QTI equivalent",
+                task.id
+            ));
+        }
+        TaskConfig::Code { .. } => {
+            return Err(format!(
+                "task {} is a code task, which has no QTI equivalent",
+                task.id
+            ));
+        }
+        TaskConfig::Expression { .. } => {
+            return Err(format!(
+                "task {} is an expression task, which has no QTI equivalent",
+                task.id
+            ));
+        }
+        TaskConfig::Numeric {
+            answer,
+            absolute_tolerance,
+            relative_tolerance,
+            units,
+            bases,
+            ..
+        } => {
+            item.declare(RESPONSE, "single", "float", &[format_number(*answer)]);
+            item.end_declaration(&[], None);
+            if !units.is_empty() || !bases.is_empty() {
+                item.body
+                    .push_str("<!-- units and number bases have no equivalent -->");
+            }
+            let _ = write!(
+                item.body,
+                "<p><textEntryInteraction responseIdentifier=\"{RESPONSE}\"/></p>"
+            );
+            let equal = |mode: &str, tolerance: f64| {
+                format!(
+                    "<equal toleranceMode=\"{mode}\" tolerance=\"{0} {0}\">\
+                     <variable identifier=\"RESPONSE\"/><correct identifier=\"RESPONSE\"/></equal>",
+                    format_number(tolerance)
+                )
+            };
+            match (*absolute_tolerance > 0f64, *relative_tolerance > 0f64) {
+                (false, false) => item.template(MATCH_CORRECT),
+                (true, false) => item.score_if(&equal("absolute", *absolute_tolerance)),
+                (false, true) => item.score_if(&equal("relative", relative_tolerance * 100f64)),
+                (true, true) => item.score_if(&format!(
+                    "<or>{}{}</or>",
+                    equal("absolute", *absolute_tolerance),
+                    equal("relative", relative_tolerance * 100f64)
+                )),
+            }
+        }
+        TaskConfig::Matching {
+            left,
+            right,
+            correct,
+            partial_score,
+        } => {
+            let pairs: Vec<String> = correct
+                .iter()
+                .enumerate()
+                .map(|(i, j)| format!("left-{i} right-{j}"))
+                .collect();
+            item.declare(RESPONSE, "multiple", "directedPair", &pairs);
+            if *partial_score {
+                let share = 1f64 / left.len().max(1) as f64;
+                let mapping: Vec<(String, f64, bool)> = pairs
+                    .iter()
+                    .map(|pair| (pair.clone(), share, true))
+                    .collect();
+                item.end_declaration(&mapping, Some((0f64, 1f64)));
+                item.template(MAP_RESPONSE);
+            } else {
+                item.end_declaration(&[], None);
+                item.template(MATCH_CORRECT);
+            }
+            let _ = write!(
+                item.body,
+                "<matchInteraction responseIdentifier=\"{RESPONSE}\" shuffle=\"true\" maxAssociations=\"{}\"><simpleMatchSet>",
+                left.len()
+            );
+            for (i, text) in left.iter().enumerate() {
+                let _ = write!(
+                    item.body,
+                    "<simpleAssociableChoice identifier=\"left-{i}\" matchMax=\"1\">{}</simpleAssociableChoice>",
+                    escape(text)
+                );
+            }
+            item.body.push_str("</simpleMatchSet><simpleMatchSet>");
+            for (j, text) in right.iter().enumerate() {
+                let _ = write!(
+                    item.body,
+                    "<simpleAssociableChoice identifier=\"right-{j}\" matchMax=\"{}\">{}</simpleAssociableChoice>",
+                    left.len(),
+                    escape(text)
+                );
+            }
+            item.body.push_str("</simpleMatchSet></matchInteraction>");
+        }
+        TaskConfig::Cloze {
+            text,
+            blanks,
+            case_sensitive,
+        } => {
+            let mut interactions = Vec::with_capacity(blanks.len());
+            let mut sums = String::new();
+            for (i, blank) in blanks.iter().enumerate() {
+                let identifier = format!("{RESPONSE}_{}", i + 1);
+                let _ = write!(sums, "<mapResponse identifier=\"{identifier}\"/>");
+                if blank.options.is_empty() {
+                    item.declare(
+                        &identifier,
+                        "single",
+                        "string",
+                        &blank.answers[..blank.answers.len().min(1)],
+                    );
+                    let mapping: Vec<(String, f64, bool)> = blank
+                        .answers
+                        .iter()
+                        .map(|answer| (answer.clone(), blank.weight, *case_sensitive))
+                        .collect();
+                    item.end_declaration(&mapping, None);
+                    interactions.push(format!(
+                        "<textEntryInteraction responseIdentifier=\"{identifier}\"/>"
+                    ));
+                } else {
+                    let option_id = |answer: &String| {
+                        blank
+                            .options
+                            .iter()
+                            .position(|option| option == answer)
+                            .map(|k| format!("blank{}-option-{k}", i + 1))
+                    };
+                    let correct: Vec<String> = blank.answers.iter().filter_map(option_id).collect();
+                    item.declare(
+                        &identifier,
+                        "single",
+                        "identifier",
+                        &correct[..correct.len().min(1)],
+                    );
+                    let mapping: Vec<(String, f64, bool)> = correct
+                        .iter()
+                        .map(|id| (id.clone(), blank.weight, true))
+                        .collect();
+                    item.end_declaration(&mapping, None);
+                    let mut interaction = format!(
+                        "<inlineChoiceInteraction responseIdentifier=\"{identifier}\" shuffle=\"true\">"
+                    );
+                    for (k, option) in blank.options.iter().enumerate() {
+                        let _ = write!(
+                            interaction,
+                            "<inlineChoice identifier=\"blank{}-option-{k}\">{}</inlineChoice>",
+                            i + 1,
+                            escape(option)
+                        );
+                    }
+                    interaction.push_str("</inlineChoiceInteraction>");
+                    interactions.push(interaction);
+                }
+            }
+            let html = paragraphs(text);
+            let html = CLOZE_BLANK.replace_all(&html, |captures: &regex::Captures| {
+                captures[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| interactions.get(number.wrapping_sub(1)))
+                    .cloned()
+                    .unwrap_or_default()
+            });
+            let _ = write!(item.body, "<div class=\"cloze\">{html}</div>");
+            item.processing = format!(
+                "  <responseProcessing><setOutcomeValue identifier=\"SCORE\"><divide><sum>{sums}</sum>\
+                 <baseValue baseType=\"float\">{}</baseValue></divide></setOutcomeValue></responseProcessing>\n",
+                format_number(blanks.iter().map(|blank| blank.weight).sum::<f64>())
+            );
+        }
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<assessmentItem xmlns=\"{QTI_NAMESPACE}\" identifier=\"task-{}\" title=\"{}\" adaptive=\"false\" timeDependent=\"false\">",
+        task.id,
+        escape(&task.title)
+    );
+    xml.push_str(&item.declarations);
+    xml.push_str("  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\"><defaultValue><value>0</value></defaultValue></outcomeDeclaration>\n");
+    let _ = writeln!(
+        xml,
+        "  <outcomeDeclaration identifier=\"MAXSCORE\" cardinality=\"single\" baseType=\"float\"><defaultValue><value>{}</value></defaultValue></outcomeDeclaration>",
+        task.points
+    );
+    let _ = writeln!(xml, "  <itemBody>{}</itemBody>", item.body);
+    xml.push_str(&item.processing);
+    xml.push_str("</assessmentItem>\n");
+    Ok(xml)
+}
+
+fn push_choices(body: &mut String, options: &[String], shuffle: bool, max_choices: usize) {
+    let _ = write!(
+        body,
+        "<choiceInteraction responseIdentifier=\"{RESPONSE}\" shuffle=\"{shuffle}\" maxChoices=\"{max_choices}\">"
+    );
+    for (i, option) in options.iter().enumerate() {
+        let _ = write!(
+            body,
+            "<simpleChoice identifier=\"choice-{i}\">{}</simpleChoice>",
+            escape(option)
+        );
+    }
+    body.push_str("</choiceInteraction>");
+}
+
+/* ---------------------------------- import --------------------------------- */
+
+/// XML files of a package, parsed, keyed by their path in the zip.
+struct Package {
+    files: Vec<(String, Node)>,
+}
+
+impl Package {
+    fn read(bytes: &[u8]) -> Result<Self, String> {
+        let mut archive =
+            ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Not a zip package: {e}"))?;
+        let mut total = 0u64;
+        let mut files = Vec::new();
+        for index in 0..archive.len() {
+            let mut file = archive
+                .by_index(index)
+                .map_err(|e| format!("Broken zip package: {e}"))?;
+            if !file.is_file() || !file.name().to_lowercase().ends_with(".xml") {
+                continue;
+            }
+            total += file.size();
+            if total > MAX_PACKAGE_SIZE {
+                return Err("The package is too large".to_string());
+            }
+            let name = file.name().to_string();
+            let mut source = String::new();
+            file.by_ref()
+                .take(MAX_PACKAGE_SIZE)
+                .read_to_string(&mut source)
+                .map_err(|e| format!("{name}: {e}"))?;
+            let document = parse_document(&source).map_err(|e| format!("{name}: {e}"))?;
+            files.push((name, document));
+        }
+        Ok(Self { files })
+    }
+
+    fn root<'a>(document: &'a Node, name: &str) -> Option<&'a Node> {
+        document.child(name)
+    }
+
+    fn get(&self, path: &str) -> Option<&Node> {
+        self.files
+            .iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, document)| document)
+    }
+
+    /// Item files in manifest order, or in zip order without a manifest.
+    fn item_paths(&self) -> Vec<String> {
+        let manifest = self
+            .get("imsmanifest.xml")
+            .and_then(|document| Self::root(document, "manifest"));
+        if let Some(manifest) = manifest {
+            let paths: Vec<String> = manifest
+                .descendants("resource")
+                .into_iter()
+                .filter(|resource| {
+                    resource
+                        .attribute("type")
+                        .is_some_and(|kind| kind.starts_with("imsqti_item"))
+                })
+                .filter_map(|resource| resource.attribute("href"))
+                .map(|href| resolve("", href))
+                .collect();
+            if !paths.is_empty() {
+                return paths;
+            }
+        }
+        self.files
+            .iter()
+            .filter(|(_, document)| Self::root(document, "assessmentItem").is_some())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// Path of `href` relative to the file at `base`, `.` and `..` resolved.
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.retain(|part| !part.is_empty());
+    parts.join("/")
+}
+
+/// Reads every item of a package.
+pub fn import(bytes: &[u8]) -> Result<Vec<ImportedQuestion>, String> {
+    let package = Package::read(bytes)?;
+    Ok(package
+        .item_paths()
+        .iter()
+        .map(|path| import_item_file(&package, path, None))
+        .collect())
+}
+
+/// Reads the (single) test of a package with the items it references.
+#[allow(clippy::cast_possible_truncation)]
+pub fn import_test(bytes: &[u8]) -> Result<QtiTest, String> {
+    let package = Package::read(bytes)?;
+    let mut tests = package.files.iter().filter_map(|(path, document)| {
+        Package::root(document, "assessmentTest").map(|test| (path.as_str(), test))
+    });
+    let (test_path, test) = tests
+        .next()
+        .ok_or_else(|| "The package has no assessment test".to_string())?;
+    if tests.next().is_some() {
+        return Err("The package has more than one assessment test".to_string());
+    }
+
+    let mut imported = QtiTest {
+        title: test
+            .attribute("title")
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        description: None,
+        duration: test
+            .child("timeLimits")
+            .into_iter()
+            .chain(
+                test.children("testPart")
+                    .filter_map(|part| part.child("timeLimits")),
+            )
+            .find_map(|limits| limits.attribute("maxTime"))
+            .and_then(|seconds| seconds.trim().parse::<f64>().ok())
+            .filter(|seconds| seconds.is_finite() && *seconds > 0f64)
+            .map(|seconds| seconds.round().min(f64::from(i32::MAX)) as i32),
+        shuffle: false,
+        entities: Vec::new(),
+    };
+    for part in test.children("testPart") {
+        for section in part.children("assessmentSection") {
+            walk_section(&package, test_path, section, true, &mut imported);
+        }
+    }
+    Ok(imported)
+}
+
+fn walk_section(package: &Package, test_path: &str, section: &Node, top: bool, test: &mut QtiTest) {
+    if section
+        .child("ordering")
+        .and_then(|ordering| ordering.attribute("shuffle"))
+        == Some("true")
+    {
+        test.shuffle = true;
+    }
+    for child in &section.children {
+        match child.name.as_str() {
+            "rubricBlock" => {
+                let content = render(child);
+                if content.is_empty() {
+                    continue;
+                }
+                let is_description = child.attribute("class").is_some_and(|class| {
+                    class.split_whitespace().any(|class| class == "description")
+                });
+                if top && is_description && test.description.is_none() {
+                    test.description = Some(content);
+                } else {
+                    test.entities.push(QtiEntity::Text(content));
+                }
+            }
+            "assessmentSection" => walk_section(package, test_path, child, false, test),
+            "assessmentItemRef" => {
+                let href = child.attribute("href").unwrap_or_default();
+                let weight = child
+                    .children("weight")
+                    .find_map(|weight| weight.attribute("value"))
+                    .and_then(|value| value.trim().parse::<f64>().ok());
+                let question = import_item_file(package, &resolve(test_path, href), weight);
+                test.entities.push(QtiEntity::Question(Box::new(question)));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn import_item_file(package: &Package, path: &str, weight: Option<f64>) -> ImportedQuestion {
+    package
+        .get(path)
+        .and_then(|document| Package::root(document, "assessmentItem"))
+        .map_or_else(
+            || {
+                ImportedQuestion::skipped(
+                    path.to_string(),
+                    format!("{path} is not an assessment item of the package"),
+                )
+            },
+            |item| import_item(item, weight),
+        )
+}
+
+/* ----------------------------- text rendering ------------------------------ */
+
+const BLOCKS: [&str; 17] = [
+    "p",
+    "div",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "li",
+    "table",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "rubricBlock",
+    "prompt",
+];
+
+/// Turns XHTML content into plain text with paragraphs separated by blank
+/// lines. In cloze mode inline text interactions become `{{n}}` blanks.
+struct Renderer<'a> {
+    text: String,
+    cloze: bool,
+    blanks: Vec<&'a Node>,
+}
+
+impl<'a> Renderer<'a> {
+    const fn new(cloze: bool) -> Self {
+        Self {
+            text: String::new(),
+            cloze,
+            blanks: Vec::new(),
+        }
+    }
+
+    fn break_block(&mut self) {
+        let trimmed = self.text.trim_end_matches([' ', '\n']).len();
+        self.text.truncate(trimmed);
+        if !self.text.is_empty() {
+            self.text.push_str("\n\n");
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        let mut words = text.split_whitespace().peekable();
+        if words.peek().is_none() {
+            if !text.is_empty() && !self.text.is_empty() && !self.text.ends_with([' ', '\n']) {
+                self.text.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace)
+            && !self.text.is_empty()
+            && !self.text.ends_with([' ', '\n'])
+        {
+            self.text.push(' ');
+        }
+        self.text.push_str(&words.collect::<Vec<_>>().join(" "));
+        if text.ends_with(char::is_whitespace) {
+            self.text.push(' ');
+        }
+    }
+
+    fn render(&mut self, node: &'a Node, pre: bool) {
+        for child in &node.children {
+            self.render_child(child, pre);
+        }
+    }
+
+    fn render_child(&mut self, child: &'a Node, pre: bool) {
+        match child.name.as_str() {
+            TEXT_NODE if pre => self.text.push_str(&child.text),
+            TEXT_NODE => self.push_inline(&child.text),
+            "br" => {
+                let trimmed = self.text.trim_end_matches(' ').len();
+                self.text.truncate(trimmed);
+                self.text.push('\n');
+            }
+            "textEntryInteraction" | "inlineChoiceInteraction" if self.cloze => {
+                self.blanks.push(child);
+                let _ = write!(self.text, "{{{{{}}}}}", self.blanks.len());
+            }
+            "img" => {
+                if let Some(alt) = child.attribute("alt").filter(|alt| !alt.trim().is_empty()) {
+                    self.push_inline(&format!("[{alt}]"));
+                }
+            }
+            "modalFeedback" | "feedbackBlock" | "feedbackInline" | "templateBlock"
+            | "templateInline" | "math" | "object" | "printedVariable" => {}
+            name if MAPPED_INTERACTIONS.contains(&name)
+                || UNMAPPED_INTERACTIONS.contains(&name) => {}
+            name if BLOCKS.contains(&name) => {
+                self.break_block();
+                if name == "li" {
+                    self.text.push_str("- ");
+                }
+                self.render(child, pre || name == "pre");
+                self.break_block();
+            }
+            _ => self.render(child, pre),
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::with_capacity(self.text.len());
+        let mut newlines = 0;
+        for c in self.text.trim().chars() {
+            if c == '\n' {
+                newlines += 1;
+                if newlines > 2 {
+                    continue;
+                }
+            } else {
+                newlines = 0;
+            }
+            text.push(c);
+        }
+        text.lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn render(node: &Node) -> String {
+    let mut renderer = Renderer::new(false);
+    renderer.render(node, false);
+    renderer.finish()
+}
+
+fn has_class(node: &Node, class: &str) -> bool {
+    node.attribute("class")
+        .is_some_and(|classes| classes.split_whitespace().any(|name| name == class))
+}
+
+/* ------------------------------- item import ------------------------------- */
+
+/// Scoring of a multiple choice item: a mapping allowed below zero is negative
+/// marking with its largest penalty, any other mapping the default partial one.
+fn choice_scoring(declaration: &Declaration, partial_by_template: bool) -> MultipleChoiceScoring {
+    if declaration.lower_bound.is_none_or(|bound| bound < 0f64) && !declaration.mapping.is_empty() {
+        let penalty = declaration
+            .mapping
+            .iter()
+            .map(|(_, value, _)| -value)
+            .fold(0f64, f64::max)
+            .min(1f64);
+        MultipleChoiceScoring::NegativeMarking { penalty }
+    } else if partial_by_template || !declaration.mapping.is_empty() {
+        MultipleChoiceScoring::CorrectMinusIncorrect
+    } else {
+        MultipleChoiceScoring::AllOrNothing
+    }
+}
+
+/// A `responseDeclaration`: cardinality, base type, correct values, the
+/// `(key, value, case sensitive)` mapping entries and the mapping's lower bound.
+struct Declaration {
+    cardinality: String,
+    base_type: String,
+    correct: Vec<String>,
+    mapping: Vec<(String, f64, bool)>,
+    lower_bound: Option<f64>,
+}
+
+impl Declaration {
+    fn read(item: &Node, identifier: &str) -> Option<Self> {
+        let declaration = item
+            .children("responseDeclaration")
+            .find(|declaration| declaration.attribute("identifier") == Some(identifier))?;
+        Some(Self {
+            cardinality: declaration
+                .attribute("cardinality")
+                .unwrap_or("single")
+                .to_string(),
+            base_type: declaration
+                .attribute("baseType")
+                .unwrap_or_default()
+                .to_string(),
+            correct: declaration
+                .child("correctResponse")
+                .into_iter()
+                .flat_map(|correct| correct.children("value"))
+                .map(|value| value.text.trim().to_string())
+                .collect(),
+            mapping: declaration
+                .child("mapping")
+                .into_iter()
+                .flat_map(|mapping| mapping.children("mapEntry"))
+                .filter_map(|entry| {
+                    let value = entry.attribute("mappedValue")?.trim().parse::<f64>().ok()?;
+                    Some((
+                        entry.attribute("mapKey")?.trim().to_string(),
+                        value,
+                        entry.attribute("caseSensitive") != Some("false"),
+                    ))
+                })
+                .collect(),
+            lower_bound: declaration
+                .child("mapping")
+                .and_then(|mapping| mapping.attribute("lowerBound"))
+                .and_then(|bound| bound.trim().parse().ok()),
+        })
+    }
+
+    /// Correct values followed by the other positively mapped keys.
+    fn accepted(&self) -> Vec<String> {
+        let mut accepted = self.correct.clone();
+        for (key, value, _) in &self.mapping {
+            if *value > 0f64 && !accepted.contains(key) {
+                accepted.push(key.clone());
+            }
+        }
+        accepted
+    }
+
+    fn case_sensitive(&self) -> bool {
+        self.mapping
+            .iter()
+            .all(|(_, _, case_sensitive)| *case_sensitive)
+    }
+}
+
+fn identifiers_and_texts<'a>(
+    choices: impl Iterator<Item = &'a Node>,
+) -> (Vec<String>, Vec<String>) {
+    choices
+        .map(|choice| {
+            (
+                choice
+                    .attribute("identifier")
+                    .unwrap_or_default()
+                    .to_string(),
+                render(choice),
+            )
+        })
+        .unzip()
+}
+
+fn expected_length(interaction: &Node, default: usize, max: usize) -> usize {
+    interaction
+        .attribute("expectedLength")
+        .and_then(|length| length.trim().parse::<usize>().ok())
+        .filter(|length| *length > 0)
+        .unwrap_or(default)
+        .min(max)
+}
+
+/// Reads an `assessmentItem`; `weight` is the test's weight for it, used for
+/// points if the item has no `MAXSCORE`.
+#[allow(clippy::too_many_lines)]
+fn import_item(item: &Node, weight: Option<f64>) -> ImportedQuestion {
+    let name = item
+        .attribute("title")
+        .or_else(|| item.attribute("identifier"))
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if item.child("templateDeclaration").is_some() {
+        return ImportedQuestion::skipped(
+            name,
+            "Items with template variables (randomized items) can't be mapped".to_string(),
+        );
+    }
+    let Some(body) = item.child("itemBody") else {
+        return ImportedQuestion::skipped(name, "The item has no body".to_string());
+    };
+
+    let unmapped: Vec<&str> = UNMAPPED_INTERACTIONS
+        .iter()
+        .copied()
+        .filter(|interaction| !body.descendants(interaction).is_empty())
+        .collect();
+    if !unmapped.is_empty() {
+        return ImportedQuestion::skipped(
+            name,
+            format!("Interactions that can't be mapped: {}", unmapped.join(", ")),
+        );
+    }
+    let interactions: Vec<&Node> = MAPPED_INTERACTIONS
+        .iter()
+        .flat_map(|interaction| body.descendants(interaction))
+        .collect();
+    let Some(first) = interactions.first() else {
+        return ImportedQuestion::skipped(name, "The item has no interactions".to_string());
+    };
+    let is_cloze = interactions
+        .iter()
+        .any(|interaction| interaction.name == "inlineChoiceInteraction")
+        || (interactions.len() > 1
+            && interactions
+                .iter()
+                .all(|interaction| interaction.name == "textEntryInteraction"));
+    if interactions.len() > 1 && !is_cloze {
+        let names: Vec<&str> = interactions
+            .iter()
+            .map(|interaction| interaction.name.as_str())
+            .collect();
+        return ImportedQuestion::skipped(
+            name,
+            format!(
+                "Several interactions in one item can't be mapped: {}",
+                names.join(", ")
+            ),
+        );
+    }
+
+    let mut warnings = Vec::new();
+    if ["modalFeedback", "feedbackBlock", "feedbackInline"]
+        .iter()
+        .any(|name| !item.descendants(name).is_empty())
+    {
+        warnings.push("Feedback is not imported".to_string());
+    }
+    if ["img", "object", "math"]
+        .iter()
+        .any(|name| !body.descendants(name).is_empty())
+    {
+        warnings.push("Images, media and formulas are not imported".to_string());
+    }
+    let max_score = item
+        .children("outcomeDeclaration")
+        .find(|outcome| outcome.attribute("identifier") == Some("MAXSCORE"))
+        .and_then(|outcome| outcome.child("defaultValue"))
+        .and_then(|default| default.value_of("value"))
+        .and_then(|value| value.parse::<f64>().ok());
+    let points = points_from_grade(max_score.or(weight).unwrap_or(1f64), &mut warnings);
+    let template = item
+        .child("responseProcessing")
+        .and_then(|processing| {
+            processing
+                .attribute("template")
+                .or_else(|| processing.attribute("templateLocation"))
+        })
+        .unwrap_or_default();
+    let partial_by_template = template.ends_with("map_response");
+
+    if is_cloze {
+        let description = body
+            .children
+            .iter()
+            .find(|child| has_class(child, "description"))
+            .map(render)
+            .unwrap_or_default();
+        let mut renderer = Renderer::new(true);
+        for child in body
+            .children
+            .iter()
+            .filter(|child| !has_class(child, "description"))
+        {
+            renderer.render_child(child, false);
+        }
+        let blank_nodes = renderer.blanks.clone();
+        let text = renderer.finish();
+        let mut draft = QuestionDraft {
+            name,
+            text: description,
+            points,
+            tags: Vec::new(),
+            warnings,
+        };
+        let mut blanks = Vec::with_capacity(blank_nodes.len());
+        let mut case_sensitive = true;
+        for (i, interaction) in blank_nodes.iter().enumerate() {
+            let identifier = interaction
+                .attribute("responseIdentifier")
+                .unwrap_or_default();
+            let Some(declaration) = Declaration::read(item, identifier) else {
+                return draft.skip(format!("Blank {} has no response declaration", i + 1));
+            };
+            case_sensitive &= declaration.case_sensitive();
+            let weight = declaration
+                .mapping
+                .iter()
+                .map(|(_, value, _)| *value)
+                .fold(0f64, f64::max);
+            let weight = if weight > 0f64 { weight } else { 1f64 };
+            let accepted = declaration.accepted();
+            let blank = if interaction.name == "inlineChoiceInteraction" {
+                let (ids, options) = identifiers_and_texts(interaction.children("inlineChoice"));
+                let answers: Vec<String> = accepted
+                    .iter()
+                    .filter_map(|id| ids.iter().position(|option| option == id))
+                    .map(|k| options[k].clone())
+                    .collect();
+                ClozeBlank {
+                    answers,
+                    options,
+                    weight,
+                }
+            } else {
+                ClozeBlank {
+                    answers: accepted,
+                    options: Vec::new(),
+                    weight,
+                }
+            };
+            if blank.answers.is_empty() {
+                return draft.skip(format!("Blank {} has no correct response", i + 1));
+            }
+            blanks.push(blank);
+        }
+        if draft.text.is_empty() {
+            draft.text.clone_from(&draft.name);
+        }
+        return draft.into_imported(TaskConfig::Cloze {
+            text,
+            blanks,
+            case_sensitive,
+        });
+    }
+
+    let interaction = *first;
+    let mut description = render(body);
+    let prompts: Vec<String> = interaction.children("prompt").map(render).collect();
+    for prompt in prompts.into_iter().filter(|prompt| !prompt.is_empty()) {
+        if !description.is_empty() {
+            description.push_str("\n\n");
+        }
+        description.push_str(&prompt);
+    }
+    let mut draft = QuestionDraft {
+        name,
+        text: description,
+        points,
+        tags: Vec::new(),
+        warnings,
+    };
+    let identifier = interaction
+        .attribute("responseIdentifier")
+        .unwrap_or(RESPONSE);
+    let declaration = Declaration::read(item, identifier);
+    let needs_declaration = !matches!(
+        interaction.name.as_str(),
+        "extendedTextInteraction" | "uploadInteraction"
+    );
+    let Some(declaration) = declaration.or_else(|| {
+        (!needs_declaration).then(|| Declaration {
+            cardinality: "single".to_string(),
+            base_type: "string".to_string(),
+            correct: Vec::new(),
+            mapping: Vec::new(),
+            lower_bound: None,
+        })
+    }) else {
+        return draft.skip("The interaction has no response declaration");
+    };
+    let shuffle = interaction.attribute("shuffle") == Some("true");
+
+    let configuration = match interaction.name.as_str() {
+        "choiceInteraction" => {
+            let (ids, options) = identifiers_and_texts(interaction.children("simpleChoice"));
+            let correct: Vec<usize> = declaration
+                .accepted()
+                .iter()
+                .filter_map(|id| ids.iter().position(|choice| choice == id))
+                .collect();
+            if correct.is_empty() {
+                return draft.skip("The item has no correct response");
+            }
+            if declaration.cardinality == "single" {
+                if correct.len() > 1 {
+                    draft
+                        .warnings
+                        .push("Only the first correct choice is kept".to_string());
+                }
+                TaskConfig::SingleChoice {
+                    options,
+                    correct: correct[0],
+                    shuffle,
+                    explanations: Vec::new(),
+                }
+            } else {
+                TaskConfig::MultipleChoice {
+                    options,
+                    correct,
+                    shuffle,
+                    scoring: choice_scoring(&declaration, partial_by_template),
+                    explanations: Vec::new(),
+                }
+            }
+        }
+        "orderInteraction" => {
+            let (ids, items) = identifiers_and_texts(interaction.children("simpleChoice"));
+            let to_order = |values: &[String]| -> Option<Vec<usize>> {
+                values
+                    .iter()
+                    .map(|id| ids.iter().position(|item| item == id))
+                    .collect()
+            };
+            let mut orders = vec![declaration.correct];
+            if let Some(processing) = item.child("responseProcessing") {
+                for ordered in processing.descendants("ordered") {
+                    orders.push(
+                        ordered
+                            .children("baseValue")
+                            .map(|value| value.text.trim().to_string())
+                            .collect(),
+                    );
+                }
+            }
+            let mut answers: Vec<Vec<usize>> = Vec::new();
+            for order in orders.iter().filter(|order| !order.is_empty()) {
+                match to_order(order) {
+                    Some(order) if order.len() == items.len() => {
+                        if !answers.contains(&order) {
+                            answers.push(order);
+                        }
+                    }
+                    _ => draft.warnings.push(
+                        "An accepted order that doesn't use every item is dropped".to_string(),
+                    ),
+                }
+            }
+            if answers.is_empty() {
+                return draft.skip("The item has no correct response");
+            }
+            if !shuffle {
+                draft
+                    .warnings
+                    .push("Items are always shown shuffled".to_string());
+            }
+            TaskConfig::Ordering {
+                items,
+                answers,
+                partial_score: OrderingPartialScore::Off,
+            }
+        }
+        "matchInteraction" => {
+            let sets: Vec<&Node> = interaction.children("simpleMatchSet").collect();
+            let [left_set, right_set] = sets.as_slice() else {
+                return draft.skip("A match interaction needs exactly two sets");
+            };
+            let (left_ids, left) =
+                identifiers_and_texts(left_set.children("simpleAssociableChoice"));
+            let (right_ids, right) =
+                identifiers_and_texts(right_set.children("simpleAssociableChoice"));
+            let mut pairs: Vec<Option<usize>> = vec![None; left.len()];
+            for pair in declaration.accepted() {
+                let Some((a, b)) = pair.split_once(char::is_whitespace) else {
+                    continue;
+                };
+                let (a, b) = (a.trim(), b.trim());
+                let position = |ids: &[String], id: &str| ids.iter().position(|x| x == id);
+                let found = position(&left_ids, a)
+                    .zip(position(&right_ids, b))
+                    .or_else(|| position(&left_ids, b).zip(position(&right_ids, a)));
+                if let Some((i, j)) = found {
+                    if pairs[i].is_some() {
+                        draft
+                            .warnings
+                            .push("Only one correct pair per item is kept".to_string());
+                    } else {
+                        pairs[i] = Some(j);
+                    }
+                }
+            }
+            let (left, correct): (Vec<String>, Vec<usize>) = left
+                .into_iter()
+                .zip(pairs)
+                .filter_map(|(item, pair)| pair.map(|j| (item, j)))
+                .unzip();
+            if left.len() < left_ids.len() {
+                draft
+                    .warnings
+                    .push("Items without a correct pair are dropped".to_string());
+            }
+            if left.is_empty() {
+                return draft.skip("The item has no correct response");
+            }
+            if !shuffle {
+                draft
+                    .warnings
+                    .push("The right column is always shown shuffled".to_string());
+            }
+            TaskConfig::Matching {
+                left,
+                right,
+                correct,
+                partial_score: partial_by_template || !declaration.mapping.is_empty(),
+            }
+        }
+        "textEntryInteraction" if matches!(declaration.base_type.as_str(), "float" | "integer") => {
+            let Some(answer) = declaration
+                .accepted()
+                .first()
+                .and_then(|value| value.parse::<f64>().ok())
+            else {
+                return draft.skip("The item has no numeric correct response");
+            };
+            let mut absolute_tolerance = 0f64;
+            let mut relative_tolerance = 0f64;
+            let equals = item
+                .child("responseProcessing")
+                .map(|processing| processing.descendants("equal"))
+                .unwrap_or_default();
+            for equal in equals {
+                let tolerance = equal
+                    .attribute("tolerance")
+                    .and_then(|tolerance| tolerance.split_whitespace().next())
+                    .and_then(|tolerance| tolerance.parse::<f64>().ok())
+                    .filter(|tolerance| tolerance.is_finite() && *tolerance >= 0f64)
+                    .unwrap_or(0f64);
+                match equal.attribute("toleranceMode") {
+                    Some("absolute") => absolute_tolerance = absolute_tolerance.max(tolerance),
+                    Some("relative") => {
+                        relative_tolerance = relative_tolerance.max(tolerance / 100f64);
+                    }
+                    _ => {}
+                }
+            }
+            TaskConfig::Numeric {
+                answer,
+                absolute_tolerance,
+                relative_tolerance,
+                units: Vec::new(),
+                unit_required: false,
+                bases: Vec::new(),
+            }
+        }
+        "textEntryInteraction" => {
+            let top = declaration
+                .mapping
+                .iter()
+                .map(|(_, value, _)| *value)
+                .fold(0f64, f64::max);
+            let scale = if top > 0f64 { top } else { 1f64 };
+            let mut answers: Vec<String> = declaration.correct.clone();
+            let mut patterns = Vec::new();
+            for (key, value, _) in &declaration.mapping {
+                let score = value / scale;
+                if score >= 1f64 {
+                    if !answers.contains(key) {
+                        answers.push(key.clone());
+                    }
+                } else if score > 0f64 {
+                    patterns.push(AnswerPattern {
+                        value: key.clone(),
+                        regex: false,
+                        score,
+                    });
+                }
+            }
+            if answers.is_empty() && patterns.is_empty() {
+                return draft.skip("The item has no correct response");
+            }
+            let longest = answers
+                .iter()
+                .chain(patterns.iter().map(|pattern| &pattern.value))
+                .map(|answer| answer.chars().count())
+                .max()
+                .unwrap_or_default();
+            TaskConfig::ShortText {
+                auto_grade: true,
+                max_chars_count: expected_length(interaction, 100, SHORT_TEXT_MAX_LEN)
+                    .max(longest)
+                    .min(SHORT_TEXT_MAX_LEN),
+                case_sensitive: declaration.case_sensitive(),
+                answers,
+                whitespace: WhitespaceMode::Trim,
+                normalize_unicode: false,
+                max_distance: 0,
+                patterns,
+                feedback: Vec::new(),
+            }
+        }
+        "extendedTextInteraction" => TaskConfig::LongText {
+            max_chars_count: expected_length(interaction, LONG_TEXT_MAX_LEN, LONG_TEXT_MAX_LEN),
+            rubric: Vec::new(),
+        },
+        "uploadInteraction" => {
+            draft.warnings.push(format!(
+                "QTI has no file size limit, {} MB is used",
+                UPLOAD_MAX_SIZE / 1024 / 1024
+            ));
+            TaskConfig::FileUpload {
+                max_size: UPLOAD_MAX_SIZE,
+                allowed_types: interaction
+                    .attribute("type")
+                    .map(str::trim)
+                    .filter(|expected| expected.contains('/'))
+                    .map(str::to_string)
+                    .into_iter()
+                    .collect(),
+                rubric: Vec::new(),
+            }
+        }
+        other => return draft.skip(format!("The {other} can't be mapped")),
+    };
+    draft.into_imported(configuration)
+}
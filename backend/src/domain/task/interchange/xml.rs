@@ -0,0 +1,149 @@
+//! A tiny DOM over `quick_xml` events, enough for reading question files.
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+/// Name of the pseudo-elements that keep text in document order.
+pub const TEXT_NODE: &str = "#text";
+
+/// A parsed XML element. Element names are local, namespace prefixes are
+/// dropped.
+#[derive(Default)]
+pub struct Node {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    /// All direct text of the element, concatenated.
+    pub text: String,
+    /// Child elements, with the text between them as [`TEXT_NODE`]s for
+    /// formats that mix the two (QTI item bodies).
+    pub children: Vec<Self>,
+}
+
+impl Node {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Self> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Every element of the subtree named `name`, in document order.
+    pub fn descendants<'a>(&'a self, name: &'a str) -> Vec<&'a Self> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+        found
+    }
+
+    /// The element's own `<text>` child, as in `<answer><text>...</text></answer>`.
+    pub fn inner_text(&self) -> Option<&str> {
+        self.child("text").map(|text| text.text.as_str())
+    }
+
+    /// `<name><text>...</text></name>` and the like.
+    pub fn text_of(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(Self::inner_text)
+    }
+
+    /// Text of a plain `<name>value</name>` child.
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+
+    pub fn flag(&self, name: &str) -> Option<bool> {
+        self.value_of(name)
+            .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true"))
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.children.push(Self {
+            name: TEXT_NODE.to_string(),
+            text: text.to_string(),
+            ..Self::default()
+        });
+    }
+}
+
+/// Parses a whole document into a nameless root node holding the top-level
+/// elements.
+pub fn parse_document(source: &str) -> Result<Node, String> {
+    let mut reader = Reader::from_str(source);
+    let mut stack = vec![Node::default()];
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at byte {}: {e}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref start) | Event::Empty(ref start) => {
+                let mut node = Node {
+                    name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+                    ..Node::default()
+                };
+                for attribute in start.attributes() {
+                    let attribute = attribute.map_err(|e| format!("Invalid XML attribute: {e}"))?;
+                    node.attributes.push((
+                        String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                        attribute
+                            .unescape_value()
+                            .map_err(|e| format!("Invalid XML attribute: {e}"))?
+                            .into_owned(),
+                    ));
+                }
+                if matches!(event, Event::Empty(_)) {
+                    stack
+                        .last_mut()
+                        .expect("Document root is never popped")
+                        .children
+                        .push(node);
+                } else {
+                    stack.push(node);
+                }
+            }
+            Event::End(_) => {
+                if stack.len() < 2 {
+                    return Err("Unbalanced XML".to_string());
+                }
+                let node = stack.pop().expect("Checked above");
+                stack
+                    .last_mut()
+                    .expect("Document root is never popped")
+                    .children
+                    .push(node);
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| format!("Invalid XML text: {e}"))?;
+                stack
+                    .last_mut()
+                    .expect("Document root is never popped")
+                    .push_text(&text);
+            }
+            Event::CData(data) => {
+                stack
+                    .last_mut()
+                    .expect("Document root is never popped")
+                    .push_text(&String::from_utf8_lossy(&data));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if stack.len() != 1 {
+        return Err("Unbalanced XML".to_string());
+    }
+    Ok(stack.pop().expect("Checked above"))
+}
@@ -1,3 +1,8 @@
+pub mod expression;
+pub mod grader;
+pub mod interchange;
 pub mod model;
 pub mod repository;
+pub mod rubric;
 pub mod service;
+pub mod template;
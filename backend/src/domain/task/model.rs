@@ -1,15 +1,27 @@
+use crate::domain::task::grader::{GradeError, TaskGrader, code, grader};
+use crate::domain::task::rubric::RubricCriterion;
+use crate::domain::task::template::{TaskTemplate, TemplateValues, render_text};
 use crate::dto::task::TaskVerdict;
 use crate::errors::LMSError;
+use crate::infrastructure::sandbox::Sandbox;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use unicode_normalization::UnicodeNormalization;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::{ValidationError, ValidationErrors};
 
-#[derive(
-    Serialize, Deserialize, FromRow, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash,
-)]
+/// `{{1}}`, `{{2}}`, ... markers in a `Cloze` text.
+pub static CLOZE_BLANK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{(\d+)\}\}").expect("Cloze blank regex is valid"));
+
+#[derive(Serialize, Deserialize, FromRow, ToSchema, PartialEq, PartialOrd, Clone)]
 pub struct Task {
     pub id: i64,
     pub title: String,
@@ -17,10 +29,30 @@ pub struct Task {
     pub task_type: TaskType,
     pub points: i64,
     pub configuration: TaskConfig,
+    pub template: TaskTemplate,
+    /// Task bank metadata, lowercase and unique.
+    pub tags: Vec<String>,
+    pub difficulty: Option<TaskDifficulty>,
+    /// Who created the task; `None` for tasks older than authorship or whose
+    /// author was deleted.
+    pub author_id: Option<Uuid>,
+    /// Shown to the learner with every automatic verdict.
+    pub explanation: Option<String>,
+}
+
+/// An immutable snapshot of a task, taken on every create/update. Exam
+/// attempts are pinned to the revisions they were started with.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct TaskRevision {
+    pub id: i64,
+    /// 1-based, per task.
+    pub revision: i32,
+    pub created_at: DateTime<Utc>,
+    pub task: Task,
 }
 
 #[derive(
-    Serialize, Deserialize, sqlx::Type, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash,
+    Serialize, Deserialize, sqlx::Type, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug,
 )]
 #[sqlx(type_name = "TASK_TYPE")]
 #[sqlx(rename_all = "snake_case")]
@@ -34,6 +66,23 @@ pub enum TaskType {
     #[serde(rename = "ctfd")]
     #[sqlx(rename = "ctfd")]
     CTFd,
+    Numeric,
+    Matching,
+    Cloze,
+    Code,
+    Expression,
+}
+
+#[derive(
+    Serialize, Deserialize, sqlx::Type, ToSchema, Eq, PartialEq, PartialOrd, Clone, Copy, Debug,
+)]
+#[sqlx(type_name = "TASK_DIFFICULTY")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TaskDifficulty {
+    Easy,
+    Medium,
+    Hard,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
@@ -44,7 +93,7 @@ pub enum PublicTaskConfig {
     },
     MultipleChoice {
         options: Vec<String>,
-        partial_score: bool,
+        scoring: MultipleChoiceScoring,
     },
     ShortText {
         max_chars_count: usize,
@@ -54,150 +103,705 @@ pub enum PublicTaskConfig {
     },
     Ordering {
         items: Vec<String>,
+        partial_score: OrderingPartialScore,
     },
     FileUpload {
         max_size: usize,
+        allowed_types: Vec<String>,
     },
     #[serde(rename = "ctfd")]
     CTFd {
         task_id: usize,
     },
+    Numeric {
+        units: Vec<String>,
+        unit_required: bool,
+        bases: Vec<NumericBase>,
+    },
+    Matching {
+        left: Vec<String>,
+        right: Vec<String>,
+        partial_score: bool,
+    },
+    Cloze {
+        text: String,
+        blanks: Vec<PublicClozeBlank>,
+    },
+    Code {
+        language: CodeLanguage,
+        time_limit_ms: u32,
+        memory_limit_mb: u32,
+    },
+    /// Names of the variables the answer may use.
+    Expression {
+        variables: Vec<String>,
+    },
 }
 
-#[derive(Serialize, Deserialize, ToSchema, Eq, PartialEq, Ord, PartialOrd, Clone, Hash)]
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
 #[serde(tag = "name", rename_all = "snake_case")]
 pub enum TaskConfig {
+    /// `explanations[i]`, if any, tells why `options[i]` is right or wrong;
+    /// an empty string means no explanation for that option.
     SingleChoice {
         options: Vec<String>,
         correct: usize,
         shuffle: bool,
+        #[serde(default)]
+        explanations: Vec<String>,
     },
     MultipleChoice {
         options: Vec<String>,
         correct: Vec<usize>,
         shuffle: bool,
-        partial_score: bool,
+        /// Stored configs from before strategies have `partial_score: bool`
+        /// here instead.
+        #[serde(
+            alias = "partial_score",
+            deserialize_with = "MultipleChoiceScoring::deserialize_legacy"
+        )]
+        scoring: MultipleChoiceScoring,
+        #[serde(default)]
+        explanations: Vec<String>,
     },
     ShortText {
         auto_grade: bool,
         max_chars_count: usize,
         case_sensitive: bool,
         answers: Vec<String>,
+        #[serde(default)]
+        whitespace: WhitespaceMode,
+        #[serde(default)]
+        normalize_unicode: bool,
+        #[serde(default)]
+        max_distance: usize,
+        #[serde(default)]
+        patterns: Vec<AnswerPattern>,
+        /// Comments for anticipated wrong answers, the first match wins.
+        #[serde(default)]
+        feedback: Vec<AnswerFeedback>,
     },
+    /// With a `rubric`, reviewers grade by its levels (see
+    /// `domain::task::rubric`); its best levels must add up to the points.
     LongText {
         max_chars_count: usize,
+        #[serde(default)]
+        rubric: Vec<RubricCriterion>,
     },
     Ordering {
         items: Vec<String>,
         answers: Vec<Vec<usize>>,
+        #[serde(default)]
+        partial_score: OrderingPartialScore,
     },
+    /// `allowed_types` are MIME types (`image/*` covers a whole family) the
+    /// learner may upload; empty allows any. `rubric` is as for `LongText`.
     FileUpload {
         max_size: usize,
+        #[serde(default)]
+        allowed_types: Vec<String>,
+        #[serde(default)]
+        rubric: Vec<RubricCriterion>,
     },
     #[serde(rename = "ctfd")]
-    CTFd {
-        task_id: usize,
+    CTFd { task_id: usize },
+    Numeric {
+        answer: f64,
+        #[serde(default)]
+        absolute_tolerance: f64,
+        #[serde(default)]
+        relative_tolerance: f64,
+        #[serde(default)]
+        units: Vec<String>,
+        #[serde(default)]
+        unit_required: bool,
+        #[serde(default)]
+        bases: Vec<NumericBase>,
+    },
+    /// `correct[i]` is the index in `right` that `left[i]` pairs with. Items of
+    /// `right` no left item points to are distractors.
+    Matching {
+        left: Vec<String>,
+        right: Vec<String>,
+        correct: Vec<usize>,
+        partial_score: bool,
+    },
+    /// `text` references blanks as `{{1}}`, `{{2}}`, ... (1-based, each exactly
+    /// once); `blanks[0]` describes `{{1}}` and so on.
+    Cloze {
+        text: String,
+        blanks: Vec<ClozeBlank>,
+        case_sensitive: bool,
+    },
+    /// The submitted program is run against every one of the hidden `tests`
+    /// in the sandbox (see `infrastructure::sandbox`), each with the same
+    /// limits. That happens in the background: the verdict is `OnReview`
+    /// until the runs are over.
+    Code {
+        language: CodeLanguage,
+        tests: Vec<CodeTestCase>,
+        time_limit_ms: u32,
+        memory_limit_mb: u32,
+    },
+    /// `answer` is a math expression (see `domain::task::expression`) over
+    /// `variables`; any equivalent one is accepted. `tolerance` is relative to
+    /// the reference's value, and absolute for values below 1.
+    Expression {
+        answer: String,
+        #[serde(default)]
+        variables: Vec<ExpressionVariable>,
+        #[serde(default = "TaskConfig::default_expression_tolerance")]
+        tolerance: f64,
     },
 }
 
-pub struct TaskConfigStruct {
-    pub configuration: TaskConfig,
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
+pub struct ClozeBlank {
+    /// Accepted answers. For a dropdown blank they must be among `options`.
+    pub answers: Vec<String>,
+    /// Non-empty turns the blank into a dropdown with these options.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Relative weight of the blank in the task's points.
+    #[serde(default = "ClozeBlank::default_weight")]
+    pub weight: f64,
 }
 
-impl TaskConfig {
-    pub fn validate(&self) -> Result<(), ValidationErrors> {
-        let mut errors = ValidationErrors::new();
+impl ClozeBlank {
+    const fn default_weight() -> f64 {
+        1f64
+    }
+}
 
-        match self {
-            Self::SingleChoice {
-                options, correct, ..
-            } => {
-                if options.is_empty() {
-                    let mut error = ValidationError::new("empty_options");
-                    error.message = Some("Options must not be empty".into());
-                    errors.add("options", error);
-                }
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeLanguage {
+    /// Python 3, run as `python3 main.py`.
+    Python,
+    /// C11, compiled with `cc` (and linked with `-lm`) before the tests.
+    C,
+    /// Run as `bash main.sh`.
+    Bash,
+}
 
-                if options.iter().any(String::is_empty) {
-                    let mut error = ValidationError::new("empty_option");
-                    error.message = Some("Option value must not be empty".into());
-                    errors.add("options", error);
-                }
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
+pub struct CodeTestCase {
+    #[serde(default)]
+    pub stdin: String,
+    /// Compared ignoring trailing whitespace of every line and trailing
+    /// empty lines.
+    pub expected_stdout: String,
+    /// Relative weight of the test in the task's points.
+    #[serde(default = "CodeTestCase::default_weight")]
+    pub weight: f64,
+}
+
+impl CodeTestCase {
+    const fn default_weight() -> f64 {
+        1f64
+    }
+}
+
+/// A variable of an `Expression` task, which answers are compared over for
+/// values from `min` to `max`.
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone, Debug)]
+pub struct ExpressionVariable {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    /// Only integer values, for expressions like `2^k mod n`.
+    #[serde(default)]
+    pub integer: bool,
+}
+
+/// What a learner sees of a [`ClozeBlank`].
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct PublicClozeBlank {
+    /// Dropdown options, empty for a free-text blank.
+    pub options: Vec<String>,
+    pub weight: f64,
+}
+
+impl From<ClozeBlank> for PublicClozeBlank {
+    fn from(blank: ClozeBlank) -> Self {
+        Self {
+            options: blank.options,
+            weight: blank.weight,
+        }
+    }
+}
+
+/// Blank numbers referenced in a `Cloze` text, in order of appearance.
+/// Numbers too large to parse come back as `0`, which is never valid.
+pub fn cloze_blank_numbers(text: &str) -> Vec<usize> {
+    CLOZE_BLANK
+        .captures_iter(text)
+        .map(|captures| captures[1].parse().unwrap_or(0))
+        .collect()
+}
+
+/// Upper bound for a single option explanation of a choice task.
+const EXPLANATION_MAX_LEN: usize = 2000;
+
+pub(crate) fn validate_explanations(
+    options: &[String],
+    explanations: &[String],
+    errors: &mut ValidationErrors,
+) {
+    if !explanations.is_empty() && explanations.len() != options.len() {
+        let mut error = ValidationError::new("invalid_explanations");
+        error.message = Some("Explanations must be empty or one per option".into());
+        errors.add("explanations", error);
+    }
+    if explanations
+        .iter()
+        .any(|explanation| explanation.chars().count() > EXPLANATION_MAX_LEN)
+    {
+        let mut error = ValidationError::new("explanation_too_long");
+        error.message =
+            Some(format!("Explanation must be at most {EXPLANATION_MAX_LEN} characters").into());
+        errors.add("explanations", error);
+    }
+}
+
+/// How an `Ordering` answer that matches none of the accepted orders is scored.
+/// Partial strategies compare against the closest accepted order.
+#[derive(
+    Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderingPartialScore {
+    /// Anything but an exact match scores zero.
+    #[default]
+    Off,
+    /// Share of items in the longest run (not necessarily adjacent) that
+    /// keeps the correct relative order, not counting its first item.
+    LongestSubsequence,
+    /// Share of item pairs placed in the correct relative order
+    /// (normalized Kendall tau distance).
+    KendallTau,
+}
 
-                if *correct >= options.len() {
-                    let mut error = ValidationError::new("invalid_correct_index");
-                    error.message = Some("Invalid index specified for correct answer".into());
-                    errors.add("correct", error);
+impl OrderingPartialScore {
+    /// Similarity in `[0, 1]` of an order given as `ranks` - the position of
+    /// every answered item in the accepted order.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn similarity(self, ranks: &[usize]) -> f64 {
+        let n = ranks.len();
+        if n == 0 {
+            return 0f64;
+        }
+        match self {
+            Self::Off => 0f64,
+            Self::LongestSubsequence => {
+                // patience sorting: tails[k] is the smallest tail of an increasing run of k + 1
+                let mut tails: Vec<usize> = Vec::with_capacity(n);
+                for &rank in ranks {
+                    let position = tails.partition_point(|&tail| tail < rank);
+                    if position == tails.len() {
+                        tails.push(rank);
+                    } else {
+                        tails[position] = rank;
+                    }
+                }
+                // a single item is always "in order", so a reversed answer scores zero
+                if n == 1 {
+                    1f64
+                } else {
+                    (tails.len() - 1) as f64 / (n - 1) as f64
                 }
             }
-            Self::MultipleChoice {
-                options, correct, ..
-            } => {
-                if options.is_empty() {
-                    let mut error = ValidationError::new("empty_options");
-                    error.message = Some("Options must not be empty".into());
-                    errors.add("options", error);
+            Self::KendallTau => {
+                if n == 1 {
+                    return 1f64;
                 }
+                let pairs = n * (n - 1) / 2;
+                let discordant = (0..n)
+                    .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                    .filter(|&(i, j)| ranks[i] > ranks[j])
+                    .count();
+                (pairs - discordant) as f64 / pairs as f64
+            }
+        }
+    }
+}
 
-                if options.iter().any(String::is_empty) {
-                    let mut error = ValidationError::new("empty_option");
-                    error.message = Some("Option value must not be empty".into());
-                    errors.add("options", error);
-                }
+/// Whether a `FileUpload` task with `allowed_types` accepts a file of
+/// `content_type`. Parameters (`; charset=...`) and case are ignored.
+pub fn content_type_allowed(allowed_types: &[String], content_type: &str) -> bool {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    allowed_types.is_empty()
+        || allowed_types.iter().any(|allowed| {
+            let allowed = allowed.to_lowercase();
+            allowed
+                .strip_suffix('*')
+                .map_or(allowed == content_type, |prefix| {
+                    prefix.ends_with('/') && content_type.starts_with(prefix)
+                })
+        })
+}
 
-                if (*correct).iter().any(|&x| x >= options.len()) {
-                    let mut error = ValidationError::new("invalid_correct_index");
-                    error.message = Some("Invalid index specified for correct answer".into());
-                    errors.add("correct", error);
-                }
+/// How a `MultipleChoice` answer other than the exact set of correct options
+/// is scored. Each strategy gives a share of the task's points, at most 1.
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone, Copy, Debug, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum MultipleChoiceScoring {
+    /// Anything but the exact set of correct options scores zero.
+    #[default]
+    AllOrNothing,
+    /// Picked correct options minus picked wrong ones, over the number of
+    /// correct options, floored at zero. Missed options cost nothing.
+    CorrectMinusIncorrect,
+    /// Every option is a true/false statement: the share of options whose
+    /// checkbox is in the right state, missed ones included.
+    PerOption,
+    /// `1 / |correct|` per picked correct option, minus `penalty` per picked
+    /// wrong one. Not floored, so a task can score below zero; only the
+    /// attempt total is (see [`ScoringData::total_score`]).
+    ///
+    /// [`ScoringData::total_score`]: crate::dto::exam::ScoringData::total_score
+    NegativeMarking { penalty: f64 },
+}
+
+impl MultipleChoiceScoring {
+    /// Upper bound for the negative marking penalty, a whole task's points.
+    pub(crate) const MAX_PENALTY: f64 = 1f64;
+
+    /// Share of the points for an answer that isn't exactly right: out of
+    /// `options`, `correct` are correct; the answer picks `hits` of them and
+    /// `wrong` other ones.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn share(self, options: usize, correct: usize, hits: usize, wrong: usize) -> f64 {
+        let gained = if correct == 0 {
+            0f64
+        } else {
+            hits as f64 / correct as f64
+        };
+        match self {
+            Self::AllOrNothing => 0f64,
+            Self::CorrectMinusIncorrect if correct == 0 => 0f64,
+            Self::CorrectMinusIncorrect => (gained - wrong as f64 / correct as f64).max(0f64),
+            Self::PerOption if options == 0 => 0f64,
+            Self::PerOption => {
+                let wrong_states = (correct - hits) + wrong;
+                options.saturating_sub(wrong_states) as f64 / options as f64
+            }
+            Self::NegativeMarking { penalty } => penalty.mul_add(-(wrong as f64), gained),
+        }
+    }
+
+    /// Reads either a strategy or the legacy `partial_score` flag: `true` was
+    /// [`Self::CorrectMinusIncorrect`], `false` [`Self::AllOrNothing`].
+    fn deserialize_legacy<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Legacy(bool),
+            Strategy(MultipleChoiceScoring),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Legacy(true) => Self::CorrectMinusIncorrect,
+            Repr::Legacy(false) => Self::AllOrNothing,
+            Repr::Strategy(scoring) => scoring,
+        })
+    }
+}
+
+/// How whitespace in a `ShortText` answer is treated before matching.
+#[derive(
+    Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WhitespaceMode {
+    /// Only leading and trailing whitespace is dropped.
+    #[default]
+    Trim,
+    /// Every run of whitespace becomes a single space.
+    Collapse,
+    /// All whitespace is dropped (`flag{ a b }` == `flag{ab}`).
+    Remove,
+}
+
+/// An extra accepted `ShortText` answer with its own share of the points.
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
+pub struct AnswerPattern {
+    pub value: String,
+    /// `value` is a regex that has to match the whole (normalized) answer.
+    #[serde(default)]
+    pub regex: bool,
+    /// Share of the task's points awarded on a match, in `(0, 1]`.
+    #[serde(default = "AnswerPattern::full_share")]
+    pub score: f64,
+}
+
+impl AnswerPattern {
+    const fn full_share() -> f64 {
+        1f64
+    }
+}
+
+/// A comment for a `ShortText` answer that isn't fully correct, matched the
+/// same way as an [`AnswerPattern`].
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Clone)]
+pub struct AnswerFeedback {
+    pub value: String,
+    #[serde(default)]
+    pub regex: bool,
+    pub comment: String,
+}
+
+/// Longest regex source accepted in an [`AnswerPattern`].
+const ANSWER_REGEX_MAX_LEN: usize = 500;
+/// Compiled program size cap for answer regexes (the `regex` crate runs in
+/// linear time, so bounding the program is enough to bound grading cost).
+const ANSWER_REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+/// How many compiled answer regexes are kept per case mode; the cache is
+/// dropped as a whole once it fills up.
+const ANSWER_REGEX_CACHE_SIZE: usize = 1024;
+
+/// Compiled answer regexes by source, case-insensitive ones first.
+static ANSWER_REGEXES: LazyLock<Mutex<[HashMap<String, Regex>; 2]>> = LazyLock::new(Mutex::default);
+
+/// Compiles an [`AnswerPattern`] regex anchored to the whole answer, within
+/// the size/complexity caps. Compiled regexes are cached, since the same
+/// patterns are checked against every answer to the task.
+pub fn compile_answer_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, String> {
+    let cache = || {
+        ANSWER_REGEXES
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    };
+    let slot = usize::from(case_sensitive);
+    if let Some(regex) = cache()[slot].get(pattern) {
+        return Ok(regex.clone());
+    }
+    if pattern.len() > ANSWER_REGEX_MAX_LEN {
+        return Err(format!(
+            "Pattern is longer than allowed ({ANSWER_REGEX_MAX_LEN})"
+        ));
+    }
+    let regex = RegexBuilder::new(&format!("^(?:{pattern})$"))
+        .case_insensitive(!case_sensitive)
+        .size_limit(ANSWER_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(ANSWER_REGEX_SIZE_LIMIT)
+        .nest_limit(32)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let regexes = &mut cache()[slot];
+    if regexes.len() >= ANSWER_REGEX_CACHE_SIZE {
+        regexes.clear();
+    }
+    regexes.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// Brings a `ShortText` answer (or a reference answer) to the form it's
+/// compared in. Case is handled by the caller, since regexes fold it
+/// themselves.
+pub fn normalize_short_text(value: &str, whitespace: WhitespaceMode, nfkc: bool) -> String {
+    let value: String = if nfkc {
+        value.nfkc().collect()
+    } else {
+        value.to_string()
+    };
+    match whitespace {
+        WhitespaceMode::Trim => value.trim().to_string(),
+        WhitespaceMode::Collapse => value.split_whitespace().collect::<Vec<_>>().join(" "),
+        WhitespaceMode::Remove => value.chars().filter(|c| !c.is_whitespace()).collect(),
+    }
+}
+
+/// Non-decimal notations a `Numeric` answer may additionally be written in.
+/// Decimal is always accepted.
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericBase {
+    /// `0b101010`
+    Binary,
+    /// `0o52`
+    Octal,
+    /// `0x2a`
+    Hex,
+}
+
+impl NumericBase {
+    const fn prefix(self) -> &'static str {
+        match self {
+            Self::Binary => "0b",
+            Self::Octal => "0o",
+            Self::Hex => "0x",
+        }
+    }
+
+    const fn radix(self) -> u32 {
+        match self {
+            Self::Binary => 2,
+            Self::Octal => 8,
+            Self::Hex => 16,
+        }
+    }
+}
+
+/// Parses a learner's `Numeric` answer into a value.
+///
+/// Strips one of the accepted `units` from the end (case-sensitive, so `ms`
+/// and `Ms` stay different), then reads the number either as a prefixed
+/// integer in one of the allowed `bases` or as a decimal. A comma is taken as
+/// the decimal separator only when it's the only separator and can't be read
+/// as thousands grouping, so `1,5` is accepted while `1,000` and `1,2,3` are
+/// not. Returns why the input isn't a number in an accepted format otherwise.
+#[allow(clippy::cast_precision_loss)]
+pub fn parse_numeric_answer(
+    input: &str,
+    units: &[String],
+    unit_required: bool,
+    bases: &[NumericBase],
+) -> Result<f64, String> {
+    let not_a_number = || "Your answer is not a number in an accepted format".to_string();
+    let mut value = input.trim();
+    let unit = units
+        .iter()
+        .filter(|unit| !unit.is_empty() && value.ends_with(unit.as_str()))
+        .max_by_key(|unit| unit.len());
+    match unit {
+        Some(unit) => value = value[..value.len() - unit.len()].trim_end(),
+        None if unit_required => return Err("Your answer must have a unit".to_string()),
+        None => {}
+    }
+
+    let (negative, digits) = value.strip_prefix('-').map_or_else(
+        || (false, value.strip_prefix('+').unwrap_or(value)),
+        |rest| (true, rest),
+    );
+    let lowered = digits.to_ascii_lowercase();
+    for base in bases {
+        if let Some(rest) = lowered.strip_prefix(base.prefix()) {
+            let parsed = u128::from_str_radix(&rest.replace('_', ""), base.radix())
+                .map_err(|_| not_a_number())? as f64;
+            return Ok(if negative { -parsed } else { parsed });
+        }
+    }
+
+    let decimal = match digits.split_once(',') {
+        None => value.to_string(),
+        Some((whole, fraction)) => {
+            let grouped = fraction.len() == 3 && !whole.trim_start_matches('0').is_empty();
+            if fraction.contains(',') || digits.contains('.') || grouped {
+                return Err(
+                    "Use a comma only as the decimal separator, without grouping digits"
+                        .to_string(),
+                );
+            }
+            value.replacen(',', ".", 1)
+        }
+    };
+    decimal
+        .parse::<f64>()
+        .ok()
+        .filter(|parsed| parsed.is_finite())
+        .ok_or_else(not_a_number)
+}
+
+pub struct TaskConfigStruct {
+    pub configuration: TaskConfig,
+}
+
+impl TaskConfig {
+    const fn default_expression_tolerance() -> f64 {
+        1e-9
+    }
+
+    pub const fn task_type(&self) -> TaskType {
+        match self {
+            Self::SingleChoice { .. } => TaskType::SingleChoice,
+            Self::MultipleChoice { .. } => TaskType::MultipleChoice,
+            Self::ShortText { .. } => TaskType::ShortText,
+            Self::LongText { .. } => TaskType::LongText,
+            Self::Ordering { .. } => TaskType::Ordering,
+            Self::FileUpload { .. } => TaskType::FileUpload,
+            Self::CTFd { .. } => TaskType::CTFd,
+            Self::Numeric { .. } => TaskType::Numeric,
+            Self::Matching { .. } => TaskType::Matching,
+            Self::Cloze { .. } => TaskType::Cloze,
+            Self::Code { .. } => TaskType::Code,
+            Self::Expression { .. } => TaskType::Expression,
+        }
+    }
+
+    /// Review rubric of a manually graded task; empty when it has none.
+    pub fn rubric(&self) -> &[RubricCriterion] {
+        match self {
+            Self::LongText { rubric, .. } | Self::FileUpload { rubric, .. } => rubric,
+            _ => &[],
+        }
+    }
+
+    /// Shuffles everything a learner is meant to see in random order: options
+    /// with `shuffle` on, `Ordering` items, the right side of `Matching` and
+    /// `Cloze` dropdowns.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        match self {
+            Self::SingleChoice {
+                options,
+                shuffle,
+                explanations,
+                ..
             }
-            Self::ShortText {
-                auto_grade,
-                max_chars_count,
-                answers,
+            | Self::MultipleChoice {
+                options,
+                shuffle,
+                explanations,
                 ..
-            } => {
-                if *auto_grade && answers.is_empty() {
-                    let mut error = ValidationError::new("empty_options");
-                    error.message = Some("Options must not be empty if auto_grade is on".into());
-                    errors.add("answers", error);
-                }
-                if *max_chars_count == 0 || *max_chars_count > 500 {
-                    let mut error = ValidationError::new("invalid_max_chars_count");
-                    error.message = Some("Maximum chars count (500) exceeded".into());
-                    errors.add("max_chars_count", error);
+            } if *shuffle => {
+                if explanations.is_empty() {
+                    options.shuffle(rng);
+                } else {
+                    // same swaps as shuffling `options` alone, so seeds keep their layouts
+                    let mut pairs: Vec<(String, String)> =
+                        options.drain(..).zip(explanations.drain(..)).collect();
+                    pairs.shuffle(rng);
+                    (*options, *explanations) = pairs.into_iter().unzip();
                 }
             }
-            Self::LongText { max_chars_count } => {
-                if *max_chars_count == 0 || *max_chars_count > 5000 {
-                    let mut error = ValidationError::new("invalid_max_chars_count");
-                    error.message = Some("Maximum chars count (5000) exceeded".into());
-                    errors.add("max_chars_count", error);
-                }
+            Self::Ordering { items, .. } => {
+                items.shuffle(rng);
             }
-            // checking if order answers belong in items vec & all items are included in ordering
-            Self::Ordering { items, answers } => {
-                if answers.iter().any(|answer| {
-                    // Check if any position is out of bounds
-                    answer.iter().any(|&position| position >= items.len())
-                        // Or if the answer does not contain all items (duplicates or missing)
-                        || answer.iter().collect::<HashSet<_>>().len() != items.len()
-                }) {
-                    let mut error = ValidationError::new("invalid_ordering");
-                    error.message = Some("Invalid order index specified for answer".into());
-                    errors.add("answers", error);
-                }
+            Self::Matching { right, .. } => {
+                right.shuffle(rng);
             }
-            Self::FileUpload { max_size } => {
-                if *max_size == 0 || *max_size > 10 * 1024 * 1024 {
-                    let mut error = ValidationError::new("invalid_file_upload");
-                    error.message =
-                        Some("File max size must be less than or equals to 10 MB".into());
-                    errors.add("max_size", error);
+            Self::Cloze { blanks, .. } => {
+                for blank in blanks {
+                    blank.options.shuffle(rng);
                 }
             }
-            Self::CTFd { .. } => {} // TODO: go to CTFd to check task
+            _ => {}
         }
+    }
+
+    /// The grader of the configuration's type.
+    pub fn grader(&self) -> &'static dyn TaskGrader {
+        grader(&self.task_type())
+    }
+
+    /// Whether answers are graded without a reviewer.
+    pub fn is_auto_gradable(&self) -> bool {
+        self.grader().is_auto_gradable(self)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        self.grader().validate_config(self, &mut errors);
 
         if errors.is_empty() {
             Ok(())
@@ -208,185 +812,103 @@ impl TaskConfig {
 }
 
 impl Task {
+    /// The task as the learner with the given seed (their attempt's, or the
+    /// one derived from their id for practice) gets it: template values filled
+    /// into the texts and the configuration. Values the rendered task isn't
+    /// valid with are drawn again, see [`TaskTemplate::draw`]. Tasks without a
+    /// template come back unchanged.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn instantiate(self, seed: i64) -> crate::errors::Result<Self> {
+        if self.template.is_empty() {
+            return Ok(self);
+        }
+        self.template
+            .draw((seed ^ self.id) as u64, |values| self.render(values))
+            .map_err(|e| LMSError::ServerError(format!("Template of task {} failed: {e}", self.id)))
+    }
+
+    fn render(&self, values: &TemplateValues) -> Result<Self, String> {
+        let configuration = self.template.apply(&self.configuration, values)?;
+        if std::mem::discriminant(&configuration) != std::mem::discriminant(&self.configuration) {
+            return Err("A template can't change the kind of the task".to_string());
+        }
+        configuration
+            .validate()
+            .map_err(|e| format!("The rendered configuration is invalid: {e}"))?;
+        Ok(Self {
+            title: render_text(&self.title, values)?,
+            description: self
+                .description
+                .as_deref()
+                .map(|description| render_text(description, values))
+                .transpose()?,
+            explanation: self
+                .explanation
+                .as_deref()
+                .map(|explanation| render_text(explanation, values))
+                .transpose()?,
+            configuration,
+            ..self.clone()
+        })
+    }
+
     /// Checks that the provided answer matches this task's type and satisfies
     /// basic constraints (e.g. text length). Does not perform any external
     /// checks (like `CTFd` solve status) - those stay in the caller.
     pub fn validate_answer(&self, answer: &TaskAnswer) -> crate::errors::Result<()> {
-        match (&self.configuration, answer) {
-            (
-                TaskConfig::ShortText {
-                    max_chars_count, ..
-                },
-                TaskAnswer::ShortText { answer },
-            )
-            | (TaskConfig::LongText { max_chars_count }, TaskAnswer::LongText { answer }) => {
-                if answer.len() > *max_chars_count {
-                    return Err(LMSError::ShitHappened(format!(
-                        "Your answer length is more than allowed ({max_chars_count})"
-                    )));
-                }
-                Ok(())
-            }
-            (TaskConfig::SingleChoice { .. }, TaskAnswer::SingleChoice { .. })
-            | (TaskConfig::MultipleChoice { .. }, TaskAnswer::MultipleChoice { .. })
-            | (TaskConfig::Ordering { .. }, TaskAnswer::Ordering { .. })
-            | (TaskConfig::FileUpload { .. }, TaskAnswer::FileUpload { .. })
-            | (TaskConfig::CTFd { .. }, TaskAnswer::CTFd) => Ok(()),
-            _ => Err(LMSError::ShitHappened(
-                "You've sent an answer for another task type".to_string(),
-            )),
-        }
+        Ok(self
+            .configuration
+            .grader()
+            .validate_answer(&self.configuration, answer)?)
     }
 
     /// Grades an answer against this task's configuration and returns a verdict.
     ///
     /// Types that need manual review (`LongText`, `FileUpload`, non-`auto_grade`
-    /// `ShortText`) return [`TaskVerdict::OnReview`]. Callers must ensure the
-    /// answer variant matches the task type (see [`Task::validate_answer`]);
-    /// a mismatched pair is a programming error and panics.
+    /// `ShortText`) return [`TaskVerdict::OnReview`], and so does `Code`,
+    /// whose tests are run by [`Self::run_code`]. An answer of another type is
+    /// a [`GradeError::AnswerMismatch`].
+    ///
+    /// Automatic verdicts carry what the learner should read with them as the
+    /// comment: explanations of the options involved, the comment for an
+    /// anticipated wrong `ShortText` answer and the task's own explanation.
     #[allow(clippy::cast_precision_loss)]
-    #[allow(clippy::too_many_lines)]
-    pub fn grade(&self, answer: &TaskAnswer) -> TaskVerdict {
-        let points = self.points as f64;
-        match (answer, &self.configuration) {
-            (
-                TaskAnswer::SingleChoice { answer },
-                TaskConfig::SingleChoice {
-                    options, correct, ..
-                },
-            ) => {
-                if *answer == options[*correct] {
-                    TaskVerdict::FullScore {
-                        comment: None,
-                        score: points,
-                        max_score: points,
-                    }
-                } else {
-                    TaskVerdict::Incorrect {
-                        comment: None,
-                        score: 0f64,
-                        max_score: points,
-                    }
-                }
-            }
-            (
-                TaskAnswer::MultipleChoice { answers },
-                TaskConfig::MultipleChoice {
-                    options,
-                    correct,
-                    partial_score,
-                    ..
-                },
-            ) => {
-                let correct_answers: HashSet<_> = correct.iter().map(|&i| &options[i]).collect();
-                let user_answers: HashSet<_> = answers.iter().collect();
-
-                if user_answers == correct_answers {
-                    return TaskVerdict::FullScore {
-                        comment: None,
-                        score: points,
-                        max_score: points,
-                    };
-                }
-                if !partial_score {
-                    return TaskVerdict::Incorrect {
-                        comment: None,
-                        score: 0f64,
-                        max_score: points,
-                    };
-                }
+    pub fn grade(&self, answer: &TaskAnswer) -> Result<TaskVerdict, GradeError> {
+        let grader = self.configuration.grader();
+        let verdict = grader.grade(&self.configuration, answer, self.points as f64)?;
+        let full_score = matches!(verdict, TaskVerdict::FullScore { .. });
+        let feedback = grader.feedback(&self.configuration, answer, full_score);
+        Ok(self.with_feedback(verdict, feedback))
+    }
 
-                let correct_count = correct_answers.intersection(&user_answers).count() as f64;
-                let incorrect_count = user_answers.difference(&correct_answers).count() as f64;
-                // punish for wrong answers, not for missing
-                let score_multiplier =
-                    (correct_count - incorrect_count) / correct_answers.len() as f64;
-                if score_multiplier <= 0f64 {
-                    return TaskVerdict::Incorrect {
-                        comment: None,
-                        score: 0f64,
-                        max_score: points,
-                    };
-                }
+    /// Runs the tests of a `Code` task against the answer in the sandbox and
+    /// grades it by the ones it passes. Takes as long as the runs do.
+    #[allow(clippy::cast_precision_loss)]
+    pub async fn run_code(
+        &self,
+        sandbox: &Sandbox,
+        answer: &TaskAnswer,
+    ) -> crate::errors::Result<TaskVerdict> {
+        let TaskAnswer::Code { source } = answer else {
+            return Err(GradeError::AnswerMismatch.into());
+        };
+        let (verdict, breakdown) =
+            code::run_tests(sandbox, &self.configuration, source, self.points as f64).await?;
+        Ok(self.with_feedback(verdict, vec![&breakdown]))
+    }
 
-                TaskVerdict::PartialScore {
-                    score: points * score_multiplier,
-                    comment: None,
-                    max_score: points,
-                }
-            }
-            (
-                TaskAnswer::ShortText { answer },
-                TaskConfig::ShortText {
-                    answers,
-                    auto_grade,
-                    case_sensitive,
-                    ..
-                },
-            ) => {
-                if !auto_grade {
-                    return TaskVerdict::OnReview;
-                }
-                let matched = if *case_sensitive {
-                    answers.contains(&answer.trim().to_string())
-                } else {
-                    let answer = answer.trim().to_lowercase();
-                    answers
-                        .iter()
-                        .map(|x| x.to_lowercase())
-                        .any(|x| x == answer)
-                };
-                if matched {
-                    TaskVerdict::FullScore {
-                        comment: None,
-                        score: points,
-                        max_score: points,
-                    }
-                } else {
-                    TaskVerdict::Incorrect {
-                        comment: None,
-                        score: 0f64,
-                        max_score: points,
-                    }
-                }
-            }
-            (TaskAnswer::Ordering { answer }, TaskConfig::Ordering { items, answers }) => {
-                let precomputed_answers: Vec<Vec<String>> = answers
-                    .iter()
-                    .map(|correct| correct.iter().map(|&i| items[i].clone()).collect())
-                    .collect();
-                if precomputed_answers
-                    .iter()
-                    .any(|precomputed| precomputed == answer)
-                {
-                    TaskVerdict::FullScore {
-                        comment: None,
-                        score: points,
-                        max_score: points,
-                    }
-                } else {
-                    TaskVerdict::Incorrect {
-                        comment: None,
-                        score: 0f64,
-                        max_score: points,
-                    }
-                }
-            }
-            (TaskAnswer::LongText { .. }, TaskConfig::LongText { .. })
-            | (TaskAnswer::FileUpload { .. }, TaskConfig::FileUpload { .. }) => {
-                TaskVerdict::OnReview
-            }
-            (TaskAnswer::CTFd, TaskConfig::CTFd { .. }) => {
-                // if answer exists then task is solved
-                TaskVerdict::FullScore {
-                    comment: None,
-                    score: points,
-                    max_score: points,
-                }
-            }
-            // such cases (when TaskConfig type != TaskAnswer type) just shouldn't
-            // exist due to checks in validate_answer / modify_attempt
-            _ => unreachable!(),
+    /// Joins `feedback` and the task's explanation into the comment of an
+    /// automatic verdict.
+    fn with_feedback(&self, verdict: TaskVerdict, feedback: Vec<&str>) -> TaskVerdict {
+        let comment: Vec<&str> = feedback
+            .into_iter()
+            .chain(self.explanation.as_deref())
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+        if comment.is_empty() {
+            verdict
+        } else {
+            verdict.with_comment(comment.join("\n\n"))
         }
     }
 
@@ -395,47 +917,7 @@ impl Task {
     /// (`LongText`, `FileUpload`), non-`auto_grade` `ShortText`, and `CTFd`
     /// return `None`.
     pub fn solution(&self) -> Option<TaskSolution> {
-        match &self.configuration {
-            TaskConfig::SingleChoice {
-                options, correct, ..
-            } => options
-                .get(*correct)
-                .map(|label| TaskSolution::SingleChoice {
-                    answer: label.clone(),
-                }),
-            TaskConfig::MultipleChoice {
-                options, correct, ..
-            } => Some(TaskSolution::MultipleChoice {
-                answers: correct
-                    .iter()
-                    .filter_map(|&i| options.get(i).cloned())
-                    .collect(),
-            }),
-            TaskConfig::ShortText {
-                auto_grade,
-                answers,
-                ..
-            } => {
-                if *auto_grade && !answers.is_empty() {
-                    Some(TaskSolution::ShortText {
-                        answers: answers.clone(),
-                    })
-                } else {
-                    None
-                }
-            }
-            TaskConfig::Ordering { items, answers } => {
-                answers.first().map(|order| TaskSolution::Ordering {
-                    answer: order
-                        .iter()
-                        .filter_map(|&i| items.get(i).cloned())
-                        .collect(),
-                })
-            }
-            TaskConfig::LongText { .. }
-            | TaskConfig::FileUpload { .. }
-            | TaskConfig::CTFd { .. } => None,
-        }
+        self.configuration.grader().solution(&self.configuration)
     }
 }
 
@@ -452,6 +934,10 @@ pub enum TaskSolution {
     MultipleChoice { answers: Vec<String> },
     ShortText { answers: Vec<String> },
     Ordering { answer: Vec<String> },
+    Numeric { answer: f64, unit: Option<String> },
+    Matching { answer: HashMap<String, String> },
+    Cloze { answers: Vec<String> },
+    Expression { answer: String },
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
@@ -477,6 +963,23 @@ pub enum TaskAnswer {
     },
     #[serde(rename = "ctfd")]
     CTFd,
+    Numeric {
+        answer: String,
+    },
+    /// Left item -> right item it's paired with.
+    Matching {
+        answer: HashMap<String, String>,
+    },
+    /// One entry per blank, in blank order; `""` leaves a blank unfilled.
+    Cloze {
+        answers: Vec<String>,
+    },
+    Code {
+        source: String,
+    },
+    Expression {
+        answer: String,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -1,19 +1,33 @@
 use crate::domain::exam::model::Exam;
-use crate::domain::task::model::Task;
-use crate::dto::task::UpsertTaskRequestDTO;
+use crate::domain::task::model::{Task, TaskRevision};
+use crate::dto::task::{TaskFilterDTO, UpsertTaskRequestDTO};
 use crate::errors::Result;
 use crate::gen_openapi::DummyRepository;
 use async_trait::async_trait;
 use impl_unimplemented::impl_unimplemented;
+use uuid::Uuid;
 
 #[impl_unimplemented(DummyRepository)]
 #[async_trait]
 pub trait TaskRepository {
-    async fn create(&self, task_data: UpsertTaskRequestDTO) -> Result<Task>;
+    async fn create(&self, task_data: UpsertTaskRequestDTO, author_id: Uuid) -> Result<Task>;
+    /// Creates all of the tasks, in order, or none of them.
+    async fn create_many(
+        &self,
+        tasks: Vec<UpsertTaskRequestDTO>,
+        author_id: Uuid,
+    ) -> Result<Vec<Task>>;
     async fn get_task(&self, id: i32) -> Result<Task>;
+    /// Tasks with the given ids in the order of `ids`; unknown ids are skipped.
+    async fn get_tasks_by_ids(&self, ids: &[i32]) -> Result<Vec<Task>>;
     async fn get_exams(&self, id: i32) -> Result<Vec<Exam>>;
     async fn get_topic_tasks(&self) -> Result<Vec<Task>>;
-    async fn get_tasks(&self, limit: i32, offset: i32) -> Result<Vec<Task>>;
+    /// A page of tasks matching `filter`, best full-text matches first.
+    async fn get_tasks(&self, filter: &TaskFilterDTO) -> Result<Vec<Task>>;
+    /// Number of tasks matching `filter`, ignoring limit/offset.
+    async fn count_tasks(&self, filter: &TaskFilterDTO) -> Result<i64>;
     async fn delete_task(&self, id: i32) -> Result<()>;
     async fn update_task(&self, task_id: i32, task_data: UpsertTaskRequestDTO) -> Result<Task>;
+    /// All revisions of a task, oldest first.
+    async fn get_task_revisions(&self, task_id: i32) -> Result<Vec<TaskRevision>>;
 }
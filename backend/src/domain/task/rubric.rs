@@ -0,0 +1,292 @@
+//! Rubrics for manually reviewed tasks (`LongText`, `FileUpload`).
+//!
+//! A rubric is a list of criteria with fixed levels, so that every reviewer
+//! grades the same way: a reviewer picks one level per criterion, and the
+//! verdict and its comment are built from the picks.
+
+use crate::dto::task::TaskVerdict;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use utoipa::ToSchema;
+use validator::{ValidationError, ValidationErrors};
+
+/// Upper bound for the number of criteria in a rubric.
+const MAX_CRITERIA: usize = 20;
+/// Upper bound for the number of levels of a criterion.
+const MAX_LEVELS: usize = 10;
+/// Upper bound for criterion and level titles.
+const TITLE_MAX_LEN: usize = 200;
+/// Upper bound for criterion and level descriptions.
+const DESCRIPTION_MAX_LEN: usize = 2000;
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
+pub struct RubricCriterion {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// The reviewer picks exactly one of them.
+    pub levels: Vec<RubricLevel>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, PartialOrd, Clone)]
+pub struct RubricLevel {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub points: f64,
+}
+
+/// A criterion of a filled-in rubric, with the level the reviewer picked.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct RubricGrade {
+    pub criterion: String,
+    pub level: String,
+    pub description: String,
+    pub points: f64,
+    pub max_points: f64,
+}
+
+impl RubricCriterion {
+    fn max_points(&self) -> f64 {
+        self.levels
+            .iter()
+            .map(|level| level.points)
+            .fold(0f64, f64::max)
+    }
+}
+
+/// Points of a rubric where every criterion gets its best level.
+pub fn max_points(rubric: &[RubricCriterion]) -> f64 {
+    rubric.iter().map(RubricCriterion::max_points).sum()
+}
+
+pub fn validate_rubric(rubric: &[RubricCriterion], errors: &mut ValidationErrors) {
+    let mut fail = |code: &'static str, message: String| {
+        let mut error = ValidationError::new(code);
+        error.message = Some(message.into());
+        errors.add("rubric", error);
+    };
+    if rubric.len() > MAX_CRITERIA {
+        fail(
+            "too_many_criteria",
+            format!("A rubric has at most {MAX_CRITERIA} criteria"),
+        );
+    }
+    if rubric
+        .iter()
+        .any(|criterion| criterion.levels.is_empty() || criterion.levels.len() > MAX_LEVELS)
+    {
+        fail(
+            "invalid_levels",
+            format!("Every criterion needs from 1 to {MAX_LEVELS} levels"),
+        );
+    }
+    let titles = rubric.iter().map(|criterion| &criterion.title).chain(
+        rubric
+            .iter()
+            .flat_map(|criterion| criterion.levels.iter().map(|level| &level.title)),
+    );
+    if titles
+        .clone()
+        .any(|title| title.trim().is_empty() || title.chars().count() > TITLE_MAX_LEN)
+    {
+        fail(
+            "invalid_title",
+            format!("Rubric titles must be non-empty and at most {TITLE_MAX_LEN} characters"),
+        );
+    }
+    let descriptions = rubric.iter().map(|criterion| &criterion.description).chain(
+        rubric
+            .iter()
+            .flat_map(|criterion| criterion.levels.iter().map(|level| &level.description)),
+    );
+    if descriptions
+        .into_iter()
+        .any(|description| description.chars().count() > DESCRIPTION_MAX_LEN)
+    {
+        fail(
+            "description_too_long",
+            format!("Rubric descriptions must be at most {DESCRIPTION_MAX_LEN} characters"),
+        );
+    }
+    if rubric
+        .iter()
+        .flat_map(|criterion| &criterion.levels)
+        .any(|level| !level.points.is_finite() || level.points < 0f64)
+    {
+        fail(
+            "invalid_points",
+            "Level points must be non-negative".to_string(),
+        );
+    }
+}
+
+/// Grades a task worth `points` by `levels`, the index of the picked level of
+/// every criterion. The comment lists the picks, followed by the reviewer's
+/// own `comment`, if any.
+#[allow(clippy::cast_precision_loss)]
+pub fn grade(
+    rubric: &[RubricCriterion],
+    levels: &[usize],
+    points: i64,
+    comment: Option<&str>,
+) -> Result<(TaskVerdict, Vec<RubricGrade>), String> {
+    if levels.len() != rubric.len() {
+        return Err(format!(
+            "Pick one level for each of the {} criteria",
+            rubric.len()
+        ));
+    }
+    let grades = rubric
+        .iter()
+        .zip(levels)
+        .map(|(criterion, &index)| {
+            let level = criterion
+                .levels
+                .get(index)
+                .ok_or_else(|| format!("Criterion {} has no level {index}", criterion.title))?;
+            Ok(RubricGrade {
+                criterion: criterion.title.clone(),
+                level: level.title.clone(),
+                description: level.description.clone(),
+                points: level.points,
+                max_points: criterion.max_points(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut text = String::new();
+    for grade in &grades {
+        let _ = writeln!(
+            text,
+            "{}: {} ({}/{})",
+            grade.criterion, grade.level, grade.points, grade.max_points
+        );
+        if !grade.description.is_empty() {
+            let _ = writeln!(text, "{}", grade.description);
+        }
+    }
+    if let Some(comment) = comment.map(str::trim).filter(|comment| !comment.is_empty()) {
+        let _ = write!(text, "\n{comment}");
+    }
+    let comment = Some(text.trim_end().to_string());
+
+    let max_score = points as f64;
+    // validation keeps the rubric total equal to the task's points
+    let score = grades
+        .iter()
+        .map(|grade| grade.points)
+        .sum::<f64>()
+        .min(max_score);
+    let verdict = if score >= max_score {
+        TaskVerdict::FullScore {
+            comment,
+            score,
+            max_score,
+        }
+    } else if score > 0f64 {
+        TaskVerdict::PartialScore {
+            comment,
+            score,
+            max_score,
+        }
+    } else {
+        TaskVerdict::Incorrect {
+            comment,
+            score: 0f64,
+            max_score,
+        }
+    };
+    Ok((verdict, grades))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(title: &str, points: f64) -> RubricLevel {
+        RubricLevel {
+            title: title.to_string(),
+            description: String::new(),
+            points,
+        }
+    }
+
+    fn rubric() -> Vec<RubricCriterion> {
+        vec![
+            RubricCriterion {
+                title: "Argument".to_string(),
+                description: String::new(),
+                levels: vec![level("Weak", 0.0), level("Sound", 3.0)],
+            },
+            RubricCriterion {
+                title: "Style".to_string(),
+                description: String::new(),
+                levels: vec![
+                    RubricLevel {
+                        description: "Reads well".to_string(),
+                        ..level("Good", 2.0)
+                    },
+                    level("Poor", 0.5),
+                ],
+            },
+        ]
+    }
+
+    fn invalid(rubric: &[RubricCriterion]) -> bool {
+        let mut errors = ValidationErrors::new();
+        validate_rubric(rubric, &mut errors);
+        !errors.is_empty()
+    }
+
+    #[test]
+    fn max_points_takes_the_best_levels() {
+        assert!((max_points(&rubric()) - 5.0).abs() < 1e-9);
+        assert!(max_points(&[]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rubric_is_validated() {
+        assert!(!invalid(&rubric()));
+        let mut no_levels = rubric();
+        no_levels[0].levels.clear();
+        assert!(invalid(&no_levels));
+        let mut blank_title = rubric();
+        blank_title[1].levels[0].title = " ".to_string();
+        assert!(invalid(&blank_title));
+        let mut negative = rubric();
+        negative[0].levels[0].points = -1.0;
+        assert!(invalid(&negative));
+        assert!(invalid(&vec![rubric()[0].clone(); MAX_CRITERIA + 1]));
+    }
+
+    #[test]
+    fn grades_sum_the_picked_levels() {
+        let (verdict, grades) = grade(&rubric(), &[1, 0], 5, Some(" Nice ")).expect("Valid picks");
+        let TaskVerdict::FullScore {
+            comment: Some(comment),
+            score,
+            ..
+        } = verdict
+        else {
+            panic!("Unexpected verdict");
+        };
+        assert!((score - 5.0).abs() < 1e-9);
+        assert_eq!(
+            comment,
+            "Argument: Sound (3/3)\nStyle: Good (2/2)\nReads well\n\nNice"
+        );
+        assert_eq!(grades.len(), 2);
+
+        let (verdict, _) = grade(&rubric(), &[0, 1], 5, None).expect("Valid picks");
+        assert!(
+            matches!(verdict, TaskVerdict::PartialScore { score, .. } if (score - 0.5).abs() < 1e-9)
+        );
+    }
+
+    #[test]
+    fn picks_are_checked() {
+        assert!(grade(&rubric(), &[1], 5, None).is_err());
+        assert!(grade(&rubric(), &[1, 2], 5, None).is_err());
+    }
+}
@@ -1,11 +1,20 @@
 use crate::domain::exam::model::Exam;
-use crate::domain::task::model::{CtfdTaskResponse, Task, TaskConfig};
-use crate::dto::task::UpsertTaskRequestDTO;
+use crate::domain::report::model::ExportFile;
+use crate::domain::task::interchange::{ImportedQuestion, InterchangeFormat, gift, moodle, qti};
+use crate::domain::task::model::{CtfdTaskResponse, Task, TaskConfig, TaskRevision};
+use crate::dto::task::{
+    ImportedQuestionDTO, TaskFilterDTO, TaskImportReportDTO, TaskListResponseDTO,
+    UpsertTaskRequestDTO,
+};
 use crate::errors::{LMSError, Result};
 use crate::utils::send_and_parse;
 use crate::{domain::task::repository::TaskRepository, repo};
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::try_join;
+use uuid::Uuid;
+use validator::Validate;
 
 pub const CTFD_API_URL: &str = "https://ctfd.infosec.moscow/api/v1";
 
@@ -29,7 +38,22 @@ impl TaskService {
         }
     }
 
-    pub async fn create_task(&self, mut task: UpsertTaskRequestDTO) -> Result<Task> {
+    /// Lowercases and trims tags and drops duplicates, keeping the order.
+    fn normalize_tags(tags: &mut Vec<String>) {
+        let mut seen = HashSet::new();
+        *tags = tags
+            .drain(..)
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| seen.insert(tag.clone()))
+            .collect();
+    }
+
+    pub async fn create_task(
+        &self,
+        mut task: UpsertTaskRequestDTO,
+        author_id: Uuid,
+    ) -> Result<Task> {
+        Self::normalize_tags(&mut task.tags);
         if let TaskConfig::CTFd { task_id } = task.configuration {
             let ctfd_task = self.get_ctfd_task_data(task_id).await?;
             if !ctfd_task.success {
@@ -38,7 +62,7 @@ impl TaskService {
             task.title = ctfd_task.data.name;
             task.description = Some(ctfd_task.data.description);
         }
-        self.repo.create(task).await
+        self.repo.create(task, author_id).await
     }
 
     pub async fn get_ctfd_task_data(&self, task_id: usize) -> Result<CtfdTaskResponse> {
@@ -74,6 +98,7 @@ impl TaskService {
         task_id: i32,
         mut task_data: UpsertTaskRequestDTO,
     ) -> Result<Task> {
+        Self::normalize_tags(&mut task_data.tags);
         if let TaskConfig::CTFd { task_id } = task_data.configuration {
             let ctfd_task = self.get_ctfd_task_data(task_id).await?;
             if !ctfd_task.success {
@@ -85,7 +110,142 @@ impl TaskService {
         self.repo.update_task(task_id, task_data).await
     }
 
-    pub async fn get_tasks(&self, limit: i32, offset: i32) -> Result<Vec<Task>> {
-        self.repo.get_tasks(limit, offset).await
+    pub async fn get_tasks(&self, filter: &TaskFilterDTO) -> Result<TaskListResponseDTO> {
+        let (tasks, total) = try_join!(self.repo.get_tasks(filter), self.repo.count_tasks(filter))?;
+        Ok(TaskListResponseDTO { tasks, total })
+    }
+
+    /// Converts a Moodle XML, GIFT or QTI package file into tasks. Nothing is
+    /// created unless `commit` is set; the report is the same either way.
+    pub async fn import_tasks(
+        &self,
+        format: InterchangeFormat,
+        source: &[u8],
+        commit: bool,
+        author_id: Uuid,
+    ) -> Result<TaskImportReportDTO> {
+        let text = || {
+            std::str::from_utf8(source)
+                .map_err(|_| LMSError::ShitHappened("The file is not valid UTF-8".to_string()))
+        };
+        let imported = match format {
+            InterchangeFormat::MoodleXml => {
+                moodle::import(text()?).map_err(LMSError::ShitHappened)?
+            }
+            InterchangeFormat::Gift => gift::import(text()?),
+            InterchangeFormat::Qti => qti::import(source).map_err(LMSError::ShitHappened)?,
+        };
+        self.import_questions(imported, commit, author_id).await
+    }
+
+    /// Validates converted questions and, with `commit`, creates them, all in
+    /// one go: a failure leaves none of them behind.
+    pub async fn import_questions(
+        &self,
+        imported: Vec<ImportedQuestion>,
+        commit: bool,
+        author_id: Uuid,
+    ) -> Result<TaskImportReportDTO> {
+        let (questions, tasks) = Self::prepare_import(imported);
+        let created = if commit {
+            Some(self.repo.create_many(tasks, author_id).await?)
+        } else {
+            None
+        };
+        Ok(Self::import_report(questions, created))
+    }
+
+    /// Validates converted questions, returning the report entry of every
+    /// question and the tasks to create for the valid ones, in order.
+    pub fn prepare_import(
+        imported: Vec<ImportedQuestion>,
+    ) -> (Vec<ImportedQuestionDTO>, Vec<UpsertTaskRequestDTO>) {
+        let mut questions = Vec::with_capacity(imported.len());
+        let mut tasks = Vec::new();
+        for (index, question) in imported.into_iter().enumerate() {
+            let mut warnings = question.warnings;
+            let task = question.task.filter(|task| match task.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    warnings.push(format!("The question is not a valid task: {e}"));
+                    false
+                }
+            });
+            let task_type = task.as_ref().map(|task| task.task_type.clone());
+            if let Some(mut task) = task {
+                Self::normalize_tags(&mut task.tags);
+                tasks.push(task);
+            }
+            questions.push(ImportedQuestionDTO {
+                index,
+                name: question.name,
+                task_type,
+                task_id: None,
+                warnings,
+            });
+        }
+        (questions, tasks)
+    }
+
+    /// Sums up an import; `created` are the tasks made from the valid
+    /// questions, or `None` on a dry run.
+    pub fn import_report(
+        mut questions: Vec<ImportedQuestionDTO>,
+        created: Option<Vec<Task>>,
+    ) -> TaskImportReportDTO {
+        let committed = created.is_some();
+        if let Some(created) = created {
+            let mut created = created.into_iter();
+            for question in questions.iter_mut().filter(|q| q.task_type.is_some()) {
+                question.task_id = created.next().map(|task| task.id);
+            }
+        }
+
+        let imported = questions.iter().filter(|q| q.task_type.is_some()).count();
+        TaskImportReportDTO {
+            committed,
+            imported,
+            skipped: questions.len() - imported,
+            questions,
+        }
+    }
+
+    /// Renders the given tasks as a Moodle XML, GIFT or QTI package file. Tasks
+    /// the format can't express are left out with a comment saying why.
+    pub async fn export_tasks(
+        &self,
+        format: InterchangeFormat,
+        task_ids: &[i32],
+    ) -> Result<ExportFile> {
+        let tasks = self.repo.get_tasks_by_ids(task_ids).await?;
+        if tasks.len() != task_ids.iter().collect::<HashSet<_>>().len() {
+            return Err(LMSError::NotFound("Some tasks are not found".to_string()));
+        }
+
+        Ok(match format {
+            InterchangeFormat::MoodleXml => ExportFile {
+                bytes: moodle::export(&tasks).into_bytes(),
+                content_type: "application/xml; charset=utf-8",
+                filename: "tasks.xml".to_string(),
+            },
+            InterchangeFormat::Gift => ExportFile {
+                bytes: gift::export(&tasks).into_bytes(),
+                content_type: "text/plain; charset=utf-8",
+                filename: "tasks.gift.txt".to_string(),
+            },
+            InterchangeFormat::Qti => ExportFile {
+                bytes: qti::export(&tasks).map_err(LMSError::ServerError)?,
+                content_type: "application/zip",
+                filename: "tasks.zip".to_string(),
+            },
+        })
+    }
+
+    pub async fn get_task_revisions(&self, task_id: i32) -> Result<Vec<TaskRevision>> {
+        let revisions = self.repo.get_task_revisions(task_id).await?;
+        if revisions.is_empty() {
+            return Err(LMSError::NotFound("Task not found".to_string()));
+        }
+        Ok(revisions)
     }
 }
@@ -0,0 +1,1084 @@
+//! Parameterized tasks: a template declares variables, and every learner gets
+//! their own values for them, derived from a seed.
+//!
+//! Variables are either generated (`range`, `choice`, `hex`) or computed by a
+//! small expression language from the variables declared before them (`expr`).
+//! Values end up in the task in two ways:
+//! - `${name}` placeholders in the title, the description and any string of
+//!   the configuration are replaced with the value's text;
+//! - `bind` maps a JSON pointer into the configuration (e.g. `/answer` of a
+//!   `Numeric` task) to a variable, whose value replaces that field as is.
+//!
+//! Expressions work on integers (checked 64-bit) and strings: literals
+//! (`42`, `0xff`, `0b101`, `"text"`), variables, `+ - * / %`, bitwise
+//! `& | ^ ~ << >>`, parentheses and the functions listed in [`call`]. `+` with
+//! a string operand concatenates.
+
+use crate::domain::task::model::TaskConfig;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::net::Ipv4Addr;
+use std::num::ParseIntError;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+/// Upper bound for the number of variables in a template.
+const MAX_VARIABLES: usize = 32;
+/// Upper bound for the source of a single expression.
+const EXPR_MAX_LEN: usize = 500;
+/// Upper bound for how deep an expression may nest.
+const EXPR_MAX_DEPTH: usize = 32;
+/// Upper bound for any string a template produces.
+const STRING_MAX_LEN: usize = 4096;
+/// Upper bound for the length of a `hex` variable.
+const HEX_MAX_LEN: usize = 128;
+/// How many seeds a template is tried with before it is accepted.
+const CHECK_SEEDS: u64 = 16;
+/// How many times a learner's values are drawn before their task fails.
+const DRAWS: u64 = 8;
+
+/// `${name}` placeholders in task texts.
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("Placeholder regex is valid")
+});
+static IDENTIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("Identifier regex is valid"));
+
+/// Variables of a parameterized task. An empty template (the default) leaves
+/// the task as is.
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Clone, Default)]
+pub struct TaskTemplate {
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    /// JSON pointer into the configuration -> name of the variable whose value
+    /// is written there.
+    #[serde(default)]
+    pub bind: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Clone)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(flatten)]
+    pub generator: TemplateGenerator,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateGenerator {
+    /// A random integer out of `min`, `min + step`, ... up to `max`.
+    Range {
+        min: i64,
+        max: i64,
+        #[serde(default = "TemplateGenerator::default_step")]
+        step: i64,
+    },
+    /// One of `values`, picked at random.
+    Choice { values: Vec<String> },
+    /// A random lowercase hex string of `length` digits.
+    Hex { length: usize },
+    /// Computed from the variables declared before this one.
+    Expr { expr: String },
+}
+
+impl TemplateGenerator {
+    const fn default_step() -> i64 {
+        1
+    }
+}
+
+/// A value of a template variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateValue {
+    Int(i64),
+    Str(String),
+}
+
+impl Display for TemplateValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Str(value) => f.write_str(value),
+        }
+    }
+}
+
+impl From<&TemplateValue> for Value {
+    fn from(value: &TemplateValue) -> Self {
+        match value {
+            TemplateValue::Int(value) => Self::from(*value),
+            TemplateValue::Str(value) => Self::from(value.as_str()),
+        }
+    }
+}
+
+pub type TemplateValues = HashMap<String, TemplateValue>;
+
+impl TaskTemplate {
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty() && self.bind.is_empty()
+    }
+
+    /// Checks the template on its own and then tries it with a handful of
+    /// seeds against the task it belongs to: every placeholder must be
+    /// declared, every pointer must exist and the rendered configuration must
+    /// still be a valid one of the same kind.
+    pub fn check(&self, texts: &[&str], configuration: &TaskConfig) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if self.variables.len() > MAX_VARIABLES {
+            return Err(format!(
+                "A template can't have more than {MAX_VARIABLES} variables"
+            ));
+        }
+        let mut declared: Vec<&str> = Vec::new();
+        for variable in &self.variables {
+            if !IDENTIFIER.is_match(&variable.name) {
+                return Err(format!("Invalid variable name '{}'", variable.name));
+            }
+            if declared.contains(&variable.name.as_str()) {
+                return Err(format!("Variable '{}' is declared twice", variable.name));
+            }
+            match &variable.generator {
+                TemplateGenerator::Range { min, max, step } => {
+                    if min > max || *step <= 0 {
+                        return Err(format!(
+                            "Variable '{}' needs min <= max and a positive step",
+                            variable.name
+                        ));
+                    }
+                }
+                TemplateGenerator::Choice { values } => {
+                    if values.is_empty() {
+                        return Err(format!(
+                            "Variable '{}' has nothing to choose from",
+                            variable.name
+                        ));
+                    }
+                }
+                TemplateGenerator::Hex { length } => {
+                    if !(1..=HEX_MAX_LEN).contains(length) {
+                        return Err(format!(
+                            "Variable '{}' must be 1 to {HEX_MAX_LEN} hex digits long",
+                            variable.name
+                        ));
+                    }
+                }
+                TemplateGenerator::Expr { expr } => {
+                    let parsed =
+                        parse(expr).map_err(|e| format!("Variable '{}': {e}", variable.name))?;
+                    if let Some(unknown) = parsed.first_unknown_var(&declared) {
+                        return Err(format!(
+                            "Variable '{}' uses '{unknown}', which isn't declared before it",
+                            variable.name
+                        ));
+                    }
+                }
+            }
+            declared.push(&variable.name);
+        }
+        for (pointer, name) in &self.bind {
+            if !declared.contains(&name.as_str()) {
+                return Err(format!(
+                    "'{pointer}' is bound to an undeclared variable '{name}'"
+                ));
+            }
+        }
+
+        let source = serde_json::to_value(configuration).map_err(|e| e.to_string())?;
+        for pointer in self.bind.keys() {
+            // the tag decides the kind of the task, a template can't change it
+            if pointer == "/name" || source.pointer(pointer).is_none() {
+                return Err(format!("'{pointer}' doesn't point into the configuration"));
+            }
+        }
+        for seed in 0..CHECK_SEEDS {
+            let values = self.generate(&mut ChaCha8Rng::seed_from_u64(seed))?;
+            for text in texts {
+                render_text(text, &values)?;
+            }
+            let rendered = self.apply(configuration, &values)?;
+            if std::mem::discriminant(&rendered) != std::mem::discriminant(configuration) {
+                return Err("A template can't change the kind of the task".to_string());
+            }
+            rendered
+                .validate()
+                .map_err(|e| format!("The rendered configuration is invalid: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Renders the task of the learner with `seed` with their values. Values
+    /// `render` fails with (e.g. dividing by a zero the seeds tried in
+    /// [`Self::check`] never drew) are drawn again with a seed derived from
+    /// the learner's, so a rare bad variant only changes that learner's
+    /// values instead of failing their task.
+    pub fn draw<T>(
+        &self,
+        seed: u64,
+        mut render: impl FnMut(&TemplateValues) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut failure = String::new();
+        for draw in 0..DRAWS {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            // keep values independent of the shuffling done with the same seed
+            rng.set_stream(1 + draw);
+            match self.generate(&mut rng).and_then(|values| render(&values)) {
+                Ok(rendered) => return Ok(rendered),
+                Err(e) => failure = e,
+            }
+        }
+        Err(failure)
+    }
+
+    /// Values of all variables for one learner.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<TemplateValues, String> {
+        let mut values = TemplateValues::new();
+        for variable in &self.variables {
+            let value = match &variable.generator {
+                TemplateGenerator::Range { min, max, step } => {
+                    let count = (i128::from(*max) - i128::from(*min)) / i128::from(*step) + 1;
+                    let index = rng.random_range(0..count);
+                    #[allow(clippy::cast_possible_truncation)]
+                    TemplateValue::Int((i128::from(*min) + index * i128::from(*step)) as i64)
+                }
+                TemplateGenerator::Choice { values } => TemplateValue::Str(
+                    values
+                        .choose(rng)
+                        .ok_or_else(|| {
+                            format!("Variable '{}' has nothing to choose from", variable.name)
+                        })?
+                        .clone(),
+                ),
+                TemplateGenerator::Hex { length } => TemplateValue::Str(
+                    (0..*length)
+                        .map(|_| {
+                            char::from_digit(rng.random_range(0..16), 16)
+                                .expect("Digit below 16 is a hex digit")
+                        })
+                        .collect(),
+                ),
+                TemplateGenerator::Expr { expr } => parse(expr)?
+                    .eval(&values)
+                    .map_err(|e| format!("Variable '{}': {e}", variable.name))?,
+            };
+            values.insert(variable.name.clone(), value);
+        }
+        Ok(values)
+    }
+
+    /// Renders `configuration` with the given values: placeholders in every
+    /// string first, then the bound fields.
+    pub fn apply(
+        &self,
+        configuration: &TaskConfig,
+        values: &TemplateValues,
+    ) -> Result<TaskConfig, String> {
+        let mut json = serde_json::to_value(configuration).map_err(|e| e.to_string())?;
+        render_json(&mut json, values)?;
+        for (pointer, name) in &self.bind {
+            let value = values
+                .get(name)
+                .ok_or_else(|| format!("Unknown variable '{name}'"))?;
+            let field = json
+                .pointer_mut(pointer)
+                .ok_or_else(|| format!("'{pointer}' doesn't point into the configuration"))?;
+            *field = value.into();
+        }
+        serde_json::from_value(json)
+            .map_err(|e| format!("The rendered configuration is invalid: {e}"))
+    }
+}
+
+/// Replaces `${name}` placeholders in `text`.
+pub fn render_text(text: &str, values: &TemplateValues) -> Result<String, String> {
+    let mut unknown = None;
+    let rendered = PLACEHOLDER.replace_all(text, |captures: &regex::Captures| {
+        values.get(&captures[1]).map_or_else(
+            || {
+                unknown.get_or_insert_with(|| captures[1].to_string());
+                String::new()
+            },
+            ToString::to_string,
+        )
+    });
+    unknown.map_or_else(
+        || Ok(rendered.into_owned()),
+        |name| Err(format!("Unknown variable '{name}' in '{text}'")),
+    )
+}
+
+fn render_json(json: &mut Value, values: &TemplateValues) -> Result<(), String> {
+    match json {
+        Value::String(text) => *text = render_text(text, values)?,
+        Value::Array(items) => {
+            for item in items {
+                render_json(item, values)?;
+            }
+        }
+        Value::Object(fields) => {
+            for item in fields.values_mut() {
+                render_json(item, values)?;
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+    Ok(())
+}
+
+/// Decimal, `0x` hex or `0b` binary.
+fn parse_int(source: &str) -> Result<i64, ParseIntError> {
+    source.strip_prefix("0x").map_or_else(
+        || {
+            source
+                .strip_prefix("0b")
+                .map_or_else(|| source.parse(), |bin| i64::from_str_radix(bin, 2))
+        },
+        |hex| i64::from_str_radix(hex, 16),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 14] = [
+    "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "(", ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let literal = &rest[..end];
+            tokens.push(Token::Int(
+                parse_int(literal).map_err(|_| format!("Invalid number '{literal}'"))?,
+            ));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '"' {
+            let mut literal = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped @ ('"' | '\\'))) => literal.push(escaped),
+                        Some((_, 'n')) => literal.push('\n'),
+                        _ => return Err("Invalid escape in a string".to_string()),
+                    },
+                    Some((_, c)) => literal.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                }
+            };
+            tokens.push(Token::Str(literal));
+            rest = &rest[end..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!("Unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(TemplateValue),
+    Var(String),
+    Unary(&'static str, Box<Self>),
+    Binary(&'static str, Box<Self>, Box<Self>),
+    Call(String, Vec<Self>),
+}
+
+/// Binary operators from the loosest to the tightest binding.
+const PRECEDENCE: [&[&str]; 6] = [
+    &["|"],
+    &["^"],
+    &["&"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    if source.len() > EXPR_MAX_LEN {
+        return Err(format!(
+            "Expression is longer than {EXPR_MAX_LEN} characters"
+        ));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        depth: 0,
+    };
+    let expr = parser.binary(0)?;
+    parser
+        .tokens
+        .get(parser.position)
+        .map_or(Ok(expr), |token| Err(format!("Unexpected {token:?}")))
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect_op(&mut self, expected: &str) -> Result<(), String> {
+        if self.peek_op() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{expected}'"))
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let Some(operators) = PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| operators.contains(op)) {
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > EXPR_MAX_DEPTH {
+            return Err("Expression is nested too deep".to_string());
+        }
+        let expr = match self.peek_op() {
+            Some(op @ ("-" | "~")) => {
+                self.position += 1;
+                Expr::Unary(op, Box::new(self.unary()?))
+            }
+            _ => self.primary()?,
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "Unexpected end of expression".to_string())?;
+        self.position += 1;
+        match token {
+            Token::Int(value) => Ok(Expr::Literal(TemplateValue::Int(value))),
+            Token::Str(value) => Ok(Expr::Literal(TemplateValue::Str(value))),
+            Token::Ident(name) if self.peek_op() == Some("(") => {
+                self.position += 1;
+                let mut args = Vec::new();
+                if self.peek_op() != Some(")") {
+                    loop {
+                        args.push(self.binary(0)?);
+                        if self.peek_op() != Some(",") {
+                            break;
+                        }
+                        self.position += 1;
+                    }
+                }
+                self.expect_op(")")?;
+                if !FUNCTIONS.contains(&name.as_str()) {
+                    return Err(format!("Unknown function '{name}'"));
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Token::Ident(name) => Ok(Expr::Var(name)),
+            Token::Op("(") => {
+                let expr = self.binary(0)?;
+                self.expect_op(")")?;
+                Ok(expr)
+            }
+            Token::Op(op) => Err(format!("Unexpected '{op}'")),
+        }
+    }
+}
+
+const FUNCTIONS: [&str; 14] = [
+    "hex", "bin", "str", "int", "upper", "lower", "len", "min", "max", "abs", "pow", "ip", "ipnum",
+    "sha256",
+];
+
+fn int(value: &TemplateValue) -> Result<i64, String> {
+    match value {
+        TemplateValue::Int(value) => Ok(*value),
+        TemplateValue::Str(value) => Err(format!("Expected a number, got \"{value}\"")),
+    }
+}
+
+fn string(value: TemplateValue) -> Result<TemplateValue, String> {
+    match value {
+        TemplateValue::Str(value) if value.len() > STRING_MAX_LEN => Err(format!(
+            "Strings can't be longer than {STRING_MAX_LEN} bytes"
+        )),
+        value => Ok(value),
+    }
+}
+
+fn overflow() -> String {
+    "Integer overflow".to_string()
+}
+
+impl Expr {
+    fn first_unknown_var<'a>(&'a self, declared: &[&str]) -> Option<&'a str> {
+        match self {
+            Self::Literal(_) => None,
+            Self::Var(name) => (!declared.contains(&name.as_str())).then_some(name.as_str()),
+            Self::Unary(_, expr) => expr.first_unknown_var(declared),
+            Self::Binary(_, left, right) => left
+                .first_unknown_var(declared)
+                .or_else(|| right.first_unknown_var(declared)),
+            Self::Call(_, args) => args.iter().find_map(|arg| arg.first_unknown_var(declared)),
+        }
+    }
+
+    fn eval(&self, values: &TemplateValues) -> Result<TemplateValue, String> {
+        match self {
+            Self::Literal(value) => Ok(value.clone()),
+            Self::Var(name) => values
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Unknown variable '{name}'")),
+            Self::Unary(op, expr) => {
+                let value = int(&expr.eval(values)?)?;
+                match *op {
+                    "-" => value.checked_neg().ok_or_else(overflow),
+                    _ => Ok(!value),
+                }
+                .map(TemplateValue::Int)
+            }
+            Self::Binary(op, left, right) => {
+                let (left, right) = (left.eval(values)?, right.eval(values)?);
+                if *op == "+"
+                    && (matches!(left, TemplateValue::Str(_))
+                        || matches!(right, TemplateValue::Str(_)))
+                {
+                    return string(TemplateValue::Str(format!("{left}{right}")));
+                }
+                let (left, right) = (int(&left)?, int(&right)?);
+                let shift = || u32::try_from(right).ok().filter(|shift| *shift < 64);
+                match *op {
+                    "+" => left.checked_add(right),
+                    "-" => left.checked_sub(right),
+                    "*" => left.checked_mul(right),
+                    "/" | "%" if right == 0 => return Err("Division by zero".to_string()),
+                    "/" => left.checked_div(right),
+                    "%" => left.checked_rem(right),
+                    "&" => Some(left & right),
+                    "|" => Some(left | right),
+                    "^" => Some(left ^ right),
+                    "<<" => shift().and_then(|shift| left.checked_shl(shift)),
+                    _ => shift().and_then(|shift| left.checked_shr(shift)),
+                }
+                .map(TemplateValue::Int)
+                .ok_or_else(overflow)
+            }
+            Self::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(values))
+                    .collect::<Result<Vec<_>, _>>()?;
+                string(call(name, &args)?)
+            }
+        }
+    }
+}
+
+/// Built-in functions of template expressions:
+/// - `hex(n)`, `bin(n)`, optionally zero-padded to a width: `hex(n, 8)`;
+/// - `str(x)`, `int(s)` (decimal, `0x`/`0b` prefixed), `int(s, base)`;
+/// - `upper(s)`, `lower(s)`, `len(s)`, `sha256(s)` (lowercase hex);
+/// - `min(a, ...)`, `max(a, ...)`, `abs(n)`, `pow(n, e)`;
+/// - `ip(n)` formats a 32-bit number as a dotted IPv4 address, `ipnum(s)`
+///   goes the other way.
+#[allow(clippy::too_many_lines)]
+fn call(name: &str, args: &[TemplateValue]) -> Result<TemplateValue, String> {
+    let arity = |min: usize, max: usize| {
+        if (min..=max).contains(&args.len()) {
+            Ok(())
+        } else {
+            Err(format!("Wrong number of arguments for '{name}'"))
+        }
+    };
+    let text = |value: &TemplateValue| value.to_string();
+    match name {
+        "hex" | "bin" => {
+            arity(1, 2)?;
+            let value = u64::try_from(int(&args[0])?)
+                .map_err(|_| format!("'{name}' takes a non-negative number"))?;
+            let width = args.get(1).map(int).transpose()?.unwrap_or(0);
+            let width = usize::try_from(width)
+                .ok()
+                .filter(|width| *width <= 64)
+                .ok_or_else(|| format!("Invalid width for '{name}'"))?;
+            Ok(TemplateValue::Str(if name == "hex" {
+                format!("{value:0width$x}")
+            } else {
+                format!("{value:0width$b}")
+            }))
+        }
+        "str" => {
+            arity(1, 1)?;
+            Ok(TemplateValue::Str(text(&args[0])))
+        }
+        "int" => {
+            arity(1, 2)?;
+            let source = text(&args[0]);
+            let source = source.trim();
+            let parsed = match args.get(1).map(int).transpose()? {
+                Some(base) => {
+                    let base = u32::try_from(base)
+                        .ok()
+                        .filter(|base| (2..=36).contains(base))
+                        .ok_or_else(|| "Base must be between 2 and 36".to_string())?;
+                    i64::from_str_radix(source, base)
+                }
+                None => parse_int(source),
+            };
+            parsed
+                .map(TemplateValue::Int)
+                .map_err(|_| format!("Can't read '{source}' as a number"))
+        }
+        "upper" | "lower" | "len" | "sha256" | "ipnum" => {
+            arity(1, 1)?;
+            let source = text(&args[0]);
+            match name {
+                "upper" => Ok(TemplateValue::Str(source.to_uppercase())),
+                "lower" => Ok(TemplateValue::Str(source.to_lowercase())),
+                "len" => i64::try_from(source.chars().count())
+                    .map(TemplateValue::Int)
+                    .map_err(|_| overflow()),
+                "sha256" => Ok(TemplateValue::Str(format!(
+                    "{:x}",
+                    Sha256::digest(source.as_bytes())
+                ))),
+                _ => source
+                    .parse::<Ipv4Addr>()
+                    .map(|ip| TemplateValue::Int(i64::from(u32::from(ip))))
+                    .map_err(|_| format!("'{source}' is not an IPv4 address")),
+            }
+        }
+        "min" | "max" => {
+            arity(1, usize::MAX)?;
+            let numbers = args.iter().map(int).collect::<Result<Vec<_>, _>>()?;
+            let result = if name == "min" {
+                numbers.into_iter().min()
+            } else {
+                numbers.into_iter().max()
+            };
+            Ok(TemplateValue::Int(result.expect("At least one argument")))
+        }
+        "abs" => {
+            arity(1, 1)?;
+            int(&args[0])?
+                .checked_abs()
+                .map(TemplateValue::Int)
+                .ok_or_else(overflow)
+        }
+        "pow" => {
+            arity(2, 2)?;
+            let exponent = u32::try_from(int(&args[1])?)
+                .map_err(|_| "'pow' takes a non-negative exponent".to_string())?;
+            int(&args[0])?
+                .checked_pow(exponent)
+                .map(TemplateValue::Int)
+                .ok_or_else(overflow)
+        }
+        "ip" => {
+            arity(1, 1)?;
+            let value = u32::try_from(int(&args[0])?)
+                .map_err(|_| "'ip' takes a number from 0 to 2^32 - 1".to_string())?;
+            Ok(TemplateValue::Str(Ipv4Addr::from(value).to_string()))
+        }
+        _ => Err(format!("Unknown function '{name}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::task::grader::test_support::config;
+    use serde_json::json;
+
+    fn eval(source: &str) -> Result<TemplateValue, String> {
+        let values = TemplateValues::from([
+            ("n".to_string(), TemplateValue::Int(10)),
+            ("s".to_string(), TemplateValue::Str("Flag".to_string())),
+        ]);
+        parse(source)?.eval(&values)
+    }
+
+    fn int_value(source: &str) -> i64 {
+        match eval(source) {
+            Ok(TemplateValue::Int(value)) => value,
+            other => panic!("'{source}' gave {other:?}"),
+        }
+    }
+
+    fn str_value(source: &str) -> String {
+        match eval(source) {
+            Ok(TemplateValue::Str(value)) => value,
+            other => panic!("'{source}' gave {other:?}"),
+        }
+    }
+
+    fn variable(name: &str, generator: TemplateGenerator) -> TemplateVariable {
+        TemplateVariable {
+            name: name.to_string(),
+            generator,
+        }
+    }
+
+    fn expr(source: &str) -> TemplateGenerator {
+        TemplateGenerator::Expr {
+            expr: source.to_string(),
+        }
+    }
+
+    fn numeric() -> TaskConfig {
+        config(json!({"name": "numeric", "answer": 0.0}))
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!(int_value("1 + 2 * 3"), 7);
+        assert_eq!(int_value("(1 + 2) * 3"), 9);
+        assert_eq!(int_value("1 << 2 + 1"), 8);
+        assert_eq!(int_value("0xff & 0b1010 | 1"), 11);
+        assert_eq!(int_value("6 ^ 3"), 5);
+        assert_eq!(int_value("-n % 3"), -1);
+        assert_eq!(int_value("~0"), -1);
+        assert_eq!(str_value("s + \"{\" + n + \"}\""), "Flag{10}");
+    }
+
+    #[test]
+    fn functions_are_evaluated() {
+        assert_eq!(str_value("hex(255)"), "ff");
+        assert_eq!(str_value("hex(n, 4)"), "000a");
+        assert_eq!(str_value("bin(5, 8)"), "00000101");
+        assert_eq!(int_value("int(\"ff\", 16) + int(\"0x10\")"), 271);
+        assert_eq!(str_value("upper(s) + lower(s)"), "FLAGflag");
+        assert_eq!(int_value("len(s)"), 4);
+        assert_eq!(int_value("min(3, n, -2) + max(3, n)"), 8);
+        assert_eq!(int_value("abs(-n) + pow(2, 10)"), 1034);
+        assert_eq!(str_value("ip(3232235777)"), "192.168.1.1");
+        assert_eq!(int_value("ipnum(\"10.0.0.1\")"), 167_772_161);
+        assert_eq!(
+            str_value("sha256(\"abc\")"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn evaluation_errors_are_reported() {
+        assert_eq!(
+            eval("n / 0").expect_err("Expected an error"),
+            "Division by zero"
+        );
+        assert_eq!(
+            eval("n % 0").expect_err("Expected an error"),
+            "Division by zero"
+        );
+        assert_eq!(
+            eval("9223372036854775807 + 1").expect_err("Expected an error"),
+            "Integer overflow"
+        );
+        assert_eq!(
+            eval("pow(n, 100)").expect_err("Expected an error"),
+            "Integer overflow"
+        );
+        assert_eq!(
+            eval("1 << 64").expect_err("Expected an error"),
+            "Integer overflow"
+        );
+        assert!(eval("s * 2").is_err());
+        assert!(eval("hex(-1)").is_err());
+        assert!(eval("ip(-1)").is_err());
+        assert!(eval("abs(1, 2)").is_err());
+        assert!(eval("nope").is_err());
+    }
+
+    #[test]
+    fn parse_errors_are_reported() {
+        assert!(parse("1 +").is_err());
+        assert!(parse("(1").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("\"open").is_err());
+        assert!(parse("12abc").is_err());
+        assert!(parse("$").is_err());
+        assert_eq!(
+            parse("nope(1)").expect_err("Expected an error"),
+            "Unknown function 'nope'"
+        );
+        assert!(parse(&"-".repeat(EXPR_MAX_DEPTH + 1)).is_err());
+        assert!(parse(&"1".repeat(EXPR_MAX_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn generate_is_deterministic_per_seed() {
+        let template = TaskTemplate {
+            variables: vec![
+                variable(
+                    "port",
+                    TemplateGenerator::Range {
+                        min: 1000,
+                        max: 2000,
+                        step: 10,
+                    },
+                ),
+                variable(
+                    "user",
+                    TemplateGenerator::Choice {
+                        values: vec!["alice".to_string(), "bob".to_string()],
+                    },
+                ),
+                variable("key", TemplateGenerator::Hex { length: 8 }),
+                variable("next", expr("port + 1")),
+            ],
+            bind: BTreeMap::new(),
+        };
+        for seed in 0..CHECK_SEEDS {
+            let values = template
+                .generate(&mut ChaCha8Rng::seed_from_u64(seed))
+                .expect("Generation failed");
+            assert_eq!(
+                values,
+                template
+                    .generate(&mut ChaCha8Rng::seed_from_u64(seed))
+                    .expect("Generation failed")
+            );
+            let TemplateValue::Int(port) = values["port"] else {
+                panic!("'port' is not a number");
+            };
+            assert!((1000..=2000).contains(&port) && port % 10 == 0);
+            assert_eq!(values["next"], TemplateValue::Int(port + 1));
+            assert!(
+                matches!(&values["user"], TemplateValue::Str(user) if user == "alice" || user == "bob")
+            );
+            assert!(matches!(
+                &values["key"],
+                TemplateValue::Str(key) if key.len() == 8 && key.chars().all(|c| c.is_ascii_hexdigit())
+            ));
+        }
+    }
+
+    #[test]
+    fn draw_replaces_failing_values() {
+        let template = TaskTemplate {
+            variables: vec![
+                variable(
+                    "a",
+                    TemplateGenerator::Range {
+                        min: 0,
+                        max: 3,
+                        step: 1,
+                    },
+                ),
+                variable("b", expr("12 / a")),
+            ],
+            bind: BTreeMap::new(),
+        };
+        for seed in 0..64 {
+            let b = |seed| template.draw(seed, |values| Ok(values["b"].clone()));
+            let drawn = b(seed).expect("No valid draw");
+            assert!(matches!(drawn, TemplateValue::Int(4 | 6 | 12)));
+            assert_eq!(b(seed), Ok(drawn));
+        }
+        assert_eq!(
+            template.draw(0, |_| Err::<(), _>("broken".to_string())),
+            Err("broken".to_string())
+        );
+    }
+
+    #[test]
+    fn placeholders_are_rendered() {
+        let values = TemplateValues::from([("n".to_string(), TemplateValue::Int(7))]);
+        assert_eq!(
+            render_text("${n} + ${n} = ?", &values).expect("Rendering failed"),
+            "7 + 7 = ?"
+        );
+        assert_eq!(
+            render_text("$n and {n} stay", &values).expect("Rendering failed"),
+            "$n and {n} stay"
+        );
+        assert_eq!(
+            render_text("${m}", &values).expect_err("Expected an error"),
+            "Unknown variable 'm' in '${m}'"
+        );
+    }
+
+    #[test]
+    fn apply_renders_strings_and_bound_fields() {
+        let values = TemplateValues::from([
+            ("n".to_string(), TemplateValue::Int(12)),
+            (
+                "flag".to_string(),
+                TemplateValue::Str("ctf{12}".to_string()),
+            ),
+        ]);
+        let short_text = config(json!({
+            "name": "short_text",
+            "auto_grade": true,
+            "max_chars_count": 100,
+            "case_sensitive": true,
+            "answers": ["${flag}"],
+        }));
+        let rendered = TaskTemplate::default()
+            .apply(&short_text, &values)
+            .expect("Rendering failed");
+        assert_eq!(
+            serde_json::to_value(&rendered).expect("Invalid TaskConfig")["answers"],
+            json!(["ctf{12}"])
+        );
+
+        let template = TaskTemplate {
+            variables: Vec::new(),
+            bind: BTreeMap::from([("/answer".to_string(), "n".to_string())]),
+        };
+        let rendered = template
+            .apply(&numeric(), &values)
+            .expect("Rendering failed");
+        assert_eq!(
+            serde_json::to_value(&rendered).expect("Invalid TaskConfig")["answer"],
+            json!(12.0)
+        );
+        assert!(template.apply(&short_text, &values).is_err());
+    }
+
+    #[test]
+    fn check_accepts_a_valid_template() {
+        let template = TaskTemplate {
+            variables: vec![
+                variable(
+                    "a",
+                    TemplateGenerator::Range {
+                        min: 1,
+                        max: 9,
+                        step: 1,
+                    },
+                ),
+                variable("b", expr("a * 2")),
+            ],
+            bind: BTreeMap::from([("/answer".to_string(), "b".to_string())]),
+        };
+        assert_eq!(template.check(&["What is ${a} * 2?"], &numeric()), Ok(()));
+        assert_eq!(TaskTemplate::default().check(&["${x}"], &numeric()), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_broken_templates() {
+        let range = |min, max, step| TemplateGenerator::Range { min, max, step };
+        let check = |variables: Vec<TemplateVariable>, bind: &[(&str, &str)], text: &str| {
+            TaskTemplate {
+                variables,
+                bind: bind
+                    .iter()
+                    .map(|(pointer, name)| ((*pointer).to_string(), (*name).to_string()))
+                    .collect(),
+            }
+            .check(&[text], &numeric())
+        };
+        assert!(check(vec![variable("1a", range(1, 2, 1))], &[], "").is_err());
+        assert!(
+            check(
+                vec![variable("a", range(1, 2, 1)), variable("a", range(1, 2, 1))],
+                &[],
+                ""
+            )
+            .is_err()
+        );
+        assert!(check(vec![variable("a", range(2, 1, 1))], &[], "").is_err());
+        assert!(check(vec![variable("a", range(1, 2, 0))], &[], "").is_err());
+        assert!(
+            check(
+                vec![variable("a", TemplateGenerator::Choice { values: vec![] })],
+                &[],
+                ""
+            )
+            .is_err()
+        );
+        assert!(
+            check(
+                vec![variable("a", TemplateGenerator::Hex { length: 0 })],
+                &[],
+                ""
+            )
+            .is_err()
+        );
+        // variables may only use the ones declared before them
+        assert!(
+            check(
+                vec![variable("a", expr("b")), variable("b", range(1, 2, 1))],
+                &[],
+                ""
+            )
+            .is_err()
+        );
+        assert!(check(vec![variable("a", expr("1 / (a - a)"))], &[], "").is_err());
+        assert!(check(vec![variable("a", expr("1 / 0"))], &[], "").is_err());
+        assert!(check(vec![variable("a", range(1, 2, 1))], &[], "${b}").is_err());
+        assert!(check(vec![variable("a", range(1, 2, 1))], &[("/answer", "b")], "").is_err());
+        assert!(
+            check(
+                vec![variable("a", range(1, 2, 1))],
+                &[("/missing", "a")],
+                ""
+            )
+            .is_err()
+        );
+        assert!(check(vec![variable("a", range(1, 2, 1))], &[("/name", "a")], "").is_err());
+        // the rendered configuration is validated as well
+        assert!(
+            check(
+                vec![variable("a", range(-2, -1, 1))],
+                &[("/absolute_tolerance", "a")],
+                ""
+            )
+            .is_err()
+        );
+        let too_many = (0..=MAX_VARIABLES)
+            .map(|i| variable(&format!("v{i}"), range(1, 2, 1)))
+            .collect();
+        assert!(check(too_many, &[], "").is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use crate::domain::exam::model::{Exam, ExamScoringPolicy, ExamType};
+use crate::domain::exam::model::{Exam, ExamScoringPolicy, ExamType, LatePolicy};
+use crate::domain::exam::similarity::DEFAULT_THRESHOLD;
 use crate::domain::task::model::TaskAnswer;
-use crate::dto::task::TaskVerdict;
+use crate::domain::task::rubric::RubricGrade;
+use crate::dto::task::{TaskImportReportDTO, TaskVerdict};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -9,9 +11,10 @@ use sqlx::types::{Json, JsonValue};
 use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_late_policy"))]
 pub struct UpsertExamRequestDTO {
     pub topic_id: i32,
     pub name: String,
@@ -23,6 +26,31 @@ pub struct UpsertExamRequestDTO {
     pub ends_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub scoring_policy: ExamScoringPolicy,
+    /// Show tasks to every attempt in its own order (see [`ExamAttempt::shuffle_seed`]).
+    #[serde(default)]
+    pub shuffle_entities: bool,
+    /// Take answers after the deadline, at a penalty.
+    pub late_policy: Option<LatePolicy>,
+}
+
+fn validate_late_policy(exam: &UpsertExamRequestDTO) -> Result<(), ValidationError> {
+    let Some(policy) = &exam.late_policy else {
+        return Ok(());
+    };
+    if !(1..=8760).contains(&policy.cutoff_hours) {
+        return Err(ValidationError::new("invalid_cutoff")
+            .with_message("The cutoff must be between 1 hour and a year".into()));
+    }
+    if policy.grace_minutes < 0 || i64::from(policy.grace_minutes) >= policy.window().num_minutes()
+    {
+        return Err(ValidationError::new("invalid_grace")
+            .with_message("The grace period must end before the cutoff".into()));
+    }
+    if !(0f64..=100f64).contains(&policy.percent_per_hour) {
+        return Err(ValidationError::new("invalid_penalty")
+            .with_message("The penalty must be between 0 and 100 percent per hour".into()));
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize, ToSchema, FromRow)]
@@ -42,7 +70,15 @@ pub struct ExamAttempt {
     pub exam_id: Uuid,
     pub user_id: Uuid,
     pub started_at: DateTime<Utc>,
+    /// No answers are taken after it, late ones included.
     pub ends_at: DateTime<Utc>,
+    /// Answers after it are late. Only the exam's late policy keeps the
+    /// attempt open past it; the attempt is scored from then on and scored
+    /// again with every late answer.
+    pub due_at: DateTime<Utc>,
+    /// Drives every shuffle the attempt's owner sees, so reloads keep the same
+    /// order and reviewers can reproduce it.
+    pub shuffle_seed: i64,
     pub answer_data: Json<ExamAnswer>,
     pub scoring_data: Json<ScoringData>,
 }
@@ -55,12 +91,28 @@ pub struct ExamAttemptAdminSchema {
     pub username: String,
     pub started_at: DateTime<Utc>,
     pub ends_at: DateTime<Utc>,
+    pub due_at: DateTime<Utc>,
+    pub shuffle_seed: i64,
     pub answer_data: ExamAnswer,
     pub scoring_data: ScoringData,
+    /// Time-limited download links of the uploaded files, by task id.
+    pub files: HashMap<usize, String>,
+    /// An answer of the attempt is suspiciously similar to another learner's,
+    /// see the exam's similarity report. Updated in the background shortly
+    /// after attempts of the exam are scored.
+    pub similar_answers: bool,
+    /// Why the attempt, though over, is still unscored.
+    pub scoring_error: Option<String>,
 }
 
 impl ExamAttemptAdminSchema {
-    pub fn from_attempt(value: ExamAttempt, username: String) -> Self {
+    pub fn from_attempt(
+        value: ExamAttempt,
+        username: String,
+        files: HashMap<usize, String>,
+        similar_answers: bool,
+        scoring_error: Option<String>,
+    ) -> Self {
         Self {
             id: value.id,
             exam_id: value.exam_id,
@@ -68,8 +120,13 @@ impl ExamAttemptAdminSchema {
             username,
             started_at: value.started_at,
             ends_at: value.ends_at,
+            due_at: value.due_at,
+            shuffle_seed: value.shuffle_seed,
             answer_data: value.answer_data.into(),
             scoring_data: value.scoring_data.into(),
+            files,
+            similar_answers,
+            scoring_error,
         }
     }
 }
@@ -84,6 +141,40 @@ pub struct TaskAnswerDTO {
 pub struct ScoringData {
     pub show_results: bool, // true when exam type is instant
     pub results: HashMap<usize, TaskVerdict>,
+    /// Filled-in rubrics of the tasks graded by one, by task id.
+    #[serde(default)]
+    pub rubrics: HashMap<usize, Vec<RubricGrade>>,
+    /// Share of the score taken from each late answer, by task id.
+    #[serde(default)]
+    pub late_penalties: HashMap<usize, f64>,
+    /// The late penalty isn't taken from this attempt's score.
+    #[serde(default)]
+    pub late_penalty_waived: bool,
+}
+
+impl ScoringData {
+    /// Sum of the task scores less the late penalty. Negative marking can
+    /// take a single task below zero, but never the whole attempt.
+    pub fn total_score(&self) -> f64 {
+        let penalty = if self.late_penalty_waived {
+            0f64
+        } else {
+            self.late_penalty()
+        };
+        (self.results.values().map(TaskVerdict::score).sum::<f64>() - penalty).max(0f64)
+    }
+
+    /// Points the late answers lose, waived or not.
+    pub fn late_penalty(&self) -> f64 {
+        self.late_penalties
+            .iter()
+            .filter_map(|(task_id, rate)| {
+                self.results
+                    .get(task_id)
+                    .map(|verdict| verdict.score().max(0f64) * rate)
+            })
+            .sum()
+    }
 }
 
 #[derive(Serialize, Deserialize, FromRow, ToSchema, Default)]
@@ -92,6 +183,7 @@ pub struct ExamAttemptSchema {
     pub exam_id: Uuid,
     pub user_id: Uuid,
     pub started_at: DateTime<Utc>,
+    pub due_at: DateTime<Utc>,
     pub active: bool,
     pub answer_data: ExamAnswer,
     pub scoring_data: Option<ScoringData>,
@@ -113,6 +205,7 @@ impl From<ExamAttempt> for ExamAttemptSchema {
             exam_id: value.exam_id,
             user_id: value.user_id,
             started_at: value.started_at,
+            due_at: value.due_at,
             active: value.ends_at > Utc::now(),
             answer_data: value.answer_data.into(),
             scoring_data: Some(value.scoring_data.into()),
@@ -130,9 +223,43 @@ pub struct AttemptListingQuery {
     pub ungraded_first: bool,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct FileUploadRequestDTO {
+    pub task_id: usize,
+    /// MIME type of the file, one the task allows.
+    #[validate(length(min = 3, max = 100))]
+    pub content_type: String,
+}
+
+/// Form for uploading a file answer: POST `fields` and then the file to `url`
+/// before `expires_at`, then answer the task with `file_id`.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct FileUploadSlotDTO {
+    pub file_id: Uuid,
+    pub url: String,
+    pub fields: HashMap<String, String>,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Serialize, Deserialize, ToSchema, FromRow, Clone, Default)]
 pub struct ExamAnswer {
     pub answers: HashMap<usize, TaskAnswer>,
+    /// When the answers given after the attempt was due came, by task id.
+    #[serde(default)]
+    pub late: HashMap<usize, DateTime<Utc>>,
+}
+
+impl ExamAnswer {
+    /// Sets the answer to `task_id`, noting whether it came after `due_at`.
+    pub fn put(&mut self, task_id: usize, answer: TaskAnswer, due_at: DateTime<Utc>) {
+        let now = Utc::now();
+        if now > due_at {
+            self.late.insert(task_id, now);
+        } else {
+            self.late.remove(&task_id);
+        }
+        self.answers.insert(task_id, answer);
+    }
 }
 
 impl From<JsonValue> for ExamAnswer {
@@ -145,6 +272,7 @@ impl From<Json<Self>> for ExamAnswer {
     fn from(value: Json<Self>) -> Self {
         Self {
             answers: value.answers.clone(),
+            late: value.late.clone(),
         }
     }
 }
@@ -154,6 +282,9 @@ impl From<Json<Self>> for ScoringData {
         Self {
             show_results: value.show_results,
             results: value.results.clone(),
+            rubrics: value.rubrics.clone(),
+            late_penalties: value.late_penalties.clone(),
+            late_penalty_waived: value.late_penalty_waived,
         }
     }
 }
@@ -164,6 +295,86 @@ pub struct TextUpsertDTO {
     pub text: String,
 }
 
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_pool_source"))]
+pub struct PoolUpsertDTO {
+    /// How many tasks every attempt gets.
+    #[validate(range(min = 1, max = 100))]
+    pub count: i32,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub task_ids: Vec<i32>,
+    /// Tasks having this tag are drawn from as well.
+    #[validate(length(min = 1, max = 32))]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub balance_difficulty: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct SectionUpsertDTO {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    /// Seconds the section stays open for; without it the attempt's time is
+    /// the only limit.
+    #[validate(range(min = 1, max = 604_800))]
+    pub duration: Option<i32>,
+    #[serde(default)]
+    pub locked_after_leaving: bool,
+}
+
+/// Either `user_id` or both `attribute_key` and `attribute_value` pick whom
+/// the override is for.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_override"))]
+pub struct ExamOverrideUpsertDTO {
+    pub user_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 255))]
+    pub attribute_key: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    pub attribute_value: Option<String>,
+    #[validate(range(min = 1.0, max = 10.0))]
+    pub time_multiplier: Option<f64>,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 10080))]
+    pub extra_minutes: i32,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 100))]
+    pub extra_attempts: i32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+fn validate_override(data: &ExamOverrideUpsertDTO) -> Result<(), ValidationError> {
+    let group = match (&data.attribute_key, &data.attribute_value) {
+        (Some(_), Some(_)) => true,
+        (None, None) => false,
+        _ => {
+            return Err(ValidationError::new("incomplete_group")
+                .with_message("Attribute key and value go together".into()));
+        }
+    };
+    if data.user_id.is_some() == group {
+        return Err(ValidationError::new("invalid_target")
+            .with_message("An override is either for a user or for an attribute group".into()));
+    }
+    if let (Some(starts_at), Some(ends_at)) = (data.starts_at, data.ends_at)
+        && starts_at >= ends_at
+    {
+        return Err(ValidationError::new("invalid_window")
+            .with_message("The window must start before it ends".into()));
+    }
+    Ok(())
+}
+
+fn validate_pool_source(pool: &PoolUpsertDTO) -> Result<(), ValidationError> {
+    if pool.task_ids.is_empty() && pool.tag.is_none() {
+        return Err(ValidationError::new("empty_pool")
+            .with_message("A pool needs tasks or a tag to draw from".into()));
+    }
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, ToSchema, Validate)]
 pub struct TaskVerdictPatchRequest {
     #[validate(range(min = 0))]
@@ -171,12 +382,183 @@ pub struct TaskVerdictPatchRequest {
     pub verdict: TaskVerdict,
 }
 
+/// Grades a task by its rubric: `levels[i]` is the index of the level picked
+/// for the `i`-th criterion.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct RubricGradeRequest {
+    #[validate(range(min = 0))]
+    pub task_id: i32,
+    pub levels: Vec<usize>,
+    /// Added below the filled-in rubric in the verdict's comment.
+    #[validate(length(max = 5000))]
+    pub comment: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct AttemptVisibilityPatchRequest {
     pub show_results: bool,
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LatePenaltyWaiverRequest {
+    pub waived: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct RegradeQuery {
+    /// `false` only previews the changes.
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// Score change of one task in a regraded attempt.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TaskRegradeDTO {
+    pub task_id: i64,
+    pub old_revision: i32,
+    pub new_revision: i32,
+    pub old_score: f64,
+    pub new_score: f64,
+}
+
+/// How regrading against the latest task revisions changes an attempt.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AttemptRegradeDTO {
+    pub attempt_id: Uuid,
+    pub user_id: Uuid,
+    pub old_score: f64,
+    pub new_score: f64,
+    pub old_max_score: i64,
+    pub new_max_score: i64,
+    /// Tasks whose revision or score changes.
+    pub tasks: Vec<TaskRegradeDTO>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct SimilarityQuery {
+    /// Similarity from which a pair of answers is reported.
+    #[validate(range(min = 0.0, max = 1.0))]
+    #[serde(default = "SimilarityQuery::default_threshold")]
+    pub threshold: f64,
+}
+
+impl SimilarityQuery {
+    const fn default_threshold() -> f64 {
+        DEFAULT_THRESHOLD
+    }
+}
+
+/// Two learners' answers to a task that are suspiciously alike.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SimilarAnswersDTO {
+    pub first_attempt_id: Uuid,
+    pub first_user_id: Uuid,
+    pub first_username: String,
+    pub second_attempt_id: Uuid,
+    pub second_user_id: Uuid,
+    pub second_username: String,
+    /// Jaccard index of the answers' word 3-grams, from 0 to 1.
+    pub similarity: f64,
+    /// The longest passages both answers have, as lowercase words.
+    pub fragments: Vec<String>,
+}
+
+/// Similar answers to one free-text task of an exam, the most similar first.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TaskSimilarityDTO {
+    pub task_id: i64,
+    pub title: String,
+    /// Answers long enough to be compared.
+    pub answers_compared: usize,
+    pub pairs: Vec<SimilarAnswersDTO>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct ExamImportQuery {
+    /// Topic the imported exam goes to.
+    #[validate(range(min = 1))]
+    pub topic_id: i32,
+    /// Actually create the exam; without it the import is a dry run.
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// Outcome of importing a QTI assessment test as an exam.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExamImportReportDTO {
+    pub committed: bool,
+    /// Id of the created exam, set only when the import is committed.
+    pub exam_id: Option<Uuid>,
+    pub name: String,
+    /// Number of text blocks (rubric blocks of the test).
+    pub texts: usize,
+    /// The test's items; skipped ones are left out of the exam.
+    pub tasks: TaskImportReportDTO,
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct ExamScoringAmount {
     pub amount: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(score: f64) -> TaskVerdict {
+        TaskVerdict::PartialScore {
+            comment: None,
+            score,
+            max_score: 10f64,
+        }
+    }
+
+    fn scoring(scores: &[(usize, f64)], late_penalties: &[(usize, f64)]) -> ScoringData {
+        ScoringData {
+            results: scores
+                .iter()
+                .map(|(task_id, score)| (*task_id, verdict(*score)))
+                .chain([(99, TaskVerdict::OnReview)])
+                .collect(),
+            late_penalties: late_penalties.iter().copied().collect(),
+            ..ScoringData::default()
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} instead of {expected}"
+        );
+    }
+
+    #[test]
+    fn total_score_sums_the_tasks() {
+        assert_close(scoring(&[(1, 4f64), (2, 6f64)], &[]).total_score(), 10f64);
+        // negative marking takes points from the other tasks
+        assert_close(scoring(&[(1, 4f64), (2, -1f64)], &[]).total_score(), 3f64);
+    }
+
+    #[test]
+    fn total_score_is_never_negative() {
+        assert_close(scoring(&[(1, -2f64), (2, -3f64)], &[]).total_score(), 0f64);
+        assert_close(scoring(&[], &[]).total_score(), 0f64);
+    }
+
+    #[test]
+    fn late_penalty_is_taken_unless_waived() {
+        // a negative score isn't penalized, a penalty for no result is ignored
+        let mut late = scoring(
+            &[(1, 4f64), (2, 6f64), (3, -1f64)],
+            &[(1, 0.5), (3, 0.5), (7, 1f64)],
+        );
+        assert_close(late.late_penalty(), 2f64);
+        assert_close(late.total_score(), 7f64);
+        late.late_penalty_waived = true;
+        assert_close(late.late_penalty(), 2f64);
+        assert_close(late.total_score(), 9f64);
+
+        let all_late = scoring(&[(1, 1f64)], &[(1, 1f64)]);
+        assert_close(all_late.total_score(), 0f64);
+    }
+}
@@ -1,13 +1,24 @@
-use crate::domain::exam::model::{ExamExtendedEntity, TextEntity};
-use crate::domain::task::model::{PublicTaskConfig, Task, TaskAnswer, TaskConfig, TaskType};
+use crate::domain::exam::model::{ExamExtendedEntity, PoolEntity, SectionEntity, TextEntity};
+use crate::domain::task::interchange::InterchangeFormat;
+use crate::domain::task::model::{
+    PublicTaskConfig, Task, TaskAnswer, TaskConfig, TaskDifficulty, TaskType,
+};
+use crate::domain::task::rubric;
+use crate::domain::task::template::TaskTemplate;
 use serde::{Deserialize, Serialize};
 use serde_json::from_value;
 use sqlx::FromRow;
 use sqlx::types::JsonValue;
 use utoipa::ToSchema;
-use validator::Validate;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+/// Upper bound for the length of a single task tag.
+const TAG_MAX_LEN: usize = 32;
 
 #[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validate_template"))]
+#[validate(schema(function = "validate_rubric_points"))]
 pub struct UpsertTaskRequestDTO {
     #[validate(length(max = 50))]
     pub title: String,
@@ -17,14 +28,137 @@ pub struct UpsertTaskRequestDTO {
     pub points: i32,
     #[validate(nested)]
     pub configuration: TaskConfig,
+    /// Makes the task parameterized; see `domain::task::template`.
+    #[serde(default)]
+    pub template: TaskTemplate,
+    /// Stored lowercase, duplicates dropped.
+    #[serde(default)]
+    #[validate(length(max = 20), custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub difficulty: Option<TaskDifficulty>,
+    /// Shown to learners with the verdict once results are visible.
+    #[serde(default)]
+    #[validate(length(max = 5000))]
+    pub explanation: Option<String>,
+}
+
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags
+        .iter()
+        .any(|tag| tag.trim().is_empty() || tag.chars().count() > TAG_MAX_LEN)
+    {
+        return Err(ValidationError::new("invalid_tag").with_message(
+            format!("Tags must be non-empty and at most {TAG_MAX_LEN} characters long").into(),
+        ));
+    }
+    Ok(())
 }
 
+fn validate_template(task: &UpsertTaskRequestDTO) -> Result<(), ValidationError> {
+    let mut texts = vec![task.title.as_str()];
+    texts.extend(task.description.as_deref());
+    task.template
+        .check(&texts, &task.configuration)
+        .map_err(|e| ValidationError::new("invalid_template").with_message(e.into()))
+}
+
+fn validate_rubric_points(task: &UpsertTaskRequestDTO) -> Result<(), ValidationError> {
+    let rubric = task.configuration.rubric();
+    if !rubric.is_empty() && (rubric::max_points(rubric) - f64::from(task.points)).abs() > 1e-9 {
+        return Err(ValidationError::new("invalid_rubric_points")
+            .with_message("The rubric's best levels must add up to the task's points".into()));
+    }
+    Ok(())
+}
+
+/// Task bank listing: a page of tasks matching every filter that is set.
 #[derive(Serialize, Deserialize, ToSchema, Validate)]
-pub struct LimitOffsetDTO {
+pub struct TaskFilterDTO {
     #[validate(range(min = 0, max = 20))]
     pub limit: i32,
     #[validate(range(min = 0))]
     pub offset: i32,
+    pub task_type: Option<TaskType>,
+    pub tag: Option<String>,
+    pub difficulty: Option<TaskDifficulty>,
+    pub author_id: Option<Uuid>,
+    /// Only tasks that are in no exam, no exam's pool and no practice.
+    #[serde(default)]
+    pub unused: bool,
+    /// Only tasks used by an exam (pools included) or a practice of this
+    /// course.
+    pub course_id: Option<i32>,
+    /// Full-text search over title and description (web search syntax:
+    /// `"exact phrase"`, `-excluded`, `or`). Ranks results by relevance.
+    #[validate(length(max = 200))]
+    pub q: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TaskListResponseDTO {
+    pub tasks: Vec<Task>,
+    /// Number of tasks matching the filters, regardless of limit/offset.
+    pub total: i64,
+}
+
+/// Question formats of other LMSes the task bank can import and export.
+#[derive(Deserialize, Serialize, ToSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum InterchangeFormatDTO {
+    MoodleXml,
+    Gift,
+    /// IMS QTI 2.1 content package (zip)
+    Qti,
+}
+
+impl From<InterchangeFormatDTO> for InterchangeFormat {
+    fn from(value: InterchangeFormatDTO) -> Self {
+        match value {
+            InterchangeFormatDTO::MoodleXml => Self::MoodleXml,
+            InterchangeFormatDTO::Gift => Self::Gift,
+            InterchangeFormatDTO::Qti => Self::Qti,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Validate)]
+pub struct TaskImportQuery {
+    pub format: InterchangeFormatDTO,
+    /// Actually create the tasks; without it the import is a dry run.
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// Outcome of importing one question of the file.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ImportedQuestionDTO {
+    /// Position of the question in the file, from 0.
+    pub index: usize,
+    pub name: String,
+    /// `None` if the question is skipped.
+    pub task_type: Option<TaskType>,
+    /// Id of the created task, set only when the import is committed.
+    pub task_id: Option<i64>,
+    /// Everything that was lost or changed on the way.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TaskImportReportDTO {
+    pub committed: bool,
+    /// Number of questions that were (or, on a dry run, would be) created.
+    pub imported: usize,
+    pub skipped: usize,
+    pub questions: Vec<ImportedQuestionDTO>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Validate)]
+pub struct TaskExportRequestDTO {
+    pub format: InterchangeFormatDTO,
+    /// Tasks to export, in this order.
+    #[validate(length(min = 1, max = 500))]
+    pub task_ids: Vec<i32>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Validate)]
@@ -46,8 +180,19 @@ pub struct PublicTaskDTO {
 #[derive(Serialize, Deserialize, ToSchema, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum PubExamExtendedEntity {
-    Task { task: PublicTaskDTO },
-    Text { text: TextEntity },
+    Task {
+        task: PublicTaskDTO,
+    },
+    Text {
+        text: TextEntity,
+    },
+    /// Only seen by staff: in an attempt a pool is replaced by its draw.
+    Pool {
+        pool: PoolEntity,
+    },
+    Section {
+        section: SectionEntity,
+    },
 }
 
 impl From<ExamExtendedEntity> for PubExamExtendedEntity {
@@ -55,6 +200,8 @@ impl From<ExamExtendedEntity> for PubExamExtendedEntity {
         match value {
             ExamExtendedEntity::Task { task } => Self::Task { task: task.into() },
             ExamExtendedEntity::Text { text } => Self::Text { text },
+            ExamExtendedEntity::Pool { pool } => Self::Pool { pool },
+            ExamExtendedEntity::Section { section } => Self::Section { section },
         }
     }
 }
@@ -74,30 +221,10 @@ impl From<Task> for PublicTaskDTO {
 
 impl From<TaskConfig> for PublicTaskConfig {
     fn from(value: TaskConfig) -> Self {
-        match value {
-            TaskConfig::SingleChoice { options, .. } => Self::SingleChoice { options },
-
-            TaskConfig::MultipleChoice {
-                options,
-                partial_score,
-                ..
-            } => Self::MultipleChoice {
-                options,
-                partial_score,
-            },
-
-            TaskConfig::ShortText {
-                max_chars_count, ..
-            } => Self::ShortText { max_chars_count },
-
-            TaskConfig::LongText { max_chars_count } => Self::LongText { max_chars_count },
-
-            TaskConfig::Ordering { items, .. } => Self::Ordering { items },
-
-            TaskConfig::FileUpload { max_size } => Self::FileUpload { max_size },
-
-            TaskConfig::CTFd { task_id } => Self::CTFd { task_id },
-        }
+        value
+            .grader()
+            .public_config(value)
+            .expect("Graders are picked by the configuration's own type")
     }
 }
 
@@ -118,6 +245,12 @@ impl From<JsonValue> for TaskConfig {
     }
 }
 
+impl From<JsonValue> for TaskTemplate {
+    fn from(val: JsonValue) -> Self {
+        from_value(val).expect("Invalid JSON for TaskTemplate")
+    }
+}
+
 impl From<JsonValue> for TaskAnswer {
     fn from(val: JsonValue) -> Self {
         from_value(val).expect("Invalid JSON for TaskAnswer")
@@ -128,7 +261,8 @@ impl From<JsonValue> for TaskAnswer {
 #[serde(tag = "verdict", rename_all = "snake_case")]
 pub enum TaskVerdict {
     FullScore {
-        comment: Option<String>, // for manual review
+        /// Set by reviewers, or by grading from the task's explanations.
+        comment: Option<String>,
         score: f64,
         max_score: f64,
     },
@@ -154,4 +288,33 @@ impl TaskVerdict {
             Self::OnReview => &0f64,
         }
     }
+
+    /// The same verdict with `comment` set; `OnReview` has nowhere to keep it.
+    #[must_use]
+    pub fn with_comment(self, comment: String) -> Self {
+        match self {
+            Self::FullScore {
+                score, max_score, ..
+            } => Self::FullScore {
+                comment: Some(comment),
+                score,
+                max_score,
+            },
+            Self::PartialScore {
+                score, max_score, ..
+            } => Self::PartialScore {
+                comment: Some(comment),
+                score,
+                max_score,
+            },
+            Self::Incorrect {
+                score, max_score, ..
+            } => Self::Incorrect {
+                comment: Some(comment),
+                score,
+                max_score,
+            },
+            Self::OnReview => Self::OnReview,
+        }
+    }
 }
@@ -42,6 +42,8 @@ pub fn save_openapi() {
         client.clone(),
         config.ctfd_token.clone(),
         topic.clone(),
+        dummy.clone(),
+        None,
     );
     let lecture = LectureService::new(dummy.clone(), topic.clone());
     let oauth = OAuthService::new(dummy.clone(), dummy.clone());
@@ -149,14 +149,13 @@ impl AccountRepository for RepositoryPostgres {
         let tasks = sqlx::query_as!(
             TaskConfigStruct,
             r#"
-                SELECT t.configuration
+                SELECT tr.configuration
                 FROM attempts a
-                LEFT JOIN exam_entities et ON et.exam_id = a.exam_id
-                LEFT JOIN exams e ON e.id = a.exam_id
-                LEFT JOIN tasks t ON t.id = et.task_id
+                JOIN attempt_tasks at ON at.attempt_id = a.id
+                JOIN task_revisions tr ON tr.id = at.task_revision_id
                 WHERE
                     a.user_id = $1 AND a.ends_at > NOW() AND
-                    t.task_type = 'ctfd'
+                    tr.task_type = 'ctfd'
             "#,
             user_id
         )
@@ -161,7 +161,7 @@ impl CourseRepository for RepositoryPostgres {
         Ok(())
     }
 
-    async fn get_course_feed(&self, user_id: Uuid) -> Result<Vec<CourseModel>> {
+    async fn get_course_feed(&self, _user_id: Uuid) -> Result<Vec<CourseModel>> {
         todo!()
     }
 
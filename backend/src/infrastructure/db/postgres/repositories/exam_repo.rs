@@ -1,21 +1,27 @@
 use crate::domain::account::model::UserModel;
 use crate::domain::account::model::UserRole;
 use crate::domain::exam::model::{
-    Exam, ExamEntity, ExamEntityType, ExamExtendedEntity, ExamScoringPolicy, ExamType, TextEntity,
+    Exam, ExamEntity, ExamEntityType, ExamExtendedEntity, ExamLimits, ExamOverride,
+    ExamScoringPolicy, ExamType, ImportedExamEntity, LatePolicy, PendingCodeRun, PoolEntity,
+    SectionEntity, SectionVisit, TextEntity,
 };
 use crate::domain::exam::repository::ExamRepository;
 use crate::domain::task::model::TaskType;
-use crate::domain::task::model::{Task, TaskAnswer};
+use crate::domain::task::model::{Task, TaskAnswer, TaskDifficulty, TaskRevision};
+use crate::domain::task::rubric::RubricGrade;
 use crate::dto::exam::ScoringData;
-use crate::dto::exam::{ExamAnswer, ExamAttempt, UpsertExamRequestDTO};
+use crate::dto::exam::{
+    ExamAnswer, ExamAttempt, ExamOverrideUpsertDTO, PoolUpsertDTO, SectionUpsertDTO,
+    UpsertExamRequestDTO,
+};
 use crate::dto::task::TaskVerdict;
 use crate::errors::{LMSError, Result};
 use crate::infrastructure::db::postgres::RepositoryPostgres;
 use async_trait::async_trait;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 use serde_json::to_value;
 use sqlx::types::Json;
-use std::cmp::min;
+use sqlx::{Postgres, Transaction};
 use std::collections::HashMap;
 use tokio::try_join;
 use uuid::Uuid;
@@ -25,46 +31,7 @@ impl ExamRepository for RepositoryPostgres {
     async fn create(&self, exam_data: UpsertExamRequestDTO) -> Result<Exam> {
         let order_index = self.next_topic_order(exam_data.topic_id).await?;
         let mut tx = self.pool.begin().await?;
-
-        let exam = sqlx::query_as!(
-            Exam,
-            r#"
-                INSERT INTO exams
-                (topic_id, tries_count, duration, type, description, name, starts_at, ends_at, scoring_policy)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                RETURNING id, topic_id, tries_count, duration, type AS "type: ExamType", name, description, starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy"
-            "#,
-            exam_data.topic_id,
-            exam_data.tries_count,
-            exam_data.duration,
-            exam_data.r#type as ExamType,
-            exam_data.description,
-            exam_data.name,
-            exam_data.starts_at,
-            exam_data.ends_at,
-            exam_data.scoring_policy as ExamScoringPolicy
-        )
-        .fetch_one(tx.as_mut())
-        .await
-        .map_err(|err| match err {
-            sqlx::Error::Database(ref e) if e.is_foreign_key_violation() => {
-                LMSError::Conflict("Topic with such id doesn't exist".to_string())
-            }
-            _ => LMSError::DatabaseError(err),
-        })?;
-
-        let _ = sqlx::query!(
-            r#"
-                INSERT INTO exam_ordering (exam_id, topic_id, order_index)
-                VALUES ($1, $2, $3);
-            "#,
-            exam.id,
-            exam.topic_id,
-            order_index
-        )
-        .execute(tx.as_mut())
-        .await?;
-
+        let exam = Self::insert_exam(&mut tx, exam_data, order_index).await?;
         tx.commit().await?;
 
         Ok(exam)
@@ -74,7 +41,7 @@ impl ExamRepository for RepositoryPostgres {
         let exam = sqlx::query_as!(
             Exam,
             r#"
-                SELECT id, topic_id, name, description, tries_count, duration, type AS "type: ExamType", starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy"
+                SELECT id, topic_id, name, description, tries_count, duration, type AS "type: ExamType", starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy", shuffle_entities, late_policy AS "late_policy: Json<LatePolicy>"
                 FROM exams
                 WHERE id = $1
             "#,
@@ -105,9 +72,11 @@ impl ExamRepository for RepositoryPostgres {
                     description = $6,
                     starts_at = $7,
                     ends_at = $8,
-                    scoring_policy = $9
-                WHERE id = $10
-                RETURNING id, topic_id, tries_count, name, description, duration, type AS "type: ExamType", starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy"
+                    scoring_policy = $9,
+                    shuffle_entities = $10,
+                    late_policy = $11
+                WHERE id = $12
+                RETURNING id, topic_id, tries_count, name, description, duration, type AS "type: ExamType", starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy", shuffle_entities, late_policy AS "late_policy: Json<LatePolicy>"
             "#,
             exam_data.topic_id,
             exam_data.tries_count,
@@ -118,6 +87,8 @@ impl ExamRepository for RepositoryPostgres {
             exam_data.starts_at,
             exam_data.ends_at,
             exam_data.scoring_policy as ExamScoringPolicy,
+            exam_data.shuffle_entities,
+            exam_data.late_policy.map(|policy| to_value(policy).expect("Something bad happened with LatePolicy")),
             id
         )
         .fetch_one(&self.pool)
@@ -155,11 +126,13 @@ impl ExamRepository for RepositoryPostgres {
 
     async fn get_entities(&self, id: Uuid) -> Result<Vec<ExamExtendedEntity>> {
         // TODO: yes, this is shit and a terrible implementation, but right now I need PoC, and I promise to fix it later
-        if let Ok((tasks, texts)) = try_join!(sqlx::query_as!(
+        if let Ok((tasks, texts, pools, sections)) = try_join!(sqlx::query_as!(
             Task,
             r#"
                 SELECT
-                    t.id, t.title, t.description, t.task_type AS "task_type: TaskType", t.points, t.configuration
+                    t.id, t.title, t.description, t.task_type AS "task_type: TaskType", t.points, t.configuration,
+                    t.template, t.tags, t.difficulty AS "difficulty: TaskDifficulty", t.author_id,
+                    t.explanation
                 FROM exam_entities et
                 LEFT JOIN tasks t ON et.task_id = t.id
                 WHERE et.exam_id = $1 AND et.entity_type = 'task'
@@ -177,11 +150,34 @@ impl ExamRepository for RepositoryPostgres {
             "#,
             id
         )
+        .fetch_all(&self.pool), sqlx::query_as!(
+            PoolEntity,
+            r#"
+                SELECT
+                    p.id, p.count, p.task_ids, p.tag, p.balance_difficulty
+                FROM exam_entities et
+                JOIN exam_pools p ON et.pool_id = p.id
+                WHERE et.exam_id = $1 AND et.entity_type = 'pool'
+            "#,
+            id
+        )
+        .fetch_all(&self.pool), sqlx::query_as!(
+            SectionEntity,
+            r#"
+                SELECT
+                    s.id, s.title, s.duration, s.locked_after_leaving
+                FROM exam_entities et
+                JOIN exam_sections s ON et.section_id = s.id
+                WHERE et.exam_id = $1 AND et.entity_type = 'section'
+            "#,
+            id
+        )
         .fetch_all(&self.pool)) {
             let mut orders = sqlx::query!(
             r#"
                 SELECT
-                    et.order_index, et.entity_type as "entity_type: ExamEntityType", et.task_id, et.text_id
+                    et.order_index, et.entity_type as "entity_type: ExamEntityType", et.task_id, et.text_id,
+                    et.pool_id, et.section_id
                 FROM exam_entities et
                 WHERE et.exam_id = $1
                 ORDER BY et.order_index ASC
@@ -206,6 +202,20 @@ impl ExamRepository for RepositoryPostgres {
                             text: text.clone(),
                         })
                     }
+                    ExamEntityType::Pool => {
+                        let pool_id = row.pool_id.expect("Pool id should exist since entity type is pool");
+                        let pool = pools.iter().find(|p| p.id == pool_id).expect("Pool should exist since entity type is pool");
+                        (row.order_index, ExamExtendedEntity::Pool {
+                            pool: pool.clone(),
+                        })
+                    }
+                    ExamEntityType::Section => {
+                        let section_id = row.section_id.expect("Section id should exist since entity type is section");
+                        let section = sections.iter().find(|s| s.id == section_id).expect("Section should exist since entity type is section");
+                        (row.order_index, ExamExtendedEntity::Section {
+                            section: section.clone(),
+                        })
+                    }
                 }
             }).collect::<Vec<(i32, ExamExtendedEntity)>>();
             orders.sort_by_key(|x1| x1.0);
@@ -216,64 +226,55 @@ impl ExamRepository for RepositoryPostgres {
         ))
     }
 
-    #[allow(clippy::cast_possible_wrap)]
-    #[allow(clippy::cast_possible_truncation)]
     async fn update_entities(&self, id: Uuid, tasks: Vec<ExamEntity>) -> Result<()> {
         let mut tx = self.pool.begin().await?;
+        Self::replace_entities(&mut tx, id, &tasks).await?;
+        tx.commit().await?;
 
-        let _ = sqlx::query!(
-            r#"
-                DELETE FROM exam_entities
-                WHERE exam_id = $1
-            "#,
-            id
-        )
-        .execute(tx.as_mut())
-        .await?;
+        Ok(())
+    }
 
-        let _ = sqlx::query!(
-            r#"
-                INSERT INTO exam_entities (exam_id, task_id, text_id, entity_type, order_index)
-                SELECT
-                    $1,
-                    NULLIF(x.task_id, -1),
-                    NULLIF(x.text_id, '00000000-0000-0000-0000-000000000000'),
-                    CASE WHEN x.task_id <> -1
-                        THEN 'task'::EXAM_ENTITY_TYPE
-                        ELSE 'text'::EXAM_ENTITY_TYPE
-                    END,
-                    x.order_index
-                FROM UNNEST($2::INT[], $3::UUID[], $4::INT[]) AS x(task_id, text_id, order_index)
-            "#,
-            id,
-            &tasks
-                .iter()
-                .map(|t| match t {
-                    ExamEntity::Task { id } => *id,
-                    ExamEntity::Text { .. } => -1,
-                })
-                .collect::<Vec<i32>>(),
-            &tasks
-                .iter()
-                .map(|t| match t {
-                    ExamEntity::Text { id } => *id,
-                    ExamEntity::Task { .. } => Uuid::nil(),
-                })
-                .collect::<Vec<Uuid>>(),
-            &(0..tasks.len()).map(|x| x as i32).collect::<Vec<i32>>()
-        )
-        .execute(tx.as_mut())
-        .await
-        .map_err(|err| match err {
-            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
-                LMSError::Conflict("You should provide existing task & text ids.".to_string())
-            }
-            _ => LMSError::DatabaseError(err),
-        })?;
+    async fn import_exam(
+        &self,
+        exam_data: UpsertExamRequestDTO,
+        entities: Vec<ImportedExamEntity>,
+        author_id: Uuid,
+    ) -> Result<(Exam, Vec<Task>)> {
+        let order_index = self.next_topic_order(exam_data.topic_id).await?;
+        let mut tx = self.pool.begin().await?;
+        let exam = Self::insert_exam(&mut tx, exam_data, order_index).await?;
 
+        let mut tasks = Vec::new();
+        let mut exam_entities = Vec::with_capacity(entities.len());
+        for entity in entities {
+            match entity {
+                ImportedExamEntity::Task(config) => {
+                    let task = Self::insert_task(&mut tx, config, author_id).await?;
+                    let id = i32::try_from(task.id).map_err(|_| {
+                        LMSError::ServerError("Task id is out of range".to_string())
+                    })?;
+                    exam_entities.push(ExamEntity::Task { id });
+                    tasks.push(task);
+                }
+                ImportedExamEntity::Text(text) => {
+                    let id = sqlx::query_scalar!(
+                        r#"
+                            INSERT INTO exam_texts (text)
+                            VALUES ($1)
+                            RETURNING id
+                        "#,
+                        text
+                    )
+                    .fetch_one(tx.as_mut())
+                    .await?;
+                    exam_entities.push(ExamEntity::Text { id });
+                }
+            }
+        }
+        Self::replace_entities(&mut tx, exam.id, &exam_entities).await?;
         tx.commit().await?;
 
-        Ok(())
+        Ok((exam, tasks))
     }
 
     async fn get_exam_attempts(
@@ -285,7 +286,7 @@ impl ExamRepository for RepositoryPostgres {
         let attempts: Vec<ExamAttempt> = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1
@@ -306,7 +307,7 @@ impl ExamRepository for RepositoryPostgres {
         let attempts: Vec<ExamAttempt> = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1
@@ -324,12 +325,12 @@ impl ExamRepository for RepositoryPostgres {
         let attempts: Vec<ExamAttempt> = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1
-                AND scoring_data = '{"results": {}, "show_results": false}'::jsonb
-                AND ends_at < NOW()
+                AND scored_at IS NULL
+                AND due_at <= NOW()
                 ORDER BY started_at ASC
             "#,
             exam_id
@@ -340,11 +341,149 @@ impl ExamRepository for RepositoryPostgres {
         Ok(attempts)
     }
 
+    async fn claim_expired_attempts(
+        &self,
+        limit: i64,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<ExamAttempt>> {
+        let attempts: Vec<ExamAttempt> = sqlx::query_as!(
+            ExamAttempt,
+            r#"
+                UPDATE attempts
+                SET scoring_retry_at = $2
+                WHERE id IN (
+                    SELECT id
+                    FROM attempts
+                    WHERE scored_at IS NULL
+                    AND due_at <= NOW()
+                    AND (scoring_retry_at IS NULL OR scoring_retry_at <= NOW())
+                    ORDER BY due_at ASC
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
+                answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
+            "#,
+            limit,
+            until
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(attempts)
+    }
+
+    async fn record_scoring_error(&self, attempt_id: Uuid, error: String) -> Result<i32> {
+        let failures = sqlx::query_scalar!(
+            r#"
+                UPDATE attempts
+                SET scoring_error = $2, scoring_failures = scoring_failures + 1
+                WHERE id = $1
+                RETURNING scoring_failures
+            "#,
+            attempt_id,
+            error
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(failures)
+    }
+
+    async fn postpone_scoring(&self, attempt_id: Uuid, until: DateTime<Utc>) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET scoring_retry_at = $2
+                WHERE id = $1
+            "#,
+            attempt_id,
+            until
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_scoring_errors(&self, exam_id: Uuid) -> Result<Vec<(Uuid, String)>> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT id, scoring_error AS "scoring_error!"
+                FROM attempts
+                WHERE exam_id = $1 AND scored_at IS NULL AND scoring_error IS NOT NULL
+            "#,
+            exam_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.id, r.scoring_error)).collect())
+    }
+
+    async fn claim_stale_similarity(&self, limit: i64) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+                UPDATE exams
+                SET similarity_stale = FALSE
+                WHERE id IN (
+                    SELECT id
+                    FROM exams
+                    WHERE similarity_stale
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    async fn set_similar_answers(&self, exam_id: Uuid, attempt_ids: &[Uuid]) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET similar_answers = (id = ANY($2))
+                WHERE exam_id = $1 AND similar_answers <> (id = ANY($2))
+            "#,
+            exam_id,
+            attempt_ids
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_similarity_stale(&self, exam_id: Uuid) -> Result<()> {
+        let _ = sqlx::query!(
+            "UPDATE exams SET similarity_stale = TRUE WHERE id = $1",
+            exam_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_similar_attempts(&self, exam_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar!(
+            "SELECT id FROM attempts WHERE exam_id = $1 AND similar_answers",
+            exam_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
     async fn get_user_attempts_in_exam(&self, id: Uuid, user_id: Uuid) -> Result<Vec<ExamAttempt>> {
         let attempts: Vec<ExamAttempt> = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1 AND user_id = $2
@@ -363,7 +502,7 @@ impl ExamRepository for RepositoryPostgres {
         let attempt = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1 AND user_id = $2
@@ -383,31 +522,59 @@ impl ExamRepository for RepositoryPostgres {
         Ok(attempt)
     }
 
-    async fn stop_attempt(&self, attempt_id: Uuid) -> Result<()> {
-        let _ = sqlx::query!(
+    async fn get_attempt(&self, attempt_id: Uuid) -> Result<ExamAttempt> {
+        let attempt = sqlx::query_as!(
+            ExamAttempt,
             r#"
-                UPDATE attempts
-                SET ends_at = NOW()
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
+                answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
+                FROM attempts
                 WHERE id = $1
             "#,
             attempt_id
+        )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::RowNotFound => LMSError::NotFound("Attempt not found".to_string()),
+                _ => LMSError::DatabaseError(err),
+            })?;
+
+        Ok(attempt)
+    }
+
+    async fn stop_attempt(&self, attempt_id: Uuid, scoring_until: DateTime<Utc>) -> Result<bool> {
+        let stopped = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET ends_at = NOW(), due_at = NOW(), scoring_retry_at = $2
+                WHERE id = $1 AND due_at > NOW()
+            "#,
+            attempt_id,
+            scoring_until
         )
         .execute(&self.pool)
-        .await?;
+        .await?
+        .rows_affected()
+            > 0;
 
-        Ok(())
+        Ok(stopped)
     }
 
-    #[allow(clippy::cast_sign_loss)]
-    async fn start_exam(&self, id: Uuid, user_id: Uuid) -> Result<ExamAttempt> {
+    #[allow(clippy::too_many_lines)]
+    async fn start_exam(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        limits: &ExamLimits,
+        draws: &[(Uuid, i32)],
+    ) -> Result<ExamAttempt> {
         let mut tx = self.pool.begin().await?;
 
-        let exam = self.get(id).await?;
-
         let attempts: Vec<ExamAttempt> = sqlx::query_as!(
             ExamAttempt,
             r#"
-                SELECT id, exam_id, user_id, started_at, ends_at,
+                SELECT id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
                 FROM attempts
                 WHERE exam_id = $1 AND user_id = $2
@@ -418,41 +585,275 @@ impl ExamRepository for RepositoryPostgres {
             .fetch_all(tx.as_mut())
             .await?;
 
-        if attempts.iter().any(|att| att.ends_at > Utc::now())
-            || (attempts.len() >= exam.tries_count as usize && exam.tries_count != 0)
+        if attempts.iter().any(|att| att.due_at > Utc::now())
+            || limits.ran_out_of_attempts(attempts.len())
         {
             return Err(LMSError::Conflict(
                 "You can't start exam: you either have an active attempt or ran out of attempts"
                     .to_string(),
             ));
         }
+        // a new attempt gives up the late window of the previous one, so only
+        // one ever takes answers
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET ends_at = NOW()
+                WHERE exam_id = $1 AND user_id = $2 AND ends_at > NOW()
+            "#,
+            id,
+            user_id
+        )
+        .execute(tx.as_mut())
+        .await?;
 
+        let started_at = Utc::now();
+        let due_at = limits.due_at(started_at);
         let empty_answer_data = ExamAnswer::default();
         let empty_scoring_data = ScoringData::default();
         let attempt: ExamAttempt = sqlx::query_as!(
             ExamAttempt,
             r#"
-                INSERT INTO attempts (exam_id, user_id, answer_data, scoring_data, started_at, ends_at)
-                VALUES ($1, $2, $3, $4, $5, $6)
-                RETURNING id, exam_id, user_id, started_at, ends_at,
+                INSERT INTO attempts (exam_id, user_id, answer_data, scoring_data, started_at, ends_at, due_at, shuffle_seed)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id, exam_id, user_id, started_at, ends_at, due_at, shuffle_seed,
                 answer_data as "answer_data: Json<ExamAnswer>", scoring_data as "scoring_data: Json<ScoringData>"
             "#,
             id,
             user_id,
             to_value(empty_answer_data).expect("Something bad happened with ExamAnswer data"),
             to_value(empty_scoring_data).expect("Something bad happened with ScoringData"),
-            Utc::now(),
-            min(Utc::now() + Duration::seconds(i64::from(exam.duration)), exam.ends_at.unwrap_or(DateTime::<Utc>::MAX_UTC))
+            started_at,
+            limits.closes_at(due_at),
+            due_at,
+            rand::random::<i64>()
         )
             .fetch_one(tx.as_mut())
             .await?;
 
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO attempt_tasks (attempt_id, task_revision_id)
+                SELECT $1, latest.id
+                FROM exam_entities ee
+                    JOIN LATERAL (
+                        SELECT tr.id
+                        FROM task_revisions tr
+                        WHERE tr.task_id = ee.task_id
+                        ORDER BY tr.revision DESC
+                        LIMIT 1
+                    ) latest ON TRUE
+                WHERE ee.exam_id = $2 AND ee.entity_type = 'task'
+            "#,
+            attempt.id,
+            id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let pool_ids: Vec<Uuid> = draws.iter().map(|(pool_id, _)| *pool_id).collect();
+        let task_ids: Vec<i32> = draws.iter().map(|(_, task_id)| *task_id).collect();
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO attempt_tasks (attempt_id, task_revision_id)
+                SELECT $1, latest.id
+                FROM UNNEST($2::INT[]) AS d(task_id)
+                    JOIN LATERAL (
+                        SELECT tr.id
+                        FROM task_revisions tr
+                        WHERE tr.task_id = d.task_id
+                        ORDER BY tr.revision DESC
+                        LIMIT 1
+                    ) latest ON TRUE
+                ON CONFLICT DO NOTHING
+            "#,
+            attempt.id,
+            &task_ids
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO attempt_pool_draws (attempt_id, pool_id, task_id)
+                SELECT $1, d.pool_id, d.task_id
+                FROM UNNEST($2::UUID[], $3::INT[]) AS d(pool_id, task_id)
+                ON CONFLICT DO NOTHING
+            "#,
+            attempt.id,
+            &pool_ids,
+            &task_ids
+        )
+        .execute(tx.as_mut())
+        .await?;
+
         tx.commit().await?;
 
         Ok(attempt)
     }
 
-    async fn modify_attempt(
+    async fn get_attempt_draws(&self, attempt_id: Uuid) -> Result<Vec<(Uuid, i32)>> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT pool_id, task_id
+                FROM attempt_pool_draws
+                WHERE attempt_id = $1
+            "#,
+            attempt_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.pool_id, r.task_id)).collect())
+    }
+
+    async fn get_attempts_tasks(&self, attempt_ids: &[Uuid]) -> Result<Vec<(Uuid, TaskRevision)>> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT at.attempt_id, tr.id, tr.task_id, tr.revision, tr.created_at, tr.title,
+                       tr.description, tr.task_type AS "task_type: TaskType", tr.points, tr.configuration,
+                       tr.template, tr.tags, tr.difficulty AS "difficulty: TaskDifficulty", tr.author_id,
+                       tr.explanation
+                FROM attempt_tasks at
+                    JOIN task_revisions tr ON tr.id = at.task_revision_id
+                WHERE at.attempt_id = ANY($1)
+                ORDER BY tr.task_id
+            "#,
+            attempt_ids
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                row.attempt_id,
+                TaskRevision {
+                    id: row.id,
+                    revision: row.revision,
+                    created_at: row.created_at,
+                    task: Task {
+                        id: i64::from(row.task_id),
+                        title: row.title,
+                        description: row.description,
+                        task_type: row.task_type,
+                        points: i64::from(row.points),
+                        configuration: row.configuration.into(),
+                        template: row.template.into(),
+                        tags: row.tags,
+                        difficulty: row.difficulty,
+                        author_id: row.author_id,
+                        explanation: row.explanation,
+                    },
+                },
+            )
+        })
+        .collect();
+
+        Ok(rows)
+    }
+
+    async fn get_latest_task_revisions(&self, task_ids: &[i32]) -> Result<Vec<TaskRevision>> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT DISTINCT ON (task_id) id, task_id, revision, created_at, title, description,
+                       task_type AS "task_type: TaskType", points, configuration, template, tags,
+                       difficulty AS "difficulty: TaskDifficulty", author_id, explanation
+                FROM task_revisions
+                WHERE task_id = ANY($1)
+                ORDER BY task_id, revision DESC
+            "#,
+            task_ids
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| TaskRevision {
+            id: row.id,
+            revision: row.revision,
+            created_at: row.created_at,
+            task: Task {
+                id: i64::from(row.task_id),
+                title: row.title,
+                description: row.description,
+                task_type: row.task_type,
+                points: i64::from(row.points),
+                configuration: row.configuration.into(),
+                template: row.template.into(),
+                tags: row.tags,
+                difficulty: row.difficulty,
+                author_id: row.author_id,
+                explanation: row.explanation,
+            },
+        })
+        .collect();
+
+        Ok(rows)
+    }
+
+    async fn repin_attempt(
+        &self,
+        attempt_id: Uuid,
+        revision_ids: &[i64],
+        attempt_score: &ScoringData,
+        code_task_ids: &[i32],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let _ = sqlx::query!(
+            r#"
+                DELETE FROM attempt_tasks
+                WHERE attempt_id = $1
+            "#,
+            attempt_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO attempt_tasks (attempt_id, task_revision_id)
+                SELECT $1, UNNEST($2::BIGINT[])
+            "#,
+            attempt_id,
+            revision_ids
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET scoring_data = $1, scored_at = NOW(), scoring_error = NULL,
+                    scoring_failures = 0, scoring_retry_at = NULL
+                WHERE id = $2
+            "#,
+            to_value(attempt_score).expect("Something bad happened with ScoringData"),
+            attempt_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO pending_code_runs (attempt_id, task_id, answer)
+                SELECT a.id, t.task_id, a.answer_data -> 'answers' -> t.task_id::TEXT
+                FROM attempts a, UNNEST($2::INTEGER[]) AS t(task_id)
+                WHERE a.id = $1
+                ON CONFLICT (attempt_id, task_id)
+                DO UPDATE SET queued_at = NOW(), claimed_until = NOW(), answer = EXCLUDED.answer
+            "#,
+            attempt_id,
+            code_task_ids
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn modify_attempt(
         &self,
         exam_id: Uuid,
         user_id: Uuid,
@@ -462,17 +863,21 @@ impl ExamRepository for RepositoryPostgres {
         let mut attempts = self.get_user_attempts_in_exam(exam_id, user_id).await?;
         if let Some(active_attempt) = attempts.iter_mut().find(|a| a.ends_at > Utc::now()) {
             let mut answer_data = active_attempt.answer_data.clone();
-            answer_data.answers.insert(task_id, answer);
+            answer_data.put(task_id, answer, active_attempt.due_at);
             active_attempt.answer_data = answer_data;
             let _ = sqlx::query!(
                 r#"
                     UPDATE attempts
-                    SET answer_data = $1
+                    SET answer_data = $1, scored_at = NULL, scoring_retry_at = NULL,
+                        scoring_data = scoring_data #- ARRAY['results', $3]
+                                                    #- ARRAY['rubrics', $3]
+                                                    #- ARRAY['late_penalties', $3]
                     WHERE id = $2
                 "#,
                 to_value(&active_attempt.answer_data)
                     .expect("Something bad happened with AnswerData"),
-                active_attempt.id
+                active_attempt.id,
+                task_id.to_string()
             )
             .execute(&self.pool)
             .await?;
@@ -484,20 +889,136 @@ impl ExamRepository for RepositoryPostgres {
     async fn update_attempt_score(
         &self,
         attempt_id: Uuid,
+        answer_data: &ExamAnswer,
         attempt_score: &ScoringData,
-    ) -> Result<()> {
-        let _ = sqlx::query!(
+        code_task_ids: &[i32],
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let scored = sqlx::query!(
             r#"
                 UPDATE attempts
-                SET scoring_data = $1
-                WHERE id = $2
+                SET scoring_data = $1, scored_at = NOW(), scoring_error = NULL,
+                    scoring_failures = 0, scoring_retry_at = NULL
+                WHERE id = $2 AND answer_data = $3
             "#,
             to_value(&attempt_score).expect("Something bad happened with ScoringData"),
+            attempt_id,
+            to_value(answer_data).expect("Something bad happened with AnswerData")
+        )
+        .execute(tx.as_mut())
+        .await?
+        .rows_affected()
+            > 0;
+        if !scored {
+            return Ok(false);
+        }
+
+        let _ = sqlx::query!(
+            r#"
+                UPDATE exams
+                SET similarity_stale = TRUE
+                WHERE id = (SELECT exam_id FROM attempts WHERE id = $1)
+            "#,
             attempt_id
         )
-        .execute(&self.pool)
+        .execute(tx.as_mut())
         .await?;
 
+        // a run already queued for the same answer is left to finish
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO pending_code_runs (attempt_id, task_id, answer)
+                SELECT a.id, t.task_id, a.answer_data -> 'answers' -> t.task_id::TEXT
+                FROM attempts a, UNNEST($2::INTEGER[]) AS t(task_id)
+                WHERE a.id = $1
+                ON CONFLICT (attempt_id, task_id)
+                DO UPDATE SET queued_at = NOW(), claimed_until = NOW(), answer = EXCLUDED.answer
+                WHERE pending_code_runs.answer IS DISTINCT FROM EXCLUDED.answer
+            "#,
+            attempt_id,
+            code_task_ids
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn claim_code_runs(
+        &self,
+        limit: i64,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<PendingCodeRun>> {
+        let runs = sqlx::query_as!(
+            PendingCodeRun,
+            r#"
+                UPDATE pending_code_runs
+                SET claimed_until = $2
+                WHERE (attempt_id, task_id) IN (
+                    SELECT attempt_id, task_id
+                    FROM pending_code_runs
+                    WHERE claimed_until <= NOW()
+                    ORDER BY queued_at ASC
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING attempt_id, task_id, queued_at
+            "#,
+            limit,
+            until
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    async fn finish_code_run(
+        &self,
+        run: &PendingCodeRun,
+        verdict: Option<TaskVerdict>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let queued = sqlx::query!(
+            r#"
+                DELETE FROM pending_code_runs
+                WHERE attempt_id = $1 AND task_id = $2 AND queued_at = $3
+            "#,
+            run.attempt_id,
+            run.task_id,
+            run.queued_at
+        )
+        .execute(tx.as_mut())
+        .await?
+        .rows_affected()
+            > 0;
+
+        if queued && let Some(verdict) = verdict {
+            let _ = sqlx::query!(
+                r#"
+                    UPDATE attempts
+                    SET scoring_data = jsonb_set(
+                        scoring_data,
+                        ARRAY['results', $1],
+                        to_jsonb($2::jsonb),
+                        true
+                    ) #- ARRAY['rubrics', $1]
+                    WHERE id = $3
+                "#,
+                run.task_id.to_string(),
+                to_value(verdict).expect("Something bad happened with TaskVerdict data"),
+                run.attempt_id,
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 
@@ -641,6 +1162,364 @@ impl ExamRepository for RepositoryPostgres {
         Ok(text_entity)
     }
 
+    async fn create_pool(&self, pool: PoolUpsertDTO) -> Result<PoolEntity> {
+        let pool_entity = sqlx::query_as!(
+            PoolEntity,
+            r#"
+                INSERT INTO exam_pools (count, task_ids, tag, balance_difficulty)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, count, task_ids, tag, balance_difficulty
+            "#,
+            pool.count,
+            &pool.task_ids,
+            pool.tag,
+            pool.balance_difficulty
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(pool_entity)
+    }
+
+    async fn update_pool(&self, id: Uuid, pool: PoolUpsertDTO) -> Result<PoolEntity> {
+        let pool_entity = sqlx::query_as!(
+            PoolEntity,
+            r#"
+                UPDATE exam_pools
+                SET count = $1, task_ids = $2, tag = $3, balance_difficulty = $4
+                WHERE id = $5
+                RETURNING id, count, task_ids, tag, balance_difficulty
+            "#,
+            pool.count,
+            &pool.task_ids,
+            pool.tag,
+            pool.balance_difficulty,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Pool with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+        Ok(pool_entity)
+    }
+
+    async fn delete_pool(&self, id: Uuid) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                DELETE FROM exam_pools
+                WHERE id = $1
+                RETURNING id
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Pool with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+        Ok(())
+    }
+
+    async fn create_section(&self, section: SectionUpsertDTO) -> Result<SectionEntity> {
+        let section_entity = sqlx::query_as!(
+            SectionEntity,
+            r#"
+                INSERT INTO exam_sections (title, duration, locked_after_leaving)
+                VALUES ($1, $2, $3)
+                RETURNING id, title, duration, locked_after_leaving
+            "#,
+            section.title,
+            section.duration,
+            section.locked_after_leaving
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(section_entity)
+    }
+
+    async fn update_section(&self, id: Uuid, section: SectionUpsertDTO) -> Result<SectionEntity> {
+        let section_entity = sqlx::query_as!(
+            SectionEntity,
+            r#"
+                UPDATE exam_sections
+                SET title = $1, duration = $2, locked_after_leaving = $3
+                WHERE id = $4
+                RETURNING id, title, duration, locked_after_leaving
+            "#,
+            section.title,
+            section.duration,
+            section.locked_after_leaving,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Section with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+        Ok(section_entity)
+    }
+
+    async fn delete_section(&self, id: Uuid) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                DELETE FROM exam_sections
+                WHERE id = $1
+                RETURNING id
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Section with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+        Ok(())
+    }
+
+    async fn get_section_visits(&self, attempt_id: Uuid) -> Result<Vec<SectionVisit>> {
+        let visits = sqlx::query_as!(
+            SectionVisit,
+            r#"
+                SELECT section_id, opened_at, left_at
+                FROM attempt_sections
+                WHERE attempt_id = $1
+                ORDER BY opened_at ASC
+            "#,
+            attempt_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(visits)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    async fn get_task_section(
+        &self,
+        attempt_id: Uuid,
+        task_id: i64,
+    ) -> Result<Option<SectionEntity>> {
+        let section = sqlx::query_as!(
+            SectionEntity,
+            r#"
+                SELECT s.id, s.title, s.duration, s.locked_after_leaving
+                FROM attempts a
+                    JOIN exam_entities placed ON placed.exam_id = a.exam_id
+                    JOIN LATERAL (
+                        SELECT et.section_id
+                        FROM exam_entities et
+                        WHERE et.exam_id = a.exam_id
+                          AND et.entity_type = 'section'
+                          AND et.order_index < placed.order_index
+                        ORDER BY et.order_index DESC
+                        LIMIT 1
+                    ) previous ON TRUE
+                    JOIN exam_sections s ON s.id = previous.section_id
+                WHERE a.id = $1
+                  AND ((placed.entity_type = 'task' AND placed.task_id = $2)
+                      OR (placed.entity_type = 'pool'
+                          AND EXISTS (SELECT 1
+                                      FROM attempt_pool_draws d
+                                      WHERE d.attempt_id = a.id
+                                        AND d.pool_id = placed.pool_id
+                                        AND d.task_id = $2)))
+                ORDER BY placed.order_index ASC
+                LIMIT 1
+            "#,
+            attempt_id,
+            task_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(section)
+    }
+
+    async fn enter_section(&self, attempt_id: Uuid, section_id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempt_sections
+                SET left_at = NOW()
+                WHERE attempt_id = $1 AND section_id <> $2 AND left_at IS NULL
+            "#,
+            attempt_id,
+            section_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO attempt_sections (attempt_id, section_id, opened_at)
+                VALUES ($1, $2, NOW())
+                ON CONFLICT (attempt_id, section_id) DO UPDATE SET left_at = NULL
+            "#,
+            attempt_id,
+            section_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_overrides(&self, exam_id: Uuid) -> Result<Vec<ExamOverride>> {
+        let overrides = sqlx::query_as!(
+            ExamOverride,
+            r#"
+                SELECT id, exam_id, user_id, attribute_key, attribute_value, time_multiplier,
+                       extra_minutes, extra_attempts, starts_at, ends_at
+                FROM exam_overrides
+                WHERE exam_id = $1
+                ORDER BY user_id NULLS LAST, attribute_key, attribute_value
+            "#,
+            exam_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(overrides)
+    }
+
+    async fn create_override(
+        &self,
+        exam_id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride> {
+        let exam_override = sqlx::query_as!(
+            ExamOverride,
+            r#"
+                INSERT INTO exam_overrides (exam_id, user_id, attribute_key, attribute_value,
+                                            time_multiplier, extra_minutes, extra_attempts,
+                                            starts_at, ends_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                RETURNING id, exam_id, user_id, attribute_key, attribute_value, time_multiplier,
+                          extra_minutes, extra_attempts, starts_at, ends_at
+            "#,
+            exam_id,
+            data.user_id,
+            data.attribute_key,
+            data.attribute_value,
+            data.time_multiplier,
+            data.extra_minutes,
+            data.extra_attempts,
+            data.starts_at,
+            data.ends_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                LMSError::NotFound("Exam or user with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+
+        Ok(exam_override)
+    }
+
+    async fn update_override(
+        &self,
+        exam_id: Uuid,
+        id: Uuid,
+        data: ExamOverrideUpsertDTO,
+    ) -> Result<ExamOverride> {
+        let exam_override = sqlx::query_as!(
+            ExamOverride,
+            r#"
+                UPDATE exam_overrides
+                SET user_id = $1, attribute_key = $2, attribute_value = $3, time_multiplier = $4,
+                    extra_minutes = $5, extra_attempts = $6, starts_at = $7, ends_at = $8
+                WHERE id = $9 AND exam_id = $10
+                RETURNING id, exam_id, user_id, attribute_key, attribute_value, time_multiplier,
+                          extra_minutes, extra_attempts, starts_at, ends_at
+            "#,
+            data.user_id,
+            data.attribute_key,
+            data.attribute_value,
+            data.time_multiplier,
+            data.extra_minutes,
+            data.extra_attempts,
+            data.starts_at,
+            data.ends_at,
+            id,
+            exam_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Override with such id doesn't exist".to_string())
+            }
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                LMSError::NotFound("User with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+
+        Ok(exam_override)
+    }
+
+    async fn delete_override(&self, exam_id: Uuid, id: Uuid) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                DELETE FROM exam_overrides
+                WHERE id = $1 AND exam_id = $2
+                RETURNING id
+            "#,
+            id,
+            exam_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => {
+                LMSError::NotFound("Override with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+        Ok(())
+    }
+
+    async fn get_pool_tasks(&self, pool: &PoolEntity) -> Result<Vec<Task>> {
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"
+                SELECT
+                    t.id, t.title, t.description, t.task_type AS "task_type: TaskType", t.points, t.configuration,
+                    t.template, t.tags, t.difficulty AS "difficulty: TaskDifficulty", t.author_id,
+                    t.explanation
+                FROM tasks t
+                WHERE t.id = ANY($1) OR ($2::TEXT IS NOT NULL AND t.tags @> ARRAY [$2])
+                ORDER BY t.id
+            "#,
+            &pool.task_ids,
+            pool.tag
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
     async fn update_attempt_verdict(
         &self,
         attempt_id: Uuid,
@@ -655,7 +1534,7 @@ impl ExamRepository for RepositoryPostgres {
                     ARRAY['results', $1],
                     to_jsonb($2::jsonb),
                     true
-                )
+                ) #- ARRAY['rubrics', $1]
                 WHERE id = $3
             "#,
             task_id.to_string(),
@@ -668,6 +1547,70 @@ impl ExamRepository for RepositoryPostgres {
         Ok(())
     }
 
+    async fn update_attempt_rubric(
+        &self,
+        attempt_id: Uuid,
+        task_id: i32,
+        verdict: TaskVerdict,
+        grades: &[RubricGrade],
+    ) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                UPDATE attempts
+                SET scoring_data = jsonb_set(
+                    jsonb_set(
+                        jsonb_set(
+                            scoring_data,
+                            ARRAY['results', $1],
+                            to_jsonb($2::jsonb),
+                            true
+                        ),
+                        '{rubrics}',
+                        COALESCE(scoring_data -> 'rubrics', '{}'::jsonb),
+                        true
+                    ),
+                    ARRAY['rubrics', $1],
+                    $3::jsonb,
+                    true
+                )
+                WHERE id = $4
+            "#,
+            task_id.to_string(),
+            to_value(verdict).expect("Something bad happened with TaskVerdict data"),
+            to_value(grades).expect("Something bad happened with rubric data"),
+            attempt_id,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_attempt_late_penalty_waiver(
+        &self,
+        attempt_id: Uuid,
+        waived: bool,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE attempts
+            SET scoring_data = jsonb_set(
+                scoring_data,
+                ARRAY['late_penalty_waived'],
+                to_jsonb($1::boolean),
+                true
+            )
+            WHERE id = $2
+            "#,
+            waived,
+            attempt_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     async fn update_attempt_visibility_by_id(
         &self,
         attempt_id: Uuid,
@@ -718,3 +1661,145 @@ impl ExamRepository for RepositoryPostgres {
         Ok(())
     }
 }
+
+impl RepositoryPostgres {
+    /// Creates an exam at `order_index` of its topic.
+    async fn insert_exam(
+        tx: &mut Transaction<'_, Postgres>,
+        exam_data: UpsertExamRequestDTO,
+        order_index: i32,
+    ) -> Result<Exam> {
+        let exam = sqlx::query_as!(
+            Exam,
+            r#"
+                INSERT INTO exams
+                (topic_id, tries_count, duration, type, description, name, starts_at, ends_at, scoring_policy, shuffle_entities, late_policy)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                RETURNING id, topic_id, tries_count, duration, type AS "type: ExamType", name, description, starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy", shuffle_entities, late_policy AS "late_policy: Json<LatePolicy>"
+            "#,
+            exam_data.topic_id,
+            exam_data.tries_count,
+            exam_data.duration,
+            exam_data.r#type as ExamType,
+            exam_data.description,
+            exam_data.name,
+            exam_data.starts_at,
+            exam_data.ends_at,
+            exam_data.scoring_policy as ExamScoringPolicy,
+            exam_data.shuffle_entities,
+            exam_data.late_policy.map(|policy| to_value(policy).expect("Something bad happened with LatePolicy"))
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(ref e) if e.is_foreign_key_violation() => {
+                LMSError::Conflict("Topic with such id doesn't exist".to_string())
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO exam_ordering (exam_id, topic_id, order_index)
+                VALUES ($1, $2, $3);
+            "#,
+            exam.id,
+            exam.topic_id,
+            order_index
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(exam)
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    #[allow(clippy::cast_possible_truncation)]
+    /// Replaces the entities of exam `id` with `tasks`, in order.
+    async fn replace_entities(
+        tx: &mut Transaction<'_, Postgres>,
+        id: Uuid,
+        tasks: &[ExamEntity],
+    ) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                DELETE FROM exam_entities
+                WHERE exam_id = $1
+            "#,
+            id
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO exam_entities (exam_id, task_id, text_id, pool_id, section_id, entity_type, order_index)
+                SELECT
+                    $1,
+                    NULLIF(x.task_id, -1),
+                    NULLIF(x.text_id, '00000000-0000-0000-0000-000000000000'),
+                    NULLIF(x.pool_id, '00000000-0000-0000-0000-000000000000'),
+                    NULLIF(x.section_id, '00000000-0000-0000-0000-000000000000'),
+                    CASE
+                        WHEN x.task_id <> -1 THEN 'task'::EXAM_ENTITY_TYPE
+                        WHEN x.pool_id <> '00000000-0000-0000-0000-000000000000' THEN 'pool'::EXAM_ENTITY_TYPE
+                        WHEN x.section_id <> '00000000-0000-0000-0000-000000000000' THEN 'section'::EXAM_ENTITY_TYPE
+                        ELSE 'text'::EXAM_ENTITY_TYPE
+                    END,
+                    x.order_index
+                FROM UNNEST($2::INT[], $3::UUID[], $4::UUID[], $5::UUID[], $6::INT[])
+                    AS x(task_id, text_id, pool_id, section_id, order_index)
+            "#,
+            id,
+            &tasks
+                .iter()
+                .map(|t| match t {
+                    ExamEntity::Task { id } => *id,
+                    ExamEntity::Text { .. }
+                    | ExamEntity::Pool { .. }
+                    | ExamEntity::Section { .. } => -1,
+                })
+                .collect::<Vec<i32>>(),
+            &tasks
+                .iter()
+                .map(|t| match t {
+                    ExamEntity::Text { id } => *id,
+                    ExamEntity::Task { .. }
+                    | ExamEntity::Pool { .. }
+                    | ExamEntity::Section { .. } => Uuid::nil(),
+                })
+                .collect::<Vec<Uuid>>(),
+            &tasks
+                .iter()
+                .map(|t| match t {
+                    ExamEntity::Pool { id } => *id,
+                    ExamEntity::Task { .. }
+                    | ExamEntity::Text { .. }
+                    | ExamEntity::Section { .. } => Uuid::nil(),
+                })
+                .collect::<Vec<Uuid>>(),
+            &tasks
+                .iter()
+                .map(|t| match t {
+                    ExamEntity::Section { id } => *id,
+                    ExamEntity::Task { .. } | ExamEntity::Text { .. } | ExamEntity::Pool { .. } => {
+                        Uuid::nil()
+                    }
+                })
+                .collect::<Vec<Uuid>>(),
+            &(0..tasks.len()).map(|x| x as i32).collect::<Vec<i32>>()
+        )
+        .execute(tx.as_mut())
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                LMSError::Conflict(
+                    "You should provide existing task, text, pool & section ids.".to_string(),
+                )
+            }
+            _ => LMSError::DatabaseError(err),
+        })?;
+
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
             model::{PracticeModel, PracticeProgressModel, PracticeSummary, PracticeTaskRow},
             repository::PracticeRepository,
         },
-        task::model::{Task, TaskType},
+        task::model::{Task, TaskDifficulty, TaskType},
     },
     dto::practice::{CreatePracticeRequestDTO, UpdatePracticeRequestDTO},
     errors::{LMSError, Result},
@@ -182,6 +182,11 @@ impl PracticeRepository for RepositoryPostgres {
                        t.task_type AS "task_type: TaskType",
                        t.points,
                        t.configuration,
+                       t.template,
+                       t.tags,
+                       t.difficulty AS "difficulty: TaskDifficulty",
+                       t.author_id,
+                       t.explanation,
                        pt.order_index,
                        COALESCE(pp.solved, FALSE) AS "solved!",
                        COALESCE(pp.attempts, 0) AS "attempts!"
@@ -210,7 +215,12 @@ impl PracticeRepository for RepositoryPostgres {
                        t.description,
                        t.task_type AS "task_type: TaskType",
                        t.points,
-                       t.configuration
+                       t.configuration,
+                       t.template,
+                       t.tags,
+                       t.difficulty AS "difficulty: TaskDifficulty",
+                       t.author_id,
+                       t.explanation
                 FROM practice_tasks pt
                     JOIN tasks t ON t.id = pt.task_id
                 WHERE pt.practice_id = $1
@@ -3,7 +3,10 @@ use crate::{
     domain::{
         exam::model::ExamScoringPolicy,
         rating::{
-            model::{CourseExamTask, CoursePracticeTask, CourseRef, PracticeSolve, RatingAttempt},
+            model::{
+                CourseExamPool, CourseExamTask, CoursePracticeTask, CourseRef, PracticeSolve,
+                RatingAttempt,
+            },
             repository::RatingRepository,
         },
         report::model::ReportUser,
@@ -39,6 +42,34 @@ impl RatingRepository for RepositoryPostgres {
         Ok(rows)
     }
 
+    async fn course_exam_pools(&self, course_id: i32) -> Result<Vec<CourseExamPool>> {
+        let rows = sqlx::query_as!(
+            CourseExamPool,
+            r#"
+                SELECT e.id                                        AS exam_id,
+                       e.name                                      AS exam_name,
+                       e.scoring_policy AS "scoring_policy: ExamScoringPolicy",
+                       p.id                                        AS pool_id,
+                       (LEAST(p.count, c.tasks) * c.average)::FLOAT8 AS "points!"
+                FROM topics tp
+                    JOIN exams e ON e.topic_id = tp.id
+                    JOIN exam_entities ee ON ee.exam_id = e.id AND ee.entity_type = 'pool'
+                    JOIN exam_pools p ON p.id = ee.pool_id
+                    JOIN LATERAL (
+                        SELECT COUNT(*) AS tasks, COALESCE(AVG(t.points), 0) AS average
+                        FROM tasks t
+                        WHERE t.id = ANY(p.task_ids) OR (p.tag IS NOT NULL AND t.tags @> ARRAY [p.tag])
+                    ) c ON TRUE
+                WHERE tp.course_id = $1
+            "#,
+            course_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
     async fn course_practice_tasks(&self, course_id: i32) -> Result<Vec<CoursePracticeTask>> {
         let rows = sqlx::query_as!(
             CoursePracticeTask,
@@ -68,7 +99,13 @@ impl RatingRepository for RepositoryPostgres {
                 SELECT a.exam_id,
                        a.user_id,
                        a.started_at,
-                       a.scoring_data AS "scoring_data: _"
+                       a.scoring_data AS "scoring_data: _",
+                       (
+                           SELECT COALESCE(SUM(tr.points), 0)
+                           FROM attempt_tasks at
+                               JOIN task_revisions tr ON tr.id = at.task_revision_id
+                           WHERE at.attempt_id = a.id
+                       )::BIGINT AS "max_score!"
                 FROM attempts a
                     JOIN exams e ON e.id = a.exam_id
                     JOIN topics tp ON tp.id = e.topic_id
@@ -1,46 +1,51 @@
 use crate::domain::exam::model::Exam;
 use crate::domain::exam::model::ExamScoringPolicy;
 use crate::domain::exam::model::ExamType;
-use crate::domain::task::model::{Task, TaskType};
+use crate::domain::exam::model::LatePolicy;
+use crate::domain::task::model::{Task, TaskDifficulty, TaskRevision, TaskType};
 use crate::domain::task::repository::TaskRepository;
-use crate::dto::task::UpsertTaskRequestDTO;
+use crate::dto::task::{TaskFilterDTO, UpsertTaskRequestDTO};
 use crate::errors::{LMSError, Result};
 use crate::infrastructure::db::postgres::RepositoryPostgres;
 use async_trait::async_trait;
 use serde_json::to_value;
+use sqlx::types::Json;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
 
 #[async_trait]
 impl TaskRepository for RepositoryPostgres {
-    async fn create(&self, config: UpsertTaskRequestDTO) -> Result<Task> {
-        let mut conn = self.pool.acquire().await?;
-
-        let task = sqlx::query_as!(
-            Task,
-            r#"
-                INSERT INTO tasks (title, description, task_type, points, configuration)
-                VALUES ($1, $2, $3, $4, $5)
-                RETURNING id, title, description, task_type AS "task_type: TaskType", points, configuration
-            "#,
-            config.title,
-            config.description,
-            config.task_type as TaskType,
-            config.points,
-            to_value(config.configuration)
-                .expect("Shit happened while converting configuration to serde Value")
-        )
-            .fetch_one(conn.as_mut())
-            .await?;
+    async fn create(&self, config: UpsertTaskRequestDTO, author_id: Uuid) -> Result<Task> {
+        let mut tx = self.pool.begin().await?;
+        let task = Self::insert_task(&mut tx, config, author_id).await?;
+        tx.commit().await?;
 
         Ok(task)
     }
 
+    async fn create_many(
+        &self,
+        tasks: Vec<UpsertTaskRequestDTO>,
+        author_id: Uuid,
+    ) -> Result<Vec<Task>> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = Vec::with_capacity(tasks.len());
+        for config in tasks {
+            created.push(Self::insert_task(&mut tx, config, author_id).await?);
+        }
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
     async fn get_task(&self, id: i32) -> Result<Task> {
         let mut conn = self.pool.acquire().await?;
 
         let task = sqlx::query_as!(
             Task,
             r#"
-                SELECT id, title, description, task_type AS "task_type: TaskType", points, configuration
+                SELECT id, title, description, task_type AS "task_type: TaskType", points, configuration,
+                       template, tags, difficulty AS "difficulty: TaskDifficulty", author_id, explanation
                 FROM tasks
                 WHERE id = $1
             "#,
@@ -56,13 +61,32 @@ impl TaskRepository for RepositoryPostgres {
         Ok(task)
     }
 
+    async fn get_tasks_by_ids(&self, ids: &[i32]) -> Result<Vec<Task>> {
+        let tasks = sqlx::query_as!(
+            Task,
+            r#"
+                SELECT id, title, description, task_type AS "task_type: TaskType", points, configuration,
+                       template, tags, difficulty AS "difficulty: TaskDifficulty", author_id, explanation
+                FROM tasks
+                WHERE id = ANY($1)
+                ORDER BY array_position($1, id)
+            "#,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tasks)
+    }
+
     async fn get_exams(&self, id: i32) -> Result<Vec<Exam>> {
         let exams: Vec<Exam> = sqlx::query_as!(
             Exam,
             r#"
                 SELECT e.id, e.topic_id, e.tries_count, e.duration, e.type AS "type: ExamType",
                        e.description, e.name, e.starts_at, e.ends_at,
-                       e.scoring_policy AS "scoring_policy: ExamScoringPolicy"
+                       e.scoring_policy AS "scoring_policy: ExamScoringPolicy", e.shuffle_entities,
+                       e.late_policy AS "late_policy: Json<LatePolicy>"
                 FROM exam_entities et
                 LEFT JOIN exams e ON e.id = et.exam_id
                 WHERE et.task_id = $1 AND et.entity_type = 'task'
@@ -79,28 +103,61 @@ impl TaskRepository for RepositoryPostgres {
         todo!()
     }
 
-    async fn get_tasks(&self, limit: i32, offset: i32) -> Result<Vec<Task>> {
+    async fn get_tasks(&self, filter: &TaskFilterDTO) -> Result<Vec<Task>> {
         let tasks = sqlx::query_as!(
             Task,
             r#"
-                SELECT id,
-                       title,
-                       description,
-                       task_type as "task_type: TaskType",
-                       points,
-                       configuration
-                FROM tasks
-                OFFSET $1
-                LIMIT $2
+                SELECT t.id,
+                       t.title,
+                       t.description,
+                       t.task_type as "task_type: TaskType",
+                       t.points,
+                       t.configuration,
+                       t.template,
+                       t.tags,
+                       t.difficulty AS "difficulty: TaskDifficulty",
+                       t.author_id,
+                       t.explanation
+                FROM filter_tasks($1, $2, $3, $4, $5, $6, $7) f
+                    JOIN tasks t ON t.id = f.id
+                ORDER BY f.rank DESC, t.id
+                OFFSET $8
+                LIMIT $9
             "#,
-            i64::from(offset),
-            i64::from(limit)
+            filter.task_type.clone() as Option<TaskType>,
+            filter.tag.as_deref().map(|tag| tag.trim().to_lowercase()),
+            filter.difficulty as Option<TaskDifficulty>,
+            filter.author_id,
+            filter.unused,
+            filter.course_id,
+            filter.q.as_deref(),
+            i64::from(filter.offset),
+            i64::from(filter.limit)
         )
         .fetch_all(&self.pool)
         .await?;
         Ok(tasks)
     }
 
+    async fn count_tasks(&self, filter: &TaskFilterDTO) -> Result<i64> {
+        let total = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "total!"
+                FROM filter_tasks($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            filter.task_type.clone() as Option<TaskType>,
+            filter.tag.as_deref().map(|tag| tag.trim().to_lowercase()),
+            filter.difficulty as Option<TaskDifficulty>,
+            filter.author_id,
+            filter.unused,
+            filter.course_id,
+            filter.q.as_deref()
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(total)
+    }
+
     async fn delete_task(&self, id: i32) -> Result<()> {
         let mut conn = self.pool.acquire().await?;
 
@@ -123,7 +180,7 @@ impl TaskRepository for RepositoryPostgres {
     }
 
     async fn update_task(&self, task_id: i32, task_data: UpsertTaskRequestDTO) -> Result<Task> {
-        let mut conn = self.pool.acquire().await?;
+        let mut tx = self.pool.begin().await?;
 
         let task = sqlx::query_as!(
             Task,
@@ -133,10 +190,15 @@ impl TaskRepository for RepositoryPostgres {
                     description = $2,
                     task_type = $3,
                     points = $4,
-                    configuration = $5
-                WHERE id = $6
+                    configuration = $5,
+                    template = $6,
+                    tags = $7,
+                    difficulty = $8,
+                    explanation = $9
+                WHERE id = $10
                 RETURNING id, title, description, task_type AS "task_type: TaskType",
-                          points, configuration
+                          points, configuration, template, tags, difficulty AS "difficulty: TaskDifficulty",
+                          author_id, explanation
             "#,
             task_data.title,
             task_data.description,
@@ -144,15 +206,129 @@ impl TaskRepository for RepositoryPostgres {
             task_data.points,
             to_value(task_data.configuration)
                 .expect("Shit happened while converting configuration to serde Value"),
+            to_value(task_data.template)
+                .expect("Shit happened while converting template to serde Value"),
+            &task_data.tags,
+            task_data.difficulty as Option<TaskDifficulty>,
+            task_data.explanation,
             task_id
         )
-        .fetch_one(conn.as_mut())
+        .fetch_one(tx.as_mut())
         .await
         .map_err(|e| match e {
             sqlx::Error::RowNotFound => LMSError::NotFound("Task not found".to_string()),
             _ => LMSError::DatabaseError(e),
         })?;
 
+        Self::save_task_revision(&mut tx, &task).await?;
+        tx.commit().await?;
+
         Ok(task)
     }
+
+    async fn get_task_revisions(&self, task_id: i32) -> Result<Vec<TaskRevision>> {
+        let revisions = sqlx::query!(
+            r#"
+                SELECT id, task_id, revision, created_at, title, description,
+                       task_type AS "task_type: TaskType", points, configuration, template, tags,
+                       difficulty AS "difficulty: TaskDifficulty", author_id, explanation
+                FROM task_revisions
+                WHERE task_id = $1
+                ORDER BY revision ASC
+            "#,
+            task_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| TaskRevision {
+            id: row.id,
+            revision: row.revision,
+            created_at: row.created_at,
+            task: Task {
+                id: i64::from(row.task_id),
+                title: row.title,
+                description: row.description,
+                task_type: row.task_type,
+                points: i64::from(row.points),
+                configuration: row.configuration.into(),
+                template: row.template.into(),
+                tags: row.tags,
+                difficulty: row.difficulty,
+                author_id: row.author_id,
+                explanation: row.explanation,
+            },
+        })
+        .collect();
+
+        Ok(revisions)
+    }
+}
+
+impl RepositoryPostgres {
+    /// Creates a task along with its first revision.
+    pub(super) async fn insert_task(
+        tx: &mut Transaction<'_, Postgres>,
+        config: UpsertTaskRequestDTO,
+        author_id: Uuid,
+    ) -> Result<Task> {
+        let task = sqlx::query_as!(
+            Task,
+            r#"
+                INSERT INTO tasks (title, description, task_type, points, configuration, template, tags,
+                                   difficulty, author_id, explanation)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING id, title, description, task_type AS "task_type: TaskType", points, configuration,
+                          template, tags, difficulty AS "difficulty: TaskDifficulty", author_id, explanation
+            "#,
+            config.title,
+            config.description,
+            config.task_type as TaskType,
+            config.points,
+            to_value(config.configuration)
+                .expect("Shit happened while converting configuration to serde Value"),
+            to_value(config.template)
+                .expect("Shit happened while converting template to serde Value"),
+            &config.tags,
+            config.difficulty as Option<TaskDifficulty>,
+            author_id,
+            config.explanation
+        )
+            .fetch_one(tx.as_mut())
+            .await?;
+
+        Self::save_task_revision(tx, &task).await?;
+
+        Ok(task)
+    }
+
+    /// Appends the task's current state as its next revision.
+    #[allow(clippy::cast_possible_truncation)]
+    async fn save_task_revision(tx: &mut Transaction<'_, Postgres>, task: &Task) -> Result<()> {
+        let _ = sqlx::query!(
+            r#"
+                INSERT INTO task_revisions (task_id, revision, title, description, task_type, points, configuration,
+                                            template, tags, difficulty, author_id, explanation)
+                SELECT $1, COALESCE(MAX(revision), 0) + 1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+                FROM task_revisions
+                WHERE task_id = $1
+            "#,
+            task.id as i32,
+            task.title,
+            task.description,
+            task.task_type.clone() as TaskType,
+            task.points as i32,
+            to_value(&task.configuration)
+                .expect("Shit happened while converting configuration to serde Value"),
+            to_value(&task.template).expect("Shit happened while converting template to serde Value"),
+            &task.tags,
+            task.difficulty as Option<TaskDifficulty>,
+            task.author_id,
+            task.explanation
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use crate::domain::exam::model::{Exam, ExamScoringPolicy, ExamType};
+use crate::domain::exam::model::{Exam, ExamScoringPolicy, ExamType, LatePolicy};
 use crate::{
     domain::topics::{
         model::{TopicContentRow, TopicModel},
@@ -9,6 +9,7 @@ use crate::{
     infrastructure::db::postgres::RepositoryPostgres,
 };
 use async_trait::async_trait;
+use sqlx::types::Json;
 use uuid::Uuid;
 
 #[async_trait]
@@ -101,7 +102,7 @@ impl TopicRepository for RepositoryPostgres {
         let exams = sqlx::query_as!(
             Exam,
             r#"
-                SELECT id, topic_id, tries_count, duration, type as "type: ExamType", description, name, starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy"
+                SELECT id, topic_id, tries_count, duration, type as "type: ExamType", description, name, starts_at, ends_at, scoring_policy AS "scoring_policy: ExamScoringPolicy", shuffle_entities, late_policy AS "late_policy: Json<LatePolicy>"
                 FROM exams
                 WHERE topic_id = $1
             "#,
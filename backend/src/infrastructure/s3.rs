@@ -13,11 +13,29 @@ use tracing::warn;
 
 use crate::{config::Config, errors::LMSError, gen_openapi::DummyRepository};
 
+/// Size and type of a stored object.
+pub struct ObjectMeta {
+    pub size: u64,
+    pub content_type: Option<String>,
+}
+
 #[impl_unimplemented(DummyRepository)]
 #[async_trait]
 pub trait S3 {
     async fn presign_post(&self, path: &str) -> Result<PresignedPost, S3Error>;
     async fn save_from_url(&self, path: &str, url: &str) -> Result<(), LMSError>;
+    /// Upload form for exactly `path`, with the given content type and a size
+    /// of at most `max_size` bytes.
+    async fn presign_upload(
+        &self,
+        path: &str,
+        content_type: &str,
+        max_size: u32,
+        expires_in: u32,
+    ) -> Result<PresignedPost, S3Error>;
+    /// `None` when there is no object at `path`.
+    async fn object_meta(&self, path: &str) -> Result<Option<ObjectMeta>, S3Error>;
+    async fn presign_get(&self, path: &str, expires_in: u32) -> Result<String, S3Error>;
 }
 
 #[derive(Clone)]
@@ -109,4 +127,46 @@ impl S3 for S3Manager {
 
         Ok(())
     }
+
+    async fn presign_upload(
+        &self,
+        path: &str,
+        content_type: &str,
+        max_size: u32,
+        expires_in: u32,
+    ) -> Result<PresignedPost, S3Error> {
+        let post_policy = PostPolicy::new(expires_in)
+            .condition(
+                PostPolicyField::Key,
+                PostPolicyValue::Exact(Cow::from(path)),
+            )?
+            .condition(
+                PostPolicyField::ContentType,
+                PostPolicyValue::Exact(Cow::from(content_type)),
+            )?
+            .condition(
+                PostPolicyField::ContentLengthRange,
+                PostPolicyValue::Range(1, max_size),
+            )?;
+
+        self.bucket.presign_post(post_policy).await
+    }
+
+    async fn object_meta(&self, path: &str) -> Result<Option<ObjectMeta>, S3Error> {
+        match self.bucket.head_object(path).await {
+            Ok((head, _)) => Ok(Some(ObjectMeta {
+                size: head
+                    .content_length
+                    .and_then(|length| u64::try_from(length).ok())
+                    .unwrap_or_default(),
+                content_type: head.content_type,
+            })),
+            Err(S3Error::HttpFailWithBody(404, _)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn presign_get(&self, path: &str, expires_in: u32) -> Result<String, S3Error> {
+        self.bucket.presign_get(path, expires_in, None).await
+    }
 }
@@ -0,0 +1,589 @@
+//! Local sandbox for learners' programs (`Code` tasks).
+//!
+//! Every run is a separate unprivileged process in its own user, mount,
+//! network (so no network at all) and pid namespaces, under rlimits for CPU
+//! time, address space, written files and processes, and with a wall clock
+//! timeout. Whatever the program leaves running dies with it, along with
+//! its pid namespace. Every concurrent run gets a uid and gid of
+//! its own, so runs can't reach each other's files; that takes a server
+//! started as root, and the sandbox refuses to work otherwise.
+//!
+//! Inside, the root filesystem is read-only, `/proc` only shows the run's
+//! own processes, `/tmp` (also `HOME`) is a private scratch tmpfs, and the
+//! working directory is mounted at `/tmp/work`, read-only unless the run is
+//! to write there, e.g. a compiler.
+
+use std::ffi::CStr;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// Upper bound for what a run may print to stdout (and, separately, stderr).
+pub const OUTPUT_LIMIT: usize = 64 * 1024;
+/// Upper bound for any file a run writes.
+const FILE_SIZE_LIMIT: u64 = 1024 * 1024;
+/// Upper bound for processes a run may spawn, to keep fork bombs in check.
+const PROCESS_LIMIT: u64 = 64;
+/// First of the uids (and gids) runs get, one per worker, far from those of
+/// real accounts.
+const RUN_ID_BASE: libc::uid_t = 200_000;
+/// `PATH` of a run, whose environment is otherwise empty.
+const SANDBOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+/// Where a run finds its working directory.
+const WORK_DIR: &CStr = c"/tmp/work";
+/// Options of a run's scratch `/tmp`.
+const SCRATCH_OPTIONS: &CStr = c"size=16m,mode=700";
+
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// Wall clock time; CPU time is capped at the next whole second.
+    pub time: Duration,
+    /// Address space in bytes.
+    pub memory: u64,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RunStatus {
+    Exited(i32),
+    /// Killed by a signal other than the CPU time limit's.
+    Signaled(i32),
+    TimedOut,
+}
+
+pub struct RunOutput {
+    pub status: RunStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Either stream went over [`OUTPUT_LIMIT`] and was cut.
+    pub output_truncated: bool,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DirAccess {
+    ReadOnly,
+    /// For runs producing files for later ones, which [`seal`] them first.
+    ReadWrite,
+}
+
+pub struct Sandbox {
+    permits: Semaphore,
+    /// Uids free for runs.
+    run_ids: Mutex<Vec<libc::uid_t>>,
+}
+
+/// Who a run is, as seen from outside its namespaces.
+struct RunAccount<'a> {
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    /// Where the uid was taken from, and is given back on drop.
+    pool: &'a Mutex<Vec<libc::uid_t>>,
+}
+
+impl RunAccount<'_> {
+    /// Kills whatever still runs as the account once the run is over, so
+    /// that the next one to get the uid starts clean. On a blocking thread,
+    /// as it forks a process to do that and waits for it.
+    async fn sweep(&self) -> io::Result<()> {
+        let (uid, gid) = (self.uid, self.gid);
+        tokio::task::spawn_blocking(move || {
+            // SAFETY: the child only makes async-signal-safe syscalls
+            unsafe {
+                let pid = libc::fork();
+                check(pid)?;
+                if pid == 0 {
+                    if libc::setgid(gid) == 0 && libc::setuid(uid) == 0 {
+                        libc::kill(-1, libc::SIGKILL);
+                    }
+                    libc::_exit(0);
+                }
+                while libc::waitpid(pid, std::ptr::null_mut(), 0) == -1 {
+                    let error = io::Error::last_os_error();
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+            }
+            Ok(())
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+impl Drop for RunAccount<'_> {
+    fn drop(&mut self) {
+        self.pool
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(self.uid);
+    }
+}
+
+impl Sandbox {
+    /// A sandbox running at most `workers` programs at once. Fails unless
+    /// the server is root, which it takes to give runs uids of their own.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn new(workers: usize) -> io::Result<Self> {
+        // SAFETY: a plain syscall
+        if unsafe { libc::geteuid() } != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "The code runner has to be started as root",
+            ));
+        }
+        let workers = workers.max(1);
+        Ok(Self {
+            permits: Semaphore::new(workers),
+            run_ids: Mutex::new(
+                (0..workers as libc::uid_t)
+                    .map(|i| RUN_ID_BASE + i)
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Runs `program` with `args` in `dir`, feeding it `stdin`. Waits for a
+    /// free worker first. `program` is looked up in `PATH` or, given as
+    /// `./name`, in `dir`. Errors are the sandbox's own (e.g. no user
+    /// namespaces on the host), never the program's.
+    pub async fn run(
+        &self,
+        dir: &Path,
+        program: &Path,
+        args: &[&str],
+        stdin: &[u8],
+        limits: Limits,
+        access: DirAccess,
+    ) -> io::Result<RunOutput> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| io::Error::other("Sandbox is closed"))?;
+        let account = self.account();
+        let output = execute(&account, dir, program, args, stdin, limits, access).await;
+        account.sweep().await?;
+        output
+    }
+
+    /// A uid of its own for the next run, which it demotes to.
+    fn account(&self) -> RunAccount<'_> {
+        let uid = self
+            .run_ids
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .pop()
+            .expect("there are as many uids as permits");
+        RunAccount {
+            uid,
+            gid: uid,
+            pool: &self.run_ids,
+        }
+    }
+}
+
+/// Runs the program of [`Sandbox::run`] as `account`.
+#[allow(clippy::cast_possible_wrap)]
+async fn execute(
+    account: &RunAccount<'_>,
+    dir: &Path,
+    program: &Path,
+    args: &[&str],
+    stdin: &[u8],
+    limits: Limits,
+    access: DirAccess,
+) -> io::Result<RunOutput> {
+    // only the run's own group may enter the directory, and only the run
+    // itself write there
+    let owner = match access {
+        DirAccess::ReadOnly => 0,
+        DirAccess::ReadWrite => account.uid,
+    };
+    std::os::unix::fs::chown(dir, Some(owner), Some(account.gid))?;
+    let mode = match access {
+        DirAccess::ReadOnly => 0o750,
+        DirAccess::ReadWrite => 0o770,
+    };
+    tokio::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode)).await?;
+
+    let cpu_seconds = limits.time.as_secs() + 1;
+    let memory = limits.memory;
+    let demote = (account.uid, account.gid);
+    let uid_map = format!("{0} {0} 1", account.uid).into_bytes();
+    let gid_map = format!("{0} {0} 1", account.gid).into_bytes();
+    let writable = access == DirAccess::ReadWrite;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .current_dir(dir)
+        .env_clear()
+        .env("PATH", SANDBOX_PATH)
+        .env("HOME", "/tmp")
+        .env("TMPDIR", "/tmp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // SAFETY: `confine` and `isolate` only make async-signal-safe syscalls
+    unsafe {
+        command.pre_exec(move || {
+            confine(cpu_seconds, memory, demote)?;
+            isolate(&uid_map, &gid_map, writable)
+        });
+    }
+
+    let mut child = command.spawn()?;
+    let group = child.id().map(|pid| -(pid as i32));
+    let input = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let feed = async move {
+        if let Some(mut input) = input {
+            // a program that doesn't read its input closes the pipe early
+            let _ = input.write_all(stdin).await;
+        }
+    };
+
+    let finished = tokio::time::timeout(limits.time, async {
+        let ((), stdout, stderr, status) =
+            tokio::join!(feed, read_capped(stdout), read_capped(stderr), child.wait());
+        (stdout, stderr, status)
+    })
+    .await;
+    // takes down the run's pid namespace too, if the program isn't done
+    if let Some(group) = group {
+        // SAFETY: a plain syscall on the group `setsid` made for the run
+        unsafe {
+            libc::kill(group, libc::SIGKILL);
+        }
+    }
+
+    let Ok((stdout, stderr, status)) = finished else {
+        let _ = child.wait().await;
+        return Ok(RunOutput {
+            status: RunStatus::TimedOut,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            output_truncated: false,
+        });
+    };
+    let status = status?;
+    let ((stdout, stdout_cut), (stderr, stderr_cut)) = (stdout?, stderr?);
+    let status = match (status.code(), status.signal()) {
+        (Some(code), _) => RunStatus::Exited(code),
+        (None, Some(libc::SIGXCPU)) => RunStatus::TimedOut,
+        (None, signal) => RunStatus::Signaled(signal.unwrap_or_default()),
+    };
+    Ok(RunOutput {
+        status,
+        stdout,
+        stderr,
+        output_truncated: stdout_cut || stderr_cut,
+    })
+}
+
+/// Hands a file a [`DirAccess::ReadWrite`] run made over to the server,
+/// leaving it read-only for later runs.
+pub fn seal(path: &Path) -> io::Result<()> {
+    // SAFETY: plain syscalls
+    let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    std::os::unix::fs::lchown(path, Some(uid), Some(gid))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o555))
+}
+
+/// Runs in the forked child right before `exec`.
+fn confine(
+    cpu_seconds: u64,
+    memory: u64,
+    (uid, gid): (libc::uid_t, libc::gid_t),
+) -> io::Result<()> {
+    // SAFETY: plain syscalls, nothing here allocates or takes locks
+    unsafe {
+        // own process group, so that a timeout kills everything the program spawned
+        if libc::setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::setgroups(0, std::ptr::null()) == -1
+            || libc::setgid(gid) == -1
+            || libc::setuid(uid) == -1
+            // changing uids hides the process's own /proc files from it
+            || libc::prctl(libc::PR_SET_DUMPABLE, 1) == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    set_limit(libc::RLIMIT_CPU, cpu_seconds)?;
+    set_limit(libc::RLIMIT_AS, memory)?;
+    set_limit(libc::RLIMIT_FSIZE, FILE_SIZE_LIMIT)?;
+    set_limit(libc::RLIMIT_NPROC, PROCESS_LIMIT)?;
+    set_limit(libc::RLIMIT_CORE, 0)
+}
+
+/// Moves the child into namespaces of its own and lays out its filesystem.
+/// The uid inside stays the one outside, so the program, not being root
+/// there, loses every capability on `exec` and can't undo any of it.
+fn isolate(uid_map: &[u8], gid_map: &[u8], writable: bool) -> io::Result<()> {
+    // SAFETY: plain syscalls on static or caller-owned buffers
+    unsafe {
+        // a fresh network namespace has no interfaces but a downed loopback,
+        // and the user namespace lets an unprivileged process create it
+        check(libc::unshare(
+            libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWPID,
+        ))?;
+        write_proc(c"/proc/self/setgroups", b"deny")?;
+        write_proc(c"/proc/self/uid_map", uid_map)?;
+        write_proc(c"/proc/self/gid_map", gid_map)?;
+
+        check(libc::mount(
+            std::ptr::null(),
+            c"/".as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        ))?;
+        // hides every other run's directory along with the host's /tmp
+        check(libc::mount(
+            c"tmpfs".as_ptr(),
+            c"/tmp".as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            SCRATCH_OPTIONS.as_ptr().cast(),
+        ))?;
+        if libc::mount(
+            c"tmpfs".as_ptr(),
+            c"/dev/shm".as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            SCRATCH_OPTIONS.as_ptr().cast(),
+        ) == -1
+            && *libc::__errno_location() != libc::ENOENT
+        {
+            return Err(io::Error::last_os_error());
+        }
+        // the working directory is still the current one
+        check(libc::mkdir(WORK_DIR.as_ptr(), 0o755))?;
+        check(libc::mount(
+            c".".as_ptr(),
+            WORK_DIR.as_ptr(),
+            std::ptr::null(),
+            libc::MS_BIND,
+            std::ptr::null(),
+        ))?;
+        // after binding the directory, which would copy the flag otherwise
+        remount_read_only(c"/")?;
+        if !writable {
+            remount_read_only(WORK_DIR)?;
+        }
+        check(libc::chdir(WORK_DIR.as_ptr()))?;
+        enter_pid_namespace()
+    }
+}
+
+/// Forks the init of the pid namespace `unshare` made, which forks the
+/// process going on to `exec` the program. Init reaps whatever the program
+/// leaves behind, and everything in the namespace dies with it. This process
+/// stays outside and passes the program's wait status on, so that it's what
+/// the server sees; init itself can't die of most signals.
+unsafe fn enter_pid_namespace() -> io::Result<()> {
+    // SAFETY: plain syscalls, nothing here allocates or takes locks
+    unsafe {
+        let shared = libc::mmap(
+            std::ptr::null_mut(),
+            std::mem::size_of::<libc::c_int>(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if shared == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let program_status = shared.cast::<libc::c_int>();
+        let init = libc::fork();
+        check(init)?;
+        if init == 0 {
+            // a timeout kills this process, the namespace has to follow
+            check(libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL))?;
+            let program = libc::fork();
+            check(program)?;
+            if program == 0 {
+                return mount_proc();
+            }
+            close_inherited();
+            program_status.write_volatile(reap(program));
+            libc::_exit(0);
+        }
+        close_inherited();
+        let init_status = reap(init);
+        let status = if libc::WIFEXITED(init_status) && libc::WEXITSTATUS(init_status) == 0 {
+            program_status.read_volatile()
+        } else {
+            init_status
+        };
+        if libc::WIFSIGNALED(status) {
+            let signal = libc::WTERMSIG(status);
+            libc::signal(signal, libc::SIG_DFL);
+            libc::kill(libc::getpid(), signal);
+            libc::_exit(128 + signal);
+        }
+        libc::_exit(libc::WEXITSTATUS(status))
+    }
+}
+
+/// Closes every descriptor of a process that stays out of `exec`: neither
+/// the program's pipes nor the one `spawn` waits on for `exec` may stay open
+/// while the program runs.
+unsafe fn close_inherited() {
+    // SAFETY: plain syscalls
+    unsafe {
+        if libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0) == -1 {
+            for fd in 0..1024 {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+/// Waits for the child `pid`, reaping any other one along the way, and
+/// returns its wait status.
+unsafe fn reap(pid: libc::pid_t) -> libc::c_int {
+    let mut status = 0;
+    // SAFETY: plain syscalls, `status` outlives them
+    unsafe {
+        loop {
+            match libc::waitpid(-1, &raw mut status, 0) {
+                reaped if reaped == pid => return status,
+                -1 if *libc::__errno_location() != libc::EINTR => libc::_exit(127),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Gives the program a `/proc` of its own pid namespace. Where the
+/// host's `/proc` is partly hidden, as in containers, the kernel refuses
+/// that, and an empty one has to do.
+unsafe fn mount_proc() -> io::Result<()> {
+    let flags = libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC;
+    // SAFETY: plain syscalls on static strings
+    unsafe {
+        if libc::mount(
+            c"proc".as_ptr(),
+            c"/proc".as_ptr(),
+            c"proc".as_ptr(),
+            flags,
+            std::ptr::null(),
+        ) == 0
+        {
+            return Ok(());
+        }
+        check(libc::mount(
+            c"tmpfs".as_ptr(),
+            c"/proc".as_ptr(),
+            c"tmpfs".as_ptr(),
+            flags | libc::MS_RDONLY,
+            c"size=0".as_ptr().cast(),
+        ))
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Writes `data` to a file of `/proc` in one go, as its files need.
+unsafe fn write_proc(path: &CStr, data: &[u8]) -> io::Result<()> {
+    // SAFETY: plain syscalls, `data` outlives them
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        check(fd)?;
+        let written = libc::write(fd, data.as_ptr().cast(), data.len());
+        let error = io::Error::last_os_error();
+        libc::close(fd);
+        match usize::try_from(written) {
+            Ok(written) if written == data.len() => Ok(()),
+            Ok(_) => Err(io::Error::from_raw_os_error(libc::EIO)),
+            Err(_) => Err(error),
+        }
+    }
+}
+
+/// Makes the mount at `path` read-only. Its other flags are locked for a
+/// user namespace and have to be repeated.
+unsafe fn remount_read_only(path: &CStr) -> io::Result<()> {
+    const KEPT: [(libc::c_ulong, libc::c_ulong); 6] = [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ];
+    // SAFETY: plain syscalls, `stat` outlives them
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        check(libc::statvfs(path.as_ptr(), &raw mut stat))?;
+        let flags = KEPT
+            .iter()
+            .filter(|(kept, _)| stat.f_flag & kept != 0)
+            .fold(
+                libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY,
+                |flags, (_, flag)| flags | flag,
+            );
+        check(libc::mount(
+            std::ptr::null(),
+            path.as_ptr(),
+            std::ptr::null(),
+            flags,
+            std::ptr::null(),
+        ))
+    }
+}
+
+#[cfg(target_env = "gnu")]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(target_env = "gnu"))]
+type Resource = libc::c_int;
+
+fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    // SAFETY: `limit` outlives the call
+    if unsafe { libc::setrlimit(resource, &raw const limit) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Reads a stream to the end, keeping the first [`OUTPUT_LIMIT`] bytes, so
+/// that a chatty program never blocks on a full pipe. The flag tells whether
+/// anything was dropped.
+async fn read_capped<R: AsyncRead + Unpin>(stream: Option<R>) -> io::Result<(Vec<u8>, bool)> {
+    let mut output = Vec::new();
+    let mut truncated = false;
+    let Some(mut stream) = stream else {
+        return Ok((output, truncated));
+    };
+    let mut buffer = vec![0u8; 8192];
+    loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok((output, truncated));
+        }
+        let room = OUTPUT_LIMIT - output.len();
+        truncated |= read > room;
+        output.extend_from_slice(&buffer[..read.min(room)]);
+    }
+}
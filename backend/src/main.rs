@@ -15,7 +15,7 @@ use crate::{
     config::Config,
     domain::{
         account::service::AccountService, basic::service::BasicAuthService,
-        courses::service::CourseService, exam::service::ExamService,
+        courses::service::CourseService, exam::scorer, exam::service::ExamService,
         lectures::service::LectureService, oauth::service::OAuthService,
         practice::service::PracticeService, rating::service::RatingService,
         refresh_token::service::RefreshTokenService, report::service::ReportService,
@@ -26,6 +26,7 @@ use crate::{
         iam::IAMTokenManager,
         logging::init_tracing,
         s3::S3Manager,
+        sandbox::Sandbox,
     },
 };
 
@@ -64,6 +65,7 @@ pub mod openapi;
 pub mod utils;
 
 #[tokio::main]
+#[allow(clippy::too_many_lines)]
 async fn main() -> anyhow::Result<()> {
     #[cfg(feature = "gen-openapi")]
     gen_openapi::save_openapi();
@@ -85,6 +87,10 @@ async fn main() -> anyhow::Result<()> {
     let jwt = Arc::new(JWT::new(&config.jwt_secret));
     let iam = Arc::new(IAMTokenManager::new(&config.iam_key_file)?);
     let rdb_repo = Arc::new(RepositoryRedis::new(&config.redis_url).await?);
+    let sandbox = config
+        .code_runner
+        .then(|| Sandbox::new(config.sandbox_workers).map(Arc::new))
+        .transpose()?;
 
     let account = AccountService::new(
         db_repo.clone(),
@@ -102,7 +108,15 @@ async fn main() -> anyhow::Result<()> {
         client.clone(),
         config.ctfd_token.clone(),
         topic.clone(),
+        s3.clone(),
+        sandbox,
     );
+    if config.code_runner {
+        info!("Running learners' code");
+        scorer::run_code(exam, config.sandbox_workers).await;
+        return Ok(());
+    }
+    scorer::spawn(exam.clone(), config.scoring_workers);
     let lecture = LectureService::new(db_repo.clone(), topic.clone());
     let oauth = OAuthService::new(db_repo.clone(), s3.clone());
     let refresh_token = RefreshTokenService::new(rdb_repo.clone(), jwt.clone());
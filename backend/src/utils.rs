@@ -1,7 +1,8 @@
 use std::{collections::HashMap, hash::BuildHasher};
 
-use axum::extract::Query;
+use axum::extract::{FromRequestParts, Query};
 use axum::http::HeaderMap;
+use axum::http::request::Parts;
 use axum::{
     Json,
     extract::{FromRequest, Request},
@@ -37,15 +38,15 @@ where
 
 pub struct ValidatedQuery<T>(pub T);
 
-impl<S, T> FromRequest<S> for ValidatedQuery<T>
+impl<S, T> FromRequestParts<S> for ValidatedQuery<T>
 where
     S: Send + Sync,
     T: DeserializeOwned + Validate,
 {
     type Rejection = LMSError;
 
-    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        let Query(value): Query<T> = Query::from_request(req, state)
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value): Query<T> = Query::from_request_parts(parts, state)
             .await
             .map_err(|err| LMSError::ShitHappened(err.to_string()))?;
 